@@ -72,26 +72,41 @@ impl Script {
 }
 
 /// An argument to a script.
+///
+/// Variant order matches the on-chain `TransactionArgument` enum, since BCS
+/// encodes the variant index:
+/// - 0: U8
+/// - 1: U64
+/// - 2: U128
+/// - 3: Address
+/// - 4: U8Vector
+/// - 5: Bool
+/// - 6: U16 (added later)
+/// - 7: U32 (added later)
+/// - 8: U256 (added later)
+/// - 9: Serialized (added later)
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScriptArgument {
-    /// A u8 value.
+    /// A u8 value (variant 0).
     U8(u8),
-    /// A u16 value.
-    U16(u16),
-    /// A u32 value.
-    U32(u32),
-    /// A u64 value.
+    /// A u64 value (variant 1).
     U64(u64),
-    /// A u128 value.
+    /// A u128 value (variant 2).
     U128(u128),
-    /// A u256 value (as bytes).
-    U256([u8; 32]),
-    /// An address value.
+    /// An address value (variant 3).
     Address(crate::types::AccountAddress),
-    /// A vector of u8 (bytes).
+    /// A vector of u8 (bytes) (variant 4).
     U8Vector(#[serde(with = "serde_bytes")] Vec<u8>),
-    /// A boolean value.
+    /// A boolean value (variant 5).
     Bool(bool),
+    /// A u16 value (variant 6).
+    U16(u16),
+    /// A u32 value (variant 7).
+    U32(u32),
+    /// A u256 value (as bytes) (variant 8).
+    U256([u8; 32]),
+    /// Any other value, as its BCS bytes (variant 9).
+    Serialized(#[serde(with = "serde_bytes")] Vec<u8>),
 }
 
 /// An entry function call payload.
@@ -340,6 +355,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_script_argument_variant_indices() {
+        // Must match the on-chain TransactionArgument layout
+        let cases = [
+            (ScriptArgument::U8(0), 0u8),
+            (ScriptArgument::U64(0), 1),
+            (ScriptArgument::U128(0), 2),
+            (ScriptArgument::Address(AccountAddress::ONE), 3),
+            (ScriptArgument::U8Vector(vec![]), 4),
+            (ScriptArgument::Bool(false), 5),
+            (ScriptArgument::U16(0), 6),
+            (ScriptArgument::U32(0), 7),
+            (ScriptArgument::U256([0; 32]), 8),
+            (ScriptArgument::Serialized(vec![]), 9),
+        ];
+
+        for (arg, index) in cases {
+            let serialized = aptos_bcs::to_bytes(&arg).unwrap();
+            assert_eq!(serialized[0], index, "{arg:?}");
+        }
+    }
+
     #[test]
     fn test_transaction_payload_from_entry_function() {
         let entry_fn = EntryFunction::apt_transfer(AccountAddress::ONE, 100).unwrap();
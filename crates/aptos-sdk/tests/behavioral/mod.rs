@@ -1002,3 +1002,167 @@ mod auth_key_tests {
         );
     }
 }
+
+mod golden_vector_tests {
+    //! Known-good BCS in the layouts the TypeScript SDK produces for the
+    //! multi-agent flows (see `tests/testdata/README.md`). Every vector must
+    //! decode to its expected fields and re-encode to exactly the same bytes,
+    //! so a layout change in an SDK upgrade shows up here first.
+
+    use aptos_sdk::aptos_bcs;
+    use aptos_sdk::transaction::types::{FeePayerRawTransaction, MultiAgentRawTransaction};
+    use aptos_sdk::transaction::{RawTransaction, TransactionPayload};
+    use aptos_sdk::types::AccountAddress;
+    use serde::Deserialize;
+
+    const CORPUS: &str = include_str!("../testdata/golden_vectors.json");
+
+    #[derive(Deserialize)]
+    struct Corpus {
+        vectors: Vec<Vector>,
+    }
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        kind: String,
+        hex: String,
+        expected: Expected,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Expected {
+        sender: AccountAddress,
+        sequence_number: u64,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        expiration_timestamp_secs: u64,
+        chain_id: u8,
+        payload: String,
+        #[serde(default)]
+        function: Option<String>,
+        type_args: Vec<String>,
+        argument_count: usize,
+        #[serde(default)]
+        secondary_signer_addresses: Option<Vec<AccountAddress>>,
+        #[serde(default)]
+        fee_payer_address: Option<AccountAddress>,
+    }
+
+    impl Expected {
+        /// The fields `raw_txn` and its signer addresses actually hold
+        fn of(
+            raw_txn: &RawTransaction,
+            secondary_signer_addresses: Option<Vec<AccountAddress>>,
+            fee_payer_address: Option<AccountAddress>,
+        ) -> Self {
+            let (payload, function, type_args, argument_count) = match &raw_txn.payload {
+                TransactionPayload::EntryFunction(entry_function) => (
+                    "entry_function",
+                    Some(format!(
+                        "{}::{}",
+                        entry_function.module, entry_function.function
+                    )),
+                    entry_function
+                        .type_args
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                    entry_function.args.len(),
+                ),
+                TransactionPayload::Script(script) => (
+                    "script",
+                    None,
+                    script.type_args.iter().map(ToString::to_string).collect(),
+                    script.args.len(),
+                ),
+                other => panic!("unexpected payload in corpus: {other:?}"),
+            };
+            Self {
+                sender: raw_txn.sender,
+                sequence_number: raw_txn.sequence_number,
+                max_gas_amount: raw_txn.max_gas_amount,
+                gas_unit_price: raw_txn.gas_unit_price,
+                expiration_timestamp_secs: raw_txn.expiration_timestamp_secs,
+                chain_id: raw_txn.chain_id.id(),
+                payload: payload.to_string(),
+                function,
+                type_args,
+                argument_count,
+                secondary_signer_addresses,
+                fee_payer_address,
+            }
+        }
+    }
+
+    /// Decode `bytes` as `kind`, returning the fields found and the bytes the
+    /// decoded value serializes back to
+    fn decode(kind: &str, bytes: &[u8]) -> (Expected, Vec<u8>) {
+        match kind {
+            "multi_agent" => {
+                let tx: MultiAgentRawTransaction = aptos_bcs::from_bytes(bytes).unwrap();
+                let fields = Expected::of(
+                    &tx.raw_txn,
+                    Some(tx.secondary_signer_addresses.clone()),
+                    None,
+                );
+                (fields, aptos_bcs::to_bytes(&tx).unwrap())
+            }
+            "fee_payer" => {
+                let tx: FeePayerRawTransaction = aptos_bcs::from_bytes(bytes).unwrap();
+                let fields = Expected::of(
+                    &tx.raw_txn,
+                    Some(tx.secondary_signer_addresses.clone()),
+                    Some(tx.fee_payer_address),
+                );
+                (fields, aptos_bcs::to_bytes(&tx).unwrap())
+            }
+            "raw_transaction" => {
+                let tx: RawTransaction = aptos_bcs::from_bytes(bytes).unwrap();
+                (
+                    Expected::of(&tx, None, None),
+                    aptos_bcs::to_bytes(&tx).unwrap(),
+                )
+            }
+            other => panic!("unknown kind in corpus: {other}"),
+        }
+    }
+
+    fn corpus() -> Vec<Vector> {
+        serde_json::from_str::<Corpus>(CORPUS).unwrap().vectors
+    }
+
+    #[test]
+    fn test_corpus_covers_every_kind() {
+        let vectors = corpus();
+        for kind in ["multi_agent", "fee_payer", "raw_transaction"] {
+            assert!(
+                vectors.iter().any(|vector| vector.kind == kind),
+                "no {kind} vector in the corpus"
+            );
+        }
+    }
+
+    #[test]
+    fn test_golden_vectors_decode_to_expected_fields() {
+        for vector in corpus() {
+            let bytes = hex::decode(vector.hex.trim_start_matches("0x")).unwrap();
+            let (fields, _) = decode(&vector.kind, &bytes);
+            assert_eq!(fields, vector.expected, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_golden_vectors_roundtrip() {
+        for vector in corpus() {
+            let bytes = hex::decode(vector.hex.trim_start_matches("0x")).unwrap();
+            let (_, reserialized) = decode(&vector.kind, &bytes);
+            assert_eq!(
+                hex::encode(&reserialized),
+                hex::encode(&bytes),
+                "{} changed on reserialization",
+                vector.name
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Embeds build provenance for `GET /version`: the git commit (and whether the
+//! tree had uncommitted changes), the aptos-sdk version from `Cargo.lock`, and
+//! the compiler version. Anything unavailable (e.g. building outside a git
+//! checkout) is reported as `unknown`.
+//!
+//! With the `grpc` feature it also generates the service in
+//! `proto/repro.proto`, which needs `protoc` on the `PATH`.
+
+use std::process::Command;
+
+fn main() {
+    let commit = git(&["rev-parse", "HEAD"]);
+    let dirty = git(&["status", "--porcelain"]).map(|status| !status.is_empty());
+    println!(
+        "cargo:rustc-env=GIT_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=GIT_DIRTY={}",
+        dirty.map_or("unknown", |dirty| if dirty { "true" } else { "false" })
+    );
+    for path in ["HEAD", "index"] {
+        if let Some(path) = git(&["rev-parse", "--git-path", path]) {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    // So edits to the backend or the SDK it's built against update GIT_DIRTY
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=core/src");
+    println!("cargo:rerun-if-changed=../../crates/aptos-sdk/src");
+
+    let sdk_version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "aptos-sdk"));
+    println!(
+        "cargo:rustc-env=APTOS_SDK_VERSION={}",
+        sdk_version.as_deref().unwrap_or("unknown")
+    );
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string());
+    println!(
+        "cargo:rustc-env=RUSTC_VERSION={}",
+        rustc_version.as_deref().unwrap_or("unknown")
+    );
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/repro.proto").expect("failed to compile proto/repro.proto");
+}
+
+/// Output of a git command, if it succeeded
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+/// Version of `package` in a `Cargo.lock`
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name)?;
+    let version = lines.next()?.strip_prefix("version = \"")?;
+    Some(version.trim_end_matches('"').to_string())
+}
@@ -0,0 +1,98 @@
+//! Benchmarks for the path every stored transaction takes: hex decode, BCS
+//! deserialize, re-serialize and hex encode.
+//!
+//! Inputs are multi-agent transactions whose entry function takes one
+//! `vector<u8>` argument of growing size, so a regression that only shows on
+//! large payloads (a copy per byte, say) stands out next to the small cases.
+//! Run before and after bumping the SDK and compare with Criterion's report.
+
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::types::MultiAgentRawTransaction;
+use aptos_sdk::transaction::{EntryFunction, RawTransaction, TransactionPayload};
+use aptos_sdk::types::{AccountAddress, ChainId, Identifier, MoveModuleId};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use repro_core::reserialize::{self, Strategy};
+use repro_core::storage::hex_bytes;
+use repro_core::transaction::DecodedTransaction;
+use std::hint::black_box;
+
+/// Size of the `vector<u8>` argument in each input
+const PAYLOAD_SIZES: [usize; 4] = [0, 256, 4 * 1024, 64 * 1024];
+
+/// BCS of a multi-agent transaction with two secondary signers and an entry
+/// function argument of `payload_len` bytes
+fn multi_agent_transaction(payload_len: usize) -> Vec<u8> {
+    let module = MoveModuleId::new(
+        AccountAddress::from_hex("0xcafe").unwrap(),
+        Identifier::new("multi_agent").unwrap(),
+    );
+    let payload = TransactionPayload::EntryFunction(EntryFunction::new(
+        module,
+        "store_blob",
+        vec![],
+        vec![aptos_bcs::to_bytes(&vec![0xab_u8; payload_len]).unwrap()],
+    ));
+    let raw_txn = RawTransaction::new(
+        AccountAddress::from_hex("0xa11ce").unwrap(),
+        42,
+        payload,
+        200_000,
+        100,
+        u64::from(u32::MAX),
+        ChainId::testnet(),
+    );
+    let secondary_signers = vec![
+        AccountAddress::from_hex("0xb0b").unwrap(),
+        AccountAddress::from_hex("0xca201").unwrap(),
+    ];
+    aptos_bcs::to_bytes(&MultiAgentRawTransaction::new(raw_txn, secondary_signers)).unwrap()
+}
+
+fn serialization(c: &mut Criterion) {
+    let inputs: Vec<(usize, Vec<u8>)> = PAYLOAD_SIZES
+        .iter()
+        .map(|&size| (size, multi_agent_transaction(size)))
+        .collect();
+
+    let mut group = c.benchmark_group("hex_decode");
+    for (size, bcs) in &inputs {
+        let hex = hex_bytes::encode(bcs, true);
+        group.throughput(Throughput::Bytes(bcs.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &hex, |b, hex| {
+            b.iter(|| hex_bytes::decode(black_box(hex)).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("bcs_deserialize");
+    for (size, bcs) in &inputs {
+        group.throughput(Throughput::Bytes(bcs.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), bcs, |b, bcs| {
+            b.iter(|| DecodedTransaction::decode(black_box(bcs)).unwrap())
+        });
+    }
+    group.finish();
+
+    for strategy in [Strategy::Typed, Strategy::RawTransaction, Strategy::Untyped] {
+        let mut group = c.benchmark_group(format!("reserialize/{}", strategy.as_str()));
+        for (size, bcs) in &inputs {
+            group.throughput(Throughput::Bytes(bcs.len() as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(size), bcs, |b, bcs| {
+                b.iter(|| reserialize::reserialize(strategy, black_box(bcs)).unwrap())
+            });
+        }
+        group.finish();
+    }
+
+    let mut group = c.benchmark_group("hex_encode");
+    for (size, bcs) in &inputs {
+        group.throughput(Throughput::Bytes(bcs.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), bcs, |b, bcs| {
+            b.iter(|| hex_bytes::encode(black_box(bcs), true))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialization);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+-- Stored transactions and their secondary signatures
+CREATE TABLE IF NOT EXISTS transactions (
+    transaction_id TEXT PRIMARY KEY,
+    raw_bcs_hex TEXT NOT NULL,
+    secondary_signature_hex TEXT,
+    stored_at BIGINT NOT NULL,
+    -- Decoded at ingest (long hex form); NULL if the BCS could not be decoded
+    sender_address TEXT
+);
+
+CREATE INDEX IF NOT EXISTS transactions_sender_address_idx
+    ON transactions (sender_address);
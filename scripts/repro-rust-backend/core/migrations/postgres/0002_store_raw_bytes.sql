@@ -0,0 +1,17 @@
+-- Store payloads as raw bytes instead of hex text, remembering whether the
+-- submitted BCS hex had a 0x prefix so it can be returned unchanged
+ALTER TABLE transactions
+    ADD COLUMN raw_bcs BYTEA,
+    ADD COLUMN hex_prefixed BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN secondary_signature BYTEA;
+
+UPDATE transactions SET
+    raw_bcs = decode(regexp_replace(raw_bcs_hex, '^0x', ''), 'hex'),
+    hex_prefixed = raw_bcs_hex LIKE '0x%',
+    secondary_signature = decode(regexp_replace(secondary_signature_hex, '^0x', ''), 'hex');
+
+ALTER TABLE transactions
+    ALTER COLUMN raw_bcs SET NOT NULL,
+    ALTER COLUMN hex_prefixed DROP DEFAULT,
+    DROP COLUMN raw_bcs_hex,
+    DROP COLUMN secondary_signature_hex;
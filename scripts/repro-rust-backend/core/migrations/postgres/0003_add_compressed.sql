@@ -0,0 +1,2 @@
+-- Whether raw_bcs holds zstd-compressed bytes
+ALTER TABLE transactions ADD COLUMN compressed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+-- Decoded at ingest alongside sender_address; NULL if the BCS could not be decoded
+ALTER TABLE transactions ADD COLUMN sequence_number BIGINT;
+
+-- Serves lookups by sender alone as well as by sender and sequence number range
+DROP INDEX IF EXISTS transactions_sender_address_idx;
+CREATE INDEX transactions_sender_address_sequence_number_idx
+    ON transactions (sender_address, sequence_number);
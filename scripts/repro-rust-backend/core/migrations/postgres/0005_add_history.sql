@@ -0,0 +1,2 @@
+-- Per-transaction event history (stored, signature added, retrieved, ...), oldest first
+ALTER TABLE transactions ADD COLUMN history JSONB NOT NULL DEFAULT '[]';
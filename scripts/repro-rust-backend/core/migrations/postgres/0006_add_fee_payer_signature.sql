@@ -0,0 +1,2 @@
+-- Fee payer's AccountAuthenticator, for sponsored (fee payer) transactions
+ALTER TABLE transactions ADD COLUMN fee_payer_signature BYTEA;
@@ -0,0 +1,5 @@
+-- Secondary signatures keyed by long-form signer address, as a JSON object of
+-- hex strings. Earlier signatures don't record their signer and are dropped.
+ALTER TABLE transactions
+    ADD COLUMN secondary_signatures JSONB NOT NULL DEFAULT '{}',
+    DROP COLUMN secondary_signature;
@@ -0,0 +1,3 @@
+-- Whether the stored BCS was canonical at ingest; NULL if it follows no
+-- transaction layout or was stored before the check
+ALTER TABLE transactions ADD COLUMN canonical BOOLEAN;
@@ -0,0 +1,4 @@
+-- Signers other than the sender, decoded at ingest, as a JSON array of
+-- long-form addresses (and "fee_payer"); NULL if the BCS could not be decoded
+-- or was stored before signers were recorded
+ALTER TABLE transactions ADD COLUMN required_signers JSONB;
@@ -0,0 +1,3 @@
+-- Secondary signatures that complete the session (K of N); NULL means every
+-- secondary signer's
+ALTER TABLE transactions ADD COLUMN quorum INTEGER;
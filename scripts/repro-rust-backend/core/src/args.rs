@@ -0,0 +1,389 @@
+//! Entry function and script argument decoding.
+//!
+//! Entry function arguments are stored as opaque BCS blobs; their types are
+//! only known from the function's signature. With `FULLNODE_URL` set the
+//! signature comes from the module's on-chain ABI, otherwise from a short list
+//! of framework functions the frontend commonly calls. Knowing the types, each
+//! blob is decoded to JSON the way the Aptos REST API renders Move values
+//! (integers of 64 bits and up as strings, `vector<u8>` as hex), so what the
+//! TS SDK encoded can be compared with what the Rust SDK expects.
+
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::transaction::payload::{EntryFunction, ScriptArgument};
+use aptos_sdk::types::TypeTag;
+use aptos_sdk::AccountAddress;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::debug;
+use utoipa::ToSchema;
+
+/// Where the argument types of an entry function came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamSource {
+    /// The module ABI published on the configured fullnode
+    Abi,
+    /// The backend's list of well-known framework functions
+    Known,
+}
+
+/// The non-signer parameter types of an entry function, with any generic
+/// parameters replaced by the call's type arguments; `None` for a parameter
+/// whose type couldn't be parsed
+pub struct ParamTypes {
+    pub source: ParamSource,
+    pub types: Vec<Option<TypeTag>>,
+}
+
+/// Framework entry functions and their parameters (after the signer), in ABI
+/// notation
+const KNOWN_FUNCTIONS: &[(&str, &str, &[&str])] = &[
+    ("aptos_account", "transfer", &["address", "u64"]),
+    ("aptos_account", "transfer_coins", &["address", "u64"]),
+    ("aptos_account", "batch_transfer", &["vector<address>", "vector<u64>"]),
+    ("aptos_account", "create_account", &["address"]),
+    ("coin", "transfer", &["address", "u64"]),
+    (
+        "primary_fungible_store",
+        "transfer",
+        &["0x1::object::Object<T0>", "address", "u64"],
+    ),
+];
+
+/// Look up the parameter types of `entry_function`: from the fullnode's ABI
+/// if one is configured and has the module, else from [`KNOWN_FUNCTIONS`]
+pub async fn param_types(
+    entry_function: &EntryFunction,
+    fullnode: Option<&FullnodeClient>,
+) -> Option<ParamTypes> {
+    let type_args = &entry_function.type_args;
+    if let Some(fullnode) = fullnode {
+        match abi_params(fullnode, entry_function).await {
+            Ok(Some(params)) => {
+                return Some(ParamTypes {
+                    source: ParamSource::Abi,
+                    types: params
+                        .iter()
+                        .map(|param| parse_param(param, type_args))
+                        .collect(),
+                });
+            }
+            Ok(None) => debug!(
+                "{}::{} is not in the module ABI",
+                entry_function.module, entry_function.function
+            ),
+            Err(e) => debug!("Failed to fetch ABI of {}: {}", entry_function.module, e),
+        }
+    }
+
+    if entry_function.module.address != AccountAddress::ONE {
+        return None;
+    }
+    KNOWN_FUNCTIONS
+        .iter()
+        .find(|(module, function, _)| {
+            entry_function.module.name.as_str() == *module && entry_function.function == *function
+        })
+        .map(|(_, _, params)| ParamTypes {
+            source: ParamSource::Known,
+            types: params
+                .iter()
+                .map(|param| parse_param(param, type_args))
+                .collect(),
+        })
+}
+
+/// The function's parameters from the module ABI, without leading signers;
+/// `None` if the module has no such function
+async fn abi_params(
+    fullnode: &FullnodeClient,
+    entry_function: &EntryFunction,
+) -> aptos_sdk::AptosResult<Option<Vec<String>>> {
+    let module = fullnode
+        .get_account_module(
+            entry_function.module.address,
+            entry_function.module.name.as_str(),
+        )
+        .await?
+        .data;
+    Ok(module.abi.and_then(|abi| {
+        abi.exposed_functions
+            .into_iter()
+            .find(|function| function.name == entry_function.function)
+            .map(|function| {
+                function
+                    .params
+                    .into_iter()
+                    .filter(|param| param != "signer" && param != "&signer")
+                    .collect()
+            })
+    }))
+}
+
+/// Parse an ABI parameter type, replacing generic parameters (`T0`, `T1`, ...)
+/// with the call's type arguments
+fn parse_param(param: &str, type_args: &[TypeTag]) -> Option<TypeTag> {
+    let mut substituted = String::with_capacity(param.len());
+    let mut token = String::new();
+    for c in param.chars().chain(std::iter::once(',')) {
+        if matches!(c, '<' | '>' | ',' | ' ') {
+            match token.strip_prefix('T').map(str::parse::<usize>) {
+                Some(Ok(index)) => {
+                    substituted.push_str(&crate::type_tag::canonical(type_args.get(index)?))
+                }
+                _ => substituted.push_str(&token),
+            }
+            token.clear();
+            substituted.push(c);
+        } else {
+            token.push(c);
+        }
+    }
+    substituted.pop();
+    crate::type_tag::parse(&substituted).ok()
+}
+
+/// A script argument, which carries its own type, as `(type, value)`; a
+/// `Serialized` argument has no type and its value is the BCS as hex
+pub fn script_arg(arg: &ScriptArgument) -> (Option<&'static str>, Value) {
+    match arg {
+        ScriptArgument::U8(value) => (Some("u8"), Value::from(*value)),
+        ScriptArgument::U16(value) => (Some("u16"), Value::from(*value)),
+        ScriptArgument::U32(value) => (Some("u32"), Value::from(*value)),
+        ScriptArgument::U64(value) => (Some("u64"), Value::String(value.to_string())),
+        ScriptArgument::U128(value) => (Some("u128"), Value::String(value.to_string())),
+        ScriptArgument::U256(bytes) => (Some("u256"), Value::String(u256_to_string(*bytes))),
+        ScriptArgument::Address(address) => {
+            (Some("address"), Value::String(address.to_long_string()))
+        }
+        ScriptArgument::U8Vector(bytes) => (Some("vector<u8>"), Value::String(hex(bytes))),
+        ScriptArgument::Bool(value) => (Some("bool"), Value::Bool(*value)),
+        ScriptArgument::Serialized(bytes) => (None, Value::String(hex(bytes))),
+    }
+}
+
+/// Decode one BCS-encoded argument of type `type_tag`; `None` if the type
+/// can't be rendered or the bytes don't decode as exactly one value of it
+pub fn decode(type_tag: &TypeTag, bytes: &[u8]) -> Option<Value> {
+    let mut input = bytes;
+    let value = decode_value(type_tag, &mut input)?;
+    input.is_empty().then_some(value)
+}
+
+fn decode_value(type_tag: &TypeTag, input: &mut &[u8]) -> Option<Value> {
+    let value = match type_tag {
+        TypeTag::Bool => match take::<1>(input)? {
+            [0] => Value::Bool(false),
+            [1] => Value::Bool(true),
+            _ => return None,
+        },
+        TypeTag::U8 => Value::from(u8::from_le_bytes(take(input)?)),
+        TypeTag::U16 => Value::from(u16::from_le_bytes(take(input)?)),
+        TypeTag::U32 => Value::from(u32::from_le_bytes(take(input)?)),
+        TypeTag::U64 => Value::String(u64::from_le_bytes(take(input)?).to_string()),
+        TypeTag::U128 => Value::String(u128::from_le_bytes(take(input)?).to_string()),
+        TypeTag::U256 => Value::String(u256_to_string(take(input)?)),
+        TypeTag::I8 => Value::from(i8::from_le_bytes(take(input)?)),
+        TypeTag::I16 => Value::from(i16::from_le_bytes(take(input)?)),
+        TypeTag::I32 => Value::from(i32::from_le_bytes(take(input)?)),
+        TypeTag::I64 => Value::String(i64::from_le_bytes(take(input)?).to_string()),
+        TypeTag::I128 => Value::String(i128::from_le_bytes(take(input)?).to_string()),
+        TypeTag::I256 | TypeTag::Signer => return None,
+        TypeTag::Address => Value::String(address(input)?),
+        TypeTag::Vector(element) if **element == TypeTag::U8 => {
+            let len = uleb128(input)?;
+            Value::String(hex(take_slice(input, len)?))
+        }
+        TypeTag::Vector(element) => {
+            let len = uleb128(input)?;
+            // Every element is at least one byte, so a bogus length fails fast
+            if len > input.len() {
+                return None;
+            }
+            Value::Array(
+                (0..len)
+                    .map(|_| decode_value(element, input))
+                    .collect::<Option<_>>()?,
+            )
+        }
+        TypeTag::Struct(tag) if tag.address == AccountAddress::ONE => {
+            match (tag.module.as_str(), tag.name.as_str(), tag.type_args.as_slice()) {
+                ("string", "String", []) => {
+                    let len = uleb128(input)?;
+                    Value::String(String::from_utf8(take_slice(input, len)?.to_vec()).ok()?)
+                }
+                ("object", "Object", [_]) => Value::String(address(input)?),
+                ("option", "Option", [inner]) => match uleb128(input)? {
+                    0 => Value::Null,
+                    1 => decode_value(inner, input)?,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        TypeTag::Struct(_) => return None,
+    };
+    Some(value)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    crate::storage::hex_bytes::encode(bytes, true)
+}
+
+fn take<const N: usize>(input: &mut &[u8]) -> Option<[u8; N]> {
+    take_slice(input, N)?.try_into().ok()
+}
+
+fn take_slice<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Some(head)
+}
+
+fn address(input: &mut &[u8]) -> Option<String> {
+    take(input).map(|bytes| AccountAddress::new(bytes).to_long_string())
+}
+
+/// A BCS length prefix or variant index
+pub fn uleb128(input: &mut &[u8]) -> Option<usize> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let [byte] = take::<1>(input)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return usize::try_from(value).ok();
+        }
+    }
+    None
+}
+
+/// Decimal rendering of a little-endian `u256`
+fn u256_to_string(mut bytes: [u8; 32]) -> String {
+    let mut digits = Vec::new();
+    while bytes.iter().any(|&byte| byte != 0) {
+        // Long division by 10, most significant byte first
+        let mut remainder = 0u16;
+        for byte in bytes.iter_mut().rev() {
+            let current = (remainder << 8) | u16::from(*byte);
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_sdk::aptos_bcs;
+    use serde_json::json;
+
+    fn type_tag(s: &str) -> TypeTag {
+        crate::type_tag::parse(s).unwrap()
+    }
+
+    #[test]
+    fn values_render_as_the_rest_api_does() {
+        let cases = [
+            ("bool", aptos_bcs::to_bytes(&true).unwrap(), json!(true)),
+            ("u8", vec![7], json!(7)),
+            ("u64", aptos_bcs::to_bytes(&5u64).unwrap(), json!("5")),
+            ("i32", aptos_bcs::to_bytes(&-3i32).unwrap(), json!(-3)),
+            (
+                "u128",
+                aptos_bcs::to_bytes(&u128::MAX).unwrap(),
+                json!(u128::MAX.to_string()),
+            ),
+            ("vector<u8>", vec![2, 0xab, 0xcd], json!("0xabcd")),
+            (
+                "vector<u64>",
+                aptos_bcs::to_bytes(&vec![1u64, 2]).unwrap(),
+                json!(["1", "2"]),
+            ),
+            (
+                "0x1::string::String",
+                aptos_bcs::to_bytes("hi").unwrap(),
+                json!("hi"),
+            ),
+            ("0x1::option::Option<u8>", vec![0], json!(null)),
+            ("0x1::option::Option<u8>", vec![1, 9], json!(9)),
+        ];
+        for (move_type, bytes, expected) in cases {
+            assert_eq!(
+                decode(&type_tag(move_type), &bytes),
+                Some(expected),
+                "{}",
+                move_type
+            );
+        }
+    }
+
+    #[test]
+    fn u256_renders_in_decimal() {
+        let mut bytes = [0u8; 32];
+        assert_eq!(u256_to_string(bytes), "0");
+        bytes[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes[8] = 1;
+        // 2^64 + 2^64 - 1
+        assert_eq!(u256_to_string(bytes), "36893488147419103231");
+        assert_eq!(
+            u256_to_string([0xff; 32]),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn malformed_values_dont_decode() {
+        let u64_tag = type_tag("u64");
+        // Short, and with a byte left over
+        assert_eq!(decode(&u64_tag, &[1; 7]), None);
+        assert_eq!(decode(&u64_tag, &[1; 9]), None);
+        assert_eq!(decode(&TypeTag::Bool, &[2]), None);
+        // A vector claiming more elements than there are bytes
+        assert_eq!(decode(&type_tag("vector<u64>"), &[0x7f, 0]), None);
+        assert_eq!(decode(&type_tag("0x1::string::String"), &[1, 0xff]), None);
+        assert_eq!(decode(&type_tag("0x1::coin::Coin<u8>"), &[0]), None);
+    }
+
+    #[test]
+    fn generic_params_take_the_call_type_args() {
+        let coin = type_tag("0x1::aptos_coin::AptosCoin");
+        assert_eq!(
+            parse_param("0x1::object::Object<T0>", std::slice::from_ref(&coin)),
+            Some(type_tag("0x1::object::Object<0x1::aptos_coin::AptosCoin>"))
+        );
+        assert_eq!(
+            parse_param("vector<T1>", &[TypeTag::U8, TypeTag::Bool]),
+            Some(type_tag("vector<bool>"))
+        );
+        assert_eq!(parse_param("T1", &[TypeTag::U8]), None);
+    }
+
+    #[tokio::test]
+    async fn known_functions_are_typed_without_a_fullnode() {
+        let transfer = EntryFunction::apt_transfer(AccountAddress::ONE, 1).unwrap();
+        let params = param_types(&transfer, None).await.unwrap();
+        assert_eq!(params.source, ParamSource::Known);
+        assert_eq!(params.types, [Some(TypeTag::Address), Some(TypeTag::U64)]);
+
+        let mut other = transfer;
+        other.module.address = AccountAddress::from_hex("0x2").unwrap();
+        assert!(param_types(&other, None).await.is_none());
+    }
+
+    #[test]
+    fn uleb128_reads_multi_byte_values() {
+        let mut input: &[u8] = &[0x80, 0x01, 0xff];
+        assert_eq!(uleb128(&mut input), Some(128));
+        assert_eq!(input, [0xff]);
+        assert_eq!(uleb128(&mut &[0x80][..]), None);
+    }
+}
@@ -0,0 +1,613 @@
+//! Account authenticators as the chain lays them out.
+//!
+//! Signatures are stored as the BCS `AccountAuthenticator` the frontend sends
+//! and put into the assembled `SignedTransaction` unchanged. The SDK's own
+//! `AccountAuthenticator` only models the Ed25519 variants faithfully, so
+//! this module mirrors aptos-core's layout, with plain types only, to check
+//! what is stored and to assemble from it, including `SingleKey`
+//! authenticators whose key is a keyless (AIP-61) or federated keyless one.
+//! The `untyped` reserialize strategy uses the same mirror.
+//!
+//! Keyless signatures carry a zero-knowledge proof (or an OpenID signature)
+//! over the signer's JWT; they are checked for shape only and verified by the
+//! chain on submission.
+//!
+//! A signer may declare its [`Scheme`] when storing a signature, so a wallet
+//! that signed with another key than the one expected is caught there rather
+//! than as an `INVALID_SIGNATURE` on submission.
+//!
+//! A `MultiEd25519` or `MultiKey` signer is an account of several keys, whose
+//! holders sign separately: each sends the account's authenticator with only
+//! their own signature in it, and these partial authenticators are
+//! [merged](AccountAuthenticator::merge) until the account's
+//! [threshold](AccountAuthenticator::threshold) is met.
+
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::types::RawTransaction;
+use aptos_sdk::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+const ED25519_PUBLIC_KEY_LENGTH: usize = 32;
+const ED25519_SIGNATURE_LENGTH: usize = 64;
+/// A `MultiEd25519` signature ends with a bitmap of this many bytes
+const MULTI_ED25519_BITMAP_LENGTH: usize = 4;
+/// Most keys a `MultiEd25519` or `MultiKey` account may have
+const MAX_MULTI_KEYS: usize = 32;
+
+/// How an account authenticator signs: its variant and, for `SingleKey`,
+/// the kind of key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scheme {
+    /// The legacy Ed25519 authenticator
+    Ed25519,
+    MultiEd25519,
+    /// `SingleKey` with an Ed25519 key
+    SingleKeyEd25519,
+    /// `SingleKey` with a secp256k1 key
+    Secp256k1Ecdsa,
+    /// `SingleKey` with a secp256r1 key, signing through WebAuthn (passkeys)
+    Secp256r1Ecdsa,
+    Keyless,
+    FederatedKeyless,
+    MultiKey,
+    /// `NoAccountAuthenticator`, which only simulations accept
+    #[serde(rename = "none")]
+    NoAccountAuthenticator,
+}
+
+impl Scheme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scheme::Ed25519 => "ed25519",
+            Scheme::MultiEd25519 => "multi_ed25519",
+            Scheme::SingleKeyEd25519 => "single_key_ed25519",
+            Scheme::Secp256k1Ecdsa => "secp256k1_ecdsa",
+            Scheme::Secp256r1Ecdsa => "secp256r1_ecdsa",
+            Scheme::Keyless => "keyless",
+            Scheme::FederatedKeyless => "federated_keyless",
+            Scheme::MultiKey => "multi_key",
+            Scheme::NoAccountAuthenticator => "none",
+        }
+    }
+}
+
+/// `AccountAuthenticator`. Variant order is significant: BCS encodes enum
+/// variants by index. Abstract account authenticators aren't mirrored, so
+/// they don't decode.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)] // aptos-core's names
+pub enum AccountAuthenticator {
+    Ed25519 {
+        public_key: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    MultiEd25519 {
+        public_key: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    SingleKey {
+        authenticator: SingleKeyAuthenticator,
+    },
+    MultiKey {
+        authenticator: MultiKeyAuthenticator,
+    },
+    NoAccountAuthenticator,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SingleKeyAuthenticator {
+    pub public_key: AnyPublicKey,
+    pub signature: AnySignature,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiKeyAuthenticator {
+    pub public_keys: MultiKey,
+    pub signatures: Vec<AnySignature>,
+    /// Which of `public_keys` the `signatures` belong to, one bit per key
+    pub signatures_bitmap: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiKey {
+    pub public_keys: Vec<AnyPublicKey>,
+    pub signatures_required: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnyPublicKey {
+    Ed25519 {
+        public_key: Vec<u8>,
+    },
+    Secp256k1Ecdsa {
+        public_key: Vec<u8>,
+    },
+    Secp256r1Ecdsa {
+        public_key: Vec<u8>,
+    },
+    Keyless {
+        public_key: KeylessPublicKey,
+    },
+    FederatedKeyless {
+        public_key: FederatedKeylessPublicKey,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnySignature {
+    Ed25519 {
+        signature: Vec<u8>,
+    },
+    Secp256k1Ecdsa {
+        signature: Vec<u8>,
+    },
+    WebAuthn {
+        signature: PartialAuthenticatorAssertionResponse,
+    },
+    Keyless {
+        signature: Box<KeylessSignature>,
+    },
+}
+
+/// Identifies a keyless account: the OIDC provider and a commitment to the
+/// user's id and the app's client id
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeylessPublicKey {
+    /// The JWT's `iss`, e.g. `https://accounts.google.com`
+    pub iss_val: String,
+    pub idc: Vec<u8>,
+}
+
+/// A keyless public key whose JWKs are published at `jwk_addr` rather than
+/// by the framework
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederatedKeylessPublicKey {
+    pub jwk_addr: [u8; 32],
+    pub pk: KeylessPublicKey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeylessSignature {
+    pub cert: EphemeralCertificate,
+    pub jwt_header_json: String,
+    /// When the ephemeral key pair expires, in Unix seconds
+    pub exp_date_secs: u64,
+    pub ephemeral_pubkey: EphemeralPublicKey,
+    /// The ephemeral key's signature over the transaction
+    pub ephemeral_signature: EphemeralSignature,
+}
+
+/// Binds the ephemeral key to the JWT
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EphemeralCertificate {
+    ZeroKnowledgeSig(ZeroKnowledgeSig),
+    OpenIdSig(OpenIdSig),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZeroKnowledgeSig {
+    pub proof: ZeroKnowledgeProof,
+    pub exp_horizon_secs: u64,
+    pub extra_field: Option<String>,
+    pub override_aud_val: Option<String>,
+    pub training_wheels_signature: Option<EphemeralSignature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZeroKnowledgeProof {
+    Groth16(Groth16Proof),
+}
+
+/// Compressed BN254 points, fixed-size and so without length prefixes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Groth16Proof {
+    pub a: [u8; 32],
+    /// A G2 point is 64 bytes; serde arrays stop at 32
+    pub b: ([u8; 32], [u8; 32]),
+    pub c: [u8; 32],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenIdSig {
+    pub jwt_sig: Vec<u8>,
+    pub jwt_payload_json: String,
+    pub uid_key: String,
+    pub epk_blinder: Vec<u8>,
+    pub pepper: [u8; 31],
+    pub idc_aud_val: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EphemeralPublicKey {
+    Ed25519 { public_key: Vec<u8> },
+    Secp256r1Ecdsa { public_key: Vec<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EphemeralSignature {
+    Ed25519 {
+        signature: Vec<u8>,
+    },
+    WebAuthn {
+        signature: PartialAuthenticatorAssertionResponse,
+    },
+}
+
+/// A passkey's signature
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialAuthenticatorAssertionResponse {
+    pub signature: AssertionSignature,
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssertionSignature {
+    Secp256r1Ecdsa { signature: Vec<u8> },
+}
+
+impl AccountAuthenticator {
+    /// Decode a BCS `AccountAuthenticator`, which must use every byte
+    pub fn decode(bytes: &[u8]) -> Result<Self, aptos_bcs::Error> {
+        aptos_bcs::from_bytes(bytes)
+    }
+
+    /// The signature scheme, from the variant and the `SingleKey` key
+    pub fn scheme(&self) -> Scheme {
+        match self {
+            AccountAuthenticator::Ed25519 { .. } => Scheme::Ed25519,
+            AccountAuthenticator::MultiEd25519 { .. } => Scheme::MultiEd25519,
+            AccountAuthenticator::SingleKey { authenticator } => match authenticator.public_key {
+                AnyPublicKey::Ed25519 { .. } => Scheme::SingleKeyEd25519,
+                AnyPublicKey::Secp256k1Ecdsa { .. } => Scheme::Secp256k1Ecdsa,
+                AnyPublicKey::Secp256r1Ecdsa { .. } => Scheme::Secp256r1Ecdsa,
+                AnyPublicKey::Keyless { .. } => Scheme::Keyless,
+                AnyPublicKey::FederatedKeyless { .. } => Scheme::FederatedKeyless,
+            },
+            AccountAuthenticator::MultiKey { .. } => Scheme::MultiKey,
+            AccountAuthenticator::NoAccountAuthenticator => Scheme::NoAccountAuthenticator,
+        }
+    }
+
+    /// The keyless public key, for keyless and federated keyless signers
+    pub fn keyless(&self) -> Option<&KeylessPublicKey> {
+        let AccountAuthenticator::SingleKey { authenticator } = self else {
+            return None;
+        };
+        match &authenticator.public_key {
+            AnyPublicKey::Keyless { public_key } => Some(public_key),
+            AnyPublicKey::FederatedKeyless { public_key } => Some(&public_key.pk),
+            _ => None,
+        }
+    }
+}
+
+/// A `MultiEd25519` or `MultiKey` authenticator's signatures against its
+/// account's threshold
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Threshold {
+    /// Signatures collected
+    pub signed: usize,
+    /// Signatures the account needs
+    pub required: usize,
+    /// Keys of the account
+    pub keys: usize,
+}
+
+impl Threshold {
+    pub fn met(&self) -> bool {
+        self.signed >= self.required
+    }
+}
+
+impl AccountAuthenticator {
+    /// Signatures held and needed by a `MultiEd25519` or `MultiKey`
+    /// authenticator; `None` for single-signer ones, and an error if the
+    /// keys, signatures and bitmap don't add up
+    pub fn threshold(&self) -> Result<Option<Threshold>, String> {
+        let threshold = match self {
+            AccountAuthenticator::MultiEd25519 {
+                public_key,
+                signature,
+            } => {
+                let parts = MultiEd25519Parts::parse(public_key, signature)?;
+                Threshold {
+                    signed: parts.signatures.len(),
+                    required: parts.threshold as usize,
+                    keys: parts.public_keys.len(),
+                }
+            }
+            AccountAuthenticator::MultiKey { authenticator } => Threshold {
+                signed: authenticator.signatures_by_index()?.len(),
+                required: authenticator.public_keys.signatures_required as usize,
+                keys: authenticator.public_keys.public_keys.len(),
+            },
+            _ => return Ok(None),
+        };
+        if threshold.required == 0 || threshold.required > threshold.keys {
+            return Err(format!(
+                "threshold of {} signatures for {} keys",
+                threshold.required, threshold.keys
+            ));
+        }
+        Ok(Some(threshold))
+    }
+
+    /// Add the signatures of `partial`, another partial authenticator of the
+    /// same `MultiEd25519` or `MultiKey` account; where both hold a signature
+    /// by the same key, `partial`'s replaces this one's
+    pub fn merge(&self, partial: &Self) -> Result<Self, String> {
+        match (self, partial) {
+            (
+                AccountAuthenticator::MultiEd25519 {
+                    public_key,
+                    signature,
+                },
+                AccountAuthenticator::MultiEd25519 {
+                    public_key: partial_public_key,
+                    signature: partial_signature,
+                },
+            ) => {
+                if public_key != partial_public_key {
+                    return Err("partial signature is for another account's keys".to_string());
+                }
+                let mut merged = MultiEd25519Parts::parse(public_key, signature)?;
+                let partial = MultiEd25519Parts::parse(partial_public_key, partial_signature)?;
+                merged.signatures.extend(partial.signatures);
+                Ok(AccountAuthenticator::MultiEd25519 {
+                    public_key: public_key.clone(),
+                    signature: merged.signature(),
+                })
+            }
+            (
+                AccountAuthenticator::MultiKey { authenticator },
+                AccountAuthenticator::MultiKey {
+                    authenticator: partial,
+                },
+            ) => {
+                if authenticator.public_keys != partial.public_keys {
+                    return Err("partial signature is for another account's keys".to_string());
+                }
+                let mut signatures = authenticator.signatures_by_index()?;
+                signatures.extend(partial.signatures_by_index()?);
+                let bitmap_len = authenticator
+                    .signatures_bitmap
+                    .len()
+                    .max(partial.signatures_bitmap.len());
+                Ok(AccountAuthenticator::MultiKey {
+                    authenticator: MultiKeyAuthenticator {
+                        public_keys: authenticator.public_keys.clone(),
+                        signatures_bitmap: bitmap(signatures.keys().copied(), bitmap_len),
+                        signatures: signatures.into_values().cloned().collect(),
+                    },
+                })
+            }
+            _ => Err(format!(
+                "a {} signature can't be added to a {} one",
+                partial.scheme().as_str(),
+                self.scheme().as_str()
+            )),
+        }
+    }
+
+    /// The keys of a `MultiEd25519` or `MultiKey` authenticator that signed,
+    /// each with its signature, as they would appear in a `SingleKey` one;
+    /// empty for single-signer authenticators
+    pub fn signatures_by_key(&self) -> Result<Vec<(AnyPublicKey, AnySignature)>, String> {
+        match self {
+            AccountAuthenticator::MultiEd25519 {
+                public_key,
+                signature,
+            } => {
+                let parts = MultiEd25519Parts::parse(public_key, signature)?;
+                Ok(parts
+                    .signatures
+                    .iter()
+                    .map(|(&index, signature)| {
+                        (
+                            AnyPublicKey::Ed25519 {
+                                public_key: parts.public_keys[index].to_vec(),
+                            },
+                            AnySignature::Ed25519 {
+                                signature: signature.to_vec(),
+                            },
+                        )
+                    })
+                    .collect())
+            }
+            AccountAuthenticator::MultiKey { authenticator } => Ok(authenticator
+                .signatures_by_index()?
+                .into_iter()
+                .map(|(index, signature)| {
+                    (
+                        authenticator.public_keys.public_keys[index].clone(),
+                        signature.clone(),
+                    )
+                })
+                .collect()),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Whether a stored authenticator is all its signer needs to give: any but a
+/// `MultiEd25519` or `MultiKey` one short of its threshold. Bytes that don't
+/// decode were accepted as they are, and so count as complete.
+pub fn complete(bytes: &[u8]) -> bool {
+    AccountAuthenticator::decode(bytes)
+        .ok()
+        .and_then(|authenticator| authenticator.threshold().ok().flatten())
+        .is_none_or(|threshold| threshold.met())
+}
+
+impl MultiKeyAuthenticator {
+    /// Signatures keyed by the index of the key they're by, checked against
+    /// the bitmap and the keys
+    fn signatures_by_index(&self) -> Result<BTreeMap<usize, &AnySignature>, String> {
+        let indices = bits(&self.signatures_bitmap);
+        check_bitmap(
+            &indices,
+            self.signatures.len(),
+            self.public_keys.public_keys.len(),
+        )?;
+        Ok(indices.into_iter().zip(&self.signatures).collect())
+    }
+}
+
+/// A `MultiEd25519` authenticator split into its parts: on chain, the keys
+/// are packed with the threshold and the signatures with their bitmap
+struct MultiEd25519Parts<'a> {
+    public_keys: Vec<&'a [u8]>,
+    threshold: u8,
+    /// Keyed by the index of the key they're by
+    signatures: BTreeMap<usize, &'a [u8]>,
+}
+
+impl<'a> MultiEd25519Parts<'a> {
+    fn parse(public_key: &'a [u8], signature: &'a [u8]) -> Result<Self, String> {
+        let Some((&threshold, keys)) = public_key.split_last() else {
+            return Err("empty MultiEd25519 public key".to_string());
+        };
+        if keys.is_empty()
+            || keys.len() % ED25519_PUBLIC_KEY_LENGTH != 0
+            || keys.len() / ED25519_PUBLIC_KEY_LENGTH > MAX_MULTI_KEYS
+        {
+            return Err(format!(
+                "MultiEd25519 public key of {} bytes isn't 1 to {} keys and a threshold",
+                public_key.len(),
+                MAX_MULTI_KEYS
+            ));
+        }
+        let public_keys: Vec<&[u8]> = keys.chunks(ED25519_PUBLIC_KEY_LENGTH).collect();
+
+        let Some(split) = signature.len().checked_sub(MULTI_ED25519_BITMAP_LENGTH) else {
+            return Err(format!(
+                "MultiEd25519 signature of {} bytes has no bitmap",
+                signature.len()
+            ));
+        };
+        let (signatures, bitmap) = signature.split_at(split);
+        if signatures.len() % ED25519_SIGNATURE_LENGTH != 0 {
+            return Err(format!(
+                "MultiEd25519 signatures of {} bytes aren't whole signatures",
+                signatures.len()
+            ));
+        }
+        let signatures: Vec<&[u8]> = signatures.chunks(ED25519_SIGNATURE_LENGTH).collect();
+        let indices = bits(bitmap);
+        check_bitmap(&indices, signatures.len(), public_keys.len())?;
+
+        Ok(Self {
+            public_keys,
+            threshold,
+            signatures: indices.into_iter().zip(signatures).collect(),
+        })
+    }
+
+    /// The signatures in key order, followed by their bitmap
+    fn signature(&self) -> Vec<u8> {
+        let mut signature: Vec<u8> = self
+            .signatures
+            .values()
+            .copied()
+            .flatten()
+            .copied()
+            .collect();
+        signature.extend(bitmap(
+            self.signatures.keys().copied(),
+            MULTI_ED25519_BITMAP_LENGTH,
+        ));
+        signature
+    }
+}
+
+/// Indices of the bits set in `bitmap`, most significant bit first
+fn bits(bitmap: &[u8]) -> Vec<usize> {
+    (0..bitmap.len() * 8)
+        .filter(|index| bitmap[index / 8] & (0x80 >> (index % 8)) != 0)
+        .collect()
+}
+
+/// A bitmap of `len` bytes with the bits at `indices` set
+fn bitmap(indices: impl Iterator<Item = usize>, len: usize) -> Vec<u8> {
+    let mut bitmap = vec![0; len];
+    for index in indices {
+        bitmap[index / 8] |= 0x80 >> (index % 8);
+    }
+    bitmap
+}
+
+/// Check that a bitmap with `indices` set names one key per signature
+fn check_bitmap(indices: &[usize], signatures: usize, keys: usize) -> Result<(), String> {
+    if indices.len() != signatures {
+        return Err(format!(
+            "bitmap has {} bits set for {} signatures",
+            indices.len(),
+            signatures
+        ));
+    }
+    if let Some(&index) = indices.iter().find(|&&index| index >= keys) {
+        return Err(format!("bitmap names key {} of {}", index, keys));
+    }
+    Ok(())
+}
+
+/// `TransactionAuthenticator`, for the variants assembly builds; the others
+/// only hold their place so these get the chain's variant indices
+#[derive(Serialize)]
+#[allow(dead_code)]
+enum TransactionAuthenticator<'a> {
+    Ed25519,
+    MultiEd25519,
+    MultiAgent {
+        sender: &'a AccountAuthenticator,
+        secondary_signer_addresses: &'a [AccountAddress],
+        secondary_signers: &'a [AccountAuthenticator],
+    },
+    FeePayer {
+        sender: &'a AccountAuthenticator,
+        secondary_signer_addresses: &'a [AccountAddress],
+        secondary_signers: &'a [AccountAuthenticator],
+        fee_payer_address: AccountAddress,
+        fee_payer_signer: &'a AccountAuthenticator,
+    },
+}
+
+/// `SignedTransaction`
+#[derive(Serialize)]
+struct SignedTransaction<'a> {
+    raw_txn: &'a RawTransaction,
+    authenticator: TransactionAuthenticator<'a>,
+}
+
+/// BCS of the `SignedTransaction` of a multi-agent transaction, or of a fee
+/// payer one with `fee_payer`
+pub fn signed_transaction(
+    raw_txn: &RawTransaction,
+    sender: &AccountAuthenticator,
+    secondary_signer_addresses: &[AccountAddress],
+    secondary_signers: &[AccountAuthenticator],
+    fee_payer: Option<(AccountAddress, &AccountAuthenticator)>,
+) -> Result<Vec<u8>, aptos_bcs::Error> {
+    let authenticator = match fee_payer {
+        Some((fee_payer_address, fee_payer_signer)) => TransactionAuthenticator::FeePayer {
+            sender,
+            secondary_signer_addresses,
+            secondary_signers,
+            fee_payer_address,
+            fee_payer_signer,
+        },
+        None => TransactionAuthenticator::MultiAgent {
+            sender,
+            secondary_signer_addresses,
+            secondary_signers,
+        },
+    };
+    aptos_bcs::to_bytes(&SignedTransaction {
+        raw_txn,
+        authenticator,
+    })
+}
@@ -0,0 +1,389 @@
+//! Byte-level diff between stored and re-serialized BCS.
+//!
+//! `GET /transaction/:id/diff` re-serializes a stored transaction on demand
+//! and reports every run of differing bytes with an `xxd`-style hexdump of
+//! both sides around it, so a mismatch can be read without dumping both blobs
+//! and diffing them by hand. [`BcsDiff`] and [`MismatchReport`] are the short
+//! summaries a conflicting store and a strict-mode retrieval answer with.
+
+use crate::explain::Explanation;
+use crate::reserialize::Strategy;
+use crate::transaction::DecodedTransaction;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Bytes of hexdump shown on either side of a differing range
+const CONTEXT_BYTES: usize = 16;
+/// Differing runs closer than this are reported as one range
+const MERGE_GAP: usize = 8;
+/// Ranges listed in a [`ByteDiff`]; the rest are only counted
+const MAX_RANGES: usize = 32;
+/// Bytes per hexdump line
+const LINE_BYTES: usize = 16;
+
+/// Every difference between two byte strings
+#[derive(Serialize, ToSchema)]
+pub struct ByteDiff {
+    /// Length of the stored BCS in bytes
+    pub original_len: usize,
+    /// Length of the re-serialized BCS in bytes
+    pub reserialized_len: usize,
+    /// Offset of the first byte that differs; `None` if the bytes are identical
+    pub first_difference: Option<usize>,
+    /// Differing ranges, in order (at most [`MAX_RANGES`]); bytes past the
+    /// end of the shorter side form the last range
+    pub ranges: Vec<DiffRange>,
+    /// Total number of differing ranges, including any not listed
+    pub range_count: usize,
+}
+
+/// One run of differing bytes
+#[derive(Serialize, ToSchema)]
+pub struct DiffRange {
+    /// First differing offset
+    pub start: usize,
+    /// Offset just past the range
+    pub end: usize,
+    /// The stored bytes in the range, as hex (empty past the end)
+    pub original_hex: String,
+    /// The re-serialized bytes in the range, as hex (empty past the end)
+    pub reserialized_hex: String,
+    /// Hexdump of the stored bytes around the range
+    pub original_context: Vec<String>,
+    /// Hexdump of the re-serialized bytes around the range
+    pub reserialized_context: Vec<String>,
+    /// Fields of the stored transaction the range overlaps (see
+    /// [`crate::explain`]); empty if the stored bytes weren't mapped
+    pub fields: Vec<String>,
+}
+
+impl ByteDiff {
+    pub fn new(original: &[u8], reserialized: &[u8]) -> Self {
+        let ranges = differing_ranges(original, reserialized);
+        Self {
+            original_len: original.len(),
+            reserialized_len: reserialized.len(),
+            first_difference: ranges.first().map(|(start, _)| *start),
+            range_count: ranges.len(),
+            ranges: ranges
+                .into_iter()
+                .take(MAX_RANGES)
+                .map(|(start, end)| DiffRange {
+                    start,
+                    end,
+                    original_hex: hex::encode(slice(original, start, end)),
+                    reserialized_hex: hex::encode(slice(reserialized, start, end)),
+                    original_context: hexdump(original, start, end),
+                    reserialized_context: hexdump(reserialized, start, end),
+                    fields: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Name the fields of the stored bytes each range overlaps
+    pub fn attribute(&mut self, explanation: &Explanation) {
+        for range in &mut self.ranges {
+            range.fields = explanation.fields_in(range.start, range.end);
+        }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.first_difference.is_none()
+    }
+
+    /// The listed ranges as `start..end`, for log lines
+    pub fn summary(&self) -> String {
+        let mut ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|range| format!("{}..{}", range.start, range.end))
+            .collect();
+        if self.range_count > self.ranges.len() {
+            ranges.push(format!("+{} more", self.range_count - self.ranges.len()));
+        }
+        ranges.join(", ")
+    }
+}
+
+/// `(start, end)` of each run of differing bytes, merging runs less than
+/// [`MERGE_GAP`] apart
+fn differing_ranges(a: &[u8], b: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let shared = a.len().min(b.len());
+    let longer = a.len().max(b.len());
+    let differing = (0..shared)
+        .filter(|&offset| a[offset] != b[offset])
+        .chain(shared..longer);
+    for offset in differing {
+        match ranges.last_mut() {
+            Some((_, end)) if offset < *end + MERGE_GAP => *end = offset + 1,
+            _ => ranges.push((offset, offset + 1)),
+        }
+    }
+    ranges
+}
+
+fn slice(bytes: &[u8], start: usize, end: usize) -> &[u8] {
+    &bytes[start.min(bytes.len())..end.min(bytes.len())]
+}
+
+/// `xxd`-style lines covering `start..end` plus [`CONTEXT_BYTES`] either
+/// side, aligned to [`LINE_BYTES`]
+fn hexdump(bytes: &[u8], start: usize, end: usize) -> Vec<String> {
+    let from = start.saturating_sub(CONTEXT_BYTES) / LINE_BYTES * LINE_BYTES;
+    let to = (end + CONTEXT_BYTES).min(bytes.len());
+    if from >= to {
+        return Vec::new();
+    }
+    bytes[from..to]
+        .chunks(LINE_BYTES)
+        .enumerate()
+        .map(|(i, line)| {
+            let hex: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = line
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                from + i * LINE_BYTES,
+                hex.join(" "),
+                ascii,
+                width = LINE_BYTES * 3 - 1
+            )
+        })
+        .collect()
+}
+
+/// How a replayed transaction differs from the one already stored
+#[derive(Serialize, ToSchema)]
+pub struct BcsDiff {
+    /// Length of the stored BCS in bytes
+    pub stored_len: usize,
+    /// Length of the submitted BCS in bytes
+    pub submitted_len: usize,
+    /// Offset of the first byte that differs
+    pub first_difference: usize,
+    /// Decoded transaction fields that differ (empty if either side doesn't
+    /// decode)
+    pub changed_fields: Vec<&'static str>,
+}
+
+/// How re-serialized BCS differs from the stored bytes
+#[derive(Serialize, ToSchema)]
+pub struct MismatchReport {
+    /// Decoder the bytes went through
+    pub strategy: Strategy,
+    /// Length of the stored BCS in bytes
+    pub original_len: usize,
+    /// Length of the re-serialized BCS in bytes
+    pub reserialized_len: usize,
+    /// Offset of the first byte that differs
+    pub first_difference: usize,
+    /// Number of differing bytes within the shorter length
+    pub differing_bytes: usize,
+    /// Offsets of the first differing bytes (at most [`MISMATCH_OFFSETS_SHOWN`])
+    pub differing_offsets: Vec<usize>,
+    /// Decoded transaction fields that differ (empty if either side doesn't
+    /// decode)
+    pub changed_fields: Vec<&'static str>,
+}
+
+/// Offsets listed in a [`MismatchReport`]
+const MISMATCH_OFFSETS_SHOWN: usize = 64;
+
+impl MismatchReport {
+    pub fn new(strategy: Strategy, original: &[u8], reserialized: &[u8]) -> Self {
+        let differing: Vec<usize> = original
+            .iter()
+            .zip(reserialized)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(offset, _)| offset)
+            .collect();
+        let diff = diff_bcs(original, reserialized);
+        Self {
+            strategy,
+            original_len: diff.stored_len,
+            reserialized_len: diff.submitted_len,
+            first_difference: diff.first_difference,
+            differing_bytes: differing.len(),
+            differing_offsets: differing.into_iter().take(MISMATCH_OFFSETS_SHOWN).collect(),
+            changed_fields: diff.changed_fields,
+        }
+    }
+}
+
+/// Offset of the first differing byte (the shorter length if one is a prefix)
+fn first_difference(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .unwrap_or(a.len().min(b.len()))
+}
+
+/// Summarize how `submitted` differs from `stored`
+pub fn diff_bcs(stored: &[u8], submitted: &[u8]) -> BcsDiff {
+    let decode = DecodedTransaction::decode;
+    let changed_fields = match (decode(stored), decode(submitted)) {
+        (Ok(a), Ok(b)) => [
+            ("kind", a.kind() != b.kind()),
+            ("sender", a.raw_txn().sender != b.raw_txn().sender),
+            (
+                "sequence_number",
+                a.raw_txn().sequence_number != b.raw_txn().sequence_number,
+            ),
+            ("payload", a.raw_txn().payload != b.raw_txn().payload),
+            (
+                "max_gas_amount",
+                a.raw_txn().max_gas_amount != b.raw_txn().max_gas_amount,
+            ),
+            (
+                "gas_unit_price",
+                a.raw_txn().gas_unit_price != b.raw_txn().gas_unit_price,
+            ),
+            (
+                "expiration_timestamp_secs",
+                a.raw_txn().expiration_timestamp_secs != b.raw_txn().expiration_timestamp_secs,
+            ),
+            ("chain_id", a.raw_txn().chain_id != b.raw_txn().chain_id),
+            (
+                "secondary_signer_addresses",
+                a.secondary_signer_addresses() != b.secondary_signer_addresses(),
+            ),
+            (
+                "fee_payer_address",
+                a.fee_payer_address() != b.fee_payer_address(),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(field, _)| field)
+        .collect(),
+        _ => Vec::new(),
+    };
+    BcsDiff {
+        stored_len: stored.len(),
+        submitted_len: submitted.len(),
+        first_difference: first_difference(stored, submitted),
+        changed_fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionKind;
+    use aptos_sdk::aptos_bcs;
+
+    fn ranges(diff: &ByteDiff) -> Vec<(usize, usize)> {
+        diff.ranges
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect()
+    }
+
+    #[test]
+    fn identical_bytes_have_no_ranges() {
+        let diff = ByteDiff::new(b"same", b"same");
+        assert!(diff.is_identical());
+        assert_eq!(diff.range_count, 0);
+        assert_eq!(diff.summary(), "");
+    }
+
+    #[test]
+    fn nearby_runs_merge_and_distant_ones_dont() {
+        let original = [0u8; 64];
+        let mut reserialized = original;
+        for offset in [2, 3, 9, 30] {
+            reserialized[offset] = 1;
+        }
+        let diff = ByteDiff::new(&original, &reserialized);
+        // 3 and 9 are less than MERGE_GAP apart, 9 and 30 aren't
+        assert_eq!(ranges(&diff), [(2, 10), (30, 31)]);
+        assert_eq!(diff.first_difference, Some(2));
+        assert_eq!(diff.ranges[1].original_hex, "00");
+        assert_eq!(diff.ranges[1].reserialized_hex, "01");
+        assert_eq!(diff.summary(), "2..10, 30..31");
+    }
+
+    #[test]
+    fn extra_bytes_form_the_last_range() {
+        let diff = ByteDiff::new(&[1, 2, 3], &[1, 2, 3, 4, 5]);
+        assert_eq!(ranges(&diff), [(3, 5)]);
+        assert_eq!(diff.ranges[0].original_hex, "");
+        assert_eq!(diff.ranges[0].reserialized_hex, "0405");
+        assert_eq!(
+            diff.ranges[0].original_context,
+            ["00000000  01 02 03                                         |...|"]
+        );
+        assert_eq!(
+            diff.ranges[0].reserialized_context,
+            ["00000000  01 02 03 04 05                                   |.....|"]
+        );
+    }
+
+    #[test]
+    fn ranges_past_the_limit_are_only_counted() {
+        // One differing byte every MERGE_GAP + 1, just too far apart to merge
+        let original = vec![0u8; (MAX_RANGES + 2) * (MERGE_GAP + 1)];
+        let reserialized: Vec<u8> = (0..original.len())
+            .map(|offset| u8::from(offset % (MERGE_GAP + 1) == 0))
+            .collect();
+        let diff = ByteDiff::new(&original, &reserialized);
+        assert_eq!(diff.range_count, MAX_RANGES + 2);
+        assert_eq!(diff.ranges.len(), MAX_RANGES);
+        assert!(diff.summary().ends_with(", +2 more"));
+    }
+
+    #[test]
+    fn hexdump_is_aligned_around_the_range() {
+        let bytes: Vec<u8> = (0..64).collect();
+        assert_eq!(
+            hexdump(&bytes, 40, 41),
+            [
+                "00000010  10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f  |................|",
+                "00000020  20 21 22 23 24 25 26 27 28 29 2a 2b 2c 2d 2e 2f  |.!\"#$%&'()*+,-./|",
+                "00000030  30 31 32 33 34 35 36 37 38                       |012345678|",
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_are_attributed_to_fields() {
+        let tx = DecodedTransaction::fixture();
+        let original = aptos_bcs::to_bytes(&tx).unwrap();
+        let mut reserialized = original.clone();
+        // The last byte of the sequence number and the chain id
+        reserialized[39] ^= 1;
+        reserialized[164] ^= 1;
+        let mut diff = ByteDiff::new(&original, &reserialized);
+        diff.attribute(&Explanation::new(TransactionKind::MultiAgent, &original));
+        assert_eq!(ranges(&diff), [(39, 40), (164, 165)]);
+        assert_eq!(diff.ranges[0].fields, ["sequence_number"]);
+        assert_eq!(diff.ranges[1].fields, ["chain_id"]);
+    }
+
+    #[test]
+    fn changed_fields_are_named() {
+        let stored = DecodedTransaction::fixture();
+        let submitted = stored.with_sequence_number(7).unwrap();
+        let stored = aptos_bcs::to_bytes(&stored).unwrap();
+        let submitted = aptos_bcs::to_bytes(&submitted).unwrap();
+
+        let diff = diff_bcs(&stored, &submitted);
+        assert_eq!(diff.changed_fields, ["sequence_number"]);
+        assert_eq!(diff.first_difference, 32);
+
+        let report = MismatchReport::new(Strategy::Typed, &stored, &submitted);
+        assert_eq!(report.differing_offsets, [32]);
+        assert_eq!(report.changed_fields, ["sequence_number"]);
+    }
+}
@@ -0,0 +1,154 @@
+//! Canonical BCS checks at ingest.
+//!
+//! BCS has exactly one encoding per value, and the SDK's decoder refuses
+//! anything else, so a non-canonical blob is stored as an unrecognized
+//! transaction and fails much later. The usual culprits are a hand-rolled
+//! serializer padding ULEB128 lengths (`0x8100` instead of `0x01`) or bytes
+//! appended after the transaction. Both are found by walking the stored bytes
+//! with the lenient [`crate::explain`] walker and reported by field, and with
+//! `--reject-non-canonical` such transactions are refused outright.
+
+use crate::explain::Explanation;
+use crate::transaction::TransactionKind;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// One way stored bytes deviate from canonical BCS
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum CanonicalIssue {
+    /// A ULEB128 length or variant index encoded with more bytes than needed
+    NonMinimalUleb128 {
+        field: String,
+        start: usize,
+        end: usize,
+    },
+    /// Bytes left over after a complete transaction
+    TrailingBytes { start: usize, len: usize },
+}
+
+impl CanonicalIssue {
+    pub fn describe(&self) -> String {
+        match self {
+            CanonicalIssue::NonMinimalUleb128 { field, start, .. } => {
+                format!("non-minimal ULEB128 in {} at byte {}", field, start)
+            }
+            CanonicalIssue::TrailingBytes { start, len } => {
+                format!("{} trailing bytes at byte {}", len, start)
+            }
+        }
+    }
+}
+
+/// Check `bytes` against the layout of `kind`, or, if the kind is unknown,
+/// of a `MultiAgentRawTransaction` and the other signing payloads. Returns
+/// `None` if the bytes don't follow any of those layouts, in which case
+/// canonicity can't be judged.
+pub fn check(kind: Option<TransactionKind>, bytes: &[u8]) -> Option<Vec<CanonicalIssue>> {
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => vec![
+            TransactionKind::MultiAgent,
+            TransactionKind::FeePayer,
+            TransactionKind::RawTransaction,
+        ],
+    };
+    let walks: Vec<Explanation> = kinds
+        .into_iter()
+        .map(|kind| Explanation::new(kind, bytes))
+        .filter(|explanation| explanation.complete)
+        .collect();
+    // Prefer a layout the bytes fit exactly; otherwise the first one that
+    // leaves bytes over, which is multi-agent if that fits at all
+    let explanation = walks
+        .iter()
+        .find(|explanation| explanation.trailing().is_none())
+        .or_else(|| walks.first())?;
+
+    let mut issues: Vec<CanonicalIssue> = explanation
+        .fields
+        .iter()
+        .filter(|span| span.non_minimal)
+        .map(|span| CanonicalIssue::NonMinimalUleb128 {
+            field: span.field.clone(),
+            start: span.start,
+            end: span.end,
+        })
+        .collect();
+    if let Some(trailing) = explanation.trailing() {
+        issues.push(CanonicalIssue::TrailingBytes {
+            start: trailing.start,
+            len: trailing.end - trailing.start,
+        });
+    }
+    Some(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::DecodedTransaction;
+    use aptos_sdk::aptos_bcs;
+
+    /// The fixture's BCS and the offset of its secondary signer count
+    fn fixture() -> (Vec<u8>, usize) {
+        let tx = DecodedTransaction::fixture();
+        let raw_len = aptos_bcs::to_bytes(tx.raw_txn()).unwrap().len();
+        (aptos_bcs::to_bytes(&tx).unwrap(), raw_len)
+    }
+
+    #[test]
+    fn sdk_output_is_canonical() {
+        let (bytes, _) = fixture();
+        assert_eq!(check(None, &bytes), Some(Vec::new()));
+        assert_eq!(
+            check(Some(TransactionKind::MultiAgent), &bytes),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn padded_uleb128_is_flagged() {
+        let (mut bytes, offset) = fixture();
+        // One secondary signer as `0x8100` rather than `0x01`
+        bytes[offset] = 0x81;
+        bytes.insert(offset + 1, 0x00);
+
+        let issue = CanonicalIssue::NonMinimalUleb128 {
+            field: "secondary_signer_addresses.length".to_string(),
+            start: offset,
+            end: offset + 2,
+        };
+        assert_eq!(
+            issue.describe(),
+            format!(
+                "non-minimal ULEB128 in secondary_signer_addresses.length at byte {}",
+                offset
+            )
+        );
+        assert_eq!(check(None, &bytes), Some(vec![issue]));
+        assert!(DecodedTransaction::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn trailing_bytes_are_flagged() {
+        let (mut bytes, _) = fixture();
+        let len = bytes.len();
+        bytes.extend([0xaa, 0xbb]);
+
+        let issue = CanonicalIssue::TrailingBytes { start: len, len: 2 };
+        assert_eq!(
+            issue.describe(),
+            format!("2 trailing bytes at byte {}", len)
+        );
+        assert_eq!(check(None, &bytes), Some(vec![issue]));
+        assert!(DecodedTransaction::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn unknown_layout_cant_be_judged() {
+        let (bytes, _) = fixture();
+        assert_eq!(check(Some(TransactionKind::FeePayer), &bytes), None);
+        assert_eq!(check(None, &bytes[..40]), None);
+    }
+}
@@ -0,0 +1,373 @@
+//! Typed async client for the backend's JSON API.
+//!
+//! Rust signer services and tests use this instead of hand-rolling `reqwest`
+//! calls: byte payloads go in and come out as raw bytes (hex is handled
+//! here), and a non-2xx answer becomes a [`ClientError::Api`] carrying the
+//! server's message and, where it gives them, the session state and the
+//! signers still missing.
+//!
+//! ```no_run
+//! # async fn run(bcs: Vec<u8>, signature: Vec<u8>) -> Result<(), repro_core::client::ClientError> {
+//! use repro_core::client::{Client, StoreTransaction};
+//! use std::time::Duration;
+//!
+//! let client = Client::new("http://localhost:3001")?;
+//! client.store_transaction(&StoreTransaction::new("tx1", bcs)).await?;
+//! client.attach_signature("tx1", "0xb0b", &signature).await?;
+//! let tx = client.wait_for_complete("tx1", Duration::from_secs(60)).await?;
+//! println!("{} secondary signatures", tx.secondary_signatures.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::reserialize::{Strategy, RESERIALIZE_HEADER, STRATEGY_HEADER};
+use crate::session::SessionState;
+use crate::storage::hex_bytes;
+use crate::transaction::{ExpirationStatus, TransactionKind};
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Header the server reads API keys from
+const API_KEY_HEADER: &str = "x-api-key";
+/// Longest `timeout_ms` the server honours on one `/wait` call
+const MAX_WAIT: Duration = Duration::from_secs(300);
+/// Largest page `GET /transactions` returns
+const MAX_PAGE: usize = 1000;
+
+/// Why a request failed
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The base URL doesn't parse or can't have paths appended
+    #[error("invalid base URL: {0}")]
+    InvalidUrl(String),
+    /// The request couldn't be sent or the response read
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The server answered with an error status
+    #[error("{status}: {message}")]
+    Api {
+        status: StatusCode,
+        message: String,
+        /// Where the signing session stands, if the server said
+        session_state: Option<SessionState>,
+        /// Signers yet to sign, if the server said (e.g. on a `/wait` timeout)
+        missing_signers: Vec<String>,
+    },
+}
+
+impl ClientError {
+    /// The HTTP status the server answered with, if it answered
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// A transaction to store with [`Client::store_transaction`]
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreTransaction {
+    pub transaction_id: String,
+    /// The BCS, sent as `0x`-prefixed hex
+    #[serde(rename = "bcs_hex", serialize_with = "prefixed_hex")]
+    pub bcs: Vec<u8>,
+    /// Payload the BCS must decode as; detected by the server if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TransactionKind>,
+    /// URL the server POSTs webhooks for this transaction to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+    /// Secondary signatures that complete the session (K-of-N)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<u32>,
+}
+
+impl StoreTransaction {
+    pub fn new(transaction_id: impl Into<String>, bcs: Vec<u8>) -> Self {
+        Self {
+            transaction_id: transaction_id.into(),
+            bcs,
+            kind: None,
+            callback_url: None,
+            quorum: None,
+        }
+    }
+}
+
+/// Answer to storing a transaction
+#[derive(Clone, Debug, Deserialize)]
+pub struct Stored {
+    pub transaction_id: String,
+    /// Payload the BCS decoded as, if it did
+    pub kind: Option<TransactionKind>,
+    pub expiration: Option<ExpirationStatus>,
+    pub session_state: Option<SessionState>,
+    /// Required signers yet to sign; `None` if the BCS doesn't decode
+    pub signers_pending: Option<Vec<String>>,
+    pub signers_done: Option<Vec<String>>,
+    pub message: String,
+}
+
+/// Answer to attaching a signature
+#[derive(Clone, Debug, Deserialize)]
+pub struct SignatureAttached {
+    pub transaction_id: String,
+    pub session_state: Option<SessionState>,
+    pub signers_pending: Option<Vec<String>>,
+    pub signers_done: Option<Vec<String>>,
+    pub message: String,
+}
+
+/// A stored transaction and the signatures collected for it
+#[derive(Clone, Debug, Deserialize)]
+pub struct Transaction {
+    /// The BCS as the server returns it (re-serialized in reserialize mode)
+    #[serde(rename = "bcs_hex", with = "hex_bytes")]
+    pub bcs: Vec<u8>,
+    /// Serialized `AccountAuthenticator`s keyed by long-form signer address
+    #[serde(with = "hex_bytes::map")]
+    pub secondary_signatures: BTreeMap<String, Vec<u8>>,
+    /// Fee payer's serialized `AccountAuthenticator`, once signed
+    #[serde(
+        default,
+        rename = "fee_payer_signature_hex",
+        with = "hex_bytes::option"
+    )]
+    pub fee_payer_signature: Option<Vec<u8>>,
+    /// Unix seconds
+    pub stored_at: u64,
+    pub expiration: Option<ExpirationStatus>,
+    pub session_state: Option<SessionState>,
+    pub signers_pending: Option<Vec<String>>,
+    pub signers_done: Option<Vec<String>>,
+    pub quorum: Option<u32>,
+}
+
+/// One transaction in a [`TransactionPage`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct Listed {
+    pub transaction_id: String,
+    /// Long-form address, if the BCS decoded when stored
+    pub sender: Option<String>,
+    pub sequence_number: Option<u64>,
+    pub has_signature: bool,
+    /// Unix seconds
+    pub stored_at: u64,
+    pub session_state: Option<SessionState>,
+}
+
+/// A page of [`Client::list`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Listed>,
+    /// Pass to [`Client::list`] for the next page; `None` on the last one
+    pub next_cursor: Option<String>,
+}
+
+/// Body of an error answer; every response carries `message`
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+    #[serde(default)]
+    session_state: Option<SessionState>,
+    #[serde(default)]
+    missing_signers: Vec<String>,
+}
+
+/// Client for one backend
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    /// `<base URL>/v1`
+    base: Url,
+    api_key: Option<String>,
+    bearer_token: Option<String>,
+}
+
+impl Client {
+    /// Client for the backend at `base_url`, e.g. `http://localhost:3001`
+    pub fn new(base_url: &str) -> ClientResult<Self> {
+        let mut base = Url::parse(base_url)
+            .map_err(|e| ClientError::InvalidUrl(format!("{}: {}", base_url, e)))?;
+        base.path_segments_mut()
+            .map_err(|_| ClientError::InvalidUrl(base_url.to_string()))?
+            .pop_if_empty()
+            .push("v1");
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base,
+            api_key: None,
+            bearer_token: None,
+        })
+    }
+
+    /// Send `key` in `x-api-key` with every request
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Send `token` as a bearer token with every request
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Send requests through `http`, e.g. one with timeouts or a proxy set
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// `POST /transaction`
+    pub async fn store_transaction(&self, transaction: &StoreTransaction) -> ClientResult<Stored> {
+        let request = self
+            .request(Method::POST, &["transaction"])
+            .json(transaction);
+        send(request).await
+    }
+
+    /// `POST /signature`: attach `signer_address`'s serialized
+    /// `AccountAuthenticator`
+    pub async fn attach_signature(
+        &self,
+        transaction_id: &str,
+        signer_address: &str,
+        authenticator: &[u8],
+    ) -> ClientResult<SignatureAttached> {
+        let body = serde_json::json!({
+            "transaction_id": transaction_id,
+            "signer_address": signer_address,
+            "signature_hex": hex_bytes::encode(authenticator, true),
+        });
+        send(self.request(Method::POST, &["signature"]).json(&body)).await
+    }
+
+    /// `POST /fee-payer-signature`: attach the fee payer's serialized
+    /// `AccountAuthenticator`
+    pub async fn attach_fee_payer_signature(
+        &self,
+        transaction_id: &str,
+        authenticator: &[u8],
+    ) -> ClientResult<SignatureAttached> {
+        let body = serde_json::json!({
+            "transaction_id": transaction_id,
+            "signature_hex": hex_bytes::encode(authenticator, true),
+        });
+        let request = self.request(Method::POST, &["fee-payer-signature"]);
+        send(request.json(&body)).await
+    }
+
+    /// `GET /transaction/:id`
+    pub async fn get(&self, transaction_id: &str) -> ClientResult<Transaction> {
+        send(self.request(Method::GET, &["transaction", transaction_id])).await
+    }
+
+    /// `GET /transaction/:id` as raw BCS, reserialized or passed through
+    /// whatever the server's mode, through `strategy` if given instead of the
+    /// server's
+    pub async fn get_bcs(
+        &self,
+        transaction_id: &str,
+        reserialize: bool,
+        strategy: Option<Strategy>,
+    ) -> ClientResult<Vec<u8>> {
+        let mut request = self
+            .request(Method::GET, &["transaction", transaction_id])
+            .header(header::ACCEPT, "application/octet-stream")
+            .header(RESERIALIZE_HEADER, if reserialize { "1" } else { "0" });
+        if let Some(strategy) = strategy {
+            request = request.header(STRATEGY_HEADER, strategy.as_str());
+        }
+        Ok(send_raw(request).await?.bytes().await?.to_vec())
+    }
+
+    /// `GET /transactions`: the page after `cursor`, or the first
+    pub async fn list(&self, cursor: Option<&str>) -> ClientResult<TransactionPage> {
+        let mut request = self
+            .request(Method::GET, &["transactions"])
+            .query(&[("limit", MAX_PAGE)]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        send(request).await
+    }
+
+    /// `GET /transaction/:id/wait` until no signature is missing (or the
+    /// quorum is met), calling again as needed for timeouts past the server's
+    /// five minute limit. Fails with `408` and the signers still missing if
+    /// `timeout` passes first, and with `409` or `410` if the session is
+    /// declined or expires meanwhile.
+    pub async fn wait_for_complete(
+        &self,
+        transaction_id: &str,
+        timeout: Duration,
+    ) -> ClientResult<Transaction> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let request = self
+                .request(Method::GET, &["transaction", transaction_id, "wait"])
+                .query(&[("timeout_ms", remaining.min(MAX_WAIT).as_millis() as u64)]);
+            // A server-side timeout short of the deadline just means call again
+            let timed_out = |e: &ClientError| e.status() == Some(StatusCode::REQUEST_TIMEOUT);
+            match send(request).await {
+                Err(e) if timed_out(&e) && remaining > MAX_WAIT => continue,
+                result => return result,
+            }
+        }
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("checked in Client::new")
+            .extend(segments);
+        let mut request = self.http.request(method, url);
+        if let Some(key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+}
+
+/// Send `request` and decode a 2xx body as `T`, or the error body otherwise
+async fn send<T: DeserializeOwned>(request: RequestBuilder) -> ClientResult<T> {
+    Ok(send_raw(request).await?.json().await?)
+}
+
+/// Send `request`, returning a 2xx response as is and decoding the error body
+/// of any other
+async fn send_raw(request: RequestBuilder) -> ClientResult<Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    // Rejections before a handler (auth, rate limits) may be plain text
+    let body = response.text().await?;
+    let error = serde_json::from_str::<ErrorBody>(&body).unwrap_or(ErrorBody {
+        message: body,
+        session_state: None,
+        missing_signers: Vec::new(),
+    });
+    Err(ClientError::Api {
+        status,
+        message: error.message,
+        session_state: error.session_state,
+        missing_signers: error.missing_signers,
+    })
+}
+
+fn prefixed_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex_bytes::encode(bytes, true))
+}
@@ -0,0 +1,198 @@
+//! Pass-through vs. reserialize comparison of a running backend.
+//!
+//! The `compare-modes` binary lists every transaction a backend stores and
+//! retrieves each one twice, with `x-reserialize: 0` and `x-reserialize: 1`,
+//! instead of restarting the server with `RESERIALIZE=1` and diffing by hand.
+//! It prints a JSON report of every transaction whose two retrievals differ,
+//! with the same mismatch details strict mode gives, and exits with 1 if any
+//! did or a retrieval failed:
+//!
+//! ```text
+//! cargo run --bin compare-modes -- --url http://localhost:3001 > report.json
+//! ```
+//!
+//! A transaction the server can't re-serialize at all comes back unchanged in
+//! both modes (the server logs the failure), so it counts as identical. Every
+//! retrieval is recorded in the transaction's history like any other.
+
+use crate::bytediff::MismatchReport;
+use crate::client::{Client, ClientError};
+use crate::reserialize::Strategy;
+use clap::Parser;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Exit code when the backend can't be listed or the report can't be written
+const EXIT_ERROR: i32 = 2;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "compare-modes",
+    about = "Retrieve every stored transaction in pass-through and reserialize mode and report the differences"
+)]
+pub struct CompareArgs {
+    /// Base URL of the backend
+    #[arg(long, env = "COMPARE_URL", default_value = "http://localhost:3001")]
+    pub url: String,
+
+    /// Sent as `x-api-key` when the backend requires a key
+    #[arg(long, env = "COMPARE_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// Decoder the reserialize retrievals go through
+    #[arg(long, value_enum, default_value_t = Strategy::Typed)]
+    pub strategy: Strategy,
+
+    /// Write the report here instead of to stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// A transaction whose two retrievals differ
+#[derive(Serialize)]
+pub struct Divergence {
+    transaction_id: String,
+    sender: Option<String>,
+    sequence_number: Option<u64>,
+    #[serde(flatten)]
+    mismatch: MismatchReport,
+}
+
+/// A retrieval that failed, so the transaction couldn't be compared
+#[derive(Serialize)]
+pub struct Failure {
+    transaction_id: String,
+    /// `pass-through` or `reserialize`
+    mode: &'static str,
+    error: String,
+}
+
+/// What a comparison found
+#[derive(Serialize)]
+pub struct Report {
+    url: String,
+    strategy: Strategy,
+    /// Transactions listed
+    transactions: usize,
+    identical: usize,
+    diverged: usize,
+    failed: usize,
+    divergences: Vec<Divergence>,
+    failures: Vec<Failure>,
+}
+
+impl Report {
+    /// Whether every transaction came back the same in both modes
+    pub fn is_clean(&self) -> bool {
+        self.diverged == 0 && self.failed == 0
+    }
+
+    fn fail(&mut self, transaction_id: String, mode: &'static str, error: ClientError) {
+        self.failed += 1;
+        self.failures.push(Failure {
+            transaction_id,
+            mode,
+            error: error.to_string(),
+        });
+    }
+}
+
+/// Retrieve every transaction at `client` both ways and diff the results
+pub async fn compare(
+    client: &Client,
+    url: &str,
+    strategy: Strategy,
+) -> Result<Report, ClientError> {
+    let mut report = Report {
+        url: url.to_string(),
+        strategy,
+        transactions: 0,
+        identical: 0,
+        diverged: 0,
+        failed: 0,
+        divergences: Vec::new(),
+        failures: Vec::new(),
+    };
+    let mut cursor = None;
+    loop {
+        let page = client.list(cursor.as_deref()).await?;
+        for listed in page.transactions {
+            report.transactions += 1;
+            let id = &listed.transaction_id;
+            let original = match client.get_bcs(id, false, None).await {
+                Ok(original) => original,
+                Err(e) => {
+                    report.fail(listed.transaction_id, "pass-through", e);
+                    continue;
+                }
+            };
+            // Strict mode answers a mismatch with 500, so that fails here too
+            let reserialized = match client.get_bcs(id, true, Some(strategy)).await {
+                Ok(reserialized) => reserialized,
+                Err(e) => {
+                    report.fail(listed.transaction_id, "reserialize", e);
+                    continue;
+                }
+            };
+            if original == reserialized {
+                report.identical += 1;
+                continue;
+            }
+            report.diverged += 1;
+            report.divergences.push(Divergence {
+                transaction_id: listed.transaction_id,
+                sender: listed.sender,
+                sequence_number: listed.sequence_number,
+                mismatch: MismatchReport::new(strategy, &original, &reserialized),
+            });
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    Ok(report)
+}
+
+/// Parse the command line, compare, print the report and exit: 0 if clean, 1
+/// if anything diverged or failed, 2 if the comparison couldn't run
+pub async fn run() {
+    let args = CompareArgs::parse();
+    let mut client = match Client::new(&args.url) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    if let Some(key) = &args.api_key {
+        client = client.with_api_key(key);
+    }
+
+    let report = match compare(&client, &args.url, args.strategy).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to list transactions: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let json = serde_json::to_string_pretty(&report).expect("report serializes");
+    let written = match &args.output {
+        Some(path) => std::fs::write(path, json + "\n"),
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write the report: {}", e);
+        std::process::exit(EXIT_ERROR);
+    }
+    eprintln!(
+        "{} transactions: {} identical, {} diverged, {} failed",
+        report.transactions, report.identical, report.diverged, report.failed
+    );
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,312 @@
+//! Human-readable view of a stored transaction.
+//!
+//! `GET /transaction/:id/decoded` renders every field the Rust SDK decodes
+//! from the stored BCS, so a repro can be inspected (or asserted on in tests)
+//! without a separate decoder. Addresses are long-form hex, byte strings are
+//! `0x`-prefixed hex and Move identifiers use their canonical `0x1::coin`
+//! form. Entry function arguments are decoded too where their types can be
+//! found (see [`crate::args`]).
+
+use crate::args::{self, ParamSource, ParamTypes};
+use crate::storage::hex_bytes;
+use crate::transaction::{AuthenticatorSummary, DecodedTransaction, TransactionKind};
+use crate::type_tag;
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::transaction::payload::{
+    EntryFunction, MultisigTransactionPayload, TransactionPayload,
+};
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Every field of a decoded transaction
+#[derive(Serialize, ToSchema)]
+pub struct TransactionView {
+    pub kind: TransactionKind,
+    /// Long-form address
+    pub sender: String,
+    pub sequence_number: u64,
+    pub payload: PayloadView,
+    pub max_gas_amount: u64,
+    pub gas_unit_price: u64,
+    /// Unix seconds
+    pub expiration_timestamp_secs: u64,
+    pub chain_id: u8,
+    /// Long-form addresses, in order (empty for single-signer transactions)
+    pub secondary_signer_addresses: Vec<String>,
+    /// Long-form address, for fee payer transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_payer_address: Option<String>,
+    /// Authenticator variant and signers, for a `SignedTransaction`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authenticator: Option<AuthenticatorSummary>,
+}
+
+impl TransactionView {
+    /// View of `decoded`, looking up entry function argument types on
+    /// `fullnode` if one is configured
+    pub async fn new(decoded: &DecodedTransaction, fullnode: Option<&FullnodeClient>) -> Self {
+        let raw_txn = decoded.raw_txn();
+        let param_types = match entry_function(&raw_txn.payload) {
+            Some(entry_function) => args::param_types(entry_function, fullnode).await,
+            None => None,
+        };
+        Self {
+            kind: decoded.kind(),
+            sender: raw_txn.sender.to_long_string(),
+            sequence_number: raw_txn.sequence_number,
+            payload: PayloadView::new(&raw_txn.payload, param_types.as_ref()),
+            max_gas_amount: raw_txn.max_gas_amount,
+            gas_unit_price: raw_txn.gas_unit_price,
+            expiration_timestamp_secs: raw_txn.expiration_timestamp_secs,
+            chain_id: raw_txn.chain_id.id(),
+            secondary_signer_addresses: decoded
+                .secondary_signer_addresses()
+                .iter()
+                .map(|address| address.to_long_string())
+                .collect(),
+            fee_payer_address: decoded
+                .fee_payer_address()
+                .map(|address| address.to_long_string()),
+            authenticator: decoded.authenticator(),
+        }
+    }
+}
+
+/// A transaction payload, tagged with its variant in `type`
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayloadView {
+    EntryFunction(EntryFunctionView),
+    Script {
+        /// Move bytecode, as hex
+        code_hex: String,
+        type_args: Vec<String>,
+        args: Vec<ScriptArgumentView>,
+    },
+    /// The deprecated module bundle variant, which carries nothing
+    ModuleBundle,
+    Multisig {
+        /// Long-form address of the multisig account
+        multisig_address: String,
+        /// The call to execute; `None` if the payload is already stored on chain
+        entry_function: Option<EntryFunctionView>,
+    },
+}
+
+/// The entry function a payload calls, directly or through a multisig account
+fn entry_function(payload: &TransactionPayload) -> Option<&EntryFunction> {
+    match payload {
+        TransactionPayload::EntryFunction(entry_function) => Some(entry_function),
+        TransactionPayload::Multisig(multisig) => match &multisig.transaction_payload {
+            Some(MultisigTransactionPayload::EntryFunction(entry_function)) => Some(entry_function),
+            None => None,
+        },
+        TransactionPayload::Script(_) | TransactionPayload::ModuleBundle(_) => None,
+    }
+}
+
+impl PayloadView {
+    fn new(payload: &TransactionPayload, param_types: Option<&ParamTypes>) -> Self {
+        match payload {
+            TransactionPayload::EntryFunction(entry_function) => {
+                PayloadView::EntryFunction(EntryFunctionView::new(entry_function, param_types))
+            }
+            TransactionPayload::Script(script) => PayloadView::Script {
+                code_hex: hex_bytes::encode(&script.code, true),
+                type_args: script.type_args.iter().map(type_tag::canonical).collect(),
+                args: script
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        let (type_tag, value) = args::script_arg(arg);
+                        ScriptArgumentView { type_tag, value }
+                    })
+                    .collect(),
+            },
+            TransactionPayload::ModuleBundle(_) => PayloadView::ModuleBundle,
+            TransactionPayload::Multisig(multisig) => PayloadView::Multisig {
+                multisig_address: multisig.multisig_address.to_long_string(),
+                entry_function: multisig.transaction_payload.as_ref().map(
+                    |MultisigTransactionPayload::EntryFunction(entry_function)| {
+                        EntryFunctionView::new(entry_function, param_types)
+                    },
+                ),
+            },
+        }
+    }
+}
+
+/// An entry function call
+#[derive(Serialize, ToSchema)]
+pub struct EntryFunctionView {
+    /// `address::module`, e.g. `0x1::aptos_account`
+    pub module: String,
+    /// Long-form address of the module
+    pub module_address: String,
+    pub module_name: String,
+    pub function: String,
+    /// Canonical (AIP-40) type tags, e.g. `0x1::aptos_coin::AptosCoin`
+    pub type_args: Vec<String>,
+    /// Where the argument types came from; `None` if they aren't known
+    pub arg_types_source: Option<ParamSource>,
+    pub args: Vec<ArgumentView>,
+}
+
+impl EntryFunctionView {
+    fn new(entry_function: &EntryFunction, param_types: Option<&ParamTypes>) -> Self {
+        let types = param_types.map_or(&[][..], |param_types| param_types.types.as_slice());
+        Self {
+            module: entry_function.module.to_string(),
+            module_address: entry_function.module.address.to_long_string(),
+            module_name: entry_function.module.name.to_string(),
+            function: entry_function.function.clone(),
+            type_args: entry_function.type_args.iter().map(type_tag::canonical).collect(),
+            arg_types_source: param_types.map(|param_types| param_types.source),
+            args: entry_function
+                .args
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| {
+                    let param_type = types.get(i).and_then(Option::as_ref);
+                    ArgumentView {
+                        bcs_hex: hex_bytes::encode(arg, true),
+                        type_tag: param_type.map(type_tag::canonical),
+                        value: param_type.and_then(|param_type| args::decode(param_type, arg)),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// One entry function argument
+#[derive(Serialize, ToSchema)]
+pub struct ArgumentView {
+    /// The argument's BCS, as hex
+    pub bcs_hex: String,
+    /// Its Move type in canonical form, if known
+    pub type_tag: Option<String>,
+    /// The decoded value, in the REST API's JSON rendering; `None` if the type
+    /// isn't known or the bytes don't decode as it
+    #[schema(value_type = Option<Object>)]
+    pub value: Option<Value>,
+}
+
+/// One script argument; unlike entry function arguments these carry their type
+#[derive(Serialize, ToSchema)]
+pub struct ScriptArgumentView {
+    /// Its Move type; `None` for a `Serialized` argument, whose type only the
+    /// script knows
+    pub type_tag: Option<&'static str>,
+    /// The value, in the REST API's JSON rendering; the BCS as hex for a
+    /// `Serialized` argument
+    #[schema(value_type = Object)]
+    pub value: Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_sdk::transaction::payload::{Multisig, Script, ScriptArgument};
+    use aptos_sdk::types::TypeTag;
+    use aptos_sdk::AccountAddress;
+    use serde_json::json;
+
+    const ONE: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+    const TWO: &str = "0x0000000000000000000000000000000000000000000000000000000000000002";
+
+    #[tokio::test]
+    async fn every_field_is_rendered() {
+        let view = TransactionView::new(&DecodedTransaction::fixture(), None).await;
+        assert_eq!(
+            serde_json::to_value(&view).unwrap(),
+            json!({
+                "kind": "multi_agent",
+                "sender": ONE,
+                "sequence_number": 0,
+                "payload": {
+                    "type": "entry_function",
+                    "module": "0x1::aptos_account",
+                    "module_address": ONE,
+                    "module_name": "aptos_account",
+                    "function": "transfer",
+                    "type_args": [],
+                    "arg_types_source": "known",
+                    "args": [
+                        {
+                            "bcs_hex": format!("0x{}", &ONE[2..]),
+                            "type_tag": "address",
+                            "value": ONE,
+                        },
+                        {
+                            "bcs_hex": "0x0100000000000000",
+                            "type_tag": "u64",
+                            "value": "1",
+                        },
+                    ],
+                },
+                "max_gas_amount": 200_000,
+                "gas_unit_price": 100,
+                "expiration_timestamp_secs": u64::MAX,
+                "chain_id": 2,
+                "secondary_signer_addresses": [TWO],
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_entry_function_args_are_left_as_bcs() {
+        let mut entry_function = EntryFunction::apt_transfer(AccountAddress::ONE, 1).unwrap();
+        entry_function.function = "unknown".to_string();
+        let view = EntryFunctionView::new(&entry_function, None);
+        assert_eq!(view.arg_types_source, None);
+        assert!(view
+            .args
+            .iter()
+            .all(|arg| arg.type_tag.is_none() && arg.value.is_none()));
+    }
+
+    #[test]
+    fn script_args_carry_their_types() {
+        let payload = TransactionPayload::Script(Script {
+            code: vec![0xa1, 0x1c],
+            type_args: vec![TypeTag::U8],
+            args: vec![
+                ScriptArgument::U64(5),
+                ScriptArgument::Bool(true),
+                ScriptArgument::Serialized(vec![1, 2]),
+            ],
+        });
+        assert_eq!(
+            serde_json::to_value(PayloadView::new(&payload, None)).unwrap(),
+            json!({
+                "type": "script",
+                "code_hex": "0xa11c",
+                "type_args": ["u8"],
+                "args": [
+                    { "type_tag": "u64", "value": "5" },
+                    { "type_tag": "bool", "value": true },
+                    { "type_tag": null, "value": "0x0102" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn multisig_payload_names_its_account() {
+        let payload = TransactionPayload::Multisig(Multisig {
+            multisig_address: AccountAddress::from_hex("0x2").unwrap(),
+            transaction_payload: None,
+        });
+        assert!(entry_function(&payload).is_none());
+        assert_eq!(
+            serde_json::to_value(PayloadView::new(&payload, None)).unwrap(),
+            json!({
+                "type": "multisig",
+                "multisig_address": TWO,
+                "entry_function": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Hex or base64 text encodings of BCS payloads.
+//!
+//! JSON requests carry BCS as hex by default. Several wallet integrations
+//! hand out base64 instead, so `POST /transaction`, `POST /signature` and
+//! `POST /fee-payer-signature` take `"encoding": "base64"` to read
+//! `bcs_hex` (alias `bcs`) or `signature_hex` as standard padded base64.
+//! `GET /transaction/:id` with `x-bcs-encoding: base64` returns the
+//! transaction as `bcs_base64` in place of `bcs_hex`.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Header selecting the encoding of the BCS in a retrieval
+pub const ENCODING_HEADER: &str = "x-bcs-encoding";
+
+/// Text encoding of a BCS payload
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Lowercase hex, with or without a `0x` prefix
+    #[default]
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+}
+
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+        }
+    }
+
+    /// Decode `s`, returning the bytes and whether it had a `0x` prefix
+    /// (never for base64)
+    pub fn decode(self, s: &str) -> Result<(Vec<u8>, bool), String> {
+        match self {
+            Encoding::Hex => crate::storage::hex_bytes::decode(s).map_err(|e| e.to_string()),
+            Encoding::Base64 => STANDARD
+                .decode(s)
+                .map(|bytes| (bytes, false))
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Encode bytes as standard base64
+pub fn base64(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Encoding for a retrieval: `x-bcs-encoding` if sent, else hex
+pub fn response_encoding(headers: &HeaderMap) -> Result<Encoding, String> {
+    match headers.get(ENCODING_HEADER).map(|value| value.as_bytes()) {
+        None | Some(b"hex") => Ok(Encoding::Hex),
+        Some(b"base64") => Ok(Encoding::Base64),
+        Some(_) => Err(format!("{} must be hex or base64", ENCODING_HEADER)),
+    }
+}
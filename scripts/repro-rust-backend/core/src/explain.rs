@@ -0,0 +1,526 @@
+//! Byte-level map of a stored transaction's BCS.
+//!
+//! `GET /transaction/:id/explain` walks the stored bytes in on-chain layout
+//! order and returns the byte range of every field (`sender` is `0..32`,
+//! `sequence_number` is `32..40`, then the payload's variant index and its
+//! fields, ...), with each field's bytes and decoded value. Offsets reported
+//! by `GET /transaction/:id/diff` or a strict-mode mismatch can then be read
+//! off as fields directly.
+//!
+//! Fields are named as in aptos-core without the `raw_txn.` prefix, so they
+//! match the `changed_fields` of mismatch reports. Vectors get a `.length`
+//! field for their ULEB128 length followed by one field per element. A
+//! signed transaction's authenticator is only split into its variant index
+//! and the rest.
+//!
+//! The walk is lenient where the SDK's decoder isn't: ULEB128 values padded
+//! with extra bytes are read and flagged `non_minimal`, and bytes left after a
+//! complete transaction become a `trailing` field. [`crate::canonical`] uses
+//! both to explain why bytes are not canonical BCS.
+
+use crate::args;
+use crate::transaction::TransactionKind;
+use crate::type_tag;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::types::TypeTag;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Where each field of a transaction's BCS lies
+#[derive(Serialize, ToSchema)]
+pub struct Explanation {
+    pub kind: TransactionKind,
+    pub len: usize,
+    /// Fields in byte order; together they cover every byte
+    pub fields: Vec<FieldSpan>,
+    /// `false` if the walk stopped early, in which case the last field is
+    /// `unparsed` and covers the remaining bytes; bytes left after a complete
+    /// walk are a `trailing` field instead
+    pub complete: bool,
+}
+
+/// One field's byte range
+#[derive(Serialize, ToSchema)]
+pub struct FieldSpan {
+    /// Field path, e.g. `payload.function` or `secondary_signer_addresses[1]`
+    pub field: String,
+    pub start: usize,
+    /// Offset just past the field
+    pub end: usize,
+    /// The field's bytes, including any length prefix
+    pub hex: String,
+    /// Decoded value, in the REST API's JSON rendering, if it is a leaf
+    pub value: Option<String>,
+    /// Whether a ULEB128 length or variant index in the field is longer than
+    /// needed, which canonical BCS forbids
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub non_minimal: bool,
+}
+
+impl Explanation {
+    /// Map `bytes`, which decode as a transaction of `kind`
+    pub fn new(kind: TransactionKind, bytes: &[u8]) -> Self {
+        let mut walker = Walker {
+            bytes,
+            rest: bytes,
+            fields: Vec::new(),
+        };
+        let complete = walker.transaction(kind).is_some();
+        if !walker.rest.is_empty() {
+            let len = walker.rest.len();
+            let field = if complete { "trailing" } else { "unparsed" };
+            walker.field(field.to_string(), len, |_| None);
+        }
+        Self {
+            kind,
+            len: bytes.len(),
+            fields: walker.fields,
+            complete,
+        }
+    }
+
+    /// Names of the fields overlapping `start..end`
+    pub fn fields_in(&self, start: usize, end: usize) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|span| span.start < end && start < span.end)
+            .map(|span| span.field.clone())
+            .collect()
+    }
+
+    /// The bytes left after a complete transaction, if any
+    pub fn trailing(&self) -> Option<&FieldSpan> {
+        self.fields
+            .last()
+            .filter(|span| self.complete && span.field == "trailing")
+    }
+}
+
+struct Walker<'a> {
+    bytes: &'a [u8],
+    rest: &'a [u8],
+    fields: Vec<FieldSpan>,
+}
+
+impl<'a> Walker<'a> {
+    fn offset(&self) -> usize {
+        self.bytes.len() - self.rest.len()
+    }
+
+    /// Record the next `len` bytes as `field`, rendering them with `value`
+    fn field(
+        &mut self,
+        field: String,
+        len: usize,
+        value: impl FnOnce(&[u8]) -> Option<String>,
+    ) -> Option<&'a [u8]> {
+        if self.rest.len() < len {
+            return None;
+        }
+        let start = self.offset();
+        let (bytes, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        self.fields.push(FieldSpan {
+            field,
+            start,
+            end: start + len,
+            hex: hex::encode(bytes),
+            value: value(bytes),
+            non_minimal: false,
+        });
+        Some(bytes)
+    }
+
+    /// Flag the last field as holding a non-minimal ULEB128 value
+    fn flag_non_minimal(&mut self, minimal: bool) {
+        if let Some(span) = self.fields.last_mut() {
+            span.non_minimal |= !minimal;
+        }
+    }
+
+    /// A fixed-size field of Move type `move_type`
+    fn typed(&mut self, field: String, len: usize, move_type: &TypeTag) -> Option<()> {
+        self.field(field, len, |bytes| {
+            args::decode(move_type, bytes).map(render)
+        })
+        .map(|_| ())
+    }
+
+    /// A ULEB128 length or variant index, rendered with `name`
+    fn uleb128(&mut self, field: String, name: impl FnOnce(usize) -> String) -> Option<usize> {
+        let mut input = self.rest;
+        let (value, minimal) = read_uleb128(&mut input)?;
+        let len = self.rest.len() - input.len();
+        self.field(field, len, |_| Some(name(value)))?;
+        self.flag_non_minimal(minimal);
+        Some(value)
+    }
+
+    /// A length-prefixed byte string, as one field
+    fn byte_string(&mut self, field: String, utf8: bool) -> Option<()> {
+        let mut input = self.rest;
+        let (len, minimal) = read_uleb128(&mut input)?;
+        let prefix = self.rest.len() - input.len();
+        self.field(field, prefix + len, |bytes| {
+            let contents = &bytes[prefix..];
+            if utf8 {
+                String::from_utf8(contents.to_vec()).ok()
+            } else {
+                Some(crate::storage::hex_bytes::encode(contents, true))
+            }
+        })?;
+        self.flag_non_minimal(minimal);
+        Some(())
+    }
+
+    /// A vector: its length, then each element
+    fn vector(
+        &mut self,
+        field: &str,
+        mut element: impl FnMut(&mut Self, String) -> Option<()>,
+    ) -> Option<()> {
+        let len = self.uleb128(format!("{}.length", field), |len| len.to_string())?;
+        (0..len).try_for_each(|i| element(self, format!("{}[{}]", field, i)))
+    }
+
+    fn address(&mut self, field: String) -> Option<()> {
+        self.typed(field, 32, &TypeTag::Address)
+    }
+
+    fn transaction(&mut self, kind: TransactionKind) -> Option<()> {
+        self.raw_transaction()?;
+        match kind {
+            TransactionKind::RawTransaction => Some(()),
+            TransactionKind::MultiAgent => self.secondary_signer_addresses(),
+            TransactionKind::FeePayer => {
+                self.secondary_signer_addresses()?;
+                self.address("fee_payer_address".to_string())
+            }
+            TransactionKind::SignedTransaction => {
+                self.uleb128("authenticator.variant".to_string(), |variant| {
+                    variant_name(AUTHENTICATOR_VARIANTS, variant)
+                })?;
+                let len = self.rest.len();
+                self.field("authenticator".to_string(), len, |_| None)
+                    .map(|_| ())
+            }
+        }
+    }
+
+    fn secondary_signer_addresses(&mut self) -> Option<()> {
+        self.vector("secondary_signer_addresses", |walker, field| {
+            walker.address(field)
+        })
+    }
+
+    fn raw_transaction(&mut self) -> Option<()> {
+        self.address("sender".to_string())?;
+        self.typed("sequence_number".to_string(), 8, &TypeTag::U64)?;
+        self.payload()?;
+        self.typed("max_gas_amount".to_string(), 8, &TypeTag::U64)?;
+        self.typed("gas_unit_price".to_string(), 8, &TypeTag::U64)?;
+        self.typed("expiration_timestamp_secs".to_string(), 8, &TypeTag::U64)?;
+        self.typed("chain_id".to_string(), 1, &TypeTag::U8)
+    }
+
+    fn payload(&mut self) -> Option<()> {
+        let variant = self.uleb128("payload.variant".to_string(), |variant| {
+            variant_name(PAYLOAD_VARIANTS, variant)
+        })?;
+        match variant {
+            // Script
+            0 => {
+                self.byte_string("payload.code".to_string(), false)?;
+                self.type_args("payload.type_args")?;
+                self.vector("payload.args", |walker, field| {
+                    walker.script_argument(field)
+                })
+            }
+            // ModuleBundle
+            1 => self.vector("payload.modules", |walker, field| {
+                walker.byte_string(field, false)
+            }),
+            // EntryFunction
+            2 => self.entry_function("payload"),
+            // Multisig
+            3 => {
+                self.address("payload.multisig_address".to_string())?;
+                let present =
+                    self.uleb128("payload.transaction_payload".to_string(), |tag| match tag {
+                        0 => "none".to_string(),
+                        _ => "some".to_string(),
+                    })?;
+                if present == 0 {
+                    return Some(());
+                }
+                self.uleb128(
+                    "payload.transaction_payload.variant".to_string(),
+                    |variant| match variant {
+                        0 => "EntryFunction".to_string(),
+                        other => other.to_string(),
+                    },
+                )?;
+                self.entry_function("payload.transaction_payload")
+            }
+            _ => None,
+        }
+    }
+
+    fn entry_function(&mut self, prefix: &str) -> Option<()> {
+        self.address(format!("{}.module.address", prefix))?;
+        self.byte_string(format!("{}.module.name", prefix), true)?;
+        self.byte_string(format!("{}.function", prefix), true)?;
+        self.type_args(&format!("{}.type_args", prefix))?;
+        self.vector(&format!("{}.args", prefix), |walker, field| {
+            walker.byte_string(field, false)
+        })
+    }
+
+    fn type_args(&mut self, field: &str) -> Option<()> {
+        self.vector(field, |walker, field| {
+            let mut input = walker.rest;
+            let minimal = skip_type_tag(&mut input)?;
+            let len = walker.rest.len() - input.len();
+            walker.field(field, len, |bytes| {
+                aptos_bcs::from_bytes::<TypeTag>(bytes)
+                    .ok()
+                    .map(|type_tag| type_tag::canonical(&type_tag))
+            })?;
+            walker.flag_non_minimal(minimal);
+            Some(())
+        })
+    }
+
+    fn script_argument(&mut self, field: String) -> Option<()> {
+        let variant = self.uleb128(format!("{}.variant", field), |variant| {
+            variant_name(SCRIPT_ARGUMENT_VARIANTS, variant)
+        })?;
+        let (len, move_type) = match variant {
+            0 => (1, TypeTag::U8),
+            1 => (8, TypeTag::U64),
+            2 => (16, TypeTag::U128),
+            3 => (32, TypeTag::Address),
+            4 | 9 => return self.byte_string(field, false),
+            5 => (1, TypeTag::Bool),
+            6 => (2, TypeTag::U16),
+            7 => (4, TypeTag::U32),
+            8 => (32, TypeTag::U256),
+            _ => return None,
+        };
+        self.typed(field, len, &move_type)
+    }
+}
+
+/// `TransactionPayload` variants, by BCS index
+const PAYLOAD_VARIANTS: &[&str] = &["Script", "ModuleBundle", "EntryFunction", "Multisig"];
+
+/// `TransactionArgument` variants, by BCS index
+const SCRIPT_ARGUMENT_VARIANTS: &[&str] = &[
+    "U8",
+    "U64",
+    "U128",
+    "Address",
+    "U8Vector",
+    "Bool",
+    "U16",
+    "U32",
+    "U256",
+    "Serialized",
+];
+
+/// `TransactionAuthenticator` variants, by BCS index
+const AUTHENTICATOR_VARIANTS: &[&str] = &[
+    "Ed25519",
+    "MultiEd25519",
+    "MultiAgent",
+    "FeePayer",
+    "SingleSender",
+];
+
+/// Advance `input` past one BCS `TypeTag`, returning whether every ULEB128
+/// value in it was minimal
+fn skip_type_tag(input: &mut &[u8]) -> Option<bool> {
+    let (variant, mut minimal) = read_uleb128(input)?;
+    match variant {
+        // Primitives, including the signed integers
+        0..=5 | 8..=16 => {}
+        // Vector
+        6 => minimal &= skip_type_tag(input)?,
+        // Struct: address, module, name, type arguments
+        7 => {
+            skip(input, 32)?;
+            for _ in 0..2 {
+                let (len, len_minimal) = read_uleb128(input)?;
+                minimal &= len_minimal;
+                skip(input, len)?;
+            }
+            let (type_args, len_minimal) = read_uleb128(input)?;
+            minimal &= len_minimal;
+            for _ in 0..type_args {
+                minimal &= skip_type_tag(input)?;
+            }
+        }
+        _ => return None,
+    }
+    Some(minimal)
+}
+
+/// A ULEB128 value and whether it was minimally encoded, i.e. its last byte
+/// isn't a zero continuing an earlier one
+fn read_uleb128(input: &mut &[u8]) -> Option<(usize, bool)> {
+    let before = *input;
+    let value = args::uleb128(input)?;
+    let len = before.len() - input.len();
+    Some((value, len == 1 || before[len - 1] != 0))
+}
+
+fn skip(input: &mut &[u8], len: usize) -> Option<()> {
+    *input = input.get(len..)?;
+    Some(())
+}
+
+/// The name of variant `index`, or the index if it isn't known
+fn variant_name(names: &[&str], index: usize) -> String {
+    names
+        .get(index)
+        .map_or_else(|| index.to_string(), |name| name.to_string())
+}
+
+/// A decoded leaf value as text; strings without their JSON quotes
+fn render(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::DecodedTransaction;
+
+    fn fixture() -> Vec<u8> {
+        aptos_bcs::to_bytes(&DecodedTransaction::fixture()).unwrap()
+    }
+
+    fn spans(explanation: &Explanation) -> Vec<(&str, usize, usize)> {
+        explanation
+            .fields
+            .iter()
+            .map(|span| (span.field.as_str(), span.start, span.end))
+            .collect()
+    }
+
+    fn value<'a>(explanation: &'a Explanation, field: &str) -> Option<&'a str> {
+        explanation
+            .fields
+            .iter()
+            .find(|span| span.field == field)
+            .and_then(|span| span.value.as_deref())
+    }
+
+    #[test]
+    fn fields_cover_the_multi_agent_layout() {
+        let bytes = fixture();
+        let explanation = Explanation::new(TransactionKind::MultiAgent, &bytes);
+        assert!(explanation.complete);
+        assert_eq!(explanation.len, bytes.len());
+        assert_eq!(
+            spans(&explanation),
+            [
+                ("sender", 0, 32),
+                ("sequence_number", 32, 40),
+                ("payload.variant", 40, 41),
+                ("payload.module.address", 41, 73),
+                ("payload.module.name", 73, 87),
+                ("payload.function", 87, 96),
+                ("payload.type_args.length", 96, 97),
+                ("payload.args.length", 97, 98),
+                ("payload.args[0]", 98, 131),
+                ("payload.args[1]", 131, 140),
+                ("max_gas_amount", 140, 148),
+                ("gas_unit_price", 148, 156),
+                ("expiration_timestamp_secs", 156, 164),
+                ("chain_id", 164, 165),
+                ("secondary_signer_addresses.length", 165, 166),
+                ("secondary_signer_addresses[0]", 166, 198),
+            ]
+        );
+        assert_eq!(bytes.len(), 198);
+        assert!(explanation.trailing().is_none());
+    }
+
+    #[test]
+    fn leaf_fields_are_decoded() {
+        let explanation = Explanation::new(TransactionKind::MultiAgent, &fixture());
+        assert_eq!(value(&explanation, "sequence_number"), Some("0"));
+        assert_eq!(
+            value(&explanation, "payload.variant"),
+            Some("EntryFunction")
+        );
+        assert_eq!(
+            value(&explanation, "payload.module.name"),
+            Some("aptos_account")
+        );
+        assert_eq!(value(&explanation, "payload.function"), Some("transfer"));
+        assert_eq!(value(&explanation, "max_gas_amount"), Some("200000"));
+        assert_eq!(value(&explanation, "chain_id"), Some("2"));
+        assert_eq!(
+            value(&explanation, "secondary_signer_addresses.length"),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn fields_in_lists_overlapping_fields() {
+        let explanation = Explanation::new(TransactionKind::MultiAgent, &fixture());
+        assert_eq!(
+            explanation.fields_in(39, 42),
+            [
+                "sequence_number",
+                "payload.variant",
+                "payload.module.address"
+            ]
+        );
+        assert_eq!(explanation.fields_in(164, 165), ["chain_id"]);
+        assert!(explanation.fields_in(198, 200).is_empty());
+    }
+
+    #[test]
+    fn leftover_bytes_are_trailing_or_unparsed() {
+        let mut bytes = fixture();
+        bytes.push(0xff);
+        let explanation = Explanation::new(TransactionKind::MultiAgent, &bytes);
+        assert!(explanation.complete);
+        let trailing = explanation.trailing().unwrap();
+        assert_eq!((trailing.start, trailing.end), (198, 199));
+
+        let explanation = Explanation::new(TransactionKind::FeePayer, &bytes);
+        assert!(!explanation.complete);
+        assert!(explanation.trailing().is_none());
+        let last = explanation.fields.last().unwrap();
+        assert_eq!(
+            (last.field.as_str(), last.start, last.end),
+            ("unparsed", 198, 199)
+        );
+    }
+
+    #[test]
+    fn padded_uleb128_is_flagged_non_minimal() {
+        let mut bytes = fixture();
+        // The args length as `0x8200` rather than `0x02`
+        bytes[97] = 0x82;
+        bytes.insert(98, 0x00);
+        let explanation = Explanation::new(TransactionKind::MultiAgent, &bytes);
+        assert!(explanation.complete);
+        let flagged: Vec<_> = explanation
+            .fields
+            .iter()
+            .filter(|span| span.non_minimal)
+            .map(|span| (span.field.as_str(), span.start, span.end))
+            .collect();
+        assert_eq!(flagged, [("payload.args.length", 97, 99)]);
+        assert_eq!(value(&explanation, "payload.args.length"), Some("2"));
+    }
+}
@@ -0,0 +1,117 @@
+//! Seeded synthetic transactions.
+//!
+//! [`generate`] builds valid signing payloads (multi-agent, or fee payer with
+//! [`Options::fee_payer`]) from a seed: random senders and sequence numbers,
+//! a mix of entry function payloads (an APT transfer, a generic
+//! `coin::transfer` and a blob of random bytes), random gas parameters and
+//! the requested number of secondary signers. The same seed and options
+//! always give the same bytes, so tests can fill a store without the TS
+//! frontend and get the same transactions on every run.
+//!
+//! The signer addresses are random, not derived from keys, so generated
+//! transactions can be stored, decoded and reserialized but not signed.
+
+use crate::transaction::DecodedTransaction;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::types::{FeePayerRawTransaction, MultiAgentRawTransaction};
+use aptos_sdk::transaction::{EntryFunction, RawTransaction, TransactionPayload};
+use aptos_sdk::types::{AccountAddress, ChainId, Identifier, MoveModuleId, TypeTag};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+/// Longest random blob in a `store_blob` payload, in bytes
+const MAX_BLOB_LEN: usize = 1024;
+
+/// What to generate
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub seed: u64,
+    pub count: usize,
+    /// Secondary signers of each transaction
+    pub secondary_signers: usize,
+    /// Generate `FeePayerRawTransaction`s instead of multi-agent ones
+    pub fee_payer: bool,
+    pub chain_id: u8,
+    /// Given rather than derived from the clock so output depends only on
+    /// the options
+    pub expiration_timestamp_secs: u64,
+}
+
+/// `options.count` transactions, the same for the same options
+pub fn generate(options: &Options) -> Vec<DecodedTransaction> {
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    (0..options.count)
+        .map(|_| transaction(&mut rng, options))
+        .collect()
+}
+
+/// BCS of `transaction`
+pub fn to_bcs(transaction: &DecodedTransaction) -> Vec<u8> {
+    aptos_bcs::to_bytes(transaction).expect("generated transactions serialize")
+}
+
+fn transaction(rng: &mut ChaCha8Rng, options: &Options) -> DecodedTransaction {
+    let raw_txn = RawTransaction::new(
+        address(rng),
+        rng.gen_range(0..100_000),
+        payload(rng),
+        rng.gen_range(2_000..=2_000_000),
+        rng.gen_range(100..=1_000),
+        options.expiration_timestamp_secs,
+        ChainId::new(options.chain_id),
+    );
+    let secondary_signers = (0..options.secondary_signers)
+        .map(|_| address(rng))
+        .collect();
+    if options.fee_payer {
+        DecodedTransaction::FeePayer(FeePayerRawTransaction::new(
+            raw_txn,
+            secondary_signers,
+            address(rng),
+        ))
+    } else {
+        DecodedTransaction::MultiAgent(MultiAgentRawTransaction::new(raw_txn, secondary_signers))
+    }
+}
+
+/// An entry function argument
+fn bcs<T: Serialize>(value: &T) -> Vec<u8> {
+    aptos_bcs::to_bytes(value).expect("arguments serialize")
+}
+
+fn address(rng: &mut ChaCha8Rng) -> AccountAddress {
+    AccountAddress::new(rng.gen())
+}
+
+fn payload(rng: &mut ChaCha8Rng) -> TransactionPayload {
+    let module = |address: AccountAddress, name: &str| {
+        MoveModuleId::new(address, Identifier::new(name).expect("valid identifier"))
+    };
+    let entry_function = match rng.gen_range(0..3) {
+        0 => EntryFunction::new(
+            module(AccountAddress::ONE, "aptos_account"),
+            "transfer",
+            vec![],
+            vec![bcs(&address(rng)), bcs(&rng.gen::<u64>())],
+        ),
+        1 => EntryFunction::new(
+            module(AccountAddress::ONE, "coin"),
+            "transfer",
+            vec![TypeTag::aptos_coin()],
+            vec![bcs(&address(rng)), bcs(&rng.gen::<u64>())],
+        ),
+        _ => {
+            let blob: Vec<u8> = (0..rng.gen_range(0..=MAX_BLOB_LEN))
+                .map(|_| rng.gen())
+                .collect();
+            EntryFunction::new(
+                module(address(rng), "synthetic"),
+                "store_blob",
+                vec![],
+                vec![bcs(&blob)],
+            )
+        }
+    };
+    TransactionPayload::EntryFunction(entry_function)
+}
@@ -0,0 +1,22 @@
+//! Core of the multi-agent transaction repro backend, without the HTTP server.
+//!
+//! Storage backends, transaction decoding and serialization analysis, the
+//! signing session state machine and the API client live here so they can be
+//! embedded in integration tests and other tools. The `repro-rust-backend`
+//! package puts the axum API, auth, webhooks and background tasks around them.
+
+pub mod args;
+pub mod authenticator;
+pub mod bytediff;
+pub mod canonical;
+pub mod client;
+pub mod compare;
+pub mod decode;
+pub mod encoding;
+pub mod explain;
+pub mod generate;
+pub mod reserialize;
+pub mod session;
+pub mod storage;
+pub mod transaction;
+pub mod type_tag;
@@ -0,0 +1,295 @@
+//! Re-serialization of stored BCS, with a choice of how much of the SDK is used.
+//!
+//! `RESERIALIZE_STRATEGY` (or `x-reserialize-strategy` per request) selects the
+//! path, so a byte difference can be bisected down to the layer causing it:
+//!
+//! - `typed` (default): decode and re-encode the SDK's `MultiAgentRawTransaction`,
+//!   `FeePayerRawTransaction`, `RawTransaction` or `SignedTransaction`.
+//! - `raw-transaction`: only the inner `RawTransaction` goes through the SDK; the
+//!   secondary signer and fee payer addresses are copied as plain 32-byte arrays
+//!   and a signed transaction's authenticator uses the `untyped` mirror.
+//! - `untyped`: a mirror of the on-chain layout built from primitive fields
+//!   only, with no SDK types at all.
+//!
+//! Every strategy accepts every transaction kind (see [`crate::transaction`]).
+//!
+//! If `untyped` roundtrips cleanly but `typed` doesn't, the difference comes
+//! from an SDK type rather than from the bytes themselves; `raw-transaction`
+//! then tells whether it is inside the `RawTransaction`.
+
+use crate::transaction::DecodedTransaction;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::types::RawTransaction;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::ToSchema;
+
+/// Header that overrides the server's mode for a single retrieval
+pub const RESERIALIZE_HEADER: &str = "x-reserialize";
+/// Header that overrides the server's reserialize strategy for a single retrieval
+pub const STRATEGY_HEADER: &str = "x-reserialize-strategy";
+
+/// Which decoder a retrieval re-serializes through
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// The SDK's transaction types
+    Typed,
+    /// The SDK's `RawTransaction`, other addresses as plain bytes
+    RawTransaction,
+    /// Primitive mirror of the on-chain layout, no SDK types
+    Untyped,
+}
+
+impl Strategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Strategy::Typed => "typed",
+            Strategy::RawTransaction => "raw-transaction",
+            Strategy::Untyped => "untyped",
+        }
+    }
+}
+
+/// Decode `bytes` and encode them again using `strategy`
+pub fn reserialize(strategy: Strategy, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    match strategy {
+        Strategy::Typed => {
+            let decoded = DecodedTransaction::decode(bytes)
+                .map_err(|e| format!("BCS deserialize error: {}", e))?;
+            debug!(
+                sender = %decoded.raw_txn().sender,
+                sequence_number = decoded.raw_txn().sequence_number,
+                fee_payer = ?decoded.fee_payer_address(),
+                "Deserialized {} transaction with secondary signers {:?}",
+                decoded.kind().as_str(),
+                decoded.secondary_signer_addresses()
+            );
+            encode(&decoded)
+        }
+        // Same order as `DecodedTransaction::decode`
+        Strategy::RawTransaction => first_roundtrip(
+            bytes,
+            &[
+                roundtrip::<RawTransactionOnly>,
+                roundtrip::<FeePayerRawTransactionOnly>,
+                roundtrip::<RawTransaction>,
+                roundtrip::<SignedTransactionOnly>,
+            ],
+        ),
+        Strategy::Untyped => first_roundtrip(
+            bytes,
+            &[
+                roundtrip::<untyped::MultiAgentRawTransaction>,
+                roundtrip::<untyped::FeePayerRawTransaction>,
+                roundtrip::<untyped::RawTransaction>,
+                roundtrip::<untyped::SignedTransaction>,
+            ],
+        ),
+    }
+}
+
+type Roundtrip = fn(&[u8]) -> Result<Vec<u8>, String>;
+
+/// Re-encode `bytes` with the first of `layouts` they decode as, or return
+/// the first layout's error
+fn first_roundtrip(bytes: &[u8], layouts: &[Roundtrip]) -> Result<Vec<u8>, String> {
+    let mut first_error = None;
+    for layout in layouts {
+        match layout(bytes) {
+            Ok(reserialized) => return Ok(reserialized),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_default())
+}
+
+fn roundtrip<T: Serialize + serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    encode(&decode::<T>(bytes)?)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    aptos_bcs::from_bytes(bytes).map_err(|e| format!("BCS deserialize error: {}", e))
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    aptos_bcs::to_bytes(value).map_err(|e| format!("BCS serialize error: {}", e))
+}
+
+/// A `MultiAgentRawTransaction` where only the `RawTransaction` is an SDK type
+#[derive(Serialize, Deserialize)]
+struct RawTransactionOnly {
+    raw_txn: RawTransaction,
+    secondary_signer_addresses: Vec<[u8; 32]>,
+}
+
+/// A `FeePayerRawTransaction` where only the `RawTransaction` is an SDK type
+#[derive(Serialize, Deserialize)]
+struct FeePayerRawTransactionOnly {
+    raw_txn: RawTransaction,
+    secondary_signer_addresses: Vec<[u8; 32]>,
+    fee_payer_address: [u8; 32],
+}
+
+/// A `SignedTransaction` where only the `RawTransaction` is an SDK type
+#[derive(Serialize, Deserialize)]
+struct SignedTransactionOnly {
+    raw_txn: RawTransaction,
+    authenticator: untyped::TransactionAuthenticator,
+}
+
+/// The signing messages and signed transaction as laid out on chain (see
+/// aptos-core's `RawTransaction`, `TransactionPayload` and
+/// `TransactionAuthenticator`), with plain Rust types only. Variant order is
+/// significant: BCS encodes enum variants by index. Account authenticators
+/// are [`crate::authenticator`]'s mirror, so abstract ones don't decode.
+mod untyped {
+    use crate::authenticator::AccountAuthenticator;
+    use serde::{Deserialize, Serialize};
+
+    type Address = [u8; 32];
+
+    #[derive(Serialize, Deserialize)]
+    pub struct MultiAgentRawTransaction {
+        raw_txn: RawTransaction,
+        secondary_signer_addresses: Vec<Address>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct FeePayerRawTransaction {
+        raw_txn: RawTransaction,
+        secondary_signer_addresses: Vec<Address>,
+        fee_payer_address: Address,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RawTransaction {
+        sender: Address,
+        sequence_number: u64,
+        payload: TransactionPayload,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        expiration_timestamp_secs: u64,
+        chain_id: u8,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum TransactionPayload {
+        Script(Script),
+        /// Deprecated; a list of module bytecodes
+        ModuleBundle(Vec<Vec<u8>>),
+        EntryFunction(EntryFunction),
+        Multisig(Multisig),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Script {
+        code: Vec<u8>,
+        type_args: Vec<TypeTag>,
+        args: Vec<TransactionArgument>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum TransactionArgument {
+        U8(u8),
+        U64(u64),
+        U128(u128),
+        Address(Address),
+        U8Vector(Vec<u8>),
+        Bool(bool),
+        U16(u16),
+        U32(u32),
+        U256([u8; 32]),
+        /// Any other argument, as its BCS bytes
+        Serialized(Vec<u8>),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct EntryFunction {
+        module: ModuleId,
+        function: String,
+        type_args: Vec<TypeTag>,
+        args: Vec<Vec<u8>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ModuleId {
+        address: Address,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Multisig {
+        multisig_address: Address,
+        transaction_payload: Option<MultisigTransactionPayload>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum MultisigTransactionPayload {
+        EntryFunction(EntryFunction),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum TypeTag {
+        Bool,
+        U8,
+        U64,
+        U128,
+        Address,
+        Signer,
+        Vector(Box<TypeTag>),
+        Struct(Box<StructTag>),
+        U16,
+        U32,
+        U256,
+        I8,
+        I16,
+        I32,
+        I64,
+        I128,
+        I256,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct StructTag {
+        address: Address,
+        module: String,
+        name: String,
+        type_args: Vec<TypeTag>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SignedTransaction {
+        raw_txn: RawTransaction,
+        authenticator: TransactionAuthenticator,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum TransactionAuthenticator {
+        Ed25519 {
+            public_key: Vec<u8>,
+            signature: Vec<u8>,
+        },
+        MultiEd25519 {
+            public_key: Vec<u8>,
+            signature: Vec<u8>,
+        },
+        MultiAgent {
+            sender: AccountAuthenticator,
+            secondary_signer_addresses: Vec<Address>,
+            secondary_signers: Vec<AccountAuthenticator>,
+        },
+        FeePayer {
+            sender: AccountAuthenticator,
+            secondary_signer_addresses: Vec<Address>,
+            secondary_signers: Vec<AccountAuthenticator>,
+            fee_payer_address: Address,
+            fee_payer_signer: AccountAuthenticator,
+        },
+        SingleSender {
+            sender: AccountAuthenticator,
+        },
+    }
+}
@@ -0,0 +1,325 @@
+//! Signing sessions: where a stored transaction stands on its way on chain.
+//!
+//! ```text
+//! Created → AwaitingSignatures → Complete → Submitted → Finalized
+//!        ↘           ↓      ↘     ↗                   ↘ Failed
+//!                 Rejected  QuorumMet
+//! ```
+//!
+//! A transaction stored with a quorum passes through QuorumMet once that
+//! many secondary signers have signed: the approval is in, but the chain
+//! still needs every signer a multi-agent transaction names, so it can't be
+//! submitted until the rest have signed too.
+//!
+//! A failed submission may be retried or rebuilt with `refresh-sequence`, a
+//! signer may decline while signatures are being collected, and a session not
+//! yet submitted becomes Expired once the chain would refuse it or the
+//! server's expiry sweeper gives up on it.
+//!
+//! The state is derived from what is already stored (the signatures
+//! collected, the history and the expiration) rather than kept in a field of
+//! its own, so every backend, snapshot and export carries it unchanged and it
+//! can't disagree with the data. Handlers check an operation against it before
+//! acting, so a signature arriving after submission is refused instead of
+//! quietly changing a transaction that has already left the backend.
+
+use crate::storage::{EventKind, StoredTransaction};
+use crate::transaction::DecodedTransaction;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    /// Stored, nobody but the sender has signed yet
+    Created,
+    /// Some signatures collected, others still missing
+    AwaitingSignatures,
+    /// The quorum of secondary signers (and the fee payer) has signed, but
+    /// not every signer the chain requires; not submittable yet
+    QuorumMet,
+    /// Every secondary signer (and fee payer) has signed; ready to submit.
+    /// Transactions that don't decode are complete once anyone has signed.
+    Complete,
+    /// Accepted by the fullnode, outcome not known yet
+    Submitted,
+    /// Committed on chain and executed successfully
+    Finalized,
+    /// The fullnode rejected it or couldn't be reached; it may be submitted
+    /// again, or rebuilt with `refresh-sequence`. Also a transaction committed
+    /// but failed in execution, which used up its sequence number and so
+    /// can only be rebuilt
+    Failed,
+    /// Past its `expiration_timestamp_secs` or the backend's TTL before it
+    /// was submitted, or marked expired by the sweeper
+    Expired,
+    /// A signer declined to sign
+    Rejected,
+}
+
+/// Requests that change a session, checked with [`SessionState::check`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Sign,
+    Decline,
+    Submit,
+    RefreshSequence,
+}
+
+impl Operation {
+    fn describe(self) -> &'static str {
+        match self {
+            Operation::Sign => "attach a signature",
+            Operation::Decline => "decline",
+            Operation::Submit => "submit",
+            Operation::RefreshSequence => "refresh the sequence number",
+        }
+    }
+}
+
+impl SessionState {
+    /// The state of `tx` at `now` (Unix seconds). `decoded` is `tx`'s BCS,
+    /// decoded; `ttl_expired` whether it has outlived the backend's TTL.
+    pub fn of(
+        tx: &StoredTransaction,
+        decoded: Option<&DecodedTransaction>,
+        ttl_expired: bool,
+        now: u64,
+    ) -> Self {
+        // The latest submission outcome, decline or expiry wins; a sequence
+        // refresh rebuilds the transaction and starts collecting signatures again
+        let mut outcome = None;
+        for event in &tx.history {
+            outcome = match event.kind {
+                EventKind::Submitted => Some(SessionState::Submitted),
+                EventKind::SubmissionFailed => Some(SessionState::Failed),
+                EventKind::Committed => match &event.commit {
+                    Some(commit) if !commit.success => Some(SessionState::Failed),
+                    _ => Some(SessionState::Finalized),
+                },
+                EventKind::Declined => Some(SessionState::Rejected),
+                EventKind::Expired => Some(SessionState::Expired),
+                EventKind::SequenceRefreshed => None,
+                _ => outcome,
+            };
+        }
+        if let Some(state) = outcome {
+            return state;
+        }
+
+        let chain_expired =
+            decoded.is_some_and(|decoded| decoded.raw_txn().expiration_timestamp_secs <= now);
+        if ttl_expired || chain_expired {
+            return SessionState::Expired;
+        }
+
+        let secondary_signatures = tx.current_secondary_signatures();
+        let signed = !secondary_signatures.is_empty() || tx.current_fee_payer_signature().is_some();
+        let missing = decoded
+            .map(|decoded| decoded.missing_signers(&secondary_signatures, tx.fee_payer_signed()));
+        match missing {
+            Some(missing) if missing.is_empty() => SessionState::Complete,
+            Some(_) if tx.quorum_met() => SessionState::QuorumMet,
+            Some(_) if signed => SessionState::AwaitingSignatures,
+            None if signed => SessionState::Complete,
+            _ => SessionState::Created,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionState::Created => "created",
+            SessionState::AwaitingSignatures => "awaiting_signatures",
+            SessionState::QuorumMet => "quorum_met",
+            SessionState::Complete => "complete",
+            SessionState::Submitted => "submitted",
+            SessionState::Finalized => "finalized",
+            SessionState::Failed => "failed",
+            SessionState::Expired => "expired",
+            SessionState::Rejected => "rejected",
+        }
+    }
+
+    /// Whether `operation` may be applied in this state
+    pub fn allows(self, operation: Operation) -> bool {
+        let collecting = matches!(
+            self,
+            SessionState::Created
+                | SessionState::AwaitingSignatures
+                | SessionState::QuorumMet
+                | SessionState::Complete
+        );
+        match operation {
+            Operation::Sign | Operation::Decline => collecting,
+            // Missing signatures are reported by assembly itself, with who
+            Operation::Submit | Operation::RefreshSequence => {
+                collecting || self == SessionState::Failed
+            }
+        }
+    }
+
+    /// `Err` with the status and message to answer with if `operation` is
+    /// illegal in this state: `410` once expired, `409` otherwise
+    pub fn check(self, operation: Operation) -> Result<(), (StatusCode, String)> {
+        if self.allows(operation) {
+            return Ok(());
+        }
+        let status = match self {
+            SessionState::Expired => StatusCode::GONE,
+            _ => StatusCode::CONFLICT,
+        };
+        Err((
+            status,
+            format!(
+                "Cannot {}: transaction is {}",
+                operation.describe(),
+                self.as_str()
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Commit, Signer, TransactionEvent};
+    use aptos_sdk::transaction::types::MultiAgentRawTransaction;
+    use aptos_sdk::AccountAddress;
+
+    fn stored(history: Vec<TransactionEvent>) -> StoredTransaction {
+        StoredTransaction {
+            secondary_signatures: [("0xa".to_string(), vec![1])].into(),
+            history,
+            ..StoredTransaction::default()
+        }
+    }
+
+    fn committed(success: bool) -> TransactionEvent {
+        TransactionEvent::committed(Commit {
+            version: 1,
+            success,
+            vm_status: String::new(),
+            gas_used: 0,
+        })
+    }
+
+    #[test]
+    fn failed_execution_is_not_finalized() {
+        let state = |success| SessionState::of(&stored(vec![committed(success)]), None, false, 0);
+        assert_eq!(state(true), SessionState::Finalized);
+        assert_eq!(state(false), SessionState::Failed);
+    }
+
+    #[test]
+    fn stale_signatures_dont_count_as_signed() {
+        let signer = Signer::Secondary("0xa".to_string());
+        let stale = stored(vec![TransactionEvent::stale(&signer, "bad".to_string())]);
+        assert_eq!(
+            SessionState::of(&stale, None, false, 0),
+            SessionState::Created
+        );
+        assert_eq!(
+            SessionState::of(&stored(Vec::new()), None, false, 0),
+            SessionState::Complete
+        );
+    }
+
+    /// The fixture transaction with secondary signers `0xa` and `0xb`
+    fn two_signers() -> DecodedTransaction {
+        let raw_txn = DecodedTransaction::fixture().raw_txn().clone();
+        let signers = ["0xa", "0xb"].map(|address| AccountAddress::from_hex(address).unwrap());
+        DecodedTransaction::MultiAgent(MultiAgentRawTransaction::new(raw_txn, signers.to_vec()))
+    }
+
+    fn signed_by(signers: &[&str]) -> StoredTransaction {
+        StoredTransaction {
+            secondary_signatures: signers
+                .iter()
+                .map(|address| {
+                    let address = AccountAddress::from_hex(address).unwrap();
+                    (address.to_long_string(), vec![1])
+                })
+                .collect(),
+            ..StoredTransaction::default()
+        }
+    }
+
+    #[test]
+    fn signatures_complete_a_session() {
+        let decoded = two_signers();
+        let state = |tx: &StoredTransaction| SessionState::of(tx, Some(&decoded), false, 0);
+        assert_eq!(state(&signed_by(&[])), SessionState::Created);
+        assert_eq!(
+            state(&signed_by(&["0xa"])),
+            SessionState::AwaitingSignatures
+        );
+        assert_eq!(state(&signed_by(&["0xa", "0xb"])), SessionState::Complete);
+
+        // As recorded at ingest
+        let quorum = StoredTransaction {
+            quorum: Some(1),
+            required_signers: Some(decoded.required_signers()),
+            ..signed_by(&["0xb"])
+        };
+        assert_eq!(state(&quorum), SessionState::QuorumMet);
+    }
+
+    #[test]
+    fn unsubmitted_session_expires() {
+        let decoded = two_signers();
+        let expiration = decoded.raw_txn().expiration_timestamp_secs;
+        let tx = signed_by(&["0xa", "0xb"]);
+        assert_eq!(
+            SessionState::of(&tx, Some(&decoded), true, 0),
+            SessionState::Expired
+        );
+        assert_eq!(
+            SessionState::of(&tx, Some(&decoded), false, expiration),
+            SessionState::Expired
+        );
+
+        let submitted = StoredTransaction {
+            history: vec![TransactionEvent::now(EventKind::Submitted, None)],
+            ..tx
+        };
+        assert_eq!(
+            SessionState::of(&submitted, Some(&decoded), true, expiration),
+            SessionState::Submitted
+        );
+    }
+
+    #[test]
+    fn sequence_refresh_restarts_a_failed_session() {
+        let failed = TransactionEvent::now(EventKind::SubmissionFailed, None);
+        let refreshed = TransactionEvent::now(EventKind::SequenceRefreshed, None);
+        let state = |history| SessionState::of(&stored(history), None, false, 0);
+        assert_eq!(state(vec![failed.clone()]), SessionState::Failed);
+        assert_eq!(state(vec![failed, refreshed]), SessionState::Complete);
+    }
+
+    #[test]
+    fn operations_are_checked_against_the_state() {
+        assert!(SessionState::AwaitingSignatures
+            .check(Operation::Sign)
+            .is_ok());
+        assert!(SessionState::Failed.check(Operation::Submit).is_ok());
+        assert!(SessionState::Failed
+            .check(Operation::RefreshSequence)
+            .is_ok());
+
+        let (status, message) = SessionState::Submitted.check(Operation::Sign).unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            message,
+            "Cannot attach a signature: transaction is submitted"
+        );
+        let (status, _) = SessionState::Expired.check(Operation::Sign).unwrap_err();
+        assert_eq!(status, StatusCode::GONE);
+        let (status, _) = SessionState::Rejected
+            .check(Operation::Decline)
+            .unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(SessionState::Failed.check(Operation::Sign).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! Optional zstd compression of stored BCS payloads (the `zstd` feature).
+//!
+//! Large Move script transactions compress very well. With `COMPRESSION=zstd`
+//! the BCS bytes are compressed at ingest at `ZSTD_LEVEL` (default 3), but only
+//! kept compressed if that actually makes them smaller; such entries are
+//! flagged with [`StoredTransaction::compressed`](super::StoredTransaction).
+
+use super::{StorageArgs, StoreError, StoreResult};
+
+/// `--compression` / `COMPRESSION`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CompressionAlgorithm {
+    None,
+    Zstd,
+}
+
+/// Compression settings
+#[derive(Clone, Copy, Debug)]
+pub struct Compression {
+    level: i32,
+}
+
+impl Compression {
+    /// Returns `Ok(None)` for [`CompressionAlgorithm::None`]
+    pub fn from_config(config: &StorageArgs) -> StoreResult<Option<Self>> {
+        match config.compression {
+            CompressionAlgorithm::None => Ok(None),
+            CompressionAlgorithm::Zstd if !cfg!(feature = "zstd") => Err(StoreError::Config(
+                "--compression zstd requires building with `--features zstd`".to_string(),
+            )),
+            CompressionAlgorithm::Zstd => Ok(Some(Self {
+                level: config.zstd_level,
+            })),
+        }
+    }
+
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    /// Compress `bytes`, returning `None` if that wouldn't make them smaller
+    pub fn compress(&self, bytes: &[u8]) -> StoreResult<Option<Vec<u8>>> {
+        #[cfg(feature = "zstd")]
+        {
+            let compressed = zstd::bulk::compress(bytes, self.level).map_err(zstd_error)?;
+            Ok((compressed.len() < bytes.len()).then_some(compressed))
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            let _ = bytes;
+            Err(not_compiled_in())
+        }
+    }
+}
+
+/// Decompress a payload stored with [`Compression::compress`]
+pub fn decompress(bytes: &[u8]) -> StoreResult<Vec<u8>> {
+    #[cfg(feature = "zstd")]
+    {
+        zstd::stream::decode_all(bytes).map_err(zstd_error)
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        let _ = bytes;
+        Err(not_compiled_in())
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_error(e: std::io::Error) -> StoreError {
+    StoreError::Backend(format!("zstd: {}", e))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn not_compiled_in() -> StoreError {
+    StoreError::Config("entry is zstd-compressed; rebuild with `--features zstd`".to_string())
+}
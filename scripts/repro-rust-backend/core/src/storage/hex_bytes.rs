@@ -0,0 +1,93 @@
+//! Hex encoding helpers for byte payloads.
+//!
+//! Payloads are held as raw bytes in memory but written as hex strings
+//! wherever they are serialized (JSON snapshots, WAL records, sled values),
+//! so those files stay readable. Use with `#[serde(with = "hex_bytes")]`.
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Decode a hex string, returning the bytes and whether it had a `0x` prefix
+pub fn decode(s: &str) -> Result<(Vec<u8>, bool), hex::FromHexError> {
+    match s.strip_prefix("0x") {
+        Some(stripped) => Ok((hex::decode(stripped)?, true)),
+        None => Ok((hex::decode(s)?, false)),
+    }
+}
+
+/// Encode bytes as hex, optionally with a `0x` prefix
+pub fn encode(bytes: &[u8], prefixed: bool) -> String {
+    if prefixed {
+        format!("0x{}", hex::encode(bytes))
+    } else {
+        hex::encode(bytes)
+    }
+}
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    decode(&s)
+        .map(|(bytes, _)| bytes)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Same as the parent module, for `Option<Vec<u8>>` fields
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => super::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| {
+                super::decode(&s)
+                    .map(|(bytes, _)| bytes)
+                    .map_err(serde::de::Error::custom)
+            })
+            .transpose()
+    }
+}
+
+/// Same as the parent module, for maps whose values are byte payloads
+pub mod map {
+    use serde::ser::SerializeMap;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        map: &BTreeMap<String, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries = serializer.serialize_map(Some(map.len()))?;
+        for (key, bytes) in map {
+            entries.serialize_entry(key, &hex::encode(bytes))?;
+        }
+        entries.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Vec<u8>>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, s)| {
+                super::decode(&s)
+                    .map(|(bytes, _)| (key, bytes))
+                    .map_err(serde::de::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,221 @@
+//! In-memory storage backend (the default).
+
+use super::{Signer, StoreResult, StoredTransaction, TransactionEvent, TransactionStore};
+use async_trait::async_trait;
+use lru::LruCache;
+use std::hash::{BuildHasher, RandomState};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tracing::debug;
+
+/// Shards used unless `--memory-shards` says otherwise
+pub const DEFAULT_SHARDS: NonZeroUsize = match NonZeroUsize::new(16) {
+    Some(shards) => shards,
+    None => unreachable!(),
+};
+
+/// Stores transactions in process memory; everything is lost on restart.
+///
+/// Transactions are spread over independently locked shards by a hash of
+/// their id, so hundreds of sessions storing and polling at once rarely wait
+/// on each other. If a capacity is set, a store-wide count keeps the store
+/// at that many transactions, so long fuzz runs can't exhaust memory. A
+/// store past the cap evicts the least recently used transaction of the
+/// shard it just wrote to (or, if that shard holds nothing else, of the next
+/// shard that does). The cap is exact, but which transaction goes is only
+/// approximately the least recently used across the whole store.
+pub struct MemoryStore {
+    shards: Box<[Mutex<LruCache<String, StoredTransaction>>]>,
+    hasher: RandomState,
+    capacity: Option<NonZeroUsize>,
+    /// Transactions across all shards
+    len: AtomicUsize,
+    evictions: AtomicU64,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new(None, DEFAULT_SHARDS)
+    }
+}
+
+impl MemoryStore {
+    /// Create a store holding at most `capacity` transactions (`None` =
+    /// unbounded) across `shards` locks
+    pub fn new(capacity: Option<NonZeroUsize>, shards: NonZeroUsize) -> Self {
+        let shards = (0..shards.get())
+            .map(|_| Mutex::new(LruCache::unbounded()))
+            .collect();
+        Self {
+            shards,
+            hasher: RandomState::new(),
+            capacity,
+            len: AtomicUsize::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    fn index(&self, transaction_id: &str) -> usize {
+        self.hasher.hash_one(transaction_id) as usize % self.shards.len()
+    }
+
+    /// The shard holding `transaction_id`. A panic while a shard was held
+    /// can't leave an entry half written, so a poisoned lock is used anyway
+    /// rather than failing every later request for that shard.
+    fn shard(&self, transaction_id: &str) -> MutexGuard<'_, LruCache<String, StoredTransaction>> {
+        lock(&self.shards[self.index(transaction_id)])
+    }
+
+    /// Evict until the store is back at capacity, starting with shard
+    /// `index` and never evicting `keep`. Shards are locked one at a time,
+    /// and each eviction first claims one transaction over the cap from
+    /// `len`, so racing stores never evict more than they added.
+    fn evict(&self, index: usize, keep: &str) {
+        let Some(capacity) = self.capacity else {
+            return;
+        };
+        if self.len.load(Ordering::Relaxed) <= capacity.get() {
+            return;
+        }
+        for offset in 0..self.shards.len() {
+            let mut shard = lock(&self.shards[(index + offset) % self.shards.len()]);
+            while matches!(shard.peek_lru(), Some((id, _)) if id != keep) {
+                let claimed = self
+                    .len
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |len| {
+                        (len > capacity.get()).then(|| len - 1)
+                    });
+                if claimed.is_err() {
+                    return;
+                }
+                let (evicted_id, _) = shard.pop_lru().expect("peeked under the same lock");
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+                debug!("Evicted least recently used transaction: {}", evicted_id);
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[async_trait]
+impl TransactionStore for MemoryStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    fn durable(&self) -> bool {
+        false
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        let index = self.index(transaction_id);
+        let replaced = lock(&self.shards[index])
+            .put(transaction_id.to_string(), tx)
+            .is_some();
+        if !replaced {
+            self.len.fetch_add(1, Ordering::Relaxed);
+            self.evict(index, transaction_id);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        let mut shard = self.shard(transaction_id);
+        Ok(shard.get(transaction_id).cloned())
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        let mut shard = self.shard(transaction_id);
+        match shard.get_mut(transaction_id) {
+            Some(tx) => {
+                tx.set_signature(signer, signature);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        let mut shard = self.shard(transaction_id);
+        match shard.get_mut(transaction_id) {
+            Some(tx) => {
+                tx.history.extend(events);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let removed = self.shard(transaction_id).pop(transaction_id).is_some();
+        if removed {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+        Ok(removed)
+    }
+
+    /// Shards are copied one at a time, so writes landing meanwhile may or
+    /// may not be included
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        Ok(self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                lock(shard)
+                    .iter()
+                    .map(|(id, tx)| (id.clone(), tx.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn capacity_is_store_wide() {
+        let store = MemoryStore::new(NonZeroUsize::new(3), DEFAULT_SHARDS);
+        for i in 0..10 {
+            store
+                .put(&format!("tx{}", i), StoredTransaction::default())
+                .await
+                .unwrap();
+            // The transaction just stored is never the one evicted
+            assert!(store.get(&format!("tx{}", i)).await.unwrap().is_some());
+        }
+        assert_eq!(store.list().await.unwrap().len(), 3);
+        assert_eq!(store.evictions(), 7);
+
+        // Replacing or removing doesn't throw the count off
+        store
+            .put("tx9", StoredTransaction::default())
+            .await
+            .unwrap();
+        assert!(store.remove("tx9").await.unwrap());
+        store
+            .put("tx10", StoredTransaction::default())
+            .await
+            .unwrap();
+        assert_eq!(store.list().await.unwrap().len(), 3);
+        assert_eq!(store.evictions(), 7);
+    }
+}
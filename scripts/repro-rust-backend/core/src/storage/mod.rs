@@ -0,0 +1,954 @@
+//! Pluggable storage for serialized transactions and signatures.
+//!
+//! Handlers only talk to the [`TransactionStore`] trait, so the backing
+//! storage can be swapped without touching any request handling code.
+
+mod compression;
+pub mod hex_bytes;
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "rocksdb")]
+mod rocksdb;
+#[cfg(feature = "sled")]
+mod sled;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "redis")]
+pub use self::redis::RedisStore;
+#[cfg(feature = "rocksdb")]
+pub use self::rocksdb::RocksDbStore;
+pub use compression::{Compression, CompressionAlgorithm};
+#[cfg(feature = "sled")]
+pub use self::sled::SledStore;
+pub use memory::MemoryStore;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+use crate::authenticator;
+#[cfg(feature = "redis")]
+use crate::transaction::DecodedTransaction;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use utoipa::ToSchema;
+
+/// A stored transaction and the signatures collected for it.
+///
+/// Payloads are held as raw bytes: hex is decoded once at ingest and only
+/// encoded again when building a response. The default is an empty,
+/// unsigned transaction stored at time zero.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct StoredTransaction {
+    /// Raw BCS bytes from TypeScript SDK (stored as-is, returned as-is)
+    #[serde(with = "hex_bytes")]
+    #[schema(value_type = String)]
+    pub raw_bcs: Vec<u8>,
+    /// Whether `raw_bcs` is zstd-compressed (see [`Compression`])
+    #[serde(default)]
+    pub compressed: bool,
+    /// Whether the submitted hex had a `0x` prefix, so it is returned the same way
+    pub hex_prefixed: bool,
+    /// Secondary signers' serialized `AccountAuthenticator`s, keyed by
+    /// long-form signer address
+    #[serde(default, with = "hex_bytes::map")]
+    #[schema(value_type = BTreeMap<String, String>)]
+    pub secondary_signatures: BTreeMap<String, Vec<u8>>,
+    /// Fee payer's serialized `AccountAuthenticator`, for sponsored transactions
+    #[serde(default, with = "hex_bytes::option")]
+    #[schema(value_type = Option<String>)]
+    pub fee_payer_signature: Option<Vec<u8>>,
+    /// Timestamp when stored
+    pub stored_at: u64,
+    /// Sender address (long form), decoded at ingest; `None` if the BCS is
+    /// not a transaction the backend can decode
+    #[serde(default)]
+    pub sender: Option<String>,
+    /// Sequence number, decoded at ingest alongside `sender`
+    #[serde(default)]
+    pub sequence_number: Option<u64>,
+    /// Whether `raw_bcs` was canonical BCS at ingest (see [`crate::canonical`]);
+    /// `None` if it follows no transaction layout or was stored before the check
+    #[serde(default)]
+    pub canonical: Option<bool>,
+    /// Signers other than the sender, decoded at ingest: long-form secondary
+    /// signer addresses, then `fee_payer` for sponsored transactions. `None`
+    /// alongside `sender`, or if stored before signers were recorded.
+    #[serde(default)]
+    pub required_signers: Option<Vec<String>>,
+    /// Secondary signatures that complete the session (K of N), if fewer
+    /// than every secondary signer's
+    #[serde(default)]
+    pub quorum: Option<u32>,
+    /// Everything that happened to this transaction, oldest first
+    #[serde(default)]
+    pub history: Vec<TransactionEvent>,
+}
+
+/// An entry in a transaction's history, kept for post-mortems of failing flows
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TransactionEvent {
+    pub kind: EventKind,
+    /// Unix time in milliseconds
+    pub at_ms: u64,
+    /// Extra context, e.g. the byte lengths involved in a mismatch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// What the chain recorded, on `committed` events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<Commit>,
+    /// Long-form address of the signer, or `fee_payer`, on signature events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+}
+
+/// A submitted transaction as committed on chain
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Commit {
+    /// Ledger version it was committed at
+    pub version: u64,
+    /// Whether it executed successfully; a failed transaction is still
+    /// committed and still uses its sequence number
+    pub success: bool,
+    /// e.g. `Executed successfully` or `Move abort in 0x1::coin: EINSUFFICIENT_BALANCE(0x10006)`
+    pub vm_status: String,
+    pub gas_used: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Stored,
+    SignatureAdded,
+    FeePayerSignatureAdded,
+    Retrieved,
+    Reserialized,
+    MismatchDetected,
+    Assembled,
+    Submitted,
+    SubmissionFailed,
+    SequenceRefreshed,
+    /// The submitted transaction was committed on chain
+    Committed,
+    /// A signer refused to sign, ending the session
+    Declined,
+    /// The session expired and will be removed by the server's next sweep
+    Expired,
+    /// Chaos mode returned corrupted BCS for a retrieval
+    Corrupted,
+    /// A stored signature no longer verifies against the stored bytes; the
+    /// signer has to sign again
+    SignatureStale,
+}
+
+impl TransactionEvent {
+    /// An event of `kind` happening now
+    pub fn now(kind: EventKind, detail: Option<String>) -> Self {
+        Self {
+            kind,
+            at_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            detail,
+            commit: None,
+            signer: None,
+        }
+    }
+
+    /// A `signature_added` or `fee_payer_signature_added` event for `signer`
+    pub fn signed(signer: &Signer, detail: String) -> Self {
+        let kind = match signer {
+            Signer::Secondary(_) => EventKind::SignatureAdded,
+            Signer::FeePayer => EventKind::FeePayerSignatureAdded,
+        };
+        Self {
+            signer: Some(signer.name().to_string()),
+            ..Self::now(kind, Some(detail))
+        }
+    }
+
+    /// A `signature_stale` event for `signer`, whose signature fails `reason`
+    pub fn stale(signer: &Signer, reason: String) -> Self {
+        Self {
+            signer: Some(signer.name().to_string()),
+            ..Self::now(
+                EventKind::SignatureStale,
+                Some(format!("{}: {}", signer.name(), reason)),
+            )
+        }
+    }
+
+    /// A `committed` event for `commit`
+    pub fn committed(commit: Commit) -> Self {
+        let detail = format!("version {}: {}", commit.version, commit.vm_status);
+        Self {
+            commit: Some(commit),
+            ..Self::now(EventKind::Committed, Some(detail))
+        }
+    }
+}
+
+impl StoredTransaction {
+    /// Raw BCS bytes, decompressed if they were stored compressed
+    pub fn bcs(&self) -> StoreResult<Cow<'_, [u8]>> {
+        if self.compressed {
+            compression::decompress(&self.raw_bcs).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(&self.raw_bcs))
+        }
+    }
+
+    /// Hash of the latest submission the fullnode accepted
+    pub fn submitted_hash(&self) -> Option<&str> {
+        self.history
+            .iter()
+            .rev()
+            .find(|event| event.kind == EventKind::Submitted)
+            .and_then(|event| event.detail.as_deref())
+    }
+
+    /// How the transaction was committed, once verified
+    pub fn commit(&self) -> Option<&Commit> {
+        self.history
+            .iter()
+            .rev()
+            .find_map(|event| event.commit.as_ref())
+    }
+
+    /// Secondary signatures as `0x`-prefixed hex, keyed by signer address
+    pub fn secondary_signatures_hex(&self) -> BTreeMap<String, String> {
+        self.secondary_signatures
+            .iter()
+            .map(|(address, signature)| (address.clone(), hex_bytes::encode(signature, true)))
+            .collect()
+    }
+
+    /// Whether any secondary signer has signed yet
+    pub fn has_secondary_signature(&self) -> bool {
+        !self.secondary_signatures.is_empty()
+    }
+
+    /// Fee payer signature as `0x`-prefixed hex
+    pub fn fee_payer_signature_hex(&self) -> Option<String> {
+        self.fee_payer_signature
+            .as_deref()
+            .map(|signature| hex_bytes::encode(signature, true))
+    }
+
+    /// Signers (long-form addresses, or `fee_payer`) whose stored signature
+    /// was found stale and who haven't signed again since
+    pub fn stale_signers(&self) -> BTreeSet<String> {
+        let mut stale = BTreeSet::new();
+        for event in &self.history {
+            match (event.kind, &event.signer) {
+                (EventKind::SignatureStale, Some(signer)) => {
+                    stale.insert(signer.clone());
+                }
+                (EventKind::SignatureAdded | EventKind::FeePayerSignatureAdded, Some(signer)) => {
+                    stale.remove(signer);
+                }
+                // The signatures were dropped along with the old bytes
+                (EventKind::SequenceRefreshed, _) => stale.clear(),
+                _ => {}
+            }
+        }
+        stale
+    }
+
+    /// Secondary signatures, less those found stale
+    pub fn current_secondary_signatures(&self) -> Cow<'_, BTreeMap<String, Vec<u8>>> {
+        let stale = self.stale_signers();
+        if stale.is_empty() {
+            return Cow::Borrowed(&self.secondary_signatures);
+        }
+        Cow::Owned(
+            self.secondary_signatures
+                .iter()
+                .filter(|(address, _)| !stale.contains(*address))
+                .map(|(address, signature)| (address.clone(), signature.clone()))
+                .collect(),
+        )
+    }
+
+    /// The fee payer's signature, unless it was found stale
+    pub fn current_fee_payer_signature(&self) -> Option<&[u8]> {
+        self.fee_payer_signature
+            .as_deref()
+            .filter(|_| !self.stale_signers().contains(FEE_PAYER))
+    }
+
+    /// Whether `signer`, as listed in `required_signers`, has signed, with
+    /// enough partial signatures for a `MultiEd25519` or `MultiKey` account
+    /// and a signature that wasn't found stale
+    fn has_signed(&self, signer: &str) -> bool {
+        if signer == FEE_PAYER {
+            self.fee_payer_signed()
+        } else {
+            self.secondary_signatures
+                .get(signer)
+                .is_some_and(|signature| authenticator::complete(signature))
+                && !self.stale_signers().contains(signer)
+        }
+    }
+
+    /// Whether the fee payer's signature is attached, wasn't found stale, and
+    /// meets its threshold if the fee payer is a `MultiEd25519` or `MultiKey`
+    /// account
+    pub fn fee_payer_signed(&self) -> bool {
+        self.current_fee_payer_signature()
+            .is_some_and(authenticator::complete)
+    }
+
+    /// Required signers whose signature is attached, in `required_signers` order
+    pub fn signers_done(&self) -> Option<Vec<String>> {
+        self.required_signers.as_ref().map(|signers| {
+            signers
+                .iter()
+                .filter(|signer| self.has_signed(signer))
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// Required signers yet to sign, in `required_signers` order
+    pub fn signers_pending(&self) -> Option<Vec<String>> {
+        self.required_signers.as_ref().map(|signers| {
+            signers
+                .iter()
+                .filter(|signer| !self.has_signed(signer))
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// Whether `quorum` required secondary signers have signed, and the fee
+    /// payer too if there is one; `false` without a quorum
+    pub fn quorum_met(&self) -> bool {
+        let (Some(quorum), Some(signers)) = (self.quorum, &self.required_signers) else {
+            return false;
+        };
+        let secondaries = signers
+            .iter()
+            .filter(|signer| *signer != FEE_PAYER && self.has_signed(signer))
+            .count();
+        let fee_payer_signed =
+            !signers.iter().any(|signer| signer == FEE_PAYER) || self.fee_payer_signed();
+        secondaries >= quorum as usize && fee_payer_signed
+    }
+
+    /// `signer`'s stored signature
+    pub fn signature(&self, signer: &Signer) -> Option<&[u8]> {
+        match signer {
+            Signer::Secondary(address) => self.secondary_signatures.get(address).map(Vec::as_slice),
+            Signer::FeePayer => self.fee_payer_signature.as_deref(),
+        }
+    }
+
+    /// Store `signature` as `signer`'s, replacing any earlier one
+    pub fn set_signature(&mut self, signer: &Signer, signature: Vec<u8>) {
+        match signer {
+            Signer::Secondary(address) => {
+                self.secondary_signatures.insert(address.clone(), signature);
+            }
+            Signer::FeePayer => self.fee_payer_signature = Some(signature),
+        }
+    }
+}
+
+/// How the fee payer appears among `required_signers`, since its address may
+/// not be known (`0x0`) when the transaction is stored
+pub const FEE_PAYER: &str = "fee_payer";
+
+/// Whose signature an update sets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Signer {
+    /// A secondary signer of a multi-agent transaction, by long-form address
+    Secondary(String),
+    /// The account sponsoring gas for a fee payer transaction
+    FeePayer,
+}
+
+impl Signer {
+    /// How the signer appears among `required_signers`
+    pub fn name(&self) -> &str {
+        match self {
+            Signer::Secondary(address) => address,
+            Signer::FeePayer => FEE_PAYER,
+        }
+    }
+}
+
+/// Errors returned by a storage backend
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// The backend failed to complete the operation
+    #[error("storage backend error: {0}")]
+    Backend(String),
+    /// The backend could not be selected or configured
+    #[error("storage configuration error: {0}")]
+    Config(String),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+impl From<sqlx::Error> for StoreError {
+    fn from(e: sqlx::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+/// Storage backend for transactions and their signatures
+#[async_trait]
+pub trait TransactionStore: Send + Sync {
+    /// Short human-readable name of the backend (for logging)
+    fn name(&self) -> &'static str;
+
+    /// Number of transactions dropped to stay within a capacity limit
+    fn evictions(&self) -> u64 {
+        0
+    }
+
+    /// Whether a write survives a restart once [`flush`](Self::flush) has
+    /// returned; `false` for the in-memory store
+    fn durable(&self) -> bool {
+        true
+    }
+
+    /// Check that the backend can serve requests (for readiness probes).
+    ///
+    /// Always succeeds by default; backends with a server or connection pool
+    /// override it with a round trip.
+    async fn ping(&self) -> StoreResult<()> {
+        Ok(())
+    }
+
+    /// Make every acknowledged write durable before shutdown.
+    ///
+    /// Does nothing by default; embedded databases override it to flush to
+    /// disk.
+    async fn flush(&self) -> StoreResult<()> {
+        Ok(())
+    }
+
+    /// Store a transaction, replacing any existing entry with the same id
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()>;
+
+    /// Look up a transaction by id
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>>;
+
+    /// Attach `signer`'s signature to an existing transaction, replacing any
+    /// earlier one from the same signer.
+    ///
+    /// Returns `false` if no transaction with this id exists.
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool>;
+
+    /// Append `events` to an existing transaction's history.
+    ///
+    /// Returns `false` if no transaction with this id exists.
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool>;
+
+    /// Delete a transaction and its signatures.
+    ///
+    /// Returns `false` if no transaction with this id exists.
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool>;
+
+    /// Return every stored transaction along with its id
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>>;
+
+    /// Return the transactions sent by `sender` (long form) whose sequence
+    /// number is within `sequence_numbers`.
+    ///
+    /// Scans [`list`](Self::list) by default; backends with a sender index
+    /// override it.
+    async fn list_by_sender(
+        &self,
+        sender: &str,
+        sequence_numbers: RangeInclusive<u64>,
+    ) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|(_, tx)| {
+                tx.sender.as_deref() == Some(sender)
+                    && tx
+                        .sequence_number
+                        .is_some_and(|seq| sequence_numbers.contains(&seq))
+            })
+            .collect())
+    }
+}
+
+/// `[storage]` options: which backend and how it is set up
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Storage")]
+pub struct StorageArgs {
+    /// Storage backend (all but `memory` need the matching cargo feature)
+    #[arg(long, env = "STORAGE", value_enum, default_value_t = StorageKind::Memory)]
+    pub storage: StorageKind,
+
+    /// Cap on in-memory transactions; least recently used are evicted
+    #[arg(long, env = "MAX_TRANSACTIONS")]
+    pub max_transactions: Option<NonZeroUsize>,
+
+    /// Independently locked shards the in-memory store is split into
+    #[arg(long, env = "MEMORY_SHARDS", default_value_t = memory::DEFAULT_SHARDS)]
+    pub memory_shards: NonZeroUsize,
+
+    /// SQLite database file
+    #[arg(long, env = "SQLITE_PATH", default_value = "repro-backend.db")]
+    pub sqlite_path: String,
+
+    /// Postgres connection URL (required for `--storage postgres`)
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    pub database_url: Option<String>,
+
+    /// Redis server URL
+    #[arg(long, env = "REDIS_URL", default_value = "redis://127.0.0.1/")]
+    pub redis_url: String,
+
+    /// Prefix for every Redis key
+    #[arg(long, env = "REDIS_KEY_PREFIX", default_value = "repro:")]
+    pub redis_key_prefix: String,
+
+    /// Seconds Redis keeps an entry past the transaction's expiration
+    #[arg(long, env = "REDIS_TTL_GRACE_SECS", default_value_t = 300)]
+    pub redis_ttl_grace_secs: u64,
+
+    /// sled database directory
+    #[arg(long, env = "SLED_PATH", default_value = "repro-backend.sled")]
+    pub sled_path: String,
+
+    /// RocksDB database directory
+    #[arg(long, env = "ROCKSDB_PATH", default_value = "repro-backend.rocksdb")]
+    pub rocksdb_path: String,
+
+    /// Compression of stored BCS (`zstd` needs the `zstd` feature)
+    #[arg(long, env = "COMPRESSION", value_enum, default_value_t = CompressionAlgorithm::None)]
+    pub compression: CompressionAlgorithm,
+
+    /// zstd compression level
+    #[arg(long, env = "ZSTD_LEVEL", default_value_t = 3, allow_negative_numbers = true)]
+    pub zstd_level: i32,
+}
+
+/// `--storage` / `STORAGE`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum StorageKind {
+    /// In-process map, lost on restart; holds at most `--max-transactions`
+    /// entries (LRU eviction) if set
+    Memory,
+    /// SQLite database at `--sqlite-path`
+    Sqlite,
+    /// Redis server at `--redis-url`, keys prefixed with `--redis-key-prefix`
+    /// and kept for `--redis-ttl-grace-secs` past the transaction's expiration
+    Redis,
+    /// Postgres database at `--database-url`, migrated on startup
+    Postgres,
+    /// Embedded database directory at `--sled-path`
+    Sled,
+    /// RocksDB directory at `--rocksdb-path`
+    Rocksdb,
+}
+
+/// Build the storage backend selected by `config.storage`
+pub async fn from_config(config: &StorageArgs) -> StoreResult<Box<dyn TransactionStore>> {
+    match config.storage {
+        StorageKind::Memory => Ok(Box::new(MemoryStore::new(
+            config.max_transactions,
+            config.memory_shards,
+        ))),
+        #[cfg(feature = "sqlite")]
+        StorageKind::Sqlite => Ok(Box::new(SqliteStore::connect(&config.sqlite_path).await?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageKind::Sqlite => Err(not_compiled_in("sqlite")),
+        #[cfg(feature = "redis")]
+        StorageKind::Redis => Ok(Box::new(
+            RedisStore::connect(
+                &config.redis_url,
+                config.redis_key_prefix.clone(),
+                config.redis_ttl_grace_secs,
+            )
+            .await?,
+        )),
+        #[cfg(not(feature = "redis"))]
+        StorageKind::Redis => Err(not_compiled_in("redis")),
+        #[cfg(feature = "postgres")]
+        StorageKind::Postgres => {
+            let url = config.database_url.as_deref().ok_or_else(|| {
+                StoreError::Config("--storage postgres requires --database-url".to_string())
+            })?;
+            Ok(Box::new(PostgresStore::connect(url).await?))
+        }
+        #[cfg(not(feature = "postgres"))]
+        StorageKind::Postgres => Err(not_compiled_in("postgres")),
+        #[cfg(feature = "sled")]
+        StorageKind::Sled => Ok(Box::new(SledStore::open(&config.sled_path)?)),
+        #[cfg(not(feature = "sled"))]
+        StorageKind::Sled => Err(not_compiled_in("sled")),
+        #[cfg(feature = "rocksdb")]
+        StorageKind::Rocksdb => Ok(Box::new(RocksDbStore::open(&config.rocksdb_path)?)),
+        #[cfg(not(feature = "rocksdb"))]
+        StorageKind::Rocksdb => Err(not_compiled_in("rocksdb")),
+    }
+}
+
+/// Decode stored BCS, for backends that index or expire entries by
+/// transaction fields
+#[cfg(feature = "redis")]
+fn decode_transaction(tx: &StoredTransaction) -> Option<DecodedTransaction> {
+    DecodedTransaction::decode(&tx.bcs().ok()?).ok()
+}
+
+/// Encode history as a JSON array, for backends that keep it in a single column
+#[cfg(any(
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "redis",
+    feature = "rocksdb"
+))]
+fn encode_history(events: &[TransactionEvent]) -> StoreResult<String> {
+    serde_json::to_string(events).map_err(|e| StoreError::Backend(e.to_string()))
+}
+
+/// Decode history written by [`encode_history`]
+#[cfg(any(
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "redis",
+    feature = "rocksdb"
+))]
+fn decode_history(json: &[u8]) -> StoreResult<Vec<TransactionEvent>> {
+    serde_json::from_slice(json)
+        .map_err(|e| StoreError::Backend(format!("corrupt history: {}", e)))
+}
+
+/// Encode secondary signatures as a JSON object of hex strings, for backends
+/// that keep them in a single column
+#[cfg(any(feature = "sqlite", feature = "postgres", feature = "rocksdb"))]
+fn encode_signatures(signatures: &BTreeMap<String, Vec<u8>>) -> StoreResult<String> {
+    let mut json = Vec::new();
+    hex_bytes::map::serialize(signatures, &mut serde_json::Serializer::new(&mut json))
+        .map_err(|e| StoreError::Backend(e.to_string()))?;
+    String::from_utf8(json).map_err(|e| StoreError::Backend(e.to_string()))
+}
+
+/// Encode required signers as a JSON array, for backends that keep them in a
+/// single column
+#[cfg(any(
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "redis",
+    feature = "rocksdb"
+))]
+fn encode_signers(signers: &[String]) -> StoreResult<String> {
+    serde_json::to_string(signers).map_err(|e| StoreError::Backend(e.to_string()))
+}
+
+/// Decode required signers written by [`encode_signers`]
+#[cfg(any(
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "redis",
+    feature = "rocksdb"
+))]
+fn decode_signers(json: &[u8]) -> StoreResult<Vec<String>> {
+    serde_json::from_slice(json)
+        .map_err(|e| StoreError::Backend(format!("corrupt required signers: {}", e)))
+}
+
+/// Decode signatures written by [`encode_signatures`]
+#[cfg(any(feature = "sqlite", feature = "postgres", feature = "rocksdb"))]
+fn decode_signatures(json: &[u8]) -> StoreResult<BTreeMap<String, Vec<u8>>> {
+    hex_bytes::map::deserialize(&mut serde_json::Deserializer::from_slice(json))
+        .map_err(|e| StoreError::Backend(format!("corrupt signatures: {}", e)))
+}
+
+#[cfg(not(all(
+    feature = "sqlite",
+    feature = "postgres",
+    feature = "redis",
+    feature = "sled",
+    feature = "rocksdb"
+)))]
+fn not_compiled_in(backend: &str) -> StoreError {
+    StoreError::Config(format!(
+        "--storage {} requires building with `--features {}`",
+        backend, backend
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_by(secondaries: &[&str], fee_payer: bool) -> StoredTransaction {
+        StoredTransaction {
+            secondary_signatures: secondaries
+                .iter()
+                .map(|signer| (signer.to_string(), vec![1]))
+                .collect(),
+            fee_payer_signature: fee_payer.then(|| vec![1]),
+            required_signers: Some(vec![
+                "0xa".to_string(),
+                "0xb".to_string(),
+                "0xc".to_string(),
+                FEE_PAYER.to_string(),
+            ]),
+            quorum: Some(2),
+            ..StoredTransaction::default()
+        }
+    }
+
+    #[test]
+    fn quorum_counts_secondaries_and_needs_the_fee_payer() {
+        assert!(!signed_by(&["0xa"], true).quorum_met());
+        assert!(!signed_by(&["0xa", "0xb"], false).quorum_met());
+        assert!(signed_by(&["0xa", "0xb"], true).quorum_met());
+        assert!(signed_by(&["0xa", "0xb", "0xc"], true).quorum_met());
+    }
+
+    #[test]
+    fn stale_signatures_dont_count_toward_the_quorum() {
+        let mut tx = signed_by(&["0xa", "0xb"], true);
+        let stale = Signer::Secondary("0xb".to_string());
+        tx.history
+            .push(TransactionEvent::stale(&stale, "bad".to_string()));
+        assert!(!tx.quorum_met());
+    }
+
+    #[test]
+    fn no_quorum_is_never_met() {
+        let tx = StoredTransaction {
+            quorum: None,
+            ..signed_by(&["0xa", "0xb", "0xc"], true)
+        };
+        assert!(!tx.quorum_met());
+    }
+
+    /// A transaction with every field set, so a backend dropping one shows up
+    fn populated(sender: &str, sequence_number: u64) -> StoredTransaction {
+        StoredTransaction {
+            raw_bcs: vec![1, 2, 3],
+            compressed: false,
+            hex_prefixed: true,
+            secondary_signatures: BTreeMap::from([("0xa".to_string(), vec![4, 5])]),
+            fee_payer_signature: Some(vec![6]),
+            stored_at: 1_700_000_000,
+            sender: Some(sender.to_string()),
+            sequence_number: Some(sequence_number),
+            canonical: Some(true),
+            required_signers: Some(vec!["0xa".to_string(), FEE_PAYER.to_string()]),
+            quorum: Some(1),
+            history: vec![TransactionEvent::now(
+                EventKind::Stored,
+                Some("3 bytes".to_string()),
+            )],
+        }
+    }
+
+    fn json(tx: &StoredTransaction) -> serde_json::Value {
+        serde_json::to_value(tx).unwrap()
+    }
+
+    /// What every backend must do alike. Ids and the sender are prefixed with
+    /// `run`, so a database shared with other runs can be used.
+    async fn conformance(store: &dyn TransactionStore, run: &str) {
+        let id = |name: &str| format!("{}-{}", run, name);
+        let sender = format!("0x{}", run);
+        store.ping().await.unwrap();
+
+        // Every field round-trips, and a put replaces
+        let tx = populated(&sender, 7);
+        store.put(&id("a"), tx.clone()).await.unwrap();
+        assert_eq!(
+            json(&store.get(&id("a")).await.unwrap().unwrap()),
+            json(&tx)
+        );
+        let replaced = StoredTransaction {
+            raw_bcs: vec![9],
+            ..populated(&sender, 7)
+        };
+        store.put(&id("a"), replaced.clone()).await.unwrap();
+        assert_eq!(
+            json(&store.get(&id("a")).await.unwrap().unwrap()),
+            json(&replaced)
+        );
+        assert!(store.get(&id("missing")).await.unwrap().is_none());
+
+        // Signatures are added or replaced per signer
+        let secondary = Signer::Secondary("0xb".to_string());
+        assert!(store
+            .update_signature(&id("a"), &secondary, vec![7])
+            .await
+            .unwrap());
+        let again = Signer::Secondary("0xa".to_string());
+        assert!(store
+            .update_signature(&id("a"), &again, vec![8])
+            .await
+            .unwrap());
+        assert!(store
+            .update_signature(&id("a"), &Signer::FeePayer, vec![9])
+            .await
+            .unwrap());
+        let signed = store.get(&id("a")).await.unwrap().unwrap();
+        let expected = BTreeMap::from([("0xa".to_string(), vec![8]), ("0xb".to_string(), vec![7])]);
+        assert_eq!(signed.secondary_signatures, expected);
+        assert_eq!(signed.fee_payer_signature, Some(vec![9]));
+        assert!(!store
+            .update_signature(&id("missing"), &secondary, vec![7])
+            .await
+            .unwrap());
+
+        // History is appended in order
+        let events = vec![
+            TransactionEvent::signed(&secondary, "64 bytes".to_string()),
+            TransactionEvent::committed(Commit {
+                version: 42,
+                success: true,
+                vm_status: "Executed successfully".to_string(),
+                gas_used: 10,
+            }),
+        ];
+        assert!(store
+            .append_history(&id("a"), events.clone())
+            .await
+            .unwrap());
+        let history = store.get(&id("a")).await.unwrap().unwrap().history;
+        assert_eq!(history[1..], events[..]);
+        assert!(!store.append_history(&id("missing"), events).await.unwrap());
+
+        // Listing, by sender and sequence number
+        store.put(&id("b"), populated(&sender, 8)).await.unwrap();
+        store.put(&id("c"), populated("0xother", 7)).await.unwrap();
+        let listed: BTreeSet<String> = store
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(transaction_id, _)| transaction_id)
+            .collect();
+        assert!([id("a"), id("b"), id("c")]
+            .iter()
+            .all(|id| listed.contains(id)));
+        let by_sender = |range: RangeInclusive<u64>| async {
+            let mut ids: Vec<String> = store
+                .list_by_sender(&sender, range)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(transaction_id, _)| transaction_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(by_sender(0..=100).await, [id("a"), id("b")]);
+        assert_eq!(by_sender(8..=8).await, [id("b")]);
+        assert!(by_sender(9..=100).await.is_empty());
+
+        // Removal
+        for name in ["a", "b", "c"] {
+            assert!(store.remove(&id(name)).await.unwrap());
+            assert!(store.get(&id(name)).await.unwrap().is_none());
+        }
+        assert!(!store.remove(&id("a")).await.unwrap());
+        store.flush().await.unwrap();
+    }
+
+    /// A unique run id, so runs don't see each other's rows
+    fn run_id(backend: &str) -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        format!("{}{}{}", backend, std::process::id(), nanos)
+    }
+
+    /// An empty scratch directory for an embedded database
+    #[cfg(any(feature = "sqlite", feature = "sled", feature = "rocksdb"))]
+    fn scratch(backend: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("repro-store-{}", run_id(backend)));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn memory_conforms() {
+        let store = MemoryStore::default();
+        conformance(&store, &run_id("memory")).await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn sqlite_conforms() {
+        let dir = scratch("sqlite");
+        let path = dir.join("store.db");
+        let store = SqliteStore::connect(path.to_str().unwrap()).await.unwrap();
+        conformance(&store, &run_id("sqlite")).await;
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(feature = "sled")]
+    #[tokio::test]
+    async fn sled_conforms() {
+        let dir = scratch("sled");
+        let store = SledStore::open(dir.join("db").to_str().unwrap()).unwrap();
+        conformance(&store, &run_id("sled")).await;
+        drop(store);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(feature = "rocksdb")]
+    #[tokio::test]
+    async fn rocksdb_conforms() {
+        let dir = scratch("rocksdb");
+        let store = RocksDbStore::open(dir.join("db").to_str().unwrap()).unwrap();
+        conformance(&store, &run_id("rocksdb")).await;
+        drop(store);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    /// Needs a server: set `TEST_DATABASE_URL`, or the test passes trivially
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn postgres_conforms() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let store = PostgresStore::connect(&url).await.unwrap();
+        conformance(&store, &run_id("postgres")).await;
+    }
+
+    /// Needs a server: set `TEST_REDIS_URL`, or the test passes trivially
+    #[cfg(feature = "redis")]
+    #[tokio::test]
+    async fn redis_conforms() {
+        let Ok(url) = std::env::var("TEST_REDIS_URL") else {
+            return;
+        };
+        let run = run_id("redis");
+        let store = RedisStore::connect(&url, format!("{}:", run), 60)
+            .await
+            .unwrap();
+        conformance(&store, &run).await;
+    }
+}
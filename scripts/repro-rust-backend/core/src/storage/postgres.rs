@@ -0,0 +1,233 @@
+//! Postgres storage backend (enabled with the `postgres` feature).
+//!
+//! The schema is managed by the migrations in `migrations/postgres`, which
+//! are applied on connect. Transactions are indexed by id (primary key) and
+//! by decoded sender address and sequence number; each one's history is a
+//! JSONB array.
+
+use super::{
+    decode_history, decode_signatures, decode_signers, encode_history, encode_signatures,
+    encode_signers, Signer, StoreError, StoreResult, StoredTransaction, TransactionEvent,
+    TransactionStore,
+};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+use std::ops::RangeInclusive;
+
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Columns read back into a [`StoredTransaction`]
+const COLUMNS: &str = "transaction_id, raw_bcs, compressed, hex_prefixed, \
+                       secondary_signatures::text AS secondary_signatures, fee_payer_signature, \
+                       stored_at, sender_address, sequence_number, canonical, \
+                       required_signers::text AS required_signers, quorum, \
+                       history::text AS history";
+
+/// Stores transactions in a Postgres database
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Connect to `database_url` and run any pending migrations
+    pub async fn connect(database_url: &str) -> StoreResult<Self> {
+        let pool = PgPoolOptions::new().connect(database_url).await?;
+        MIGRATOR
+            .run(&pool)
+            .await
+            .map_err(|e| StoreError::Backend(format!("migration failed: {}", e)))?;
+        Ok(Self { pool })
+    }
+}
+
+fn row_to_transaction(row: &PgRow) -> StoreResult<StoredTransaction> {
+    Ok(StoredTransaction {
+        raw_bcs: row.try_get("raw_bcs")?,
+        compressed: row.try_get("compressed")?,
+        hex_prefixed: row.try_get("hex_prefixed")?,
+        secondary_signatures: decode_signatures(
+            row.try_get::<&str, _>("secondary_signatures")?.as_bytes(),
+        )?,
+        fee_payer_signature: row.try_get("fee_payer_signature")?,
+        stored_at: row.try_get::<i64, _>("stored_at")? as u64,
+        sender: row.try_get("sender_address")?,
+        sequence_number: row
+            .try_get::<Option<i64>, _>("sequence_number")?
+            .map(|seq| seq as u64),
+        canonical: row.try_get("canonical")?,
+        required_signers: row
+            .try_get::<Option<&str>, _>("required_signers")?
+            .map(|json| decode_signers(json.as_bytes()))
+            .transpose()?,
+        quorum: row
+            .try_get::<Option<i32>, _>("quorum")?
+            .map(|quorum| quorum as u32),
+        history: decode_history(row.try_get::<&str, _>("history")?.as_bytes())?,
+    })
+}
+
+fn row_to_entry(row: &PgRow) -> StoreResult<(String, StoredTransaction)> {
+    Ok((row.try_get("transaction_id")?, row_to_transaction(row)?))
+}
+
+/// Postgres has no unsigned integers; sequence numbers never get near `i64::MAX`
+fn to_i64(seq: u64) -> i64 {
+    seq.min(i64::MAX as u64) as i64
+}
+
+#[async_trait]
+impl TransactionStore for PostgresStore {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        sqlx::query(
+            "INSERT INTO transactions
+                (transaction_id, raw_bcs, compressed, hex_prefixed, secondary_signatures,
+                 fee_payer_signature, stored_at, sender_address, sequence_number, canonical,
+                 required_signers, quorum, history)
+             VALUES ($1, $2, $3, $4, $5::jsonb, $6, $7, $8, $9, $10, $11::jsonb, $12, $13::jsonb)
+             ON CONFLICT (transaction_id) DO UPDATE SET
+                raw_bcs = EXCLUDED.raw_bcs,
+                compressed = EXCLUDED.compressed,
+                hex_prefixed = EXCLUDED.hex_prefixed,
+                secondary_signatures = EXCLUDED.secondary_signatures,
+                fee_payer_signature = EXCLUDED.fee_payer_signature,
+                stored_at = EXCLUDED.stored_at,
+                sender_address = EXCLUDED.sender_address,
+                sequence_number = EXCLUDED.sequence_number,
+                canonical = EXCLUDED.canonical,
+                required_signers = EXCLUDED.required_signers,
+                quorum = EXCLUDED.quorum,
+                history = EXCLUDED.history",
+        )
+        .bind(transaction_id)
+        .bind(&tx.raw_bcs)
+        .bind(tx.compressed)
+        .bind(tx.hex_prefixed)
+        .bind(encode_signatures(&tx.secondary_signatures)?)
+        .bind(&tx.fee_payer_signature)
+        .bind(tx.stored_at as i64)
+        .bind(&tx.sender)
+        .bind(tx.sequence_number.map(to_i64))
+        .bind(tx.canonical)
+        .bind(
+            tx.required_signers
+                .as_deref()
+                .map(encode_signers)
+                .transpose()?,
+        )
+        .bind(tx.quorum.map(|quorum| quorum.min(i32::MAX as u32) as i32))
+        .bind(encode_history(&tx.history)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM transactions WHERE transaction_id = $1",
+            COLUMNS
+        ))
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(row_to_transaction).transpose()
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        // Lock the row so a concurrent re-store of the same transaction can't
+        // interleave with attaching its signature
+        let mut db_tx = self.pool.begin().await?;
+        let exists = sqlx::query("SELECT 1 FROM transactions WHERE transaction_id = $1 FOR UPDATE")
+            .bind(transaction_id)
+            .fetch_optional(&mut *db_tx)
+            .await?
+            .is_some();
+        if !exists {
+            db_tx.rollback().await?;
+            return Ok(false);
+        }
+
+        let query = match signer {
+            Signer::Secondary(address) => sqlx::query(
+                "UPDATE transactions
+                 SET secondary_signatures = secondary_signatures || jsonb_build_object($1::text, $2::text)
+                 WHERE transaction_id = $3",
+            )
+            .bind(address)
+            .bind(hex::encode(signature)),
+            Signer::FeePayer => sqlx::query(
+                "UPDATE transactions SET fee_payer_signature = $1 WHERE transaction_id = $2",
+            )
+            .bind(signature),
+        };
+        query
+            .bind(transaction_id)
+            .execute(&mut *db_tx)
+            .await?;
+        db_tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        // Concatenating in the UPDATE keeps concurrent appends from losing events
+        let result = sqlx::query(
+            "UPDATE transactions SET history = history || $1::jsonb WHERE transaction_id = $2",
+        )
+        .bind(encode_history(&events)?)
+        .bind(transaction_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let result = sqlx::query("DELETE FROM transactions WHERE transaction_id = $1")
+            .bind(transaction_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM transactions", COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(row_to_entry).collect()
+    }
+
+    async fn list_by_sender(
+        &self,
+        sender: &str,
+        sequence_numbers: RangeInclusive<u64>,
+    ) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM transactions
+             WHERE sender_address = $1 AND sequence_number BETWEEN $2 AND $3",
+            COLUMNS
+        ))
+        .bind(sender)
+        .bind(to_i64(*sequence_numbers.start()))
+        .bind(to_i64(*sequence_numbers.end()))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_entry).collect()
+    }
+}
@@ -0,0 +1,284 @@
+//! Redis storage backend (enabled with the `redis` feature).
+//!
+//! Lets several backend instances behind a load balancer share the same
+//! transactions. Each transaction is a Redis hash whose TTL follows the
+//! transaction's own `expiration_timestamp_secs`, so Redis drops entries
+//! once they could no longer be submitted anyway.
+
+use super::{
+    decode_history, decode_signers, decode_transaction, encode_history, encode_signers, Signer,
+    StoreError, StoreResult, StoredTransaction, TransactionEvent, TransactionStore,
+};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+
+/// Set the signature field ARGV[1] to ARGV[2] only if the transaction hash
+/// still exists, so an expired entry is not resurrected as a signature-only hash.
+const UPDATE_SIGNATURE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+    return 1
+end
+return 0
+";
+
+/// Append a JSON array of events (ARGV[1]) to the `history` array, again only
+/// if the transaction hash still exists. Splices the JSON text rather than
+/// round-tripping it through cjson, which would turn `[]` into `{}`.
+const APPEND_HISTORY_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+if ARGV[1] == '[]' then
+    return 1
+end
+local history = redis.call('HGET', KEYS[1], 'history')
+if not history or history == '[]' then
+    history = ARGV[1]
+else
+    history = string.sub(history, 1, -2) .. ',' .. string.sub(ARGV[1], 2)
+end
+redis.call('HSET', KEYS[1], 'history', history)
+return 1
+";
+
+/// Stores transactions as Redis hashes under `<prefix>tx:<transaction_id>`
+pub struct RedisStore {
+    conn: ConnectionManager,
+    key_prefix: String,
+    /// Extra seconds to keep an entry after its transaction expires
+    ttl_grace_secs: u64,
+}
+
+impl RedisStore {
+    /// Connect to the Redis server at `url`
+    pub async fn connect(url: &str, key_prefix: String, ttl_grace_secs: u64) -> StoreResult<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+        Ok(Self {
+            conn,
+            key_prefix,
+            ttl_grace_secs,
+        })
+    }
+
+    fn key(&self, transaction_id: &str) -> String {
+        format!("{}tx:{}", self.key_prefix, transaction_id)
+    }
+
+    /// TTL for a transaction: time until it expires on-chain plus the grace period.
+    ///
+    /// Returns `None` (no TTL) if the BCS cannot be decoded.
+    fn ttl_secs(&self, tx: &StoredTransaction) -> Option<u64> {
+        let decoded = decode_transaction(tx)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let remaining = decoded
+            .raw_txn()
+            .expiration_timestamp_secs
+            .saturating_sub(now);
+        Some(remaining + self.ttl_grace_secs)
+    }
+}
+
+/// Secondary signatures are stored one hash field per signer, named by this
+/// prefix and the signer's long-form address
+const SECONDARY_SIGNATURE_PREFIX: &str = "secondary_signature:";
+
+fn signature_field(signer: &Signer) -> String {
+    match signer {
+        Signer::Secondary(address) => format!("{SECONDARY_SIGNATURE_PREFIX}{address}"),
+        Signer::FeePayer => "fee_payer_signature".to_string(),
+    }
+}
+
+fn fields_to_transaction(mut fields: HashMap<String, Vec<u8>>) -> StoreResult<StoredTransaction> {
+    let raw_bcs = fields
+        .remove("raw_bcs")
+        .ok_or_else(|| StoreError::Backend("entry is missing raw_bcs".to_string()))?;
+    let stored_at = fields
+        .get("stored_at")
+        .and_then(|v| std::str::from_utf8(v).ok()?.parse().ok())
+        .ok_or_else(|| StoreError::Backend("entry has invalid stored_at".to_string()))?;
+    Ok(StoredTransaction {
+        raw_bcs,
+        compressed: fields.get("compressed").is_some_and(|v| v == b"1"),
+        hex_prefixed: fields.get("hex_prefixed").is_some_and(|v| v == b"1"),
+        secondary_signatures: fields
+            .iter()
+            .filter_map(|(field, signature)| {
+                let address = field.strip_prefix(SECONDARY_SIGNATURE_PREFIX)?;
+                Some((address.to_string(), signature.clone()))
+            })
+            .collect(),
+        fee_payer_signature: fields.remove("fee_payer_signature"),
+        stored_at,
+        sender: fields
+            .remove("sender")
+            .map(|v| String::from_utf8_lossy(&v).into_owned()),
+        sequence_number: fields
+            .get("sequence_number")
+            .and_then(|v| std::str::from_utf8(v).ok()?.parse().ok()),
+        canonical: fields.get("canonical").map(|v| v == b"1"),
+        required_signers: fields
+            .get("required_signers")
+            .map(|v| decode_signers(v))
+            .transpose()?,
+        quorum: fields
+            .get("quorum")
+            .and_then(|v| std::str::from_utf8(v).ok()?.parse().ok()),
+        history: fields
+            .get("history")
+            .map(|v| decode_history(v))
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+impl From<redis::RedisError> for StoreError {
+    fn from(e: redis::RedisError) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+#[async_trait]
+impl TransactionStore for RedisStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        let mut conn = self.conn.clone();
+        redis::cmd("PING").query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        let key = self.key(transaction_id);
+        let mut fields: Vec<(String, Vec<u8>)> = vec![
+            ("raw_bcs".into(), tx.raw_bcs.clone()),
+            ("compressed".into(), if tx.compressed { b"1" } else { b"0" }.to_vec()),
+            ("hex_prefixed".into(), if tx.hex_prefixed { b"1" } else { b"0" }.to_vec()),
+            ("stored_at".into(), tx.stored_at.to_string().into_bytes()),
+            ("history".into(), encode_history(&tx.history)?.into_bytes()),
+        ];
+        for (address, signature) in &tx.secondary_signatures {
+            fields.push((
+                signature_field(&Signer::Secondary(address.clone())),
+                signature.clone(),
+            ));
+        }
+        if let Some(signature) = &tx.fee_payer_signature {
+            fields.push((signature_field(&Signer::FeePayer), signature.clone()));
+        }
+        if let Some(sender) = &tx.sender {
+            fields.push(("sender".into(), sender.clone().into_bytes()));
+        }
+        if let Some(seq) = tx.sequence_number {
+            fields.push(("sequence_number".into(), seq.to_string().into_bytes()));
+        }
+        if let Some(canonical) = tx.canonical {
+            fields.push(("canonical".into(), if canonical { b"1" } else { b"0" }.to_vec()));
+        }
+        if let Some(signers) = &tx.required_signers {
+            fields.push((
+                "required_signers".into(),
+                encode_signers(signers)?.into_bytes(),
+            ));
+        }
+        if let Some(quorum) = tx.quorum {
+            fields.push(("quorum".into(), quorum.to_string().into_bytes()));
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(&key)
+            .ignore()
+            .hset_multiple(&key, &fields)
+            .ignore();
+        if let Some(ttl) = self.ttl_secs(&tx) {
+            pipe.expire(&key, ttl as i64).ignore();
+        }
+
+        let mut conn = self.conn.clone();
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        let mut conn = self.conn.clone();
+        let fields: HashMap<String, Vec<u8>> = conn.hgetall(self.key(transaction_id)).await?;
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        fields_to_transaction(fields).map(Some)
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        let mut conn = self.conn.clone();
+        let updated: i64 = redis::Script::new(UPDATE_SIGNATURE_SCRIPT)
+            .key(self.key(transaction_id))
+            .arg(signature_field(signer))
+            .arg(signature)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(updated == 1)
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        let mut conn = self.conn.clone();
+        let updated: i64 = redis::Script::new(APPEND_HISTORY_SCRIPT)
+            .key(self.key(transaction_id))
+            .arg(encode_history(&events)?)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(updated == 1)
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let mut conn = self.conn.clone();
+        let removed: i64 = conn.del(self.key(transaction_id)).await?;
+        Ok(removed > 0)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let mut conn = self.conn.clone();
+        let pattern = format!("{}*", self.key(""));
+        let keys: Vec<String> = {
+            let mut iter = conn.scan_match::<_, String>(&pattern).await?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        let prefix_len = self.key("").len();
+        let mut transactions = Vec::with_capacity(keys.len());
+        for key in keys {
+            let fields: HashMap<String, Vec<u8>> = conn.hgetall(&key).await?;
+            // The entry may have expired between SCAN and HGETALL
+            if fields.is_empty() {
+                continue;
+            }
+            transactions.push((
+                key[prefix_len..].to_string(),
+                fields_to_transaction(fields)?,
+            ));
+        }
+        Ok(transactions)
+    }
+}
@@ -0,0 +1,357 @@
+//! RocksDB storage backend (enabled with the `rocksdb` feature).
+//!
+//! Tuned for bulk ingestion of large BCS corpora during fuzzing and replay
+//! campaigns. Each part of an entry lives in its own column family so the
+//! large raw blobs don't slow down scans over metadata and signatures:
+//!
+//! - `raw`: raw BCS bytes, keyed by transaction id
+//! - `meta`: `stored_at` as a big-endian `u64`, then a flags byte (see
+//!   `FLAG_*`), then for decoded transactions the sequence number as a
+//!   big-endian `u64` and the 32-byte sender address, then the quorum as a
+//!   big-endian `u32` if one is set
+//! - `signatures`: secondary signatures as a JSON object of hex strings keyed
+//!   by signer address (absent if not signed yet)
+//! - `fee_payer_signatures`: fee payer signature bytes, for sponsored
+//!   transactions
+//! - `history`: the transaction's events as a JSON array
+//! - `required_signers`: signers other than the sender as a JSON array
+//!   (absent if the transaction didn't decode)
+
+use super::{
+    decode_history, decode_signatures, decode_signers, encode_history, encode_signatures,
+    encode_signers, hex_bytes, Signer, StoreError, StoreResult, StoredTransaction,
+    TransactionEvent, TransactionStore,
+};
+use async_trait::async_trait;
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, IteratorMode, Options, WriteBatch, DB};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+const CF_RAW: &str = "raw";
+const CF_META: &str = "meta";
+const CF_SIGNATURES: &str = "signatures";
+const CF_FEE_PAYER_SIGNATURES: &str = "fee_payer_signatures";
+const CF_HISTORY: &str = "history";
+const CF_REQUIRED_SIGNERS: &str = "required_signers";
+
+/// Submitted BCS hex had a `0x` prefix
+const FLAG_HEX_PREFIXED: u8 = 1 << 0;
+/// `raw` holds zstd-compressed bytes
+const FLAG_COMPRESSED: u8 = 1 << 1;
+/// `meta` also holds the decoded sequence number and sender
+const FLAG_DECODED: u8 = 1 << 2;
+/// The canonical BCS check ran at ingest; its result is `FLAG_CANONICAL`
+const FLAG_CANONICAL_CHECKED: u8 = 1 << 3;
+/// The raw bytes were canonical BCS at ingest
+const FLAG_CANONICAL: u8 = 1 << 4;
+/// `meta` ends with a quorum
+const FLAG_QUORUM: u8 = 1 << 5;
+
+/// Stores transactions in a local RocksDB database
+pub struct RocksDbStore {
+    db: DB,
+    /// Serializes the check-then-write in `update_signature`/`append_history`/`remove`
+    /// against `put`
+    write_lock: Mutex<()>,
+}
+
+impl RocksDbStore {
+    /// Open (or create) the database directory at `path`
+    pub fn open(path: &str) -> StoreResult<Self> {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.create_missing_column_families(true);
+        db_opts.increase_parallelism(num_cpus());
+        // Bigger memtables mean fewer flushes while ingesting a corpus
+        db_opts.set_write_buffer_size(64 * 1024 * 1024);
+        db_opts.set_max_write_buffer_number(4);
+
+        let mut raw_opts = Options::default();
+        raw_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+
+        let cfs = vec![
+            ColumnFamilyDescriptor::new(CF_RAW, raw_opts),
+            ColumnFamilyDescriptor::new(CF_META, Options::default()),
+            ColumnFamilyDescriptor::new(CF_SIGNATURES, Options::default()),
+            ColumnFamilyDescriptor::new(CF_FEE_PAYER_SIGNATURES, Options::default()),
+            ColumnFamilyDescriptor::new(CF_HISTORY, Options::default()),
+            ColumnFamilyDescriptor::new(CF_REQUIRED_SIGNERS, Options::default()),
+        ];
+        let db = DB::open_cf_descriptors(&db_opts, path, cfs)?;
+        Ok(Self {
+            db,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    fn cf(&self, name: &str) -> StoreResult<&ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| StoreError::Backend(format!("missing column family `{}`", name)))
+    }
+
+    /// Assemble a transaction from its column families, given its raw bytes
+    fn load(&self, key: &[u8], raw_bcs: Vec<u8>) -> StoreResult<StoredTransaction> {
+        let meta = match self.db.get_cf(self.cf(CF_META)?, key)? {
+            Some(bytes) => decode_meta(&bytes)?,
+            None => Meta::default(),
+        };
+        let secondary_signatures = self.secondary_signatures(key)?;
+        let fee_payer_signature = self.db.get_cf(self.cf(CF_FEE_PAYER_SIGNATURES)?, key)?;
+        let history = self.history(key)?;
+        let required_signers = self
+            .db
+            .get_pinned_cf(self.cf(CF_REQUIRED_SIGNERS)?, key)?
+            .map(|bytes| decode_signers(&bytes))
+            .transpose()?;
+        let (sequence_number, sender) = meta.decoded.unzip();
+        Ok(StoredTransaction {
+            raw_bcs,
+            compressed: meta.flags & FLAG_COMPRESSED != 0,
+            hex_prefixed: meta.flags & FLAG_HEX_PREFIXED != 0,
+            secondary_signatures,
+            fee_payer_signature,
+            stored_at: meta.stored_at,
+            sender,
+            sequence_number,
+            canonical: (meta.flags & FLAG_CANONICAL_CHECKED != 0)
+                .then_some(meta.flags & FLAG_CANONICAL != 0),
+            required_signers,
+            quorum: meta.quorum,
+            history,
+        })
+    }
+
+    fn secondary_signatures(&self, key: &[u8]) -> StoreResult<BTreeMap<String, Vec<u8>>> {
+        match self.db.get_pinned_cf(self.cf(CF_SIGNATURES)?, key)? {
+            Some(bytes) => decode_signatures(&bytes),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn history(&self, key: &[u8]) -> StoreResult<Vec<TransactionEvent>> {
+        match self.db.get_pinned_cf(self.cf(CF_HISTORY)?, key)? {
+            Some(bytes) => decode_history(&bytes),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Contents of the `meta` column family
+#[derive(Default)]
+struct Meta {
+    stored_at: u64,
+    flags: u8,
+    /// Sequence number and sender (long form)
+    decoded: Option<(u64, String)>,
+    quorum: Option<u32>,
+}
+
+fn encode_meta(tx: &StoredTransaction) -> Vec<u8> {
+    let mut flags = 0;
+    if tx.hex_prefixed {
+        flags |= FLAG_HEX_PREFIXED;
+    }
+    if tx.compressed {
+        flags |= FLAG_COMPRESSED;
+    }
+    if let Some(canonical) = tx.canonical {
+        flags |= FLAG_CANONICAL_CHECKED;
+        if canonical {
+            flags |= FLAG_CANONICAL;
+        }
+    }
+    // Stored as raw address bytes; skipped if the sender isn't a valid address
+    let decoded = match (tx.sequence_number, &tx.sender) {
+        (Some(seq), Some(sender)) => hex_bytes::decode(sender)
+            .ok()
+            .filter(|(address, _)| address.len() == 32)
+            .map(|(address, _)| (seq, address)),
+        _ => None,
+    };
+    if decoded.is_some() {
+        flags |= FLAG_DECODED;
+    }
+    if tx.quorum.is_some() {
+        flags |= FLAG_QUORUM;
+    }
+
+    let mut meta = tx.stored_at.to_be_bytes().to_vec();
+    meta.push(flags);
+    if let Some((seq, address)) = decoded {
+        meta.extend_from_slice(&seq.to_be_bytes());
+        meta.extend_from_slice(&address);
+    }
+    if let Some(quorum) = tx.quorum {
+        meta.extend_from_slice(&quorum.to_be_bytes());
+    }
+    meta
+}
+
+fn decode_meta(bytes: &[u8]) -> StoreResult<Meta> {
+    let corrupt = || StoreError::Backend("corrupt metadata".to_string());
+    let (stored_at, rest) = bytes.split_first_chunk::<8>().ok_or_else(corrupt)?;
+    let (&flags, rest) = rest.split_first().ok_or_else(corrupt)?;
+    let (decoded, rest) = if flags & FLAG_DECODED != 0 {
+        let (seq, rest) = rest.split_first_chunk::<8>().ok_or_else(corrupt)?;
+        let (address, rest) = rest.split_first_chunk::<32>().ok_or_else(corrupt)?;
+        let decoded = (u64::from_be_bytes(*seq), hex_bytes::encode(address, true));
+        (Some(decoded), rest)
+    } else {
+        (None, rest)
+    };
+    let (quorum, rest) = if flags & FLAG_QUORUM != 0 {
+        let (quorum, rest) = rest.split_first_chunk::<4>().ok_or_else(corrupt)?;
+        (Some(u32::from_be_bytes(*quorum)), rest)
+    } else {
+        (None, rest)
+    };
+    if !rest.is_empty() {
+        return Err(corrupt());
+    }
+    Ok(Meta {
+        stored_at: u64::from_be_bytes(*stored_at),
+        flags,
+        decoded,
+        quorum,
+    })
+}
+
+fn num_cpus() -> i32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as i32)
+        .unwrap_or(1)
+}
+
+impl From<rocksdb::Error> for StoreError {
+    fn from(e: rocksdb::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+#[async_trait]
+impl TransactionStore for RocksDbStore {
+    fn name(&self) -> &'static str {
+        "rocksdb"
+    }
+
+    async fn flush(&self) -> StoreResult<()> {
+        for name in [
+            CF_RAW,
+            CF_META,
+            CF_SIGNATURES,
+            CF_FEE_PAYER_SIGNATURES,
+            CF_HISTORY,
+            CF_REQUIRED_SIGNERS,
+        ] {
+            self.db.flush_cf(self.cf(name)?)?;
+        }
+        Ok(())
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        let key = transaction_id.as_bytes();
+        let mut batch = WriteBatch::default();
+        batch.put_cf(self.cf(CF_RAW)?, key, &tx.raw_bcs);
+        batch.put_cf(self.cf(CF_META)?, key, encode_meta(&tx));
+        let cf = self.cf(CF_SIGNATURES)?;
+        if tx.secondary_signatures.is_empty() {
+            batch.delete_cf(cf, key);
+        } else {
+            batch.put_cf(cf, key, encode_signatures(&tx.secondary_signatures)?);
+        }
+        let cf = self.cf(CF_FEE_PAYER_SIGNATURES)?;
+        match &tx.fee_payer_signature {
+            Some(signature) => batch.put_cf(cf, key, signature),
+            None => batch.delete_cf(cf, key),
+        }
+        batch.put_cf(self.cf(CF_HISTORY)?, key, encode_history(&tx.history)?);
+        let cf = self.cf(CF_REQUIRED_SIGNERS)?;
+        match &tx.required_signers {
+            Some(signers) => batch.put_cf(cf, key, encode_signers(signers)?),
+            None => batch.delete_cf(cf, key),
+        }
+
+        let _guard = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        let key = transaction_id.as_bytes();
+        match self.db.get_cf(self.cf(CF_RAW)?, key)? {
+            Some(raw) => self.load(key, raw).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        let key = transaction_id.as_bytes();
+        let _guard = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.db.get_pinned_cf(self.cf(CF_RAW)?, key)?.is_none() {
+            return Ok(false);
+        }
+        match signer {
+            Signer::Secondary(address) => {
+                let mut signatures = self.secondary_signatures(key)?;
+                signatures.insert(address.clone(), signature);
+                self.db
+                    .put_cf(self.cf(CF_SIGNATURES)?, key, encode_signatures(&signatures)?)?;
+            }
+            Signer::FeePayer => {
+                self.db
+                    .put_cf(self.cf(CF_FEE_PAYER_SIGNATURES)?, key, signature)?;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        let key = transaction_id.as_bytes();
+        let _guard = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.db.get_pinned_cf(self.cf(CF_RAW)?, key)?.is_none() {
+            return Ok(false);
+        }
+        let mut history = self.history(key)?;
+        history.extend(events);
+        self.db
+            .put_cf(self.cf(CF_HISTORY)?, key, encode_history(&history)?)?;
+        Ok(true)
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let key = transaction_id.as_bytes();
+        let _guard = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.db.get_pinned_cf(self.cf(CF_RAW)?, key)?.is_none() {
+            return Ok(false);
+        }
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(self.cf(CF_RAW)?, key);
+        batch.delete_cf(self.cf(CF_META)?, key);
+        batch.delete_cf(self.cf(CF_SIGNATURES)?, key);
+        batch.delete_cf(self.cf(CF_FEE_PAYER_SIGNATURES)?, key);
+        batch.delete_cf(self.cf(CF_HISTORY)?, key);
+        batch.delete_cf(self.cf(CF_REQUIRED_SIGNERS)?, key);
+        self.db.write(batch)?;
+        Ok(true)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let mut transactions = Vec::new();
+        for entry in self.db.iterator_cf(self.cf(CF_RAW)?, IteratorMode::Start) {
+            let (key, raw) = entry?;
+            let tx = self.load(&key, raw.into_vec())?;
+            transactions.push((String::from_utf8_lossy(&key).into_owned(), tx));
+        }
+        Ok(transactions)
+    }
+}
@@ -0,0 +1,143 @@
+//! Embedded sled storage backend (enabled with the `sled` feature).
+//!
+//! Persists to a local directory with no external database process, which
+//! makes it a good fit for CI runs where only the binary is available.
+
+use super::{
+    Signer, StoreError, StoreResult, StoredTransaction, TransactionEvent, TransactionStore,
+};
+use async_trait::async_trait;
+
+/// Stores transactions as JSON values in an embedded sled database
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl SledStore {
+    /// Open (or create) the database directory at `path`
+    pub fn open(path: &str) -> StoreResult<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+        })
+    }
+
+    /// Apply `modify` to an existing entry; returns `false` if there is none.
+    ///
+    /// fetch_and_update retries the closure on concurrent modification, so
+    /// the change is always applied to the latest stored bytes.
+    async fn update(
+        &self,
+        transaction_id: &str,
+        modify: impl Fn(&mut StoredTransaction),
+    ) -> StoreResult<bool> {
+        let mut error = None;
+        let previous = self.db.fetch_and_update(transaction_id, |old| {
+            let old = old?;
+            let updated = decode(old).and_then(|mut tx| {
+                modify(&mut tx);
+                encode(&tx)
+            });
+            match updated {
+                Ok(bytes) => Some(bytes),
+                Err(e) => {
+                    error = Some(e);
+                    Some(old.to_vec())
+                }
+            }
+        })?;
+
+        if let Some(e) = error {
+            return Err(e);
+        }
+        if previous.is_none() {
+            return Ok(false);
+        }
+        self.flush().await?;
+        Ok(true)
+    }
+
+    /// Flush pending writes so they survive a crash
+    async fn flush(&self) -> StoreResult<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
+
+fn decode(value: &[u8]) -> StoreResult<StoredTransaction> {
+    serde_json::from_slice(value).map_err(|e| StoreError::Backend(e.to_string()))
+}
+
+fn encode(tx: &StoredTransaction) -> StoreResult<Vec<u8>> {
+    serde_json::to_vec(tx).map_err(|e| StoreError::Backend(e.to_string()))
+}
+
+impl From<sled::Error> for StoreError {
+    fn from(e: sled::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+#[async_trait]
+impl TransactionStore for SledStore {
+    fn name(&self) -> &'static str {
+        "sled"
+    }
+
+    async fn flush(&self) -> StoreResult<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        self.db.insert(transaction_id, encode(&tx)?)?;
+        self.flush().await
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        self.db
+            .get(transaction_id)?
+            .map(|value| decode(&value))
+            .transpose()
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        self.update(transaction_id, |tx| {
+            tx.set_signature(signer, signature.clone())
+        })
+        .await
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        self.update(transaction_id, |tx| {
+            tx.history.extend(events.iter().cloned())
+        })
+        .await
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let removed = self.db.remove(transaction_id)?.is_some();
+        if removed {
+            self.flush().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        self.db
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((String::from_utf8_lossy(&key).into_owned(), decode(&value)?))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,257 @@
+//! SQLite storage backend (enabled with the `sqlite` feature).
+//!
+//! Transactions and signatures are written to a local database file so they
+//! survive server restarts. Transactions are indexed by decoded sender and
+//! sequence number; each one's history is a JSON array in the `history` column.
+
+use super::{
+    decode_history, decode_signatures, decode_signers, encode_history, encode_signatures,
+    encode_signers, Signer, StoreError, StoreResult, StoredTransaction, TransactionEvent,
+    TransactionStore,
+};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::ops::RangeInclusive;
+
+/// Columns read back into a [`StoredTransaction`]
+const COLUMNS: &str = "transaction_id, raw_bcs, compressed, hex_prefixed, secondary_signatures, \
+                       fee_payer_signature, stored_at, sender, sequence_number, canonical, \
+                       required_signers, quorum, history";
+
+/// Stores transactions in a SQLite database file
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path` and ensure the schema exists
+    pub async fn connect(path: &str) -> StoreResult<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS transactions (
+                transaction_id TEXT PRIMARY KEY NOT NULL,
+                raw_bcs BLOB NOT NULL,
+                compressed INTEGER NOT NULL,
+                hex_prefixed INTEGER NOT NULL,
+                secondary_signatures TEXT NOT NULL DEFAULT '{}',
+                fee_payer_signature BLOB,
+                stored_at INTEGER NOT NULL,
+                sender TEXT,
+                sequence_number INTEGER,
+                canonical INTEGER,
+                required_signers TEXT,
+                quorum INTEGER,
+                history TEXT NOT NULL DEFAULT '[]'
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS transactions_sender_idx
+             ON transactions (sender, sequence_number)",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+fn row_to_transaction(row: &sqlx::sqlite::SqliteRow) -> StoreResult<StoredTransaction> {
+    Ok(StoredTransaction {
+        raw_bcs: row.try_get("raw_bcs")?,
+        compressed: row.try_get("compressed")?,
+        hex_prefixed: row.try_get("hex_prefixed")?,
+        secondary_signatures: decode_signatures(
+            row.try_get::<&str, _>("secondary_signatures")?.as_bytes(),
+        )?,
+        fee_payer_signature: row.try_get("fee_payer_signature")?,
+        stored_at: row.try_get::<i64, _>("stored_at")? as u64,
+        sender: row.try_get("sender")?,
+        sequence_number: row
+            .try_get::<Option<i64>, _>("sequence_number")?
+            .map(|seq| seq as u64),
+        canonical: row.try_get("canonical")?,
+        required_signers: row
+            .try_get::<Option<&str>, _>("required_signers")?
+            .map(|json| decode_signers(json.as_bytes()))
+            .transpose()?,
+        quorum: row
+            .try_get::<Option<i64>, _>("quorum")?
+            .map(|quorum| quorum as u32),
+        history: decode_history(row.try_get::<&str, _>("history")?.as_bytes())?,
+    })
+}
+
+fn row_to_entry(row: &sqlx::sqlite::SqliteRow) -> StoreResult<(String, StoredTransaction)> {
+    Ok((row.try_get("transaction_id")?, row_to_transaction(row)?))
+}
+
+/// SQLite integers are signed; sequence numbers never get near `i64::MAX`
+fn to_i64(seq: u64) -> i64 {
+    seq.min(i64::MAX as u64) as i64
+}
+
+#[async_trait]
+impl TransactionStore for SqliteStore {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn flush(&self) -> StoreResult<()> {
+        // Fold a write-ahead log, if the database uses one, into the main file
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        sqlx::query(
+            "INSERT INTO transactions
+                (transaction_id, raw_bcs, compressed, hex_prefixed, secondary_signatures,
+                 fee_payer_signature, stored_at, sender, sequence_number, canonical,
+                 required_signers, quorum, history)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (transaction_id) DO UPDATE SET
+                raw_bcs = excluded.raw_bcs,
+                compressed = excluded.compressed,
+                hex_prefixed = excluded.hex_prefixed,
+                secondary_signatures = excluded.secondary_signatures,
+                fee_payer_signature = excluded.fee_payer_signature,
+                stored_at = excluded.stored_at,
+                sender = excluded.sender,
+                sequence_number = excluded.sequence_number,
+                canonical = excluded.canonical,
+                required_signers = excluded.required_signers,
+                quorum = excluded.quorum,
+                history = excluded.history",
+        )
+        .bind(transaction_id)
+        .bind(&tx.raw_bcs)
+        .bind(tx.compressed)
+        .bind(tx.hex_prefixed)
+        .bind(encode_signatures(&tx.secondary_signatures)?)
+        .bind(&tx.fee_payer_signature)
+        .bind(tx.stored_at as i64)
+        .bind(&tx.sender)
+        .bind(tx.sequence_number.map(to_i64))
+        .bind(tx.canonical)
+        .bind(
+            tx.required_signers
+                .as_deref()
+                .map(encode_signers)
+                .transpose()?,
+        )
+        .bind(tx.quorum.map(i64::from))
+        .bind(encode_history(&tx.history)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM transactions WHERE transaction_id = ?",
+            COLUMNS
+        ))
+        .bind(transaction_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(row_to_transaction).transpose()
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        let query = match signer {
+            // One key of the JSON object, so concurrent signers don't overwrite each other
+            Signer::Secondary(address) => sqlx::query(
+                "UPDATE transactions
+                 SET secondary_signatures = json_set(secondary_signatures, '$.\"' || ? || '\"', ?)
+                 WHERE transaction_id = ?",
+            )
+            .bind(address)
+            .bind(hex::encode(signature)),
+            Signer::FeePayer => sqlx::query(
+                "UPDATE transactions SET fee_payer_signature = ? WHERE transaction_id = ?",
+            )
+            .bind(signature),
+        };
+        let result = query.bind(transaction_id).execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        // `$[#]` appends to the JSON array in place, so concurrent appends don't lose events
+        let mut db_tx = self.pool.begin().await?;
+        for event in &events {
+            let event =
+                serde_json::to_string(event).map_err(|e| StoreError::Backend(e.to_string()))?;
+            let result = sqlx::query(
+                "UPDATE transactions SET history = json_insert(history, '$[#]', json(?))
+                 WHERE transaction_id = ?",
+            )
+            .bind(event)
+            .bind(transaction_id)
+            .execute(&mut *db_tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                db_tx.rollback().await?;
+                return Ok(false);
+            }
+        }
+        db_tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let result = sqlx::query("DELETE FROM transactions WHERE transaction_id = ?")
+            .bind(transaction_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM transactions", COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(row_to_entry).collect()
+    }
+
+    async fn list_by_sender(
+        &self,
+        sender: &str,
+        sequence_numbers: RangeInclusive<u64>,
+    ) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM transactions
+             WHERE sender = ? AND sequence_number BETWEEN ? AND ?",
+            COLUMNS
+        ))
+        .bind(sender)
+        .bind(to_i64(*sequence_numbers.start()))
+        .bind(to_i64(*sequence_numbers.end()))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(row_to_entry).collect()
+    }
+}
@@ -0,0 +1,839 @@
+//! The transaction shapes the backend accepts, detected from the stored BCS.
+//!
+//! The frontend stores the SDK's signing payload: a `MultiAgentRawTransaction`,
+//! a `FeePayerRawTransaction` for sponsored flows, or a plain single-signer
+//! `RawTransaction`. BCS carries no type tag, but each shape is the previous
+//! one with fields appended (`RawTransaction`, then the secondary signer
+//! addresses, then the fee payer address) and decoding must consume every
+//! byte, so at most one shape fits.
+//!
+//! A fully signed `SignedTransaction` (what the SDK finally submits) is tried
+//! last. It is a `RawTransaction` followed by an authenticator, which could in
+//! principle also fit one of the other shapes; pass `kind` when storing to
+//! rule that out.
+//!
+//! Ed25519 and secp256k1 signatures, as legacy Ed25519 or `SingleKey`
+//! authenticators, are checked against the transaction's signing message
+//! when stored, so a bad signature is caught before on-chain submission.
+//! The check is only that the signature is self-consistent: it verifies
+//! under the public key the authenticator carries. Whether that key is the
+//! signer's is left to the chain, since an account's authentication key can
+//! be rotated away from the one its address was derived from.
+//!
+//! `expiration_timestamp_secs` is checked the same way: a transaction the
+//! chain would already reject as expired is refused at ingest, since the
+//! failure is easily mistaken for a sequence number problem later on.
+
+use crate::authenticator::{self, AccountAuthenticator, AnyPublicKey, AnySignature};
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::aptos_bcs::Error;
+use aptos_sdk::crypto::{
+    sha3_256, Ed25519PublicKey, Ed25519Signature, Secp256k1PublicKey, Secp256k1Signature,
+    SECP256K1_PUBLIC_KEY_UNCOMPRESSED_LENGTH,
+};
+use aptos_sdk::transaction::authenticator::{
+    AccountAuthenticator as SdkAccountAuthenticator, TransactionAuthenticator,
+};
+use aptos_sdk::transaction::types::{
+    FeePayerRawTransaction, MultiAgentRawTransaction, RawTransaction, SignedTransaction,
+};
+use aptos_sdk::{AccountAddress, AptosError, AptosResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Which payload a transaction's BCS holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    MultiAgent,
+    FeePayer,
+    RawTransaction,
+    SignedTransaction,
+}
+
+impl TransactionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransactionKind::MultiAgent => "multi_agent",
+            TransactionKind::FeePayer => "fee_payer",
+            TransactionKind::RawTransaction => "raw_transaction",
+            TransactionKind::SignedTransaction => "signed_transaction",
+        }
+    }
+
+    /// Inverse of [`TransactionKind::as_str`]
+    pub fn parse(s: &str) -> Option<Self> {
+        [
+            TransactionKind::MultiAgent,
+            TransactionKind::FeePayer,
+            TransactionKind::RawTransaction,
+            TransactionKind::SignedTransaction,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == s)
+    }
+}
+
+/// A decoded transaction; serializes back to the same layout it was decoded
+/// from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum DecodedTransaction {
+    MultiAgent(MultiAgentRawTransaction),
+    FeePayer(FeePayerRawTransaction),
+    RawTransaction(RawTransaction),
+    SignedTransaction(Box<SignedTransaction>),
+}
+
+impl DecodedTransaction {
+    /// Decode `bytes` as whichever payload they hold.
+    ///
+    /// On failure the multi-agent error is returned, since that is the shape
+    /// most callers store.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode_as(TransactionKind::MultiAgent, bytes).or_else(|e| {
+            [
+                TransactionKind::FeePayer,
+                TransactionKind::RawTransaction,
+                TransactionKind::SignedTransaction,
+            ]
+            .into_iter()
+            .find_map(|kind| Self::decode_as(kind, bytes).ok())
+            .ok_or(e)
+        })
+    }
+
+    /// Decode `bytes` as a payload of `kind`
+    pub fn decode_as(kind: TransactionKind, bytes: &[u8]) -> Result<Self, Error> {
+        match kind {
+            TransactionKind::MultiAgent => aptos_bcs::from_bytes(bytes).map(Self::MultiAgent),
+            TransactionKind::FeePayer => aptos_bcs::from_bytes(bytes).map(Self::FeePayer),
+            TransactionKind::RawTransaction => {
+                aptos_bcs::from_bytes(bytes).map(Self::RawTransaction)
+            }
+            TransactionKind::SignedTransaction => {
+                aptos_bcs::from_bytes(bytes).map(|tx| Self::SignedTransaction(Box::new(tx)))
+            }
+        }
+    }
+
+    pub fn kind(&self) -> TransactionKind {
+        match self {
+            DecodedTransaction::MultiAgent(_) => TransactionKind::MultiAgent,
+            DecodedTransaction::FeePayer(_) => TransactionKind::FeePayer,
+            DecodedTransaction::RawTransaction(_) => TransactionKind::RawTransaction,
+            DecodedTransaction::SignedTransaction(_) => TransactionKind::SignedTransaction,
+        }
+    }
+
+    pub fn raw_txn(&self) -> &RawTransaction {
+        match self {
+            DecodedTransaction::MultiAgent(tx) => &tx.raw_txn,
+            DecodedTransaction::FeePayer(tx) => &tx.raw_txn,
+            DecodedTransaction::RawTransaction(tx) => tx,
+            DecodedTransaction::SignedTransaction(tx) => &tx.raw_txn,
+        }
+    }
+
+    pub fn secondary_signer_addresses(&self) -> &[AccountAddress] {
+        match self {
+            DecodedTransaction::MultiAgent(tx) => &tx.secondary_signer_addresses,
+            DecodedTransaction::FeePayer(tx) => &tx.secondary_signer_addresses,
+            DecodedTransaction::RawTransaction(_) => &[],
+            DecodedTransaction::SignedTransaction(tx) => match &tx.authenticator {
+                TransactionAuthenticator::MultiAgent {
+                    secondary_signer_addresses,
+                    ..
+                }
+                | TransactionAuthenticator::FeePayer {
+                    secondary_signer_addresses,
+                    ..
+                } => secondary_signer_addresses,
+                _ => &[],
+            },
+        }
+    }
+
+    /// This payload with `sequence_number` instead, every other field kept;
+    /// `None` for a `SignedTransaction`, whose authenticator would no longer
+    /// match
+    pub fn with_sequence_number(&self, sequence_number: u64) -> Option<Self> {
+        let mut rebuilt = self.clone();
+        let raw_txn = match &mut rebuilt {
+            DecodedTransaction::MultiAgent(tx) => &mut tx.raw_txn,
+            DecodedTransaction::FeePayer(tx) => &mut tx.raw_txn,
+            DecodedTransaction::RawTransaction(tx) => tx,
+            DecodedTransaction::SignedTransaction(_) => return None,
+        };
+        raw_txn.sequence_number = sequence_number;
+        Some(rebuilt)
+    }
+
+    /// Where `expiration_timestamp_secs` stands at `now` (Unix seconds);
+    /// `nearly_expired` within `warning_secs` of it
+    pub fn expiration(&self, now: u64, warning_secs: u64) -> ExpirationStatus {
+        let expiration_timestamp_secs = self.raw_txn().expiration_timestamp_secs;
+        let expires_in_secs = (i128::from(expiration_timestamp_secs) - i128::from(now))
+            .clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64;
+        ExpirationStatus {
+            expiration_timestamp_secs,
+            expires_in_secs,
+            expired: expires_in_secs <= 0,
+            nearly_expired: expires_in_secs > 0 && expires_in_secs.unsigned_abs() <= warning_secs,
+        }
+    }
+
+    /// `None` unless this is a fee payer transaction, signed or not
+    pub fn fee_payer_address(&self) -> Option<AccountAddress> {
+        match self {
+            DecodedTransaction::FeePayer(tx) => Some(tx.fee_payer_address),
+            DecodedTransaction::SignedTransaction(tx) => match &tx.authenticator {
+                TransactionAuthenticator::FeePayer {
+                    fee_payer_address, ..
+                } => Some(*fee_payer_address),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The prefixed signing message the SDK computes for this transaction:
+    /// a `RawTransactionWithData` for multi-agent and fee payer transactions
+    /// (signed or not), otherwise the `RawTransaction` itself
+    pub fn signing_message(&self) -> AptosResult<Vec<u8>> {
+        let raw_txn = self.raw_txn().clone();
+        let secondary_signer_addresses = self.secondary_signer_addresses().to_vec();
+        if let Some(fee_payer_address) = self.fee_payer_address() {
+            return FeePayerRawTransaction::new(raw_txn, secondary_signer_addresses, fee_payer_address)
+                .signing_message();
+        }
+        let multi_agent = match self {
+            DecodedTransaction::MultiAgent(_) => true,
+            DecodedTransaction::SignedTransaction(tx) => {
+                matches!(tx.authenticator, TransactionAuthenticator::MultiAgent { .. })
+            }
+            _ => false,
+        };
+        if multi_agent {
+            MultiAgentRawTransaction::new(raw_txn, secondary_signer_addresses).signing_message()
+        } else {
+            raw_txn.signing_message()
+        }
+    }
+
+    /// The messages a signature over this transaction may cover; `fee_payer`
+    /// selects the fee payer's signature rather than the sender's or a
+    /// secondary signer's.
+    ///
+    /// Fee payer transactions are signed over the fee payer address, but the
+    /// other signers may sign over `0x0` instead (the fee payer often isn't
+    /// known yet when they sign), which the chain also accepts.
+    pub fn signing_messages(&self, fee_payer: bool) -> AptosResult<Vec<Vec<u8>>> {
+        let mut messages = vec![self.signing_message()?];
+        if !fee_payer
+            && self
+                .fee_payer_address()
+                .is_some_and(|address| address != AccountAddress::ZERO)
+        {
+            messages.push(
+                FeePayerRawTransaction::new(
+                    self.raw_txn().clone(),
+                    self.secondary_signer_addresses().to_vec(),
+                    AccountAddress::ZERO,
+                )
+                .signing_message()?,
+            );
+        }
+        Ok(messages)
+    }
+
+    /// Whether an Ed25519 or secp256k1 `authenticator` is a self-consistent
+    /// signature of one of this transaction's
+    /// [signing messages](Self::signing_messages): it verifies under the
+    /// public key it carries. The key is not checked against the signer's
+    /// address or authentication key. `None` for other schemes (passkey and
+    /// keyless ones), which are left to the chain. For a partial
+    /// `MultiEd25519` or `MultiKey` authenticator, every signature it holds
+    /// of those schemes must verify.
+    pub fn verify_self_consistent(
+        &self,
+        authenticator: &AccountAuthenticator,
+        fee_payer: bool,
+    ) -> AptosResult<Option<bool>> {
+        let Some(signature) = Verifiable::of(authenticator)? else {
+            return Ok(None);
+        };
+        let verified = self
+            .signing_messages(fee_payer)?
+            .iter()
+            .any(|message| signature.verifies(message));
+        Ok(Some(verified))
+    }
+
+    /// Signers other than the sender whose signature is not among
+    /// `secondary_signatures` (keyed by long-form address, as stored), or is
+    /// short of its threshold, with `fee_payer` last if the fee payer hasn't
+    /// signed
+    pub fn missing_signers(
+        &self,
+        secondary_signatures: &BTreeMap<String, Vec<u8>>,
+        fee_payer_signed: bool,
+    ) -> Vec<String> {
+        let mut missing: Vec<String> = self
+            .secondary_signer_addresses()
+            .iter()
+            .map(AccountAddress::to_long_string)
+            .filter(|address| {
+                !secondary_signatures
+                    .get(address)
+                    .is_some_and(|signature| authenticator::complete(signature))
+            })
+            .collect();
+        if self.fee_payer_address().is_some() && !fee_payer_signed {
+            missing.push("fee_payer".to_string());
+        }
+        missing
+    }
+
+    /// Signers other than the sender: long-form secondary signer addresses,
+    /// then `fee_payer` for fee payer transactions
+    pub fn required_signers(&self) -> Vec<String> {
+        self.missing_signers(&BTreeMap::new(), false)
+    }
+
+    /// Build the BCS `SignedTransaction` a multi-agent or fee payer payload
+    /// becomes once everyone has signed.
+    ///
+    /// `secondary_signatures` are BCS `AccountAuthenticator`s keyed by
+    /// long-form signer address, as stored. They are placed in the order of
+    /// `secondary_signer_addresses`, one per address; a signature keyed by
+    /// any other address is an error rather than being dropped, since the
+    /// chain would only report the result as `INVALID_SIGNATURE`. A
+    /// `MultiEd25519` or `MultiKey` signature short of its threshold counts
+    /// as missing.
+    pub fn assemble(
+        &self,
+        sender: &AccountAuthenticator,
+        secondary_signatures: &BTreeMap<String, Vec<u8>>,
+        fee_payer_signature: Option<&[u8]>,
+    ) -> Result<Vec<u8>, AssembleError> {
+        if !matches!(
+            self.kind(),
+            TransactionKind::MultiAgent | TransactionKind::FeePayer
+        ) {
+            return Err(AssembleError::UnsupportedKind(self.kind()));
+        }
+
+        let expected: Vec<String> = self
+            .secondary_signer_addresses()
+            .iter()
+            .map(AccountAddress::to_long_string)
+            .collect();
+        let unexpected: Vec<String> = secondary_signatures
+            .keys()
+            .filter(|address| !expected.contains(address))
+            .cloned()
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(AssembleError::NotSecondarySigners {
+                unexpected,
+                expected,
+            });
+        }
+
+        let mut missing = Vec::new();
+        let mut secondary_signers = Vec::new();
+        for address in expected {
+            match secondary_signatures.get(&address) {
+                Some(signature) if authenticator::complete(signature) => {
+                    secondary_signers.push(account_authenticator(&address, signature)?)
+                }
+                _ => missing.push(address),
+            }
+        }
+        let fee_payer_signature =
+            fee_payer_signature.filter(|signature| authenticator::complete(signature));
+        let fee_payer_signer = match (self.fee_payer_address(), fee_payer_signature) {
+            (Some(_), Some(signature)) => Some(account_authenticator("fee_payer", signature)?),
+            (Some(_), None) => {
+                missing.push("fee_payer".to_string());
+                None
+            }
+            (None, _) => None,
+        };
+        if !missing.is_empty() {
+            return Err(AssembleError::Missing(missing));
+        }
+
+        authenticator::signed_transaction(
+            self.raw_txn(),
+            sender,
+            self.secondary_signer_addresses(),
+            &secondary_signers,
+            self.fee_payer_address().zip(fee_payer_signer.as_ref()),
+        )
+        .map_err(|e| AssembleError::Encode(e.to_string()))
+    }
+
+    /// How a signed transaction is authenticated; `None` for unsigned payloads
+    pub fn authenticator(&self) -> Option<AuthenticatorSummary> {
+        let DecodedTransaction::SignedTransaction(tx) = self else {
+            return None;
+        };
+        let sender = tx.raw_txn.sender;
+        let summary = match &tx.authenticator {
+            TransactionAuthenticator::Ed25519 { public_key, .. } => AuthenticatorSummary {
+                variant: "ed25519",
+                signers: vec![SignerSummary {
+                    role: "sender",
+                    address: Some(sender.to_long_string()),
+                    scheme: Some("ed25519"),
+                    public_key: Some(hex_prefixed(&public_key.0)),
+                }],
+            },
+            TransactionAuthenticator::MultiEd25519 { public_key, .. } => AuthenticatorSummary {
+                variant: "multi_ed25519",
+                signers: vec![SignerSummary {
+                    role: "sender",
+                    address: Some(sender.to_long_string()),
+                    scheme: Some("multi_ed25519"),
+                    public_key: Some(hex_prefixed(public_key)),
+                }],
+            },
+            TransactionAuthenticator::SingleSender { sender: account } => AuthenticatorSummary {
+                variant: "single_sender",
+                signers: vec![SignerSummary::new("sender", Some(&sender), Some(account))],
+            },
+            TransactionAuthenticator::MultiAgent {
+                sender: account,
+                secondary_signer_addresses,
+                secondary_signers,
+            } => AuthenticatorSummary {
+                variant: "multi_agent",
+                signers: std::iter::once(SignerSummary::new("sender", Some(&sender), Some(account)))
+                    .chain(secondaries(secondary_signer_addresses, secondary_signers))
+                    .collect(),
+            },
+            TransactionAuthenticator::FeePayer {
+                sender: account,
+                secondary_signer_addresses,
+                secondary_signers,
+                fee_payer_address,
+                fee_payer_signer,
+            } => AuthenticatorSummary {
+                variant: "fee_payer",
+                signers: std::iter::once(SignerSummary::new("sender", Some(&sender), Some(account)))
+                    .chain(secondaries(secondary_signer_addresses, secondary_signers))
+                    .chain(std::iter::once(SignerSummary::new(
+                        "fee_payer",
+                        Some(fee_payer_address),
+                        Some(fee_payer_signer),
+                    )))
+                    .collect(),
+            },
+        };
+        Some(summary)
+    }
+}
+
+/// A transaction's `expiration_timestamp_secs` relative to server time
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExpirationStatus {
+    /// As encoded in the transaction, in Unix seconds
+    pub expiration_timestamp_secs: u64,
+    /// Seconds left before the chain rejects it; zero or negative once it has
+    /// expired
+    pub expires_in_secs: i64,
+    /// The chain would reject it with `TRANSACTION_EXPIRED`
+    pub expired: bool,
+    /// Not expired yet, but within `EXPIRATION_WARNING_SECS` of it
+    pub nearly_expired: bool,
+}
+
+/// Why [`DecodedTransaction::assemble`] couldn't build a transaction
+#[derive(Debug)]
+pub enum AssembleError {
+    /// Only multi-agent and fee payer payloads are assembled
+    UnsupportedKind(TransactionKind),
+    /// Signers that haven't signed yet: long-form secondary signer addresses,
+    /// and `fee_payer` for the fee payer
+    Missing(Vec<String>),
+    /// Signatures keyed by addresses that aren't among the transaction's
+    /// `secondary_signer_addresses` (`expected`, in order)
+    NotSecondarySigners {
+        unexpected: Vec<String>,
+        expected: Vec<String>,
+    },
+    /// A stored signature no longer decodes as an `AccountAuthenticator`
+    Corrupt { signer: String, error: Error },
+    /// The assembled transaction failed to serialize
+    Encode(String),
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembleError::UnsupportedKind(kind) => {
+                write!(
+                    f,
+                    "Only multi_agent and fee_payer transactions can be assembled, not {}",
+                    kind.as_str()
+                )
+            }
+            AssembleError::Missing(signers) => {
+                write!(f, "Missing signatures from {}", signers.join(", "))
+            }
+            AssembleError::NotSecondarySigners {
+                unexpected,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "Signatures from {} do not match the secondary signer addresses [{}]",
+                    unexpected.join(", "),
+                    expected.join(", ")
+                )
+            }
+            AssembleError::Corrupt { signer, error } => {
+                write!(f, "Stored signature of {} is corrupt: {}", signer, error)
+            }
+            AssembleError::Encode(e) => write!(f, "Failed to encode signed transaction: {}", e),
+        }
+    }
+}
+
+/// A signature the backend checks itself, with the key it is checked against
+enum Verifiable {
+    Ed25519(Ed25519PublicKey, Ed25519Signature),
+    Secp256k1(Secp256k1PublicKey, Secp256k1Signature),
+    /// The checkable signatures of a `MultiEd25519` or `MultiKey`
+    /// authenticator, which must all verify
+    Multi(Vec<Verifiable>),
+}
+
+impl Verifiable {
+    fn of(authenticator: &AccountAuthenticator) -> AptosResult<Option<Self>> {
+        match authenticator {
+            AccountAuthenticator::Ed25519 {
+                public_key,
+                signature,
+            } => Self::ed25519(public_key, signature).map(Some),
+            AccountAuthenticator::SingleKey { authenticator } => {
+                Self::single_key(&authenticator.public_key, &authenticator.signature)
+            }
+            AccountAuthenticator::MultiEd25519 { .. } | AccountAuthenticator::MultiKey { .. } => {
+                let mut signatures = Vec::new();
+                for (public_key, signature) in authenticator
+                    .signatures_by_key()
+                    .map_err(AptosError::InvalidSignature)?
+                {
+                    signatures.extend(Self::single_key(&public_key, &signature)?);
+                }
+                Ok((!signatures.is_empty()).then_some(Verifiable::Multi(signatures)))
+            }
+            AccountAuthenticator::NoAccountAuthenticator => Ok(None),
+        }
+    }
+
+    fn single_key(
+        public_key: &AnyPublicKey,
+        signature: &AnySignature,
+    ) -> AptosResult<Option<Self>> {
+        match (public_key, signature) {
+            (AnyPublicKey::Ed25519 { public_key }, AnySignature::Ed25519 { signature }) => {
+                Self::ed25519(public_key, signature).map(Some)
+            }
+            (
+                AnyPublicKey::Secp256k1Ecdsa { public_key },
+                AnySignature::Secp256k1Ecdsa { signature },
+            ) => {
+                // The chain only takes uncompressed keys, where the SDK
+                // parses either form
+                if public_key.len() != SECP256K1_PUBLIC_KEY_UNCOMPRESSED_LENGTH {
+                    return Err(AptosError::InvalidPublicKey(format!(
+                        "expected an uncompressed secp256k1 key of {} bytes, got {}",
+                        SECP256K1_PUBLIC_KEY_UNCOMPRESSED_LENGTH,
+                        public_key.len()
+                    )));
+                }
+                Ok(Some(Verifiable::Secp256k1(
+                    Secp256k1PublicKey::from_bytes(public_key)?,
+                    Secp256k1Signature::from_bytes(signature)?,
+                )))
+            }
+            (AnyPublicKey::Secp256r1Ecdsa { .. }, AnySignature::WebAuthn { .. })
+            | (
+                AnyPublicKey::Keyless { .. } | AnyPublicKey::FederatedKeyless { .. },
+                AnySignature::Keyless { .. },
+            ) => Ok(None),
+            _ => Err(AptosError::InvalidSignature(
+                "signature doesn't match its key's scheme".to_string(),
+            )),
+        }
+    }
+
+    fn ed25519(public_key: &[u8], signature: &[u8]) -> AptosResult<Self> {
+        Ok(Verifiable::Ed25519(
+            Ed25519PublicKey::from_bytes(public_key)?,
+            Ed25519Signature::from_bytes(signature)?,
+        ))
+    }
+
+    fn verifies(&self, message: &[u8]) -> bool {
+        match self {
+            Verifiable::Ed25519(public_key, signature) => {
+                public_key.verify(message, signature).is_ok()
+            }
+            // The chain signs the SHA3-256 of the message; the SDK's `verify`
+            // would hash it with SHA2-256
+            Verifiable::Secp256k1(public_key, signature) => public_key
+                .verify_prehashed(&sha3_256(message), signature)
+                .is_ok(),
+            Verifiable::Multi(signatures) => signatures
+                .iter()
+                .all(|signature| signature.verifies(message)),
+        }
+    }
+}
+
+fn account_authenticator(signer: &str, bytes: &[u8]) -> Result<AccountAuthenticator, AssembleError> {
+    AccountAuthenticator::decode(bytes).map_err(|error| AssembleError::Corrupt {
+        signer: signer.to_string(),
+        error,
+    })
+}
+
+/// The `TransactionAuthenticator` of a `SignedTransaction`
+#[derive(Serialize, ToSchema)]
+pub struct AuthenticatorSummary {
+    /// Authenticator variant, e.g. `multi_agent` or `single_sender`
+    pub variant: &'static str,
+    /// Every account the authenticator covers, sender first
+    pub signers: Vec<SignerSummary>,
+}
+
+/// One account covered by a `TransactionAuthenticator`
+#[derive(Serialize, ToSchema)]
+pub struct SignerSummary {
+    /// `sender`, `secondary` or `fee_payer`
+    pub role: &'static str,
+    /// Long-form address; `None` if the authenticator lists more signatures
+    /// than secondary signer addresses
+    pub address: Option<String>,
+    /// `AccountAuthenticator` variant, e.g. `ed25519` or `single_key`; `None`
+    /// if the authenticator lists more addresses than signatures
+    pub scheme: Option<&'static str>,
+    /// Public key bytes as hex, as carried by the authenticator
+    pub public_key: Option<String>,
+}
+
+impl SignerSummary {
+    fn new(
+        role: &'static str,
+        address: Option<&AccountAddress>,
+        account: Option<&SdkAccountAuthenticator>,
+    ) -> Self {
+        let (scheme, public_key) = match account {
+            Some(SdkAccountAuthenticator::Ed25519 { public_key, .. }) => {
+                (Some("ed25519"), Some(hex_prefixed(&public_key.0)))
+            }
+            Some(SdkAccountAuthenticator::MultiEd25519 { public_key, .. }) => {
+                (Some("multi_ed25519"), Some(hex_prefixed(public_key)))
+            }
+            Some(SdkAccountAuthenticator::SingleKey { public_key, .. }) => {
+                (Some("single_key"), Some(hex_prefixed(public_key)))
+            }
+            Some(SdkAccountAuthenticator::MultiKey { public_key, .. }) => {
+                (Some("multi_key"), Some(hex_prefixed(public_key)))
+            }
+            Some(SdkAccountAuthenticator::NoAccountAuthenticator) => (Some("none"), None),
+            None => (None, None),
+        };
+        Self {
+            role,
+            address: address.map(AccountAddress::to_long_string),
+            scheme,
+            public_key,
+        }
+    }
+}
+
+/// Pair secondary signer addresses with their authenticators, keeping any
+/// unpaired extras on either side so a length mismatch is visible
+fn secondaries<'a>(
+    addresses: &'a [AccountAddress],
+    signers: &'a [SdkAccountAuthenticator],
+) -> impl Iterator<Item = SignerSummary> + 'a {
+    (0..addresses.len().max(signers.len()))
+        .map(|i| SignerSummary::new("secondary", addresses.get(i), signers.get(i)))
+}
+
+fn hex_prefixed(bytes: &[u8]) -> String {
+    crate::storage::hex_bytes::encode(bytes, true)
+}
+
+#[cfg(test)]
+impl DecodedTransaction {
+    /// A multi-agent `0x1::aptos_account::transfer` of 1 octa to `0x1`, sent
+    /// by `0x1` with `0x2` as its one secondary signer
+    pub(crate) fn fixture() -> Self {
+        let raw_txn = RawTransaction::new(
+            AccountAddress::ONE,
+            0,
+            aptos_sdk::transaction::EntryFunction::apt_transfer(AccountAddress::ONE, 1)
+                .unwrap()
+                .into(),
+            200_000,
+            100,
+            u64::MAX,
+            aptos_sdk::ChainId::testnet(),
+        );
+        DecodedTransaction::MultiAgent(MultiAgentRawTransaction::new(
+            raw_txn,
+            vec![AccountAddress::from_hex("0x2").unwrap()],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_sdk::crypto::{Ed25519PrivateKey, Secp256k1PrivateKey};
+
+    fn raw_txn() -> RawTransaction {
+        DecodedTransaction::fixture().raw_txn().clone()
+    }
+
+    fn single_key(public_key: AnyPublicKey, signature: AnySignature) -> AccountAuthenticator {
+        AccountAuthenticator::SingleKey {
+            authenticator: authenticator::SingleKeyAuthenticator {
+                public_key,
+                signature,
+            },
+        }
+    }
+
+    fn ed25519(key: &Ed25519PrivateKey, message: &[u8]) -> AccountAuthenticator {
+        AccountAuthenticator::Ed25519 {
+            public_key: key.public_key().to_bytes().to_vec(),
+            signature: key.sign(message).to_bytes().to_vec(),
+        }
+    }
+
+    fn secp256k1(public_key: Vec<u8>, signature: &Secp256k1Signature) -> AccountAuthenticator {
+        single_key(
+            AnyPublicKey::Secp256k1Ecdsa { public_key },
+            AnySignature::Secp256k1Ecdsa {
+                signature: signature.to_bytes().to_vec(),
+            },
+        )
+    }
+
+    #[test]
+    fn ed25519_signature_verifies() {
+        let tx = DecodedTransaction::fixture();
+        let key = Ed25519PrivateKey::generate();
+        let signed = ed25519(&key, &tx.signing_message().unwrap());
+        assert_eq!(
+            tx.verify_self_consistent(&signed, false).unwrap(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn secp256k1_signs_the_sha3_of_the_message() {
+        let tx = DecodedTransaction::fixture();
+        let message = tx.signing_message().unwrap();
+        let key = Secp256k1PrivateKey::generate();
+        let public_key = key.public_key().to_uncompressed_bytes();
+
+        let sha3 = secp256k1(public_key.clone(), &key.sign_prehashed(&sha3_256(&message)));
+        assert_eq!(tx.verify_self_consistent(&sha3, false).unwrap(), Some(true));
+        let sha2 = secp256k1(public_key, &key.sign(&message));
+        assert_eq!(
+            tx.verify_self_consistent(&sha2, false).unwrap(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn tampered_signature_fails() {
+        let tx = DecodedTransaction::fixture();
+        let key = Ed25519PrivateKey::generate();
+        let mut signed = ed25519(&key, &tx.signing_message().unwrap());
+        if let AccountAuthenticator::Ed25519 { signature, .. } = &mut signed {
+            signature[0] ^= 1;
+        }
+        assert_eq!(
+            tx.verify_self_consistent(&signed, false).unwrap(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn compressed_secp256k1_key_is_rejected() {
+        let tx = DecodedTransaction::fixture();
+        let key = Secp256k1PrivateKey::generate();
+        let message = sha3_256(&tx.signing_message().unwrap());
+        let signed = secp256k1(key.public_key().to_bytes(), &key.sign_prehashed(&message));
+        assert!(tx.verify_self_consistent(&signed, false).is_err());
+    }
+
+    #[test]
+    fn mismatched_key_and_signature_scheme_is_rejected() {
+        let tx = DecodedTransaction::fixture();
+        let message = tx.signing_message().unwrap();
+        let ed25519 = Ed25519PrivateKey::generate();
+        let secp256k1 = Secp256k1PrivateKey::generate();
+        let public_key = AnyPublicKey::Ed25519 {
+            public_key: ed25519.public_key().to_bytes().to_vec(),
+        };
+        let matching = AnySignature::Ed25519 {
+            signature: ed25519.sign(&message).to_bytes().to_vec(),
+        };
+        let mismatched = AnySignature::Secp256k1Ecdsa {
+            signature: secp256k1.sign(&message).to_bytes().to_vec(),
+        };
+
+        assert!(Verifiable::single_key(&public_key, &matching)
+            .unwrap()
+            .is_some_and(|signature| signature.verifies(&message)));
+        assert!(Verifiable::single_key(&public_key, &mismatched).is_err());
+        let signed = single_key(public_key, mismatched);
+        assert!(tx.verify_self_consistent(&signed, false).is_err());
+    }
+
+    #[test]
+    fn fee_payer_signers_may_sign_over_zero_fee_payer() {
+        let fee_payer = AccountAddress::from_hex("0x2").unwrap();
+        let tx = DecodedTransaction::FeePayer(FeePayerRawTransaction::new(
+            raw_txn(),
+            vec![AccountAddress::ONE],
+            fee_payer,
+        ));
+        let zero =
+            FeePayerRawTransaction::new(raw_txn(), vec![AccountAddress::ONE], AccountAddress::ZERO)
+                .signing_message()
+                .unwrap();
+        let key = Ed25519PrivateKey::generate();
+        let signed = ed25519(&key, &zero);
+
+        assert_eq!(
+            tx.verify_self_consistent(&signed, false).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            tx.verify_self_consistent(&signed, true).unwrap(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn unsigned_authenticator_is_not_checked() {
+        let tx = DecodedTransaction::fixture();
+        let unsigned = AccountAuthenticator::NoAccountAuthenticator;
+        assert_eq!(tx.verify_self_consistent(&unsigned, false).unwrap(), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! Canonical type tag strings.
+//!
+//! The SDK's `Display` shortens every address (`0xabc::m::T`), while the TS
+//! SDK and the REST API write AIP-40 standard addresses: special addresses
+//! such as `0x1` short and every other address in full. Type tags decoded
+//! from payloads are rendered the AIP-40 way, e.g.
+//! `0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>`, so they can be
+//! compared with the frontend's strings as-is.
+
+use aptos_sdk::types::TypeTag;
+
+/// AIP-40 rendering of `type_tag`
+pub fn canonical(type_tag: &TypeTag) -> String {
+    match type_tag {
+        TypeTag::Vector(element) => format!("vector<{}>", canonical(element)),
+        TypeTag::Struct(tag) => {
+            let mut rendered = format!(
+                "{}::{}::{}",
+                tag.address.to_standard_string(),
+                tag.module,
+                tag.name
+            );
+            if !tag.type_args.is_empty() {
+                let type_args: Vec<String> = tag.type_args.iter().map(canonical).collect();
+                rendered.push('<');
+                rendered.push_str(&type_args.join(", "));
+                rendered.push('>');
+            }
+            rendered
+        }
+        primitive => primitive.to_string(),
+    }
+}
+
+/// Parse a type tag string, with short or long addresses
+pub fn parse(s: &str) -> Result<TypeTag, String> {
+    TypeTag::from_str_strict(s).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aptos_sdk::aptos_bcs;
+
+    const LONG: &str = "0x00000000000000000000000000000000000000000000000000000000000000ab";
+
+    #[test]
+    fn canonical_strings_round_trip() {
+        let cases = [
+            "u8".to_string(),
+            "vector<vector<address>>".to_string(),
+            "0x1::aptos_coin::AptosCoin".to_string(),
+            "0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>".to_string(),
+            format!("{}::pool::Pool<{}::a::A, vector<u64>>", LONG, LONG),
+        ];
+        for case in cases {
+            let type_tag = parse(&case).unwrap();
+            assert_eq!(canonical(&type_tag), case);
+            let bcs = aptos_bcs::to_bytes(&type_tag).unwrap();
+            assert_eq!(aptos_bcs::from_bytes::<TypeTag>(&bcs).unwrap(), type_tag);
+        }
+    }
+
+    #[test]
+    fn special_addresses_are_short_and_others_long() {
+        let long_special = format!("0x{:0>64}::m::T", "1");
+        assert_eq!(canonical(&parse(&long_special).unwrap()), "0x1::m::T");
+        assert_eq!(
+            canonical(&parse("0xab::m::T<0xab::m::U>").unwrap()),
+            format!("{}::m::T<{}::m::U>", LONG, LONG)
+        );
+    }
+
+    #[test]
+    fn malformed_tags_are_rejected() {
+        for case in ["", "u7", "vector<u8", "0x1::coin"] {
+            assert!(parse(case).is_err(), "{}", case);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Property-based BCS roundtrip tests for the signing messages.
+//!
+//! Every generated `RawTransaction`, `MultiAgentRawTransaction` and
+//! `FeePayerRawTransaction` must come back byte for byte from each
+//! [`reserialize::Strategy`], the same path a retrieval takes in reserialize
+//! mode, and from a plain SDK decode and encode.
+
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::payload::{Multisig, MultisigTransactionPayload};
+use aptos_sdk::transaction::types::{FeePayerRawTransaction, MultiAgentRawTransaction};
+use aptos_sdk::transaction::{
+    EntryFunction, RawTransaction, Script, ScriptArgument, TransactionPayload,
+};
+use aptos_sdk::types::{AccountAddress, ChainId, Identifier, MoveModuleId, StructTag, TypeTag};
+use clap::ValueEnum;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use repro_core::reserialize;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+fn address() -> impl Strategy<Value = AccountAddress> {
+    any::<[u8; 32]>().prop_map(AccountAddress::new)
+}
+
+fn identifier() -> impl Strategy<Value = Identifier> {
+    "[a-zA-Z_][a-zA-Z0-9_]{0,31}".prop_map(|s| Identifier::new(s).unwrap())
+}
+
+fn type_tag() -> impl Strategy<Value = TypeTag> {
+    let primitive = prop_oneof![
+        Just(TypeTag::Bool),
+        Just(TypeTag::U8),
+        Just(TypeTag::U16),
+        Just(TypeTag::U32),
+        Just(TypeTag::U64),
+        Just(TypeTag::U128),
+        Just(TypeTag::U256),
+        Just(TypeTag::I8),
+        Just(TypeTag::I16),
+        Just(TypeTag::I32),
+        Just(TypeTag::I64),
+        Just(TypeTag::I128),
+        Just(TypeTag::I256),
+        Just(TypeTag::Address),
+        Just(TypeTag::Signer),
+    ];
+    primitive.prop_recursive(3, 16, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(TypeTag::vector),
+            (address(), identifier(), identifier(), vec(inner, 0..3)).prop_map(
+                |(address, module, name, type_args)| {
+                    TypeTag::Struct(Box::new(StructTag::new(address, module, name, type_args)))
+                }
+            ),
+        ]
+    })
+}
+
+fn entry_function() -> impl Strategy<Value = EntryFunction> {
+    (
+        address(),
+        identifier(),
+        identifier(),
+        vec(type_tag(), 0..3),
+        vec(vec(any::<u8>(), 0..48), 0..5),
+    )
+        .prop_map(|(address, module, function, type_args, args)| {
+            EntryFunction::new(
+                MoveModuleId::new(address, module),
+                function.as_str(),
+                type_args,
+                args,
+            )
+        })
+}
+
+fn script_argument() -> impl Strategy<Value = ScriptArgument> {
+    prop_oneof![
+        any::<u8>().prop_map(ScriptArgument::U8),
+        any::<u16>().prop_map(ScriptArgument::U16),
+        any::<u32>().prop_map(ScriptArgument::U32),
+        any::<u64>().prop_map(ScriptArgument::U64),
+        any::<u128>().prop_map(ScriptArgument::U128),
+        any::<[u8; 32]>().prop_map(ScriptArgument::U256),
+        address().prop_map(ScriptArgument::Address),
+        vec(any::<u8>(), 0..48).prop_map(ScriptArgument::U8Vector),
+        any::<bool>().prop_map(ScriptArgument::Bool),
+        vec(any::<u8>(), 0..48).prop_map(ScriptArgument::Serialized),
+    ]
+}
+
+fn payload() -> impl Strategy<Value = TransactionPayload> {
+    prop_oneof![
+        entry_function().prop_map(TransactionPayload::EntryFunction),
+        (
+            vec(any::<u8>(), 0..256),
+            vec(type_tag(), 0..3),
+            vec(script_argument(), 0..5),
+        )
+            .prop_map(|(code, type_args, args)| {
+                TransactionPayload::Script(Script::new(code, type_args, args))
+            }),
+        (address(), proptest::option::of(entry_function())).prop_map(
+            |(multisig_address, entry_function)| {
+                TransactionPayload::Multisig(Multisig {
+                    multisig_address,
+                    transaction_payload: entry_function
+                        .map(MultisigTransactionPayload::EntryFunction),
+                })
+            }
+        ),
+    ]
+}
+
+fn raw_transaction() -> impl Strategy<Value = RawTransaction> {
+    (
+        address(),
+        any::<u64>(),
+        payload(),
+        any::<u64>(),
+        any::<u64>(),
+        any::<u64>(),
+        any::<u8>(),
+    )
+        .prop_map(
+            |(sender, sequence_number, payload, max_gas, gas_price, expiration, chain_id)| {
+                RawTransaction::new(
+                    sender,
+                    sequence_number,
+                    payload,
+                    max_gas,
+                    gas_price,
+                    expiration,
+                    ChainId::new(chain_id),
+                )
+            },
+        )
+}
+
+/// Serialize `value`, then check that the SDK decodes it back to `value` and
+/// that every strategy re-serializes the bytes unchanged
+fn assert_roundtrip<T>(value: &T) -> Result<(), TestCaseError>
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let bytes = aptos_bcs::to_bytes(value).unwrap();
+    let decoded: T = aptos_bcs::from_bytes(&bytes).unwrap();
+    prop_assert_eq!(&decoded, value);
+    for &strategy in reserialize::Strategy::value_variants() {
+        let reserialized = reserialize::reserialize(strategy, &bytes);
+        prop_assert_eq!(reserialized.as_ref(), Ok(&bytes), "{}", strategy.as_str());
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn raw_transaction_roundtrips(raw_txn in raw_transaction()) {
+        assert_roundtrip(&raw_txn)?;
+    }
+
+    #[test]
+    fn multi_agent_raw_transaction_roundtrips(
+        raw_txn in raw_transaction(),
+        secondary_signer_addresses in vec(address(), 0..4),
+    ) {
+        assert_roundtrip(&MultiAgentRawTransaction::new(raw_txn, secondary_signer_addresses))?;
+    }
+
+    #[test]
+    fn fee_payer_raw_transaction_roundtrips(
+        raw_txn in raw_transaction(),
+        secondary_signer_addresses in vec(address(), 0..4),
+        fee_payer_address in address(),
+    ) {
+        assert_roundtrip(&FeePayerRawTransaction::new(
+            raw_txn,
+            secondary_signer_addresses,
+            fee_payer_address,
+        ))?;
+    }
+}
@@ -0,0 +1,24 @@
+//! Feeds arbitrary bytes through every reserialize strategy. Whenever they
+//! decode, encoding them again must give back exactly the same bytes; a crash
+//! is a layout the SDK (or the `untyped` mirror) doesn't roundtrip, found
+//! before a frontend flow ever produces it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use repro_core::reserialize::{self, Strategy};
+use repro_core::storage;
+
+fuzz_target!(|data: &[u8]| {
+    for strategy in [Strategy::Typed, Strategy::RawTransaction, Strategy::Untyped] {
+        if let Ok(reserialized) = reserialize::reserialize(strategy, data) {
+            assert!(
+                reserialized == data,
+                "{} changed the bytes:\n  input:        {}\n  reserialized: {}",
+                strategy.as_str(),
+                storage::hex_bytes::encode(data, true),
+                storage::hex_bytes::encode(&reserialized, true)
+            );
+        }
+    }
+});
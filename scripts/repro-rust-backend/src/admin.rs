@@ -0,0 +1,284 @@
+//! Admin endpoints for moving repro state between machines.
+//!
+//! `GET /admin/export` downloads the whole store in the snapshot format (see
+//! [`crate::snapshot`]) and `POST /admin/import` loads such a file back, so a
+//! repro corpus can be checked into a repository and replayed elsewhere.
+//! `POST /admin/backup` and `POST /admin/restore` do the same with
+//! timestamped files on the server (see [`crate::backup`]).
+//!
+//! `GET /admin/mode` and `POST /admin/mode` read and switch between
+//! pass-through and reserialize retrieval without a restart, so both halves of
+//! an A/B repro can run against the same stored transactions.
+
+use crate::backup;
+use crate::config::Mode;
+use crate::snapshot::Snapshot;
+use crate::storage::StoreError;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+/// Largest archive accepted by `POST /admin/import`
+pub const IMPORT_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+#[derive(Serialize, ToSchema)]
+pub struct AdminResponse {
+    success: bool,
+    /// Number of transactions affected
+    transactions: usize,
+    /// Backup file written or restored (backup/restore only)
+    backup: Option<String>,
+    message: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestoreQuery {
+    /// Backup file name; defaults to the newest backup
+    name: Option<String>,
+}
+
+impl AdminResponse {
+    fn error(status: StatusCode, message: String) -> (StatusCode, Json<Self>) {
+        (
+            status,
+            Json(Self {
+                success: false,
+                transactions: 0,
+                backup: None,
+                message,
+            }),
+        )
+    }
+}
+
+/// Download every stored transaction as a JSON archive
+#[utoipa::path(
+    get,
+    path = "/admin/export",
+    responses(
+        (status = 200, description = "Snapshot of every stored transaction", body = Snapshot),
+        (status = 500, description = "Storage error", body = AdminResponse),
+    )
+)]
+pub async fn export(State(state): State<Arc<AppState>>) -> Response {
+    info!("Exporting store");
+
+    let snapshot = match Snapshot::capture(state.store.as_ref()).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return AdminResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read from storage: {}", e),
+            )
+            .into_response();
+        }
+    };
+
+    info!("Exported {} transactions", snapshot.transactions.len());
+    let filename = format!("repro-export-{}.json", snapshot.created_at);
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )],
+        Json(snapshot),
+    )
+        .into_response()
+}
+
+/// Load an archive produced by `GET /admin/export`, replacing entries with the same ids
+#[utoipa::path(
+    post,
+    path = "/admin/import",
+    request_body = Snapshot,
+    responses(
+        (status = 200, description = "Archive imported", body = AdminResponse),
+        (status = 400, description = "Unsupported archive version", body = AdminResponse),
+        (status = 500, description = "Storage error", body = AdminResponse),
+    )
+)]
+pub async fn import(
+    State(state): State<Arc<AppState>>,
+    Json(snapshot): Json<Snapshot>,
+) -> (StatusCode, Json<AdminResponse>) {
+    info!("Importing {} transactions", snapshot.transactions.len());
+
+    let transaction_ids: Vec<String> = snapshot.transactions.keys().cloned().collect();
+    let imported = match snapshot.restore_into(state.store.as_ref()).await {
+        Ok(imported) => imported,
+        Err(e) => {
+            error!("Failed to import: {}", e);
+            let status = match e {
+                // Unsupported archive version
+                StoreError::Config(_) => StatusCode::BAD_REQUEST,
+                StoreError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return AdminResponse::error(status, format!("Failed to import: {}", e));
+        }
+    };
+
+    // Re-imported ids are live again, not expired
+    if let Some(expiry) = &state.expiry {
+        for transaction_id in &transaction_ids {
+            expiry.clear(transaction_id);
+        }
+    }
+
+    info!("Imported {} transactions", imported);
+    (
+        StatusCode::OK,
+        Json(AdminResponse {
+            success: true,
+            transactions: imported,
+            backup: None,
+            message: format!("Imported {} transactions", imported),
+        }),
+    )
+}
+
+/// Write a timestamped backup to the backup directory
+#[utoipa::path(
+    post,
+    path = "/admin/backup",
+    responses(
+        (status = 200, description = "Backup written", body = AdminResponse),
+        (status = 500, description = "Storage or file system error", body = AdminResponse),
+    )
+)]
+pub async fn backup(State(state): State<Arc<AppState>>) -> (StatusCode, Json<AdminResponse>) {
+    info!("Writing backup to {}", state.backup_dir.display());
+
+    match backup::write(state.store.as_ref(), &state.backup_dir).await {
+        Ok((name, count)) => {
+            info!("Backed up {} transactions to {}", count, name);
+            (
+                StatusCode::OK,
+                Json(AdminResponse {
+                    success: true,
+                    transactions: count,
+                    backup: Some(name),
+                    message: format!("Backed up {} transactions", count),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to write backup: {}", e);
+            AdminResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to write backup: {}", e),
+            )
+        }
+    }
+}
+
+/// Load a backup (`?name=`, default the newest) into the store
+#[utoipa::path(
+    post,
+    path = "/admin/restore",
+    params(RestoreQuery),
+    responses(
+        (status = 200, description = "Backup restored", body = AdminResponse),
+        (status = 400, description = "Unknown backup or unsupported snapshot version", body = AdminResponse),
+        (status = 500, description = "Storage or file system error", body = AdminResponse),
+    )
+)]
+pub async fn restore(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RestoreQuery>,
+) -> (StatusCode, Json<AdminResponse>) {
+    info!(
+        "Restoring backup {}",
+        query.name.as_deref().unwrap_or("(latest)")
+    );
+
+    match backup::restore(state.store.as_ref(), &state.backup_dir, query.name.as_deref()).await {
+        Ok((name, count)) => {
+            info!("Restored {} transactions from {}", count, name);
+            (
+                StatusCode::OK,
+                Json(AdminResponse {
+                    success: true,
+                    transactions: count,
+                    backup: Some(name),
+                    message: format!("Restored {} transactions", count),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to restore backup: {}", e);
+            let status = match e {
+                // Unknown backup name or unsupported snapshot version
+                StoreError::Config(_) => StatusCode::BAD_REQUEST,
+                StoreError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            AdminResponse::error(status, format!("Failed to restore backup: {}", e))
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetModeRequest {
+    mode: Mode,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ModeResponse {
+    success: bool,
+    /// Mode now in effect
+    mode: Mode,
+    /// Mode before the switch (`POST` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<Mode>,
+    message: String,
+}
+
+/// Current retrieval mode
+#[utoipa::path(
+    get,
+    path = "/admin/mode",
+    responses((status = 200, description = "Current mode", body = ModeResponse))
+)]
+pub async fn get_mode(State(state): State<Arc<AppState>>) -> Json<ModeResponse> {
+    let mode = state.mode();
+    Json(ModeResponse {
+        success: true,
+        mode,
+        previous: None,
+        message: format!("Mode is {}", mode.as_str()),
+    })
+}
+
+/// Switch between pass-through and reserialize retrieval; stored transactions are kept
+#[utoipa::path(
+    post,
+    path = "/admin/mode",
+    request_body = SetModeRequest,
+    responses((status = 200, description = "Mode switched", body = ModeResponse))
+)]
+pub async fn set_mode(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SetModeRequest>,
+) -> Json<ModeResponse> {
+    let previous = state.set_mode(request.mode);
+    info!(
+        "Switched mode from {} to {}",
+        previous.as_str(),
+        request.mode.as_str()
+    );
+    Json(ModeResponse {
+        success: true,
+        mode: request.mode,
+        previous: Some(previous),
+        message: format!("Mode is now {}", request.mode.as_str()),
+    })
+}
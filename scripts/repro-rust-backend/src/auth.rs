@@ -0,0 +1,430 @@
+//! Optional request authentication.
+//!
+//! Two schemes are supported and can be enabled together:
+//!
+//! - `API_KEYS` (a comma-separated list): a request carrying one of the keys
+//!   in an `x-api-key` header may call anything.
+//! - `JWT_SECRET`: a request may instead carry an HS256 bearer token whose
+//!   space-separated `scope` claim must include the scope the route needs
+//!   (`tx:write`, `sig:write`, `tx:read` or `admin`). This lets the secondary
+//!   signer's wallet service be handed a token that can only attach signatures.
+//!   `JWT_ISSUER` and `JWT_AUDIENCE` additionally pin the `iss` and `aud`
+//!   claims.
+//!
+//! With neither set, every request is allowed. The health probes are never
+//! checked so load balancers and orchestrators don't need credentials.
+
+use crate::config::SecurityArgs;
+use crate::{AppState, RequestGuards};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Header clients put their key in
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Permission a route requires from a bearer token
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Store, rebuild or delete transactions
+    TxWrite,
+    /// Attach secondary and fee payer signatures, and assemble and submit
+    /// signed transactions
+    SigWrite,
+    /// Read transactions, history and server info
+    TxRead,
+    /// Export, import, backup and restore
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::TxWrite => "tx:write",
+            Scope::SigWrite => "sig:write",
+            Scope::TxRead => "tx:read",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Keys accepted in the `x-api-key` header
+pub struct ApiKeys {
+    keys: HashSet<String>,
+}
+
+impl ApiKeys {
+    /// Returns `None` if no keys are configured
+    pub fn from_config(config: &SecurityArgs) -> Option<Self> {
+        Self::new(&config.api_keys)
+    }
+
+    /// Accept `keys`, ignoring surrounding whitespace and empty ones;
+    /// `None` if that leaves none
+    pub fn new(keys: &[String]) -> Option<Self> {
+        let keys: HashSet<String> = keys
+            .iter()
+            .map(|key| key.trim())
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect();
+        if keys.is_empty() {
+            return None;
+        }
+        Some(Self { keys })
+    }
+
+    pub fn count(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+}
+
+/// Validates HS256 bearer tokens
+pub struct JwtAuth {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    /// Space-separated, as in OAuth 2.0
+    #[serde(default)]
+    scope: String,
+}
+
+impl JwtAuth {
+    /// Returns `None` if no secret is configured
+    pub fn from_config(config: &SecurityArgs) -> Option<Self> {
+        let secret = config.jwt_secret.as_deref().filter(|s| !s.is_empty())?;
+        let mut validation = Validation::new(Algorithm::HS256);
+        // Pinned claims must also be present: jsonwebtoken only compares
+        // them when a token carries them
+        if let Some(issuer) = &config.jwt_issuer {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+        match &config.jwt_audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                validation.required_spec_claims.insert("aud".to_string());
+            }
+            None => validation.validate_aud = false,
+        }
+        Some(Self {
+            key: DecodingKey::from_secret(secret.as_bytes()),
+            validation,
+        })
+    }
+
+    fn decode(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        jsonwebtoken::decode::<Claims>(token, &self.key, &self.validation).map(|data| data.claims)
+    }
+}
+
+#[derive(Serialize)]
+struct AuthErrorResponse {
+    success: bool,
+    message: String,
+}
+
+/// Checks the request's credentials against `scope` (see the module docs)
+pub async fn authorize(
+    State((state, scope)): State<(Arc<AppState>, Scope)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match check(&state.guards, request.headers(), scope) {
+        Ok(()) => next.run(request).await,
+        Err((status, message)) => (
+            status,
+            Json(AuthErrorResponse {
+                success: false,
+                message,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Checks `headers` against `scope` with the configured `guards`, returning
+/// the status and message to reject with. Shared by the HTTP middleware and
+/// the gRPC service.
+pub fn check(
+    guards: &RequestGuards,
+    headers: &HeaderMap,
+    scope: Scope,
+) -> Result<(), (StatusCode, String)> {
+    if guards.api_keys.is_none() && guards.jwt.is_none() {
+        return Ok(());
+    }
+
+    if let (Some(api_keys), Some(value)) = (&guards.api_keys, headers.get(API_KEY_HEADER)) {
+        if value.to_str().is_ok_and(|key| api_keys.contains(key)) {
+            return Ok(());
+        }
+        warn!("Rejected request: invalid API key");
+        return Err((StatusCode::UNAUTHORIZED, "Invalid API key".to_string()));
+    }
+
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let (Some(jwt), Some(token)) = (&guards.jwt, bearer) else {
+        let expected = match (&guards.api_keys, &guards.jwt) {
+            (Some(_), Some(_)) => "Missing x-api-key header or bearer token",
+            (Some(_), None) => "Missing x-api-key header",
+            _ => "Missing bearer token",
+        };
+        warn!("Rejected request: {}", expected);
+        return Err((StatusCode::UNAUTHORIZED, expected.to_string()));
+    };
+
+    let claims = match jwt.decode(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            warn!("Rejected request: invalid bearer token: {}", e);
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                format!("Invalid bearer token: {}", e),
+            ));
+        }
+    };
+
+    let subject = claims.sub.as_deref().unwrap_or("-");
+    if !claims.scope.split_whitespace().any(|s| s == scope.as_str()) {
+        warn!(subject, "Rejected request: token lacks scope {}", scope);
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Token lacks required scope `{}`", scope),
+        ));
+    }
+
+    debug!(subject, "Authorized for {}", scope);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::{json, Value};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const SECRET: &str = "test-secret";
+
+    fn api_keys(keys: &[&str]) -> RequestGuards {
+        RequestGuards {
+            api_keys: ApiKeys::new(&keys.iter().map(|key| key.to_string()).collect::<Vec<_>>()),
+            ..RequestGuards::default()
+        }
+    }
+
+    fn jwt(issuer: Option<&str>, audience: Option<&str>) -> RequestGuards {
+        let config = SecurityArgs {
+            api_keys: Vec::new(),
+            jwt_secret: Some(SECRET.to_string()),
+            jwt_issuer: issuer.map(String::from),
+            jwt_audience: audience.map(String::from),
+            hmac_secret: None,
+            hmac_tolerance_secs: 300,
+            rate_limit_per_sec: None,
+            rate_limit_burst: None,
+        };
+        RequestGuards {
+            jwt: JwtAuth::from_config(&config),
+            ..RequestGuards::default()
+        }
+    }
+
+    /// Claims expiring in an hour, with `extra` merged in
+    fn claims(scope: &str, extra: Value) -> Value {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut claims = json!({ "sub": "wallet", "scope": scope, "exp": now + 3600 });
+        claims
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        claims
+    }
+
+    fn with_bearer(claims: &Value, secret: &str) -> HeaderMap {
+        let token = jsonwebtoken::encode(
+            &Header::default(),
+            claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        headers
+    }
+
+    fn with_api_key(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        headers
+    }
+
+    fn status(result: Result<(), (StatusCode, String)>) -> Option<StatusCode> {
+        result.err().map(|(status, _)| status)
+    }
+
+    #[test]
+    fn everything_is_allowed_without_credentials_configured() {
+        let guards = RequestGuards::default();
+        assert!(check(&guards, &HeaderMap::new(), Scope::Admin).is_ok());
+        assert!(check(&guards, &with_api_key("anything"), Scope::Admin).is_ok());
+    }
+
+    #[test]
+    fn configured_api_key_is_accepted_for_every_scope() {
+        let guards = api_keys(&["alpha", " beta "]);
+        for scope in [Scope::TxWrite, Scope::SigWrite, Scope::TxRead, Scope::Admin] {
+            assert!(check(&guards, &with_api_key("alpha"), scope).is_ok());
+            assert!(check(&guards, &with_api_key("beta"), scope).is_ok());
+        }
+    }
+
+    #[test]
+    fn unknown_api_key_is_rejected() {
+        let guards = api_keys(&["alpha"]);
+        let result = check(&guards, &with_api_key("gamma"), Scope::TxRead);
+        assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn missing_api_key_is_rejected() {
+        let guards = api_keys(&["alpha"]);
+        let (status, message) = check(&guards, &HeaderMap::new(), Scope::TxRead).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(message, "Missing x-api-key header");
+    }
+
+    #[test]
+    fn blank_api_keys_disable_the_check() {
+        assert!(ApiKeys::new(&[" ".to_string(), String::new()]).is_none());
+    }
+
+    #[test]
+    fn token_with_the_scope_is_accepted() {
+        let guards = jwt(None, None);
+        let headers = with_bearer(&claims("tx:read sig:write", json!({})), SECRET);
+        assert!(check(&guards, &headers, Scope::SigWrite).is_ok());
+        assert!(check(&guards, &headers, Scope::TxRead).is_ok());
+    }
+
+    #[test]
+    fn token_without_the_scope_is_forbidden() {
+        let guards = jwt(None, None);
+        let headers = with_bearer(&claims("tx:read sig:write", json!({})), SECRET);
+        let result = check(&guards, &headers, Scope::Admin);
+        assert_eq!(status(result), Some(StatusCode::FORBIDDEN));
+        // Scopes are matched whole, not by prefix
+        let headers = with_bearer(&claims("tx:writer", json!({})), SECRET);
+        let result = check(&guards, &headers, Scope::TxWrite);
+        assert_eq!(status(result), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn token_signed_with_another_secret_is_rejected() {
+        let guards = jwt(None, None);
+        let headers = with_bearer(&claims("admin", json!({})), "other-secret");
+        let result = check(&guards, &headers, Scope::Admin);
+        assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let guards = jwt(None, None);
+        let headers = with_bearer(&claims("admin", json!({ "exp": 1 })), SECRET);
+        let result = check(&guards, &headers, Scope::Admin);
+        assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn issuer_is_pinned_when_configured() {
+        let guards = jwt(Some("issuer"), None);
+        let right = with_bearer(&claims("admin", json!({ "iss": "issuer" })), SECRET);
+        assert!(check(&guards, &right, Scope::Admin).is_ok());
+        for claims in [
+            claims("admin", json!({ "iss": "someone-else" })),
+            claims("admin", json!({})),
+        ] {
+            let result = check(&guards, &with_bearer(&claims, SECRET), Scope::Admin);
+            assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+        }
+    }
+
+    #[test]
+    fn audience_is_pinned_when_configured() {
+        let guards = jwt(None, Some("repro"));
+        let right = with_bearer(&claims("admin", json!({ "aud": "repro" })), SECRET);
+        assert!(check(&guards, &right, Scope::Admin).is_ok());
+        for claims in [
+            claims("admin", json!({ "aud": "another-service" })),
+            claims("admin", json!({})),
+        ] {
+            let result = check(&guards, &with_bearer(&claims, SECRET), Scope::Admin);
+            assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+        }
+        // Unpinned, any audience is accepted
+        let headers = with_bearer(
+            &claims("admin", json!({ "aud": "another-service" })),
+            SECRET,
+        );
+        assert!(check(&jwt(None, None), &headers, Scope::Admin).is_ok());
+    }
+
+    #[test]
+    fn missing_bearer_token_is_rejected() {
+        let guards = jwt(None, None);
+        let (status, message) = check(&guards, &HeaderMap::new(), Scope::TxRead).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(message, "Missing bearer token");
+    }
+
+    #[test]
+    fn api_key_takes_precedence_over_a_token() {
+        let guards = RequestGuards {
+            api_keys: ApiKeys::new(&["alpha".to_string()]),
+            ..jwt(None, None)
+        };
+        let token = with_bearer(&claims("tx:read", json!({})), SECRET);
+        assert!(check(&guards, &token, Scope::TxRead).is_ok());
+        assert!(check(&guards, &with_api_key("alpha"), Scope::Admin).is_ok());
+
+        let mut both = with_api_key("gamma");
+        both.extend(token);
+        let result = check(&guards, &both, Scope::TxRead);
+        assert_eq!(status(result), Some(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,131 @@
+//! Timestamped backups of the whole store.
+//!
+//! Backups use the snapshot format and are written to `BACKUP_DIR` (default
+//! `backups`) as `backup-<UTC timestamp>.json`, either on demand through
+//! `POST /admin/backup` or on the cron schedule in `BACKUP_SCHEDULE`
+//! (e.g. `0 * * * *` for hourly; a leading seconds field is also accepted).
+
+use crate::config::PersistenceArgs;
+use crate::snapshot;
+use crate::storage::{StoreError, StoreResult, TransactionStore};
+use cron::Schedule;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Backup settings
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// Scheduled backups (None = on demand only)
+    pub schedule: Option<Schedule>,
+}
+
+impl BackupConfig {
+    pub fn from_config(config: &PersistenceArgs) -> StoreResult<Self> {
+        let schedule = config
+            .backup_schedule
+            .as_deref()
+            .map(parse_schedule)
+            .transpose()?;
+        Ok(Self {
+            dir: config.backup_dir.clone(),
+            schedule,
+        })
+    }
+}
+
+/// Parse a cron expression, accepting the classic 5-field form as well as the
+/// `cron` crate's form with a leading seconds field
+fn parse_schedule(expr: &str) -> StoreResult<Schedule> {
+    let expr = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&expr)
+        .map_err(|e| StoreError::Config(format!("invalid BACKUP_SCHEDULE `{}`: {}", expr, e)))
+}
+
+/// Write a new timestamped backup of `store` into `dir`.
+///
+/// Returns the backup's file name and the number of transactions in it.
+pub async fn write(store: &dyn TransactionStore, dir: &Path) -> StoreResult<(String, usize)> {
+    tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+    let name = format!(
+        "backup-{}.json",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    let count = snapshot::write(store, &dir.join(&name)).await?;
+    Ok((name, count))
+}
+
+/// Load the backup `name` from `dir` into `store` (the newest one if `None`).
+///
+/// Returns the backup's file name and the number of transactions restored.
+pub async fn restore(
+    store: &dyn TransactionStore,
+    dir: &Path,
+    name: Option<&str>,
+) -> StoreResult<(String, usize)> {
+    let name = match name {
+        // Only plain file names, so a request can't read outside the backup directory
+        Some(name) if name.contains(['/', '\\']) || name.starts_with('.') => {
+            return Err(StoreError::Config(format!("invalid backup name `{}`", name)));
+        }
+        Some(name) => name.to_string(),
+        None => latest(dir)
+            .await?
+            .ok_or_else(|| StoreError::Config(format!("no backups in {}", dir.display())))?,
+    };
+
+    let path = dir.join(&name);
+    if !tokio::fs::try_exists(&path).await.map_err(io_error)? {
+        return Err(StoreError::Config(format!("backup `{}` not found", name)));
+    }
+    let count = snapshot::restore(store, &path).await?;
+    Ok((name, count))
+}
+
+/// File name of the newest backup in `dir`, if any
+async fn latest(dir: &Path) -> StoreResult<Option<String>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error(e)),
+    };
+    let mut latest: Option<String> = None;
+    while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Timestamps are zero-padded, so the newest backup sorts last
+        if name.starts_with("backup-")
+            && name.ends_with(".json")
+            && latest.as_ref().is_none_or(|latest| name > *latest)
+        {
+            latest = Some(name);
+        }
+    }
+    Ok(latest)
+}
+
+/// Spawn a background task that writes a backup at every time in `schedule`
+pub fn spawn(store: Arc<dyn TransactionStore>, dir: PathBuf, schedule: Schedule) {
+    tokio::spawn(async move {
+        for next in schedule.upcoming(chrono::Utc) {
+            let delay = (next - chrono::Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(delay).await;
+            match write(store.as_ref(), &dir).await {
+                Ok((name, count)) => info!(
+                    "Wrote {} transactions to {}",
+                    count,
+                    dir.join(name).display()
+                ),
+                Err(e) => error!("Failed to write backup: {}", e),
+            }
+        }
+    });
+}
+
+fn io_error(e: impl std::fmt::Display) -> StoreError {
+    StoreError::Backend(format!("backup: {}", e))
+}
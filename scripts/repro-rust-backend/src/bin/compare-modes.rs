@@ -0,0 +1,8 @@
+//! Retrieves every transaction a running backend stores in both pass-through
+//! and reserialize mode and reports the differences. See
+//! `repro_core::compare`.
+
+#[tokio::main]
+async fn main() {
+    repro_core::compare::run().await;
+}
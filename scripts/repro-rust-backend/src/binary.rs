@@ -0,0 +1,218 @@
+//! Raw BCS request and response bodies.
+//!
+//! `POST /transaction` also accepts the transaction's BCS bytes as the body
+//! under `Content-Type: application/octet-stream`, with the id in
+//! `x-transaction-id` and the optional kind, callback URL and quorum in
+//! `x-transaction-kind`, `x-callback-url` and `x-quorum`.
+//! `GET /transaction/:id` with `Accept: application/octet-stream` returns the
+//! bytes the JSON response would carry as `bcs_hex`. Either way the payload
+//! is half the size and skips a hex encode and decode; signatures and
+//! metadata stay JSON-only. Both kinds of retrieval can be conditional (see
+//! [`crate::etag`]).
+
+use crate::transaction::TransactionKind;
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+pub const OCTET_STREAM: &str = "application/octet-stream";
+/// Id a binary store is made under
+pub const TRANSACTION_ID_HEADER: &str = "x-transaction-id";
+/// Kind the bytes of a binary store must decode as, like the JSON `kind`
+pub const TRANSACTION_KIND_HEADER: &str = "x-transaction-kind";
+/// Webhook for a binary store, like the JSON `callback_url`
+pub const CALLBACK_URL_HEADER: &str = "x-callback-url";
+/// Quorum of a binary store, like the JSON `quorum`
+pub const QUORUM_HEADER: &str = "x-quorum";
+
+/// A JSON request body, or raw BCS under `application/octet-stream`
+pub enum StoreBody<T> {
+    Json(T),
+    Binary(BinaryTransaction),
+}
+
+/// A transaction sent as raw BCS
+pub struct BinaryTransaction {
+    pub transaction_id: String,
+    pub kind: Option<TransactionKind>,
+    pub callback_url: Option<String>,
+    pub quorum: Option<u32>,
+    pub bcs: Bytes,
+}
+
+impl<S, T> FromRequest<S> for StoreBody<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_octet_stream(request.headers().get(header::CONTENT_TYPE)) {
+            let Json(body) = Json::<T>::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(StoreBody::Json(body));
+        }
+
+        let headers = request.headers();
+        let transaction_id = match headers.get(TRANSACTION_ID_HEADER).map(HeaderValue::to_str) {
+            Some(Ok(id)) if !id.is_empty() => id.to_string(),
+            _ => {
+                return Err(bad_request(format!(
+                    "{} is required with {}",
+                    TRANSACTION_ID_HEADER, OCTET_STREAM
+                )))
+            }
+        };
+        let kind = match headers.get(TRANSACTION_KIND_HEADER) {
+            None => None,
+            Some(value) => match value.to_str().ok().and_then(TransactionKind::parse) {
+                Some(kind) => Some(kind),
+                None => {
+                    return Err(bad_request(format!(
+                        "{} must be multi_agent, fee_payer, raw_transaction or signed_transaction",
+                        TRANSACTION_KIND_HEADER
+                    )))
+                }
+            },
+        };
+        let callback_url = match headers.get(CALLBACK_URL_HEADER).map(HeaderValue::to_str) {
+            None => None,
+            Some(Ok(url)) => Some(url.to_string()),
+            Some(Err(_)) => {
+                return Err(bad_request(format!(
+                    "{} must be a URL",
+                    CALLBACK_URL_HEADER
+                )))
+            }
+        };
+        let quorum = match headers.get(QUORUM_HEADER) {
+            None => None,
+            Some(value) => match value.to_str().ok().and_then(|value| value.parse().ok()) {
+                Some(quorum) => Some(quorum),
+                None => {
+                    return Err(bad_request(format!(
+                        "{} must be a number of signers",
+                        QUORUM_HEADER
+                    )))
+                }
+            },
+        };
+        let bcs = Bytes::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(StoreBody::Binary(BinaryTransaction {
+            transaction_id,
+            kind,
+            callback_url,
+            quorum,
+            bcs,
+        }))
+    }
+}
+
+/// Whether `Accept` asks for raw bytes rather than JSON
+pub fn wants_binary(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| is_media_type(value, OCTET_STREAM))
+}
+
+fn is_octet_stream(content_type: Option<&HeaderValue>) -> bool {
+    content_type
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| is_media_type(value, OCTET_STREAM))
+}
+
+/// Whether `value` names `media_type`, ignoring parameters such as `q=`
+fn is_media_type(value: &str, media_type: &str) -> bool {
+    value
+        .split(';')
+        .next()
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(media_type))
+}
+
+fn bad_request(message: String) -> Response {
+    #[derive(Serialize)]
+    struct ErrorResponse {
+        success: bool,
+        message: String,
+    }
+    warn!("Rejected binary store: {}", message);
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            success: false,
+            message,
+        }),
+    )
+        .into_response()
+}
+
+/// A JSON response, or raw BCS when the client sent `Accept:
+/// application/octet-stream`
+pub enum Negotiated<T> {
+    Json(StatusCode, Json<T>),
+    Binary(Vec<u8>),
+    /// Either of the above with its `ETag` (see [`crate::etag`])
+    Tagged(HeaderValue, Box<Negotiated<T>>),
+    /// `304 Not Modified`: `If-None-Match` matched this `ETag`
+    NotModified(HeaderValue),
+}
+
+impl<T> Negotiated<T> {
+    /// This response with `etag`, if there is one
+    pub fn tagged(self, etag: Option<HeaderValue>) -> Self {
+        match etag {
+            Some(etag) => Negotiated::Tagged(etag, Box::new(self)),
+            None => self,
+        }
+    }
+
+    /// This response without its `ETag`
+    pub fn untagged(self) -> Self {
+        match self {
+            Negotiated::Tagged(_, response) => *response,
+            response => response,
+        }
+    }
+}
+
+impl<T> From<(StatusCode, Json<T>)> for Negotiated<T> {
+    fn from((status, json): (StatusCode, Json<T>)) -> Self {
+        Negotiated::Json(status, json)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self {
+            Negotiated::Json(status, json) => (status, json).into_response(),
+            Negotiated::Binary(bytes) => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, HeaderValue::from_static(OCTET_STREAM))],
+                bytes,
+            )
+                .into_response(),
+            Negotiated::Tagged(etag, response) => {
+                let mut response = response.into_response();
+                response.headers_mut().insert(header::ETAG, etag);
+                response
+            }
+            Negotiated::NotModified(etag) => {
+                (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response()
+            }
+        }
+    }
+}
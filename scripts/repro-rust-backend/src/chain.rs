@@ -0,0 +1,54 @@
+//! Chain ID check on store.
+//!
+//! A transaction built for one network fails with `BAD_CHAIN_ID` on another,
+//! which in a repro run looks just like a serialization bug. When
+//! `FULLNODE_URL` is set, `POST /transaction` compares the transaction's
+//! `chain_id` with the fullnode's and, per `--chain-id-check`, rejects or only
+//! flags a mismatch.
+
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::AptosResult;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+
+/// What to do when a stored transaction's `chain_id` differs from the
+/// fullnode's (`--chain-id-check` / `CHAIN_ID_CHECK`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChainIdCheck {
+    /// Don't compare
+    Off,
+    /// Store the transaction but report the mismatch
+    Warn,
+    /// Refuse to store the transaction
+    Reject,
+}
+
+/// A transaction's `chain_id` next to the configured fullnode's
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+pub struct ChainIdMismatch {
+    pub transaction_chain_id: u8,
+    pub fullnode_chain_id: u8,
+}
+
+/// The fullnode's chain id, fetched on first use; a node never changes
+/// networks, so one successful lookup serves every later store
+#[derive(Default)]
+pub struct FullnodeChainId {
+    cached: OnceCell<u8>,
+}
+
+impl FullnodeChainId {
+    /// The chain id `fullnode` reports in its ledger info
+    pub async fn get(&self, fullnode: &FullnodeClient) -> AptosResult<u8> {
+        self.cached
+            .get_or_try_init(|| async {
+                fullnode
+                    .get_ledger_info()
+                    .await
+                    .map(|response| response.data.chain_id)
+            })
+            .await
+            .copied()
+    }
+}
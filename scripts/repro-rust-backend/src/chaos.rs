@@ -0,0 +1,323 @@
+//! Fault injection: corrupted retrievals, added latency and failed requests.
+//!
+//! With `CHAOS_PROBABILITY` set, that fraction of retrievals return corrupted
+//! BCS, so downstream clients' handling of bad bytes can be exercised. Each
+//! corrupted retrieval gets one fault, drawn from `CHAOS_FAULTS`:
+//!
+//! - `flip-bytes`: one to four bytes XORed with random non-zero values
+//! - `truncate`: the payload cut short at a random length
+//! - `bump-sequence`: the sequence number incremented, so the bytes still
+//!   decode but no longer match what the signers signed
+//!
+//! Everything built on `GET /transaction/:id` is affected: batch retrieval,
+//! `/wait` and gRPC, in either mode and encoding. The stored bytes are never
+//! touched; each injection is logged, counted in `/stats` and recorded in the
+//! transaction's history as `corrupted`.
+//!
+//! To test the frontend's retries and timeouts against a misbehaving backend,
+//! [`disrupt`] also holds HTTP requests back by `CHAOS_LATENCY` rules (e.g.
+//! `/transaction/*=200-800` milliseconds) and answers `CHAOS_ERROR_PROBABILITY`
+//! of them with a random `CHAOS_ERROR_STATUSES` status instead of running the
+//! handler. The probes, `/openapi.json` and `/ws` are left alone.
+
+use crate::config::ChaosArgs;
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// Most bytes a `flip-bytes` fault changes
+const MAX_FLIPPED_BYTES: usize = 4;
+/// Where the sender's sequence number sits in a multi-agent transaction's BCS
+const SEQUENCE_NUMBER_OFFSET: usize = 32;
+
+/// A way of corrupting returned BCS
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Fault {
+    /// XOR a few random bytes
+    FlipBytes,
+    /// Cut the payload short
+    Truncate,
+    /// Increment the sequence number
+    BumpSequence,
+}
+
+impl Fault {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Fault::FlipBytes => "flip-bytes",
+            Fault::Truncate => "truncate",
+            Fault::BumpSequence => "bump-sequence",
+        }
+    }
+
+    /// `bcs` with this fault applied and what was done, or `None` if it is too
+    /// short for the fault
+    fn apply(self, rng: &mut impl Rng, bcs: &[u8]) -> Option<(Vec<u8>, String)> {
+        let mut corrupted = bcs.to_vec();
+        let detail = match self {
+            Fault::FlipBytes => {
+                if bcs.is_empty() {
+                    return None;
+                }
+                let mut offsets: Vec<usize> = (0..rng.gen_range(1..=MAX_FLIPPED_BYTES))
+                    .map(|_| rng.gen_range(0..bcs.len()))
+                    .collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                for &offset in &offsets {
+                    corrupted[offset] ^= rng.gen_range(1..=u8::MAX);
+                }
+                format!("flipped bytes at {:?}", offsets)
+            }
+            Fault::Truncate => {
+                if bcs.is_empty() {
+                    return None;
+                }
+                corrupted.truncate(rng.gen_range(0..bcs.len()));
+                format!("truncated {} -> {} bytes", bcs.len(), corrupted.len())
+            }
+            Fault::BumpSequence => {
+                let range = SEQUENCE_NUMBER_OFFSET..SEQUENCE_NUMBER_OFFSET + 8;
+                let sequence_number = u64::from_le_bytes(bcs.get(range.clone())?.try_into().ok()?);
+                let bumped = sequence_number.wrapping_add(1);
+                corrupted[range].copy_from_slice(&bumped.to_le_bytes());
+                format!("sequence number {} -> {}", sequence_number, bumped)
+            }
+        };
+        Some((corrupted, detail))
+    }
+}
+
+/// BCS a retrieval returns instead of the real bytes
+pub struct Corruption {
+    pub fault: Fault,
+    /// What was changed, e.g. `truncated 156 -> 40 bytes`
+    pub detail: String,
+    pub bcs: Vec<u8>,
+}
+
+/// Delay for requests to matching paths, e.g. `/transaction/*=200-800`
+#[derive(Clone, Debug)]
+pub struct LatencyRule {
+    /// Path without the `/v1` prefix; `*` matches one segment, and a lone
+    /// `*` every path
+    pattern: String,
+    min_ms: u64,
+    max_ms: u64,
+}
+
+impl LatencyRule {
+    fn matches(&self, path: &str) -> bool {
+        if self.pattern == "*" {
+            return true;
+        }
+        let pattern = self.pattern.trim_end_matches('/').split('/');
+        let path = path.trim_end_matches('/').split('/');
+        pattern.clone().count() == path.clone().count()
+            && pattern
+                .zip(path)
+                .all(|(expected, actual)| expected == "*" || expected == actual)
+    }
+}
+
+/// Configured fault injection
+pub struct Chaos {
+    /// Chance of corrupting a retrieval, from 0 to 1
+    probability: f64,
+    faults: Vec<Fault>,
+    /// First matching rule wins
+    latency: Vec<LatencyRule>,
+    /// Chance of failing a request, from 0 to 1
+    error_probability: f64,
+    error_statuses: Vec<StatusCode>,
+    /// Retrievals corrupted so far
+    injected: AtomicU64,
+    /// Requests failed on purpose so far
+    errors: AtomicU64,
+}
+
+impl Chaos {
+    /// `None` unless some fault is configured
+    pub fn from_config(config: &ChaosArgs) -> Option<Self> {
+        let chaos = Self {
+            probability: config.chaos_probability.unwrap_or(0.0),
+            faults: config.chaos_faults.clone(),
+            latency: config.chaos_latency.clone(),
+            error_probability: config.chaos_error_probability.unwrap_or(0.0),
+            error_statuses: config
+                .chaos_error_statuses
+                .iter()
+                .filter_map(|status| StatusCode::from_u16(*status).ok())
+                .collect(),
+            injected: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        };
+        (chaos.probability > 0.0 || chaos.disrupts()).then_some(chaos)
+    }
+
+    /// Whether requests are delayed or failed, needing the [`disrupt`] layer
+    pub fn disrupts(&self) -> bool {
+        !self.latency.is_empty() || self.error_probability > 0.0
+    }
+
+    /// Corrupt `bcs` with the configured probability
+    pub fn corrupt(&self, bcs: &[u8]) -> Option<Corruption> {
+        let mut rng = rand::thread_rng();
+        if !rng.gen_bool(self.probability) {
+            return None;
+        }
+        let fault = *self.faults.choose(&mut rng)?;
+        let (bcs, detail) = fault.apply(&mut rng, bcs)?;
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Some(Corruption { fault, detail, bcs })
+    }
+
+    /// Retrievals corrupted so far
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    /// Requests failed on purpose so far
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// For the startup banner, e.g. `corrupting 5% of retrievals (truncate),
+    /// failing 1% of requests`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.probability > 0.0 {
+            let faults: Vec<&str> = self.faults.iter().map(|fault| fault.as_str()).collect();
+            parts.push(format!(
+                "corrupting {}% of retrievals ({})",
+                self.probability * 100.0,
+                faults.join(", ")
+            ));
+        }
+        if !self.latency.is_empty() {
+            parts.push(format!("delaying {} path patterns", self.latency.len()));
+        }
+        if self.error_probability > 0.0 {
+            parts.push(format!(
+                "failing {}% of requests",
+                self.error_probability * 100.0
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Whether requests to `path` are delayed or failed
+fn disrupted(path: &str) -> bool {
+    !matches!(
+        path,
+        "/ws" | "/health" | "/healthz" | "/readyz" | "/openapi.json"
+    )
+}
+
+/// Delays requests by the first matching latency rule, then fails some of
+/// them before they reach a handler
+pub async fn disrupt(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let Some(chaos) = &state.chaos else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    let path = path.strip_prefix("/v1").unwrap_or(path).to_string();
+    if !disrupted(&path) {
+        return next.run(request).await;
+    }
+
+    // `ThreadRng` can't be held across an await
+    let (delay, status) = {
+        let mut rng = rand::thread_rng();
+        let delay = chaos
+            .latency
+            .iter()
+            .find(|rule| rule.matches(&path))
+            .map(|rule| Duration::from_millis(rng.gen_range(rule.min_ms..=rule.max_ms)));
+        let status = if rng.gen_bool(chaos.error_probability) {
+            chaos.error_statuses.choose(&mut rng).copied()
+        } else {
+            None
+        };
+        (delay, status)
+    };
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    let Some(status) = status else {
+        return next.run(request).await;
+    };
+
+    chaos.errors.fetch_add(1, Ordering::Relaxed);
+    warn!(
+        method = %request.method(),
+        path = %request.uri().path(),
+        status = status.as_u16(),
+        "Chaos mode failed the request"
+    );
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": "Injected failure (chaos mode)",
+        })),
+    )
+        .into_response()
+}
+
+/// Parse a probability from 0 to 1
+pub fn probability(value: &str) -> Result<f64, String> {
+    let probability: f64 = value
+        .parse()
+        .map_err(|e: std::num::ParseFloatError| e.to_string())?;
+    if (0.0..=1.0).contains(&probability) {
+        Ok(probability)
+    } else {
+        Err("must be between 0 and 1".to_string())
+    }
+}
+
+/// Parse a latency rule: `PATH=MS` or `PATH=MIN-MAX`, in milliseconds
+pub fn latency_rule(value: &str) -> Result<LatencyRule, String> {
+    let (pattern, delay) = value
+        .rsplit_once('=')
+        .ok_or("expected PATH=MS or PATH=MIN-MAX")?;
+    let millis = |ms: &str| {
+        ms.trim()
+            .parse::<u64>()
+            .map_err(|e| format!("invalid delay `{}`: {}", ms, e))
+    };
+    let (min_ms, max_ms) = match delay.split_once('-') {
+        Some((min, max)) => (millis(min)?, millis(max)?),
+        None => (millis(delay)?, millis(delay)?),
+    };
+    if min_ms > max_ms {
+        return Err(format!("{} ms is more than {} ms", min_ms, max_ms));
+    }
+    let pattern = pattern.trim();
+    let pattern = pattern.strip_prefix("/v1").unwrap_or(pattern).to_string();
+    if pattern != "*" && !pattern.starts_with('/') {
+        return Err(format!(
+            "path `{}` must start with `/` (or be `*`)",
+            pattern
+        ));
+    }
+    Ok(LatencyRule {
+        pattern,
+        min_ms,
+        max_ms,
+    })
+}
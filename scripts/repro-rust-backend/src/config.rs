@@ -0,0 +1,497 @@
+//! Command-line configuration.
+//!
+//! Every setting is a flag with an environment variable fallback (`--port` or
+//! `PORT`, `--storage` or `STORAGE`, ...), so existing env-based invocations
+//! keep working while `--help` documents everything a repro run depends on.
+//!
+//! `--config repro.toml` supplies defaults for any of them, grouped into the
+//! `--help` sections (`[server]`, `[storage]`, `[persistence]`, `[security]`,
+//! `[limits]`, `[cors]`, `[chaos]`) with keys named after the flags (`fullnode_url`,
+//! `api_keys`, ...). Env vars override the file and flags override both.
+//!
+//! A subcommand (`load-test`) runs a tool instead of the server; the server
+//! options are then ignored.
+
+use crate::chain::ChainIdCheck;
+use crate::chaos::{self, Fault, LatencyRule};
+use crate::limits;
+use crate::logging::LogFormat;
+use crate::reserialize::Strategy;
+use crate::storage::StorageArgs;
+use clap::error::ErrorKind;
+use clap::{Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Stores and retrieves serialized multi-agent transactions to reproduce SDK serialization issues"
+)]
+pub struct Config {
+    /// TOML file with defaults for any of the options below
+    #[arg(long, env = "REPRO_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub server: ServerArgs,
+    #[command(flatten)]
+    pub storage: StorageArgs,
+    #[command(flatten)]
+    pub persistence: PersistenceArgs,
+    #[command(flatten)]
+    pub security: SecurityArgs,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+    #[command(flatten)]
+    pub cors: CorsArgs,
+    #[command(flatten)]
+    pub chaos: ChaosArgs,
+    #[command(subcommand)]
+    pub tool: Option<Tool>,
+}
+
+/// Tools run instead of the server
+#[derive(Subcommand, Debug)]
+pub enum Tool {
+    /// Hammer a running backend with synthetic multi-agent transactions and
+    /// report latency percentiles and error rates
+    LoadTest(LoadTestArgs),
+}
+
+/// Config file sections and the argument groups clap derives for them
+const SECTIONS: &[(&str, &str)] = &[
+    ("server", "ServerArgs"),
+    ("storage", "StorageArgs"),
+    ("persistence", "PersistenceArgs"),
+    ("security", "SecurityArgs"),
+    ("limits", "LimitArgs"),
+    ("cors", "CorsArgs"),
+    ("chaos", "ChaosArgs"),
+];
+
+impl Config {
+    /// Parse the command line, taking defaults from the `--config` file if given.
+    ///
+    /// Exits with a usage error if the file can't be read or names an unknown
+    /// option; values are checked exactly like their flags.
+    pub fn load() -> Self {
+        let mut command = Self::command();
+        let path = command
+            .clone()
+            .ignore_errors(true)
+            .get_matches()
+            .get_one::<PathBuf>("config")
+            .cloned();
+        if let Some(path) = path {
+            command = match apply_file(command.clone(), &path) {
+                Ok(command) => command,
+                Err(e) => command
+                    .error(ErrorKind::InvalidValue, format!("{}: {}", path.display(), e))
+                    .exit(),
+            };
+        }
+        Self::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
+    }
+}
+
+/// Set the defaults of `command`'s arguments from the TOML file at `path`
+fn apply_file(mut command: Command, path: &Path) -> Result<Command, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    for (section, values) in table {
+        let Some(&(_, group)) = SECTIONS.iter().find(|(name, _)| *name == section) else {
+            return Err(format!("unknown section [{}]", section));
+        };
+        let toml::Value::Table(values) = values else {
+            return Err(format!("`{}` must be a [section]", section));
+        };
+        let known: Vec<String> = command
+            .get_groups()
+            .find(|g| g.get_id() == group)
+            .map(|g| g.get_args().map(|id| id.to_string()).collect())
+            .unwrap_or_default();
+        for (key, value) in values {
+            if !known.contains(&key) {
+                return Err(format!("unknown key `{}` in [{}]", key, section));
+            }
+            let defaults = match value {
+                toml::Value::Array(items) => items.iter().map(scalar).collect(),
+                value => vec![scalar(&value)],
+            }
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("[{}] {}: {}", section, key, e))?;
+            command = command.mut_arg(key, |arg| arg.default_values(defaults));
+        }
+    }
+    Ok(command)
+}
+
+fn scalar(value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(format!("unsupported value `{}`", other)),
+    }
+}
+
+/// How retrieved transactions are produced
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Return the stored bytes unchanged
+    PassThrough,
+    /// Deserialize with the Rust SDK and re-serialize on retrieval
+    Reserialize,
+}
+
+impl Mode {
+    pub fn from_reserialize(reserialize: bool) -> Self {
+        if reserialize {
+            Mode::Reserialize
+        } else {
+            Mode::PassThrough
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::PassThrough => "pass-through",
+            Mode::Reserialize => "reserialize",
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Server")]
+pub struct ServerArgs {
+    /// Port to listen on (all interfaces)
+    #[arg(long, env = "PORT", default_value_t = 3001)]
+    pub port: u16,
+
+    /// Listen here instead of all interfaces on `--port`: `host:port`, or
+    /// `unix:/path/backend.sock` for a Unix domain socket
+    #[arg(long, env = "BIND")]
+    pub bind: Option<String>,
+
+    /// Retrieval mode
+    #[arg(long, env = "MODE", value_enum, default_value_t = Mode::PassThrough)]
+    pub mode: Mode,
+
+    /// Same as `--mode reserialize` (kept for `RESERIALIZE=1` invocations)
+    #[arg(
+        long,
+        env = "RESERIALIZE",
+        hide = true,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub reserialize: bool,
+
+    /// In reserialize mode, answer `500` with a mismatch report when the
+    /// re-serialized bytes differ instead of returning them
+    #[arg(
+        long,
+        env = "RESERIALIZE_STRICT",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub reserialize_strict: bool,
+
+    /// Decoder that reserialize mode goes through, to bisect byte differences
+    #[arg(long, env = "RESERIALIZE_STRATEGY", value_enum, default_value_t = Strategy::Typed)]
+    pub reserialize_strategy: Strategy,
+
+    /// Log output format
+    #[arg(long, env = "LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Fullnode REST URL checked by `/readyz` and submitted to by
+    /// `/transaction/:id/submit`, e.g. http://127.0.0.1:8080/v1
+    #[arg(long, env = "FULLNODE_URL")]
+    pub fullnode_url: Option<String>,
+
+    /// Milliseconds between lookups of a submitted transaction until it is
+    /// committed or expires; 0 leaves it to `/transaction/:id/verify`
+    #[arg(long, env = "SUBMISSION_POLL_INTERVAL_MS", default_value_t = 1000)]
+    pub submission_poll_interval_ms: u64,
+
+    /// Start a fresh local testnet with the `aptos` CLI for as long as the
+    /// server runs, and use it as `--fullnode-url`
+    #[arg(
+        long,
+        env = "WITH_LOCAL_TESTNET",
+        conflicts_with = "fullnode_url",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub with_local_testnet: bool,
+
+    /// `aptos` CLI that `--with-local-testnet` runs
+    #[arg(long, env = "APTOS_CLI", default_value = "aptos")]
+    pub aptos_cli: PathBuf,
+
+    /// Directory for the local testnet's data (wiped on start) and CLI log
+    #[arg(long, env = "LOCAL_TESTNET_DIR", default_value = "local-testnet")]
+    pub local_testnet_dir: PathBuf,
+
+    /// Seconds to wait for the local testnet to come up
+    #[arg(long, env = "LOCAL_TESTNET_TIMEOUT_SECS", default_value_t = 120)]
+    pub local_testnet_timeout_secs: u64,
+
+    /// What to do when a stored transaction's `chain_id` differs from the
+    /// `--fullnode-url` node's
+    #[arg(long, env = "CHAIN_ID_CHECK", value_enum, default_value_t = ChainIdCheck::Reject)]
+    pub chain_id_check: ChainIdCheck,
+
+    /// Refuse to store BCS that is not canonical (padded ULEB128 lengths,
+    /// trailing bytes) instead of storing it with `canonical: false`
+    #[arg(
+        long,
+        env = "REJECT_NON_CANONICAL",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub reject_non_canonical: bool,
+
+    /// URL POSTed to whenever a signature is attached and when a
+    /// transaction's signatures are complete, for every transaction
+    #[arg(long, env = "WEBHOOK_URL")]
+    pub webhook_url: Option<String>,
+
+    /// Seconds a webhook callback may take before it counts as failed
+    #[arg(long, env = "WEBHOOK_TIMEOUT_SECS", default_value_t = 5)]
+    pub webhook_timeout_secs: u64,
+
+    /// Flag retrieved transactions as `nearly_expired` when their
+    /// `expiration_timestamp_secs` is at most this many seconds away
+    #[arg(long, env = "EXPIRATION_WARNING_SECS", default_value_t = 30)]
+    pub expiration_warning_secs: u64,
+
+    /// Also serve the gRPC API on this port (needs the `grpc` feature)
+    #[arg(long, env = "GRPC_PORT")]
+    pub grpc_port: Option<u16>,
+
+    /// PEM certificate to serve HTTPS with (needs the `tls` feature)
+    #[arg(long, env = "TLS_CERT_PATH", requires = "tls_key_path")]
+    pub tls_cert_path: Option<PathBuf>,
+
+    /// PEM private key for `--tls-cert-path`
+    #[arg(long, env = "TLS_KEY_PATH", requires = "tls_cert_path")]
+    pub tls_key_path: Option<PathBuf>,
+
+    /// Seconds to let in-flight requests finish after SIGINT/SIGTERM
+    #[arg(long, env = "SHUTDOWN_DRAIN_SECS", default_value_t = 30)]
+    pub shutdown_drain_secs: u64,
+
+    /// Run the steps of this YAML scenario against the API, check their
+    /// assertions, and exit
+    #[arg(long, env = "SCENARIO", conflicts_with = "replay")]
+    pub scenario: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    /// `--mode`, or `reserialize` if `RESERIALIZE` is set
+    pub fn mode(&self) -> Mode {
+        if self.reserialize {
+            Mode::Reserialize
+        } else {
+            self.mode
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Persistence")]
+pub struct PersistenceArgs {
+    /// JSON file the whole store is periodically written to and restored from
+    #[arg(long, env = "SNAPSHOT_PATH")]
+    pub snapshot_path: Option<PathBuf>,
+
+    /// Seconds between snapshots
+    #[arg(long, env = "SNAPSHOT_INTERVAL_SECS", default_value_t = 30)]
+    pub snapshot_interval_secs: u64,
+
+    /// Write-ahead log of every store and signature, replayed on startup
+    #[arg(long, env = "WAL_PATH")]
+    pub wal_path: Option<PathBuf>,
+
+    /// Append every API request and the status it got to this JSON lines
+    /// journal, for `--replay`
+    #[arg(long, env = "JOURNAL_PATH")]
+    pub journal_path: Option<PathBuf>,
+
+    /// Send the requests in this journal through the API in order, report
+    /// those answered differently than recorded, and exit
+    #[arg(long, env = "REPLAY")]
+    pub replay: Option<PathBuf>,
+
+    /// Directory for `/admin/backup` and `/admin/restore`
+    #[arg(long, env = "BACKUP_DIR", default_value = "backups")]
+    pub backup_dir: PathBuf,
+
+    /// Cron expression for scheduled backups, e.g. "0 * * * *"
+    #[arg(long, env = "BACKUP_SCHEDULE")]
+    pub backup_schedule: Option<String>,
+
+    /// Expire transactions this many seconds after they are stored
+    #[arg(long, env = "TX_TTL_SECS")]
+    pub tx_ttl_secs: Option<u64>,
+
+    /// Expire signing sessions nothing has happened to for this many seconds
+    #[arg(long, env = "SESSION_IDLE_SECS")]
+    pub session_idle_secs: Option<u64>,
+
+    /// Seconds between sweeps for expired transactions
+    #[arg(long, env = "TX_SWEEP_INTERVAL_SECS", default_value_t = 60)]
+    pub tx_sweep_interval_secs: u64,
+
+    /// Re-verify stored signatures against the stored bytes every this many
+    /// seconds, marking those that no longer verify as stale
+    #[arg(
+        long,
+        env = "SIGNATURE_CHECK_INTERVAL_SECS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub signature_check_interval_secs: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Security")]
+pub struct SecurityArgs {
+    /// Comma-separated keys accepted in `x-api-key`
+    #[arg(long, env = "API_KEYS", value_delimiter = ',', hide_env_values = true)]
+    pub api_keys: Vec<String>,
+
+    /// HS256 secret for bearer tokens with scopes
+    #[arg(long, env = "JWT_SECRET", hide_env_values = true)]
+    pub jwt_secret: Option<String>,
+
+    /// Required `iss` claim
+    #[arg(long, env = "JWT_ISSUER")]
+    pub jwt_issuer: Option<String>,
+
+    /// Required `aud` claim
+    #[arg(long, env = "JWT_AUDIENCE")]
+    pub jwt_audience: Option<String>,
+
+    /// Shared secret for `x-signature` HMAC signing of writes
+    #[arg(long, env = "HMAC_SECRET", hide_env_values = true)]
+    pub hmac_secret: Option<String>,
+
+    /// Seconds a signed request's timestamp may be off from server time
+    #[arg(long, env = "HMAC_TOLERANCE_SECS", default_value_t = 300)]
+    pub hmac_tolerance_secs: u64,
+
+    /// Requests per second per API key or client IP
+    #[arg(long, env = "RATE_LIMIT_PER_SEC")]
+    pub rate_limit_per_sec: Option<NonZeroU32>,
+
+    /// Burst size for the rate limit (default: the per-second rate)
+    #[arg(long, env = "RATE_LIMIT_BURST")]
+    pub rate_limit_burst: Option<NonZeroU32>,
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Limits")]
+pub struct LimitArgs {
+    /// Largest body for `POST /transaction` and `POST /signature`, in bytes
+    #[arg(long, env = "MAX_BODY_BYTES", default_value_t = limits::DEFAULT_BODY_BYTES)]
+    pub max_body_bytes: usize,
+
+    /// Longest `bcs_hex`, in characters
+    #[arg(long, env = "MAX_BCS_HEX_LEN", default_value_t = limits::DEFAULT_BCS_HEX_LEN)]
+    pub max_bcs_hex_len: usize,
+
+    /// Longest `signature_hex`, in characters
+    #[arg(
+        long,
+        env = "MAX_SIGNATURE_HEX_LEN",
+        default_value_t = limits::DEFAULT_SIGNATURE_HEX_LEN
+    )]
+    pub max_signature_hex_len: usize,
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "CORS")]
+pub struct CorsArgs {
+    /// Comma-separated origins allowed to call the API (default: any origin)
+    #[arg(long, env = "CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Chaos")]
+pub struct ChaosArgs {
+    /// Chance (0 to 1) that a retrieval returns deliberately corrupted BCS
+    #[arg(long, env = "CHAOS_PROBABILITY", value_parser = chaos::probability)]
+    pub chaos_probability: Option<f64>,
+
+    /// Comma-separated corruptions to pick from
+    #[arg(
+        long,
+        env = "CHAOS_FAULTS",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Fault::FlipBytes, Fault::Truncate, Fault::BumpSequence]
+    )]
+    pub chaos_faults: Vec<Fault>,
+
+    /// Comma-separated `PATH=MS` or `PATH=MIN-MAX` delays for HTTP requests,
+    /// e.g. `/transaction/*=200-800`; `*` matches a path segment, or alone
+    /// every path
+    #[arg(
+        long,
+        env = "CHAOS_LATENCY",
+        value_delimiter = ',',
+        value_parser = chaos::latency_rule
+    )]
+    pub chaos_latency: Vec<LatencyRule>,
+
+    /// Chance (0 to 1) that an HTTP request is answered with an error status
+    /// instead of reaching its handler
+    #[arg(long, env = "CHAOS_ERROR_PROBABILITY", value_parser = chaos::probability)]
+    pub chaos_error_probability: Option<f64>,
+
+    /// Comma-separated statuses injected failures pick from
+    #[arg(
+        long,
+        env = "CHAOS_ERROR_STATUSES",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u16).range(500..600),
+        default_values_t = [500u16, 502, 503]
+    )]
+    pub chaos_error_statuses: Vec<u16>,
+}
+
+#[derive(Args, Debug)]
+pub struct LoadTestArgs {
+    /// Base URL of the backend under test
+    #[arg(long, env = "LOAD_TEST_URL", default_value = "http://localhost:3001")]
+    pub url: String,
+
+    /// Sessions run at once, each storing, signing, retrieving and deleting
+    /// one transaction after another
+    #[arg(long, default_value = "16")]
+    pub concurrency: NonZeroUsize,
+
+    /// How long to run, in seconds
+    #[arg(long, default_value_t = 30)]
+    pub duration_secs: u64,
+
+    /// Size of the `vector<u8>` argument of each transaction's entry function
+    #[arg(long, default_value_t = 256)]
+    pub payload_bytes: usize,
+
+    /// Secondary signers per transaction, each attaching a signature
+    #[arg(long, default_value_t = 1)]
+    pub secondary_signers: usize,
+
+    /// Sent as `x-api-key` when the backend requires a key
+    #[arg(long, env = "LOAD_TEST_API_KEY")]
+    pub api_key: Option<String>,
+
+    /// Per-request timeout, in seconds
+    #[arg(long, default_value_t = 10)]
+    pub timeout_secs: u64,
+}
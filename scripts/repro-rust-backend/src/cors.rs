@@ -0,0 +1,29 @@
+//! Cross-origin access for the browser test apps.
+//!
+//! Any origin is allowed by default, since the wallet test apps run on
+//! whatever port Vite picks. `CORS_ORIGINS` restricts it to a list, for a
+//! backend reachable from outside localhost.
+
+use crate::config::CorsArgs;
+use axum::http::HeaderValue;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// CORS policy for `config`
+pub fn layer(config: &CorsArgs) -> Result<CorsLayer, String> {
+    if config.cors_origins.is_empty() {
+        return Ok(CorsLayer::permissive());
+    }
+    let origins = config
+        .cors_origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin.trim())
+                .map_err(|_| format!("invalid CORS origin `{}`", origin))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers(Any))
+}
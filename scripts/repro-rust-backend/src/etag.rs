@@ -0,0 +1,109 @@
+//! Conditional retrieval.
+//!
+//! Frontends poll `GET /transaction/:id` while they wait for signatures,
+//! downloading the same kilobytes of hex every time. Successful retrievals
+//! carry an `ETag` derived from what the response is built from: the stored
+//! bytes, the signatures and the session state, and how the retrieval asked
+//! for them. A poll that sends it back in `If-None-Match` gets `304 Not
+//! Modified` and no body until one of those changes.
+//!
+//! Tags are weak: the `message` and the `expiration` countdown of two
+//! responses under the same tag may differ.
+
+use crate::session::SessionState;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::crypto::sha3_256;
+use axum::http::{header, HeaderMap, HeaderValue};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Everything a retrieval's response depends on, apart from the time
+#[derive(Serialize)]
+pub struct Representation<'a> {
+    pub bcs: &'a [u8],
+    pub secondary_signatures: &'a BTreeMap<String, Vec<u8>>,
+    pub fee_payer_signature: Option<&'a [u8]>,
+    pub session_state: SessionState,
+    pub quorum: Option<u32>,
+    /// Ledger version, once the transaction is verified as committed
+    pub committed_version: Option<u64>,
+    /// Retrieval mode, reserialize strategy, and `binary` or the BCS encoding
+    pub variant: [&'a str; 3],
+}
+
+impl Representation<'_> {
+    /// Weak `ETag` of this representation
+    pub fn etag(&self) -> HeaderValue {
+        // Plain data, which always serializes
+        let bcs = aptos_bcs::to_bytes(self).expect("representation serializes");
+        let digest = sha3_256(&bcs);
+        HeaderValue::from_str(&format!("W/\"{}\"", hex::encode(&digest[..16])))
+            .expect("hex is a valid header value")
+    }
+}
+
+/// Whether `If-None-Match` in `headers` matches `etag`, comparing weakly
+pub fn not_modified(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag.to_str().unwrap_or_default());
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG: &str = "W/\"0123\"";
+
+    fn if_none_match(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn matches(values: &[&str]) -> bool {
+        not_modified(&if_none_match(values), &HeaderValue::from_static(TAG))
+    }
+
+    #[test]
+    fn tags_compare_weakly() {
+        assert!(matches(&[TAG]));
+        assert!(matches(&["\"0123\""]));
+        assert!(!matches(&["W/\"4567\""]));
+        assert!(!matches(&[]));
+    }
+
+    #[test]
+    fn any_listed_tag_matches() {
+        assert!(matches(&["\"4567\", W/\"0123\""]));
+        assert!(matches(&["\"4567\"", " W/\"0123\" "]));
+        assert!(matches(&["*"]));
+        assert!(!matches(&["\"4567\", \"89ab\""]));
+    }
+
+    #[test]
+    fn tag_changes_with_a_signature() {
+        let unsigned = BTreeMap::new();
+        let signed: BTreeMap<String, Vec<u8>> = [("0xa".to_string(), vec![1])].into();
+        let representation = |secondary_signatures| Representation {
+            bcs: &[1, 2, 3],
+            secondary_signatures,
+            fee_payer_signature: None,
+            session_state: SessionState::Created,
+            quorum: None,
+            committed_version: None,
+            variant: ["pass-through", "typed", "hex"],
+        };
+        let tag = representation(&unsigned).etag();
+        assert!(tag.to_str().unwrap().starts_with("W/\""));
+        assert_eq!(representation(&unsigned).etag(), tag);
+        assert_ne!(representation(&signed).etag(), tag);
+    }
+}
@@ -0,0 +1,295 @@
+//! Expiration of stored transactions and abandoned signing sessions.
+//!
+//! A background sweeper runs every `TX_SWEEP_INTERVAL_SECS` seconds (default
+//! 60) once `TX_TTL_SECS` or `SESSION_IDLE_SECS` is set. A transaction
+//! expires when:
+//!
+//! - its `stored_at` is older than `TX_TTL_SECS`, whatever its state;
+//! - its session is still open (collecting signatures or after a failed
+//!   submission) and either its `expiration_timestamp_secs` has passed or
+//!   nothing has happened to it for `SESSION_IDLE_SECS`.
+//!
+//! The sweep that finds it records an `expired` event, pushed to `/ws`
+//! subscribers like any other, and the next sweep deletes it. The ids of
+//! swept entries are remembered for one more TTL or idle window so
+//! `GET /transaction/:id` can answer `410 Gone` rather than a plain
+//! `404 Not Found`.
+
+use crate::config::PersistenceArgs;
+use crate::session::{Operation, SessionState};
+use crate::storage::{EventKind, StoredTransaction, TransactionEvent};
+use crate::AppState;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Tracks the TTL, the idle window and recently expired transaction ids
+pub struct Expiry {
+    ttl_secs: Option<u64>,
+    idle_secs: Option<u64>,
+    sweep_interval: Duration,
+    /// transaction_id -> unix time it was swept
+    expired: Mutex<HashMap<String, u64>>,
+}
+
+impl Expiry {
+    /// Returns `None` if neither a TTL nor an idle window is configured
+    /// (entries never expire)
+    pub fn from_config(config: &PersistenceArgs) -> Option<Self> {
+        if config.tx_ttl_secs.is_none() && config.session_idle_secs.is_none() {
+            return None;
+        }
+        Some(Self {
+            ttl_secs: config.tx_ttl_secs,
+            idle_secs: config.session_idle_secs,
+            sweep_interval: Duration::from_secs(config.tx_sweep_interval_secs),
+            expired: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// What expires entries, for the startup banner
+    pub fn describe(&self) -> String {
+        let ttl = match self.ttl_secs {
+            Some(ttl_secs) => format!("TTL {}s", ttl_secs),
+            None => "no TTL".to_string(),
+        };
+        let idle = match self.idle_secs {
+            Some(idle_secs) => format!("idle sessions after {}s", idle_secs),
+            None => "no idle limit".to_string(),
+        };
+        format!("{}, {}, swept every {:?}", ttl, idle, self.sweep_interval)
+    }
+
+    /// Whether a transaction stored at `stored_at` has outlived the TTL
+    pub fn is_expired(&self, stored_at: u64) -> bool {
+        self.ttl_secs
+            .is_some_and(|ttl_secs| now_secs().saturating_sub(stored_at) > ttl_secs)
+    }
+
+    /// Why `tx`, in `session` state, should expire now, or `None` if it
+    /// shouldn't
+    fn reason(&self, tx: &StoredTransaction, session: SessionState) -> Option<&'static str> {
+        if self.is_expired(tx.stored_at) {
+            return Some("ttl");
+        }
+        match session {
+            SessionState::Expired => Some("expiration_timestamp_secs"),
+            // Submitted, finalized and rejected sessions are left alone
+            _ if !session.allows(Operation::Submit) => None,
+            _ => {
+                let idle_secs = self.idle_secs?;
+                let last_active_ms = tx
+                    .history
+                    .iter()
+                    .map(|event| event.at_ms)
+                    .max()
+                    .unwrap_or(tx.stored_at * 1000);
+                let idle_ms = (now_secs() * 1000).saturating_sub(last_active_ms);
+                (idle_ms > idle_secs * 1000).then_some("idle")
+            }
+        }
+    }
+
+    /// Recently swept ids. The map stays consistent even if a holder
+    /// panicked, so a poisoned lock is used anyway.
+    fn tombstones(&self) -> MutexGuard<'_, HashMap<String, u64>> {
+        self.expired.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether `transaction_id` was recently removed by the sweeper
+    pub fn was_swept(&self, transaction_id: &str) -> bool {
+        self.tombstones().contains_key(transaction_id)
+    }
+
+    /// Forget that `transaction_id` expired (it has been stored again)
+    pub fn clear(&self, transaction_id: &str) {
+        self.tombstones().remove(transaction_id);
+    }
+
+    /// Mark newly expired transactions and delete those marked by an earlier
+    /// sweep, returning how many of each
+    async fn sweep(&self, state: &AppState) -> (usize, usize) {
+        let transactions = match state.store.list().await {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                error!("Failed to list transactions: {}", e);
+                return (0, 0);
+            }
+        };
+
+        let (mut marked, mut removed) = (0, 0);
+        for (transaction_id, tx) in transactions {
+            let already_marked = tx
+                .history
+                .iter()
+                .any(|event| event.kind == EventKind::Expired);
+            if !already_marked {
+                let Some(reason) = self.reason(&tx, state.session_state(&tx)) else {
+                    continue;
+                };
+                let event = TransactionEvent::now(EventKind::Expired, Some(reason.to_string()));
+                crate::record_history(state, &transaction_id, vec![event]).await;
+                marked += 1;
+                continue;
+            }
+            match state.store.remove(&transaction_id).await {
+                Ok(true) => {
+                    state.webhooks.unregister(&transaction_id);
+                    self.tombstones().insert(transaction_id, now_secs());
+                    removed += 1;
+                }
+                Ok(false) => {}
+                Err(e) => error!("Failed to remove {}: {}", transaction_id, e),
+            }
+        }
+
+        // Tombstones only need to outlive a client's last poll, not forever
+        let retention = self.ttl_secs.max(self.idle_secs).unwrap_or_default();
+        let now = now_secs();
+        self.tombstones()
+            .retain(|_, swept_at| now.saturating_sub(*swept_at) <= retention);
+
+        (marked, removed)
+    }
+}
+
+/// Spawn the background sweeper
+pub fn spawn(state: Arc<AppState>, expiry: Arc<Expiry>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(expiry.sweep_interval);
+        loop {
+            ticker.tick().await;
+            let (marked, removed) = expiry.sweep(&state).await;
+            if marked > 0 {
+                info!("Marked {} transactions expired", marked);
+            }
+            if removed > 0 {
+                info!("Removed {} expired transactions", removed);
+            }
+        }
+    });
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+
+    fn expiring(ttl_secs: Option<u64>, idle_secs: Option<u64>) -> Expiry {
+        Expiry {
+            ttl_secs,
+            idle_secs,
+            sweep_interval: Duration::from_secs(60),
+            expired: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// An undecodable transaction (its session is open) stored `age_secs` ago
+    fn stored(age_secs: u64, history: Vec<TransactionEvent>) -> StoredTransaction {
+        StoredTransaction {
+            stored_at: now_secs() - age_secs,
+            history,
+            ..StoredTransaction::default()
+        }
+    }
+
+    fn event(kind: EventKind, age_secs: u64) -> TransactionEvent {
+        TransactionEvent {
+            at_ms: (now_secs() - age_secs) * 1000,
+            ..TransactionEvent::now(kind, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn sweep_marks_then_removes() {
+        let state = crate::testing::state(0, None, Limits::default());
+        let expiry = expiring(Some(60), None);
+        state.store.put("old", stored(120, vec![])).await.unwrap();
+        state.store.put("new", stored(0, vec![])).await.unwrap();
+
+        assert_eq!(expiry.sweep(&state).await, (1, 0));
+        let old = state.store.get("old").await.unwrap().unwrap();
+        let expired = old.history.last().unwrap();
+        assert_eq!(expired.kind, EventKind::Expired);
+        assert_eq!(expired.detail.as_deref(), Some("ttl"));
+        assert!(!expiry.was_swept("old"));
+
+        assert_eq!(expiry.sweep(&state).await, (0, 1));
+        assert!(state.store.get("old").await.unwrap().is_none());
+        assert!(state.store.get("new").await.unwrap().is_some());
+        assert!(expiry.was_swept("old"));
+        assert!(!expiry.was_swept("new"));
+
+        // Storing it again forgets the tombstone
+        expiry.clear("old");
+        assert!(!expiry.was_swept("old"));
+        assert_eq!(expiry.sweep(&state).await, (0, 0));
+    }
+
+    #[test]
+    fn ttl_applies_whatever_the_state() {
+        let expiry = expiring(Some(60), None);
+        let submitted = stored(120, vec![event(EventKind::Submitted, 120)]);
+        assert_eq!(
+            expiry.reason(&submitted, SessionState::Submitted),
+            Some("ttl")
+        );
+        assert!(expiry.is_expired(now_secs() - 61));
+        assert!(!expiry.is_expired(now_secs() - 59));
+    }
+
+    #[test]
+    fn idle_counts_from_the_last_event() {
+        let expiry = expiring(None, Some(60));
+        let open = SessionState::AwaitingSignatures;
+        assert_eq!(expiry.reason(&stored(120, vec![]), open), Some("idle"));
+        assert_eq!(expiry.reason(&stored(30, vec![]), open), None);
+
+        let active = stored(120, vec![event(EventKind::SignatureAdded, 30)]);
+        assert_eq!(expiry.reason(&active, open), None);
+        let stale = stored(120, vec![event(EventKind::SignatureAdded, 90)]);
+        assert_eq!(expiry.reason(&stale, open), Some("idle"));
+
+        // Failed submissions can be retried, so still idle out
+        assert_eq!(expiry.reason(&stale, SessionState::Failed), Some("idle"));
+    }
+
+    #[test]
+    fn closed_sessions_are_left_alone() {
+        let expiry = expiring(None, Some(60));
+        let tx = stored(120, vec![]);
+        for session in [
+            SessionState::Submitted,
+            SessionState::Finalized,
+            SessionState::Rejected,
+        ] {
+            assert_eq!(expiry.reason(&tx, session), None, "{:?}", session);
+        }
+        // Past its expiration_timestamp_secs, without any window configured
+        let expiry = expiring(None, None);
+        assert_eq!(
+            expiry.reason(&tx, SessionState::Expired),
+            Some("expiration_timestamp_secs")
+        );
+    }
+
+    #[test]
+    fn describe_names_each_limit() {
+        assert_eq!(
+            expiring(Some(60), None).describe(),
+            "TTL 60s, no idle limit, swept every 60s"
+        );
+        assert_eq!(
+            expiring(None, Some(30)).describe(),
+            "no TTL, idle sessions after 30s, swept every 60s"
+        );
+    }
+}
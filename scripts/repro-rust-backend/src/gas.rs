@@ -0,0 +1,87 @@
+//! Gas viability of a stored transaction.
+//!
+//! An underpriced `gas_unit_price` or a gas payer that can't cover
+//! `max_gas_amount * gas_unit_price` fails on chain just like a bad
+//! serialization would from the frontend's point of view, so
+//! `GET /transaction/:id/gas` compares both against the fullnode's
+//! `estimate_gas_price` and the payer's balance to tell them apart.
+
+use aptos_sdk::api::GasEstimation;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// The fullnode's `estimate_gas_price`, in octas per gas unit
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+pub struct GasEstimate {
+    /// Lowest price likely to be included, when the fullnode reports one
+    pub deprioritized: Option<u64>,
+    /// Recommended price
+    pub recommended: u64,
+    /// Price for urgent inclusion, when the fullnode reports one
+    pub prioritized: Option<u64>,
+}
+
+impl From<&GasEstimation> for GasEstimate {
+    fn from(estimation: &GasEstimation) -> Self {
+        Self {
+            deprioritized: estimation.deprioritized_gas_estimate,
+            recommended: estimation.gas_estimate,
+            prioritized: estimation.prioritized_gas_estimate,
+        }
+    }
+}
+
+/// Why a transaction's gas settings would fail or stall on chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GasIssue {
+    /// `max_gas_amount` is zero, so not even the prologue can run
+    ZeroMaxGasAmount,
+    /// `gas_unit_price` is below the lowest estimate; the mempool is likely
+    /// to drop the transaction
+    PriceBelowMinimum,
+    /// `gas_unit_price` is below the recommended estimate; it may wait a long
+    /// time (and expire) under load
+    PriceBelowRecommended,
+    /// The gas payer can't cover the maximum fee
+    /// (`INSUFFICIENT_BALANCE_FOR_TRANSACTION_FEE`)
+    InsufficientBalance,
+}
+
+impl GasIssue {
+    /// Whether the transaction can't succeed at all, rather than just being
+    /// slow
+    pub fn is_fatal(self) -> bool {
+        !matches!(self, GasIssue::PriceBelowRecommended)
+    }
+}
+
+/// The most a transaction can charge its gas payer, in octas; `None` on
+/// overflow, which no balance covers
+pub fn max_fee(max_gas_amount: u64, gas_unit_price: u64) -> Option<u64> {
+    max_gas_amount.checked_mul(gas_unit_price)
+}
+
+/// Every issue with a transaction's gas settings, given the fullnode's
+/// `estimate` and the gas payer's `balance` in octas
+pub fn assess(
+    max_gas_amount: u64,
+    gas_unit_price: u64,
+    estimate: GasEstimate,
+    balance: u64,
+) -> Vec<GasIssue> {
+    let mut issues = Vec::new();
+    if max_gas_amount == 0 {
+        issues.push(GasIssue::ZeroMaxGasAmount);
+    }
+    let minimum = estimate.deprioritized.unwrap_or(estimate.recommended);
+    if gas_unit_price < minimum {
+        issues.push(GasIssue::PriceBelowMinimum);
+    } else if gas_unit_price < estimate.recommended {
+        issues.push(GasIssue::PriceBelowRecommended);
+    }
+    if max_fee(max_gas_amount, gas_unit_price).is_none_or(|fee| fee > balance) {
+        issues.push(GasIssue::InsufficientBalance);
+    }
+    issues
+}
@@ -0,0 +1,289 @@
+//! GraphQL queries over stored transactions.
+//!
+//! `POST /graphql` answers queries that cut across transactions, which the
+//! per-id REST routes can't express in one request, e.g. every transaction of
+//! a sender that is still missing signatures, with its decoded payload:
+//!
+//! ```graphql
+//! {
+//!   transactions(sender: "0x1", missingSignatures: true) {
+//!     id
+//!     sequenceNumber
+//!     missingSigners
+//!     decoded
+//!   }
+//! }
+//! ```
+//!
+//! `GET /graphql` serves GraphiQL for exploring the schema. Queries are
+//! read-only and see the stored bytes as they are, never re-serialized.
+
+use crate::decode::TransactionView;
+use crate::storage::{hex_bytes, StoredTransaction, TransactionEvent};
+use crate::transaction::{DecodedTransaction, TransactionKind};
+use crate::AppState;
+use aptos_sdk::AccountAddress;
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Json, Object, Schema, SimpleObject,
+};
+use axum::response::Html;
+use std::sync::Arc;
+
+/// Transactions returned by `transactions` unless `limit` is given
+const DEFAULT_LIMIT: usize = 100;
+/// Largest `limit` honoured
+const MAX_LIMIT: usize = 1000;
+
+pub type ReproSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+pub fn schema(state: Arc<AppState>) -> ReproSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(8)
+        .finish()
+}
+
+/// GraphiQL, pointed at the `/graphql` it is served from
+pub async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("graphql").finish())
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// A stored transaction by id, or null if unknown or expired
+    async fn transaction(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Option<Transaction>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let tx = state
+            .store
+            .get(&id)
+            .await
+            .map_err(|e| format!("Failed to read from storage: {}", e))?;
+        Ok(tx
+            .filter(|tx| !expired(state, tx))
+            .map(|tx| Transaction::new(id, tx)))
+    }
+
+    /// Stored transactions ordered by id, narrowed by every filter given
+    #[allow(clippy::too_many_arguments)]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Sender address, any form")] sender: Option<String>,
+        min_seq: Option<u64>,
+        max_seq: Option<u64>,
+        #[graphql(desc = "`multi_agent`, `fee_payer`, `raw_transaction` or `signed_transaction`")]
+        kind: Option<String>,
+        #[graphql(
+            desc = "Only transactions still missing (true) or no longer missing (false) a \
+                          signature; those that don't decode match neither"
+        )]
+        missing_signatures: Option<bool>,
+        #[graphql(desc = "Only ids after this one, for paging")] after: Option<String>,
+        #[graphql(desc = "At most this many (default 100, max 1000)")] limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Transaction>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        let sender = sender
+            .as_deref()
+            .map(AccountAddress::from_hex)
+            .transpose()
+            .map_err(|e| format!("Invalid sender address: {}", e))?
+            .map(|sender| sender.to_long_string());
+        let kind = kind
+            .as_deref()
+            .map(|kind| {
+                TransactionKind::parse(kind).ok_or_else(|| format!("Unknown kind `{}`", kind))
+            })
+            .transpose()?;
+        let sequence_numbers = min_seq.unwrap_or(0)..=max_seq.unwrap_or(u64::MAX);
+        let filter_by_seq = min_seq.is_some() || max_seq.is_some();
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let transactions = match &sender {
+            Some(sender) => {
+                state
+                    .store
+                    .list_by_sender(sender, sequence_numbers.clone())
+                    .await
+            }
+            None => state.store.list().await,
+        };
+        let mut transactions =
+            transactions.map_err(|e| format!("Failed to read from storage: {}", e))?;
+        transactions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(transactions
+            .into_iter()
+            .filter(|(id, _)| after.as_ref().is_none_or(|after| id > after))
+            .filter(|(_, tx)| {
+                !filter_by_seq
+                    || tx
+                        .sequence_number
+                        .is_some_and(|seq| sequence_numbers.contains(&seq))
+            })
+            .filter(|(_, tx)| !expired(state, tx))
+            .map(|(id, tx)| Transaction::new(id, tx))
+            .filter(|tx| kind.is_none_or(|kind| tx.decoded_kind() == Some(kind)))
+            .filter(|tx| {
+                missing_signatures.is_none_or(|missing| {
+                    tx.pending_signers()
+                        .is_some_and(|signers| signers.is_empty() != missing)
+                })
+            })
+            .take(limit)
+            .collect())
+    }
+}
+
+/// Entries past their TTL are as good as gone even before the sweeper runs
+fn expired(state: &AppState, tx: &StoredTransaction) -> bool {
+    state
+        .expiry
+        .as_ref()
+        .is_some_and(|expiry| expiry.is_expired(tx.stored_at))
+}
+
+/// A stored transaction, decoded once for the fields that need it
+pub struct Transaction {
+    id: String,
+    tx: StoredTransaction,
+    decoded: Option<DecodedTransaction>,
+}
+
+/// A secondary signer's signature
+#[derive(SimpleObject)]
+struct Signature {
+    /// Long-form address
+    signer: String,
+    /// BCS `AccountAuthenticator` as hex
+    signature_hex: String,
+}
+
+impl Transaction {
+    fn new(id: String, tx: StoredTransaction) -> Self {
+        let decoded = tx
+            .bcs()
+            .ok()
+            .and_then(|bcs| DecodedTransaction::decode(&bcs).ok());
+        Self { id, tx, decoded }
+    }
+
+    fn decoded_kind(&self) -> Option<TransactionKind> {
+        self.decoded.as_ref().map(DecodedTransaction::kind)
+    }
+
+    fn pending_signers(&self) -> Option<Vec<String>> {
+        self.decoded.as_ref().map(|decoded| {
+            decoded.missing_signers(
+                &self.tx.current_secondary_signatures(),
+                self.tx.fee_payer_signed(),
+            )
+        })
+    }
+}
+
+#[Object]
+impl Transaction {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Long-form address, if the BCS decoded when stored
+    async fn sender(&self) -> Option<&str> {
+        self.tx.sender.as_deref()
+    }
+
+    async fn sequence_number(&self) -> Option<u64> {
+        self.tx.sequence_number
+    }
+
+    /// Unix seconds
+    async fn stored_at(&self) -> u64 {
+        self.tx.stored_at
+    }
+
+    /// Payload the BCS decodes as, or null if it doesn't
+    async fn kind(&self) -> Option<&'static str> {
+        self.decoded_kind().map(TransactionKind::as_str)
+    }
+
+    /// The stored BCS, hex-encoded as it was sent
+    async fn bcs_hex(&self) -> async_graphql::Result<String> {
+        let bcs = self
+            .tx
+            .bcs()
+            .map_err(|e| format!("Failed to decompress transaction: {}", e))?;
+        Ok(hex_bytes::encode(&bcs, self.tx.hex_prefixed))
+    }
+
+    /// Secondary signatures collected so far
+    async fn secondary_signatures(&self) -> Vec<Signature> {
+        self.tx
+            .secondary_signatures_hex()
+            .into_iter()
+            .map(|(signer, signature_hex)| Signature {
+                signer,
+                signature_hex,
+            })
+            .collect()
+    }
+
+    async fn fee_payer_signature_hex(&self) -> Option<String> {
+        self.tx.fee_payer_signature_hex()
+    }
+
+    /// Signers other than the sender yet to sign: long-form addresses, or
+    /// `fee_payer`. Null if the BCS doesn't decode.
+    async fn missing_signers(&self) -> Option<Vec<String>> {
+        self.pending_signers()
+    }
+
+    /// Required signers recorded at ingest that are yet to sign. Null if the
+    /// BCS didn't decode when stored.
+    async fn signers_pending(&self) -> Option<Vec<String>> {
+        self.tx.signers_pending()
+    }
+
+    /// Required signers recorded at ingest whose signature is attached
+    async fn signers_done(&self) -> Option<Vec<String>> {
+        self.tx.signers_done()
+    }
+
+    /// Secondary signatures that complete the session, if stored with a quorum
+    async fn quorum(&self) -> Option<u32> {
+        self.tx.quorum
+    }
+
+    /// Where the signing session stands, e.g. `awaiting_signatures`
+    async fn session_state(&self, ctx: &Context<'_>) -> async_graphql::Result<&'static str> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        Ok(state
+            .session_state_decoded(&self.tx, self.decoded.as_ref())
+            .as_str())
+    }
+
+    /// Every decoded field, as `GET /transaction/:id/decoded` returns it
+    async fn decoded(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<Json<TransactionView>>> {
+        let state = ctx.data::<Arc<AppState>>()?;
+        Ok(match &self.decoded {
+            Some(decoded) => Some(Json(
+                TransactionView::new(decoded, state.fullnode.as_ref()).await,
+            )),
+            None => None,
+        })
+    }
+
+    /// Events recorded for the transaction, oldest first
+    async fn history(&self) -> Json<Vec<TransactionEvent>> {
+        Json(self.tx.history.clone())
+    }
+}
@@ -0,0 +1,266 @@
+//! gRPC service alongside the HTTP API.
+//!
+//! Some signer daemons only speak gRPC, and going through JSON and hex for
+//! them adds a serialization layer to rule out when hunting a byte change.
+//! With `--grpc-port` (and `--features grpc`) the store, retrieve and
+//! signature calls of `proto/repro.proto` are served on their own port, with
+//! BCS and signatures as raw `bytes`. Each call runs the HTTP handler it
+//! mirrors, so validation, history, notifications, webhooks and reserialize
+//! mode behave identically.
+//!
+//! Credentials go in request metadata (`x-api-key` or `authorization`) and
+//! are checked like HTTP's. HMAC request signing and rate limiting apply to
+//! HTTP only.
+
+use crate::config::ServerArgs;
+use std::net::SocketAddr;
+
+/// Address to serve gRPC on, or `None` if `--grpc-port` isn't set
+pub fn addr(config: &ServerArgs) -> Result<Option<SocketAddr>, String> {
+    let Some(port) = config.grpc_port else {
+        return Ok(None);
+    };
+    if cfg!(not(feature = "grpc")) {
+        return Err("--grpc-port requires building with `--features grpc`".to_string());
+    }
+    Ok(Some(SocketAddr::from(([0, 0, 0, 0], port))))
+}
+
+#[cfg(feature = "grpc")]
+pub use service::serve;
+
+#[cfg(feature = "grpc")]
+mod service {
+    use super::proto::{
+        self,
+        repro_server::{Repro, ReproServer},
+    };
+    use crate::auth::{self, Scope};
+    use crate::binary::{BinaryTransaction, Negotiated, StoreBody, OCTET_STREAM};
+    use crate::encoding::Encoding;
+    use crate::reserialize::{RESERIALIZE_HEADER, STRATEGY_HEADER};
+    use crate::server::retrieve::get_transaction;
+    use crate::server::signatures::{
+        store_fee_payer_signature, store_signature, StoreFeePayerSignatureRequest,
+        StoreSignatureRequest, StoreSignatureResponse,
+    };
+    use crate::server::store::store_transaction;
+    use crate::shutdown::Shutdown;
+    use crate::transaction::TransactionKind;
+    use crate::AppState;
+    use axum::{
+        extract::{Path, State},
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        Json,
+    };
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tonic::{Code, Request, Response, Status};
+    use tracing::{error, info};
+
+    struct ReproService {
+        state: Arc<AppState>,
+    }
+
+    /// Serve the gRPC API on `addr` until `shutdown` is requested
+    pub async fn serve(state: Arc<AppState>, addr: SocketAddr, shutdown: Shutdown) {
+        info!("gRPC listening on {}", addr);
+        let result = tonic::transport::Server::builder()
+            .add_service(ReproServer::new(ReproService { state }))
+            .serve_with_shutdown(addr, shutdown.requested())
+            .await;
+        if let Err(e) = result {
+            error!("gRPC server failed: {}", e);
+        }
+    }
+
+    impl ReproService {
+        /// Check the call's credentials like the HTTP auth middleware does
+        #[allow(clippy::result_large_err)] // tonic's `Status`, as its handlers return
+        fn authorize<T>(&self, request: &Request<T>, scope: Scope) -> Result<(), Status> {
+            let headers = request.metadata().clone().into_headers();
+            auth::check(&self.state.guards, &headers, scope)
+                .map_err(|(status, message)| to_status(status, message))
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Repro for ReproService {
+        async fn store_transaction(
+            &self,
+            request: Request<proto::StoreTransactionRequest>,
+        ) -> Result<Response<proto::StoreTransactionResponse>, Status> {
+            self.authorize(&request, Scope::TxWrite)?;
+            let request = request.into_inner();
+            let kind = match request.kind.as_deref() {
+                None => None,
+                Some(kind) => Some(TransactionKind::parse(kind).ok_or_else(|| {
+                    Status::invalid_argument(
+                        "kind must be multi_agent, fee_payer, raw_transaction or signed_transaction",
+                    )
+                })?),
+            };
+            let (status, Json(response)) = store_transaction(
+                State(self.state.clone()),
+                StoreBody::Binary(BinaryTransaction {
+                    transaction_id: request.transaction_id,
+                    kind,
+                    callback_url: None,
+                    quorum: None,
+                    bcs: request.bcs.into(),
+                }),
+            )
+            .await;
+            if !status.is_success() {
+                return Err(to_status(status, response.message));
+            }
+            Ok(Response::new(proto::StoreTransactionResponse {
+                transaction_id: response.transaction_id,
+                kind: response.kind.map(|kind| kind.as_str().to_string()),
+                message: response.message,
+                session_state: response
+                    .session_state
+                    .map(|state| state.as_str().to_string()),
+            }))
+        }
+
+        async fn get_transaction(
+            &self,
+            request: Request<proto::GetTransactionRequest>,
+        ) -> Result<Response<proto::GetTransactionResponse>, Status> {
+            self.authorize(&request, Scope::TxRead)?;
+            let request = request.into_inner();
+
+            // The retrieval overrides travel as the headers HTTP reads them from
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_static(OCTET_STREAM));
+            if let Some(reserialize) = request.reserialize {
+                let value = if reserialize { "1" } else { "0" };
+                headers.insert(RESERIALIZE_HEADER, HeaderValue::from_static(value));
+            }
+            if let Some(strategy) = &request.reserialize_strategy {
+                let value = HeaderValue::from_str(strategy)
+                    .map_err(|_| Status::invalid_argument("invalid reserialize_strategy"))?;
+                headers.insert(STRATEGY_HEADER, value);
+            }
+
+            let bcs = match get_transaction(
+                State(self.state.clone()),
+                Path(request.transaction_id.clone()),
+                headers,
+            )
+            .await
+            .untagged()
+            {
+                Negotiated::Binary(bcs) => bcs,
+                Negotiated::Json(status, Json(response)) => {
+                    return Err(to_status(status, response.message))
+                }
+                Negotiated::Tagged(..) | Negotiated::NotModified(_) => {
+                    unreachable!("untagged, and sent without If-None-Match")
+                }
+            };
+
+            // Signatures are returned exactly as stored, as over HTTP
+            let tx = self
+                .state
+                .store
+                .get(&request.transaction_id)
+                .await
+                .map_err(|e| Status::internal(format!("Failed to read from storage: {}", e)))?
+                .ok_or_else(|| Status::not_found("Transaction not found"))?;
+            let session_state = self.state.session_state(&tx).as_str().to_string();
+            let signers_pending = tx.signers_pending().unwrap_or_default();
+            let signers_done = tx.signers_done().unwrap_or_default();
+            Ok(Response::new(proto::GetTransactionResponse {
+                bcs,
+                secondary_signatures: tx.secondary_signatures.into_iter().collect(),
+                fee_payer_signature: tx.fee_payer_signature,
+                stored_at: tx.stored_at,
+                session_state,
+                signers_pending,
+                signers_done,
+            }))
+        }
+
+        async fn store_signature(
+            &self,
+            request: Request<proto::StoreSignatureRequest>,
+        ) -> Result<Response<proto::StoreSignatureResponse>, Status> {
+            self.authorize(&request, Scope::SigWrite)?;
+            let request = request.into_inner();
+            // Hex here is internal: the bytes are decoded straight back
+            let (status, Json(response)) = store_signature(
+                State(self.state.clone()),
+                Json(StoreSignatureRequest {
+                    transaction_id: request.transaction_id,
+                    signer_address: request.signer_address,
+                    signature_hex: hex::encode(&request.signature),
+                    encoding: Encoding::Hex,
+                    scheme: None,
+                }),
+            )
+            .await;
+            signature_response(status, response)
+        }
+
+        async fn store_fee_payer_signature(
+            &self,
+            request: Request<proto::StoreFeePayerSignatureRequest>,
+        ) -> Result<Response<proto::StoreSignatureResponse>, Status> {
+            self.authorize(&request, Scope::SigWrite)?;
+            let request = request.into_inner();
+            let (status, Json(response)) = store_fee_payer_signature(
+                State(self.state.clone()),
+                Json(StoreFeePayerSignatureRequest {
+                    transaction_id: request.transaction_id,
+                    signature_hex: hex::encode(&request.signature),
+                    encoding: Encoding::Hex,
+                    scheme: None,
+                }),
+            )
+            .await;
+            signature_response(status, response)
+        }
+    }
+
+    #[allow(clippy::result_large_err)] // tonic's `Status`, as its handlers return
+    fn signature_response(
+        status: StatusCode,
+        response: StoreSignatureResponse,
+    ) -> Result<Response<proto::StoreSignatureResponse>, Status> {
+        if !status.is_success() {
+            return Err(to_status(status, response.message));
+        }
+        Ok(Response::new(proto::StoreSignatureResponse {
+            transaction_id: response.transaction_id,
+            message: response.message,
+            session_state: response
+                .session_state
+                .map(|state| state.as_str().to_string()),
+        }))
+    }
+
+    /// The gRPC code closest to an HTTP status
+    fn to_status(status: StatusCode, message: String) -> Status {
+        let code = match status {
+            StatusCode::BAD_REQUEST => Code::InvalidArgument,
+            StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+            StatusCode::FORBIDDEN => Code::PermissionDenied,
+            StatusCode::NOT_FOUND | StatusCode::GONE => Code::NotFound,
+            StatusCode::CONFLICT => Code::AlreadyExists,
+            StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => {
+                Code::ResourceExhausted
+            }
+            StatusCode::UNPROCESSABLE_ENTITY => Code::FailedPrecondition,
+            StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+            _ => Code::Internal,
+        };
+        Status::new(code, message)
+    }
+}
+
+#[cfg(feature = "grpc")]
+mod proto {
+    tonic::include_proto!("repro.v1");
+}
@@ -0,0 +1,267 @@
+//! Request journal for deterministic replays.
+//!
+//! When `JOURNAL_PATH` is set, every API request is appended to a JSON lines
+//! journal: when it arrived, its method, path and query, the headers that
+//! change what a handler does, its body, and the status it was answered with. `--replay journal.jsonl` feeds
+//! a journal back through the API one request at a time, in the order the
+//! requests were answered, then exits. Each request answered with a different
+//! status than the one recorded is logged with the new answer's body, so a
+//! flaky SEQUENCE_NUMBER repro caught once can be re-run on demand, in either
+//! mode or against another storage backend.
+//!
+//! Left out of the journal: `/ws` and `/transaction/:id/wait`, which change
+//! nothing and would stall a sequential replay, the probes and
+//! `/openapi.json`. Credentials and signatures aren't among the headers kept,
+//! so they never reach the file; the replay runs without API key, JWT, HMAC
+//! and rate limit checks, and skips requests those checks rejected.
+//!
+//! The replay doesn't fake the clock: a transaction that hadn't expired when
+//! the journal was recorded may have by the time it is replayed. Submissions
+//! go to `--fullnode-url` again; leave it unset to replay without touching a
+//! chain.
+
+use crate::admin::IMPORT_BODY_LIMIT;
+use crate::binary::{
+    CALLBACK_URL_HEADER, QUORUM_HEADER, TRANSACTION_ID_HEADER, TRANSACTION_KIND_HEADER,
+};
+use crate::encoding::ENCODING_HEADER;
+use crate::reserialize::{RESERIALIZE_HEADER, STRATEGY_HEADER};
+use crate::storage::hex_bytes;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use tracing::{info, warn};
+
+/// Headers recorded and replayed; the rest are dropped
+const KEPT_HEADERS: [&str; 9] = [
+    "content-type",
+    "accept",
+    RESERIALIZE_HEADER,
+    STRATEGY_HEADER,
+    ENCODING_HEADER,
+    TRANSACTION_ID_HEADER,
+    TRANSACTION_KIND_HEADER,
+    CALLBACK_URL_HEADER,
+    QUORUM_HEADER,
+];
+/// Statuses the request guards answer with before a handler runs
+const GUARD_STATUSES: [u16; 3] = [401, 403, 429];
+/// How much of a diverging answer's body is logged
+const LOGGED_BODY_BYTES: usize = 4 * 1024;
+
+/// One recorded request and the status it got
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    /// Unix time in milliseconds the request arrived
+    at_ms: u64,
+    method: String,
+    /// Path and query, e.g. `/v1/transactions?sender=0x1`
+    path: String,
+    /// Those of [`KEPT_HEADERS`] the request had, by lowercase name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    /// The body, if it is UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// The body as hex, if it isn't UTF-8 (e.g. raw BCS)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    body_hex: Option<Vec<u8>>,
+    status: u16,
+}
+
+impl JournalEntry {
+    /// The request to replay, as if sent from localhost
+    fn to_request(&self) -> Result<Request, String> {
+        let body = match (&self.body, &self.body_hex) {
+            (Some(body), _) => Body::from(body.clone()),
+            (None, Some(bytes)) => Body::from(bytes.clone()),
+            (None, None) => Body::empty(),
+        };
+        let mut request = Request::builder()
+            .method(self.method.as_str())
+            .uri(&self.path)
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request.body(body).map_err(|e| e.to_string())
+    }
+}
+
+/// The journal file requests are appended to
+pub struct Journal {
+    file: Mutex<tokio::fs::File>,
+}
+
+impl Journal {
+    /// Open (or create) the journal at `path` for appending
+    pub async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    async fn append(&self, entry: &JournalEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await
+    }
+}
+
+/// Whether requests to `path` are journaled
+fn journaled(path: &str) -> bool {
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    !(path == "/ws"
+        || path.ends_with("/wait")
+        || matches!(path, "/health" | "/healthz" | "/readyz" | "/openapi.json"))
+}
+
+/// Appends each request to the journal once it has been answered
+pub async fn record(State(journal): State<Arc<Journal>>, request: Request, next: Next) -> Response {
+    if !journaled(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let at_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let (parts, body) = request.into_parts();
+    // Nothing larger gets past the routes' own limits anyway
+    let bytes = match axum::body::to_bytes(body, IMPORT_BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("Failed to read request body: {}", e),
+            )
+                .into_response()
+        }
+    };
+    let (body, body_hex) = match std::str::from_utf8(&bytes) {
+        Ok(_) if bytes.is_empty() => (None, None),
+        Ok(body) => (Some(body.to_string()), None),
+        Err(_) => (None, Some(bytes.to_vec())),
+    };
+    let mut entry = JournalEntry {
+        at_ms,
+        method: parts.method.to_string(),
+        path: parts
+            .uri
+            .path_and_query()
+            .map_or_else(|| parts.uri.path().to_string(), |path| path.to_string()),
+        headers: KEPT_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = parts.headers.get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect(),
+        body,
+        body_hex,
+        status: 0,
+    };
+
+    let request = Request::from_parts(parts, Body::from(bytes));
+    let response = next.run(request).await;
+    entry.status = response.status().as_u16();
+    if let Err(e) = journal.append(&entry).await {
+        warn!("Failed to journal {} {}: {}", entry.method, entry.path, e);
+    }
+    response
+}
+
+/// What a replay did
+pub struct ReplaySummary {
+    /// Requests sent through the API
+    pub replayed: usize,
+    /// Requests the guards had rejected, not sent
+    pub skipped: usize,
+    /// Replayed requests answered with a different status than recorded
+    pub diverged: usize,
+}
+
+/// Send every request in the journal at `path` through `app`, in order
+pub async fn replay(app: Router, path: &Path) -> Result<ReplaySummary, String> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut summary = ReplaySummary {
+        replayed: 0,
+        skipped: 0,
+        diverged: 0,
+    };
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        if GUARD_STATUSES.contains(&entry.status) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let request = entry
+            .to_request()
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        let response = app
+            .clone()
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|infallible| match infallible {});
+        summary.replayed += 1;
+        let status = response.status();
+        if status.as_u16() == entry.status {
+            continue;
+        }
+        summary.diverged += 1;
+        let body = axum::body::to_bytes(response.into_body(), LOGGED_BODY_BYTES)
+            .await
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_else(|_| "(body too large to log)".to_string());
+        warn!(
+            "line {}: {} {} answered {} (recorded {}): {}",
+            index + 1,
+            entry.method,
+            entry.path,
+            status.as_u16(),
+            entry.status,
+            body
+        );
+    }
+    info!(
+        "Replayed {} requests from {} ({} skipped, {} diverged)",
+        summary.replayed,
+        path.display(),
+        summary.skipped,
+        summary.diverged
+    );
+    Ok(summary)
+}
@@ -0,0 +1,241 @@
+//! Rust backend server for multi-agent transaction reproduction test.
+//!
+//! This simulates a backend that stores and retrieves serialized transactions
+//! to test if Rust SDK serialization causes SEQUENCE_NUMBER_TOO_OLD errors.
+//! Retrieval either returns the stored BCS unchanged (`--mode pass-through`,
+//! the default) or decodes and re-serializes it with the Rust SDK
+//! (`--mode reserialize`); `POST /admin/mode` switches at runtime and the
+//! `x-reserialize` header per request.
+//!
+//! Decoding, storage and the signing session live in `repro_core`. This
+//! crate is the server around them: the API handlers under `server`, auth,
+//! webhooks and the background tasks. The `repro-rust-backend` binary only
+//! calls [`run`], and [`testing`] starts the whole API in-process.
+
+mod admin;
+mod auth;
+mod backup;
+mod binary;
+mod chain;
+mod chaos;
+mod config;
+mod cors;
+mod etag;
+mod expiry;
+mod gas;
+mod graphql;
+mod grpc;
+mod journal;
+mod limits;
+mod listener;
+mod loadtest;
+mod localnet;
+mod locks;
+mod logging;
+mod notify;
+mod openapi;
+mod probes;
+mod ratelimit;
+mod reconcile;
+mod scenario;
+mod server;
+mod shutdown;
+mod signing;
+mod snapshot;
+mod submission;
+pub mod testing;
+mod tls;
+mod verify;
+mod wal;
+mod webhook;
+
+pub use server::run;
+
+use repro_core::{
+    authenticator, bytediff, canonical, client, decode, encoding, explain, generate, reserialize,
+    session, storage, transaction, type_tag,
+};
+
+use crate::chain::{ChainIdCheck, FullnodeChainId};
+use crate::config::Mode;
+use crate::expiry::Expiry;
+use crate::limits::Limits;
+use crate::session::SessionState;
+use crate::storage::{Compression, StoredTransaction, TransactionEvent, TransactionStore};
+use crate::transaction::DecodedTransaction;
+use aptos_sdk::api::FullnodeClient;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Shared server state
+struct AppState {
+    /// Stored serialized transactions and signatures (key = transaction_id)
+    store: Arc<dyn TransactionStore>,
+    /// How retrieved transactions are re-serialized
+    reserialize: Reserialization,
+    /// TTL tracking (None = transactions never expire)
+    expiry: Option<Arc<Expiry>>,
+    /// Compression of stored BCS (None = stored uncompressed)
+    compression: Option<Compression>,
+    /// Directory for `POST /admin/backup` and `POST /admin/restore`
+    backup_dir: PathBuf,
+    /// When the server started (for uptime in `/stats`)
+    started_at: Instant,
+    /// Retrievals whose re-serialized BCS differed from what was stored
+    reserialize_mismatches: AtomicU64,
+    /// Retrievals where the stored BCS could not be re-serialized at all
+    reserialize_failures: AtomicU64,
+    /// Fullnode checked by `/readyz` (None = not configured)
+    fullnode: Option<FullnodeClient>,
+    /// How often submitted transactions are looked up until committed or
+    /// expired (None = only by `/transaction/:id/verify`)
+    submission_poll_interval: Option<Duration>,
+    /// Whether stores are checked against the fullnode's chain id
+    chain_id_check: ChainIdCheck,
+    /// The fullnode's chain id, once fetched
+    fullnode_chain_id: FullnodeChainId,
+    /// Refuse to store BCS that is not canonical
+    reject_non_canonical: bool,
+    /// Retrievals this close to `expiration_timestamp_secs` are flagged
+    expiration_warning_secs: u64,
+    /// Maximum body and hex payload sizes
+    limits: Limits,
+    /// Checks applied to API requests before they reach a handler
+    guards: RequestGuards,
+    /// Pushes transaction events to `GET /ws` subscribers
+    notifier: notify::Notifier,
+    /// Callback URLs POSTed to as signatures arrive
+    webhooks: webhook::Webhooks,
+    /// Corrupts retrieved BCS on purpose (None = never)
+    chaos: Option<chaos::Chaos>,
+    /// Held from a store's idempotency check until its write, and from a
+    /// signature's checks against the stored transaction until it is written
+    /// (merged, for a partial `MultiEd25519` or `MultiKey` signature), so
+    /// concurrent partials aren't lost
+    transaction_locks: locks::TransactionLocks,
+}
+
+/// Retrieval-time re-serialization settings
+struct Reserialization {
+    /// Whether to deserialize/re-serialize using Rust SDK; switched at runtime
+    /// by `POST /admin/mode`
+    enabled: AtomicBool,
+    /// Answer `500` with a mismatch report instead of returning changed bytes
+    strict: bool,
+    /// Decoder used unless a request picks another
+    strategy: reserialize::Strategy,
+}
+
+/// Optional per-request checks, each disabled when `None`
+#[derive(Default)]
+struct RequestGuards {
+    /// Keys accepted in `x-api-key`
+    api_keys: Option<auth::ApiKeys>,
+    /// Bearer token validation
+    jwt: Option<auth::JwtAuth>,
+    /// HMAC verification of write requests
+    signing: Option<signing::RequestSigning>,
+    /// Per-client request quota
+    rate_limit: Option<Arc<ratelimit::RateLimit>>,
+}
+
+impl AppState {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        store: Arc<dyn TransactionStore>,
+        reserialize: Reserialization,
+        expiry: Option<Arc<Expiry>>,
+        compression: Option<Compression>,
+        backup_dir: PathBuf,
+        fullnode: Option<FullnodeClient>,
+        submission_poll_interval: Option<Duration>,
+        chain_id_check: ChainIdCheck,
+        reject_non_canonical: bool,
+        expiration_warning_secs: u64,
+        limits: Limits,
+        guards: RequestGuards,
+        webhooks: webhook::Webhooks,
+        chaos: Option<chaos::Chaos>,
+    ) -> Self {
+        Self {
+            store,
+            reserialize,
+            expiry,
+            compression,
+            backup_dir,
+            started_at: Instant::now(),
+            reserialize_mismatches: AtomicU64::new(0),
+            reserialize_failures: AtomicU64::new(0),
+            fullnode,
+            submission_poll_interval,
+            chain_id_check,
+            fullnode_chain_id: FullnodeChainId::default(),
+            reject_non_canonical,
+            expiration_warning_secs,
+            limits,
+            guards,
+            notifier: notify::Notifier::default(),
+            webhooks,
+            chaos,
+            transaction_locks: locks::TransactionLocks::default(),
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        Mode::from_reserialize(self.reserialize.enabled.load(Ordering::Relaxed))
+    }
+
+    /// Switch the retrieval mode, returning the previous one
+    fn set_mode(&self, mode: Mode) -> Mode {
+        let previous = self
+            .reserialize
+            .enabled
+            .swap(mode == Mode::Reserialize, Ordering::Relaxed);
+        Mode::from_reserialize(previous)
+    }
+
+    /// Where `tx`'s signing session stands now
+    fn session_state(&self, tx: &StoredTransaction) -> SessionState {
+        let decoded = tx
+            .bcs()
+            .ok()
+            .and_then(|bcs| DecodedTransaction::decode(&bcs).ok());
+        self.session_state_decoded(tx, decoded.as_ref())
+    }
+
+    /// [`session_state`](Self::session_state) when `tx` is already decoded
+    fn session_state_decoded(
+        &self,
+        tx: &StoredTransaction,
+        decoded: Option<&DecodedTransaction>,
+    ) -> SessionState {
+        let ttl_expired = self
+            .expiry
+            .as_ref()
+            .is_some_and(|expiry| expiry.is_expired(tx.stored_at));
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        SessionState::of(tx, decoded, ttl_expired, now)
+    }
+}
+
+/// Append to a transaction's history. Failures are only logged: the history
+/// is diagnostic and shouldn't fail the request it describes.
+async fn record_history(state: &AppState, transaction_id: &str, events: Vec<TransactionEvent>) {
+    state.notifier.publish(transaction_id, &events);
+    if let Err(e) = state.store.append_history(transaction_id, events).await {
+        warn!("Failed to record history: {}", e);
+    }
+}
+
+/// Signers other than the sender who haven't signed `tx` yet, or `None` if it
+/// doesn't decode
+fn missing_signers(tx: &StoredTransaction) -> Option<Vec<String>> {
+    let decoded = DecodedTransaction::decode(&tx.bcs().ok()?).ok()?;
+    Some(decoded.missing_signers(&tx.current_secondary_signatures(), tx.fee_payer_signed()))
+}
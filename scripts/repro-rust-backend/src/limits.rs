@@ -0,0 +1,254 @@
+//! Size limits for request bodies and hex payloads.
+//!
+//! `MAX_BODY_BYTES` (default 4 MiB) caps the HTTP body of `POST /transaction`
+//! and `POST /signature`; the batch and import endpoints keep their own larger
+//! limits. `MAX_BCS_HEX_LEN` (default 2 MiB, enough for a 1 MiB transaction)
+//! and `MAX_SIGNATURE_HEX_LEN` (default 64 KiB) cap the hex fields, counted in
+//! characters. Anything over a limit gets a `413` naming the limit, instead of
+//! multi-megabyte garbage being accepted or a bare plain-text rejection.
+
+use crate::config::LimitArgs;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// Default `MAX_BODY_BYTES`
+pub const DEFAULT_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// Default `MAX_BCS_HEX_LEN`
+pub const DEFAULT_BCS_HEX_LEN: usize = 2 * 1024 * 1024;
+/// Default `MAX_SIGNATURE_HEX_LEN`
+pub const DEFAULT_SIGNATURE_HEX_LEN: usize = 64 * 1024;
+
+/// Configured maximum sizes
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Bytes of HTTP body for the single-item write endpoints
+    pub body_bytes: usize,
+    /// Characters of `bcs_hex`, including any `0x` prefix
+    pub bcs_hex_len: usize,
+    /// Characters of `signature_hex`, including any `0x` prefix
+    pub signature_hex_len: usize,
+}
+
+impl Limits {
+    pub fn from_config(config: &LimitArgs) -> Self {
+        Self {
+            body_bytes: config.max_body_bytes,
+            bcs_hex_len: config.max_bcs_hex_len,
+            signature_hex_len: config.max_signature_hex_len,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            body_bytes: DEFAULT_BODY_BYTES,
+            bcs_hex_len: DEFAULT_BCS_HEX_LEN,
+            signature_hex_len: DEFAULT_SIGNATURE_HEX_LEN,
+        }
+    }
+}
+
+/// Which limit a request exceeded, returned alongside a `413`
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct LimitExceeded {
+    /// `body`, `bcs_hex` or `signature_hex`
+    pub field: &'static str,
+    /// The limit (bytes for `body`, hex characters otherwise)
+    pub max: usize,
+    /// The size received, if known (a streamed body is cut off at the limit)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<usize>,
+}
+
+impl LimitExceeded {
+    /// `Some` if `actual` is over `max`
+    pub fn check(field: &'static str, max: usize, actual: usize) -> Option<Self> {
+        (actual > max).then_some(Self {
+            field,
+            max,
+            actual: Some(actual),
+        })
+    }
+
+    pub fn message(&self) -> String {
+        let unit = if self.field == "body" { "bytes" } else { "hex characters" };
+        match self.actual {
+            Some(actual) => format!(
+                "{} is {} {}, over the limit of {}",
+                self.field, actual, unit, self.max
+            ),
+            None => format!("{} is over the limit of {} {}", self.field, self.max, unit),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BodyTooLargeResponse {
+    success: bool,
+    limit_exceeded: LimitExceeded,
+    message: String,
+}
+
+fn body_too_large(limit: LimitExceeded) -> Response {
+    warn!("Rejected request: {}", limit.message());
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(BodyTooLargeResponse {
+            success: false,
+            message: limit.message(),
+            limit_exceeded: limit,
+        }),
+    )
+        .into_response()
+}
+
+/// Answers `413` with a JSON body when the request body is over `max` bytes.
+///
+/// Must be paired with a `DefaultBodyLimit::max(max)` on the same route: a
+/// declared `Content-Length` is checked up front, and the plain-text `413`
+/// axum produces for an oversized streamed body is replaced.
+pub async fn reject_oversized(State(max): State<usize>, request: Request, next: Next) -> Response {
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    if let Some(limit) = content_length.and_then(|len| LimitExceeded::check("body", max, len)) {
+        return body_too_large(limit);
+    }
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE
+        && response.headers().get(header::CONTENT_TYPE)
+            != Some(&header::HeaderValue::from_static("application/json"))
+    {
+        return body_too_large(LimitExceeded {
+            field: "body",
+            max,
+            actual: None,
+        });
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::Router;
+    use serde_json::Value;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+    use tower_http::cors::CorsLayer;
+
+    const BODY_BYTES: usize = 1024;
+
+    fn app() -> Router {
+        let limits = Limits {
+            body_bytes: BODY_BYTES,
+            ..Limits::default()
+        };
+        crate::server::app(
+            crate::testing::state(0, None, limits),
+            CorsLayer::new(),
+            None,
+        )
+    }
+
+    /// `POST path` with a JSON body of `len` bytes, declaring its length if
+    /// `content_length`; answers the status and JSON body, if any
+    async fn post(path: &str, len: usize, content_length: bool) -> (StatusCode, Option<Value>) {
+        let body = format!("\"{}\"", "a".repeat(len - 2));
+        let mut request = Request::post(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+        if content_length {
+            request = request.header(header::CONTENT_LENGTH, len);
+        }
+        let response = app()
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).ok())
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_get_a_json_413_on_every_write_route() {
+        let routes = [
+            "/transaction",
+            "/v1/transaction",
+            "/signature",
+            "/fee-payer-signature",
+            "/debug/generate",
+            "/transaction/tx/assemble",
+            "/transaction/tx/submit",
+            "/v1/transaction/tx/decline",
+            "/graphql",
+        ];
+        for route in routes {
+            let (status, body) = post(route, BODY_BYTES + 1, true).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{}", route);
+            let body = body.unwrap();
+            assert_eq!(body["success"], false);
+            assert_eq!(body["limit_exceeded"]["field"], "body");
+            assert_eq!(body["limit_exceeded"]["max"], BODY_BYTES, "{}", route);
+            assert_eq!(body["limit_exceeded"]["actual"], BODY_BYTES + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn undeclared_oversized_body_is_cut_off() {
+        for route in ["/transaction", "/signature", "/transaction/tx/submit"] {
+            let (status, body) = post(route, 4 * BODY_BYTES, false).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{}", route);
+            let limit = &body.unwrap()["limit_exceeded"];
+            assert_eq!(limit["max"], BODY_BYTES);
+            assert!(limit.get("actual").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn per_route_limits_override_the_global_one() {
+        // Too large for the single-item routes, but not for these: the body
+        // is read and only its shape is rejected
+        for route in ["/transactions/batch", "/transactions/get", "/admin/import"] {
+            let (status, _) = post(route, 4 * BODY_BYTES, true).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", route);
+        }
+        let (status, _) = post("/compare", 2 * BODY_BYTES, true).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, body) = post("/compare", 2 * BODY_BYTES + 1, true).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body.unwrap()["limit_exceeded"]["max"], 2 * BODY_BYTES);
+    }
+
+    #[tokio::test]
+    async fn bodies_at_the_limit_reach_the_handler() {
+        let (status, _) = post("/transaction", BODY_BYTES, true).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn hex_limits_count_characters() {
+        assert!(LimitExceeded::check("bcs_hex", 4, 4).is_none());
+        let limit = LimitExceeded::check("bcs_hex", 4, 6).unwrap();
+        assert_eq!(
+            limit.message(),
+            "bcs_hex is 6 hex characters, over the limit of 4"
+        );
+    }
+}
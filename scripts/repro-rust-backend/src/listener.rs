@@ -0,0 +1,105 @@
+//! Where the HTTP API listens.
+//!
+//! By default the server listens on all interfaces on `--port`. `--bind`
+//! picks another TCP address, or `unix:/path/backend.sock` for a Unix domain
+//! socket, so signer daemons on the same host reach the backend without it
+//! opening a network port, e.g. on shared CI hosts.
+//!
+//! Unix socket peers have no IP address. Rate limiting counts them all as
+//! `127.0.0.1`, like in-process journal and scenario requests, so without an
+//! API key they share one quota.
+
+use crate::config::ServerArgs;
+use crate::shutdown::Shutdown;
+use axum::Router;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Address the HTTP API listens on
+#[derive(Clone, Debug)]
+pub enum Bind {
+    /// `host:port`
+    Tcp(String),
+    /// A Unix domain socket at this path
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bind::Tcp(addr) => f.write_str(addr),
+            Bind::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// `--bind` if set, else all interfaces on `--port`
+pub fn bind(config: &ServerArgs) -> Result<Bind, String> {
+    let Some(bind) = &config.bind else {
+        return Ok(Bind::Tcp(format!("0.0.0.0:{}", config.port)));
+    };
+    let Some(path) = bind.strip_prefix("unix:") else {
+        return Ok(Bind::Tcp(bind.clone()));
+    };
+    if path.is_empty() {
+        return Err("--bind unix: needs a socket path".to_string());
+    }
+    if cfg!(not(unix)) {
+        return Err("Unix domain sockets are only supported on Unix".to_string());
+    }
+    if config.tls_cert_path.is_some() {
+        return Err("--bind unix: can't be combined with --tls-cert-path".to_string());
+    }
+    Ok(Bind::Unix(PathBuf::from(path)))
+}
+
+/// Serve `app` on a Unix socket at `path` until `shutdown` is requested and
+/// in-flight requests have drained, then remove the socket
+#[cfg(unix)]
+pub async fn serve_unix(path: &Path, app: Router, shutdown: Shutdown) -> std::io::Result<()> {
+    use axum::extract::ConnectInfo;
+    use axum::Extension;
+    use std::io::{Error, ErrorKind};
+    use std::net::SocketAddr;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    // A socket left behind by a run that didn't shut down would fail the
+    // bind; one a server still answers on, or any other file, is kept
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        Ok(_) if UnixStream::connect(path).is_ok() => {
+            return Err(Error::new(
+                ErrorKind::AddrInUse,
+                format!("another server is listening on {}", path.display()),
+            ));
+        }
+        Ok(_) => std::fs::remove_file(path)?,
+        Err(_) => {}
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let localhost = SocketAddr::from(([127, 0, 0, 1], 0));
+    let app = app.layer(Extension(ConnectInfo(localhost)));
+    let server = axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown.clone().requested());
+    let result = tokio::select! {
+        result = server => result,
+        _ = shutdown.drain_deadline() => Ok(()),
+    };
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+pub async fn serve_unix(path: &Path, _app: Router, _shutdown: Shutdown) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("can't listen on {}: not a Unix platform", path.display()),
+    ))
+}
@@ -0,0 +1,329 @@
+//! `load-test` subcommand: drives a running backend with synthetic sessions.
+//!
+//! Each session generates a sender and secondary signers, then repeatedly
+//! stores a fresh multi-agent transaction, attaches a real Ed25519 signature
+//! from every secondary signer, retrieves the transaction and deletes it, so
+//! the store stays the same size however long the run. Latencies are recorded
+//! per step and reported as percentiles together with error rates once the
+//! duration is up.
+
+use crate::config::LoadTestArgs;
+use aptos_sdk::account::Ed25519Account;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::authenticator::AccountAuthenticator;
+use aptos_sdk::transaction::types::MultiAgentRawTransaction;
+use aptos_sdk::transaction::{EntryFunction, RawTransaction, TransactionPayload};
+use aptos_sdk::types::{AccountAddress, ChainId, Identifier, MoveModuleId};
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::json;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinSet;
+
+/// The requests of one session iteration, in the order they are made
+#[derive(Clone, Copy)]
+enum Step {
+    Store,
+    Sign,
+    Retrieve,
+    Delete,
+}
+
+impl Step {
+    const ALL: [Step; 4] = [Step::Store, Step::Sign, Step::Retrieve, Step::Delete];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Step::Store => "store",
+            Step::Sign => "signature",
+            Step::Retrieve => "retrieve",
+            Step::Delete => "delete",
+        }
+    }
+}
+
+/// Latencies and failures of one step
+#[derive(Default)]
+struct StepStats {
+    latencies: Vec<Duration>,
+    errors: u64,
+    /// Kept to show why requests failed without logging every one
+    first_error: Option<String>,
+}
+
+impl StepStats {
+    fn merge(&mut self, other: StepStats) {
+        self.latencies.extend(other.latencies);
+        self.errors += other.errors;
+        if self.first_error.is_none() {
+            self.first_error = other.first_error;
+        }
+    }
+
+    /// The latency below which `fraction` of successful requests fell
+    fn percentile(&self, fraction: f64) -> Duration {
+        let index = ((self.latencies.len() - 1) as f64 * fraction).round() as usize;
+        self.latencies[index]
+    }
+}
+
+/// Per-step results of one or more sessions, indexed by `Step`
+#[derive(Default)]
+struct Stats([StepStats; 4]);
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        for (mine, theirs) in self.0.iter_mut().zip(other.0) {
+            mine.merge(theirs);
+        }
+    }
+}
+
+/// Run the load test described by `args` and print the report
+pub async fn run(args: LoadTestArgs) -> Result<(), String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(args.timeout_secs))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+    let base = format!("{}/v1", args.url.trim_end_matches('/'));
+    let payload = aptos_bcs::to_bytes(&vec![0xab_u8; args.payload_bytes])
+        .map_err(|e| format!("Failed to encode payload: {}", e))?;
+    // Ids from an earlier run would conflict (409) with this run's bytes
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    println!(
+        "Load testing {} with {} sessions for {}s ({} payload bytes, {} secondary signers)",
+        args.url, args.concurrency, args.duration_secs, args.payload_bytes, args.secondary_signers
+    );
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(args.duration_secs);
+    let mut sessions = JoinSet::new();
+    for index in 0..args.concurrency.get() {
+        let session = Session {
+            client: client.clone(),
+            base: base.clone(),
+            api_key: args.api_key.clone(),
+            payload: payload.clone(),
+            prefix: format!("load-{}-{}", run_id, index),
+            sender: Ed25519Account::generate(),
+            secondaries: (0..args.secondary_signers)
+                .map(|_| Ed25519Account::generate())
+                .collect(),
+        };
+        sessions.spawn(session.run(deadline));
+    }
+
+    let mut stats = Stats::default();
+    while let Some(result) = sessions.join_next().await {
+        stats.merge(result.map_err(|e| format!("Load test session failed: {}", e))?);
+    }
+    report(&mut stats, started.elapsed());
+    Ok(())
+}
+
+struct Session {
+    client: Client,
+    base: String,
+    api_key: Option<String>,
+    payload: Vec<u8>,
+    prefix: String,
+    sender: Ed25519Account,
+    secondaries: Vec<Ed25519Account>,
+}
+
+impl Session {
+    async fn run(self, deadline: Instant) -> Stats {
+        let mut stats = Stats::default();
+        let mut sequence_number = 0;
+        while Instant::now() < deadline {
+            let transaction_id = format!("{}-{}", self.prefix, sequence_number);
+            let transaction = self.transaction(sequence_number);
+            sequence_number += 1;
+
+            let bcs = match aptos_bcs::to_bytes(&transaction) {
+                Ok(bcs) => bcs,
+                Err(e) => {
+                    record(&mut stats, Step::Store, Err(e.to_string()));
+                    continue;
+                }
+            };
+            let store = self.request(Method::POST, "/transaction").json(&json!({
+                "transaction_id": transaction_id,
+                "bcs_hex": hex::encode(&bcs),
+            }));
+            if !self.time(&mut stats, Step::Store, store).await {
+                continue;
+            }
+
+            let signing_message = match transaction.signing_message() {
+                Ok(message) => message,
+                Err(e) => {
+                    record(&mut stats, Step::Sign, Err(e.to_string()));
+                    continue;
+                }
+            };
+            for signer in &self.secondaries {
+                let authenticator = AccountAuthenticator::ed25519(
+                    signer.public_key().to_bytes().to_vec(),
+                    signer.sign_message(&signing_message).to_bytes().to_vec(),
+                );
+                let signature = match aptos_bcs::to_bytes(&authenticator) {
+                    Ok(signature) => signature,
+                    Err(e) => {
+                        record(&mut stats, Step::Sign, Err(e.to_string()));
+                        continue;
+                    }
+                };
+                let sign = self.request(Method::POST, "/signature").json(&json!({
+                    "transaction_id": transaction_id,
+                    "signer_address": signer.address().to_long_string(),
+                    "signature_hex": hex::encode(&signature),
+                }));
+                self.time(&mut stats, Step::Sign, sign).await;
+            }
+
+            let path = format!("/transaction/{}", transaction_id);
+            let retrieve = self.request(Method::GET, &path);
+            self.time(&mut stats, Step::Retrieve, retrieve).await;
+            let delete = self.request(Method::DELETE, &path);
+            self.time(&mut stats, Step::Delete, delete).await;
+        }
+        stats
+    }
+
+    /// A multi-agent transaction from this session's sender, expiring well
+    /// after the run so the backend never rejects it as expired
+    fn transaction(&self, sequence_number: u64) -> MultiAgentRawTransaction {
+        let module = MoveModuleId::new(
+            AccountAddress::ONE,
+            Identifier::new("load_test").expect("valid identifier"),
+        );
+        let payload = TransactionPayload::EntryFunction(EntryFunction::new(
+            module,
+            "store_blob",
+            vec![],
+            vec![self.payload.clone()],
+        ));
+        let expiration = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            + 3600;
+        let raw_txn = RawTransaction::new(
+            self.sender.address(),
+            sequence_number,
+            payload,
+            200_000,
+            100,
+            expiration,
+            ChainId::testnet(),
+        );
+        let secondary_signers = self
+            .secondaries
+            .iter()
+            .map(Ed25519Account::address)
+            .collect();
+        MultiAgentRawTransaction::new(raw_txn, secondary_signers)
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.base, path));
+        match &self.api_key {
+            Some(key) => request.header(crate::auth::API_KEY_HEADER, key),
+            None => request,
+        }
+    }
+
+    /// Send `request`, recording its latency under `step`; true if it succeeded
+    async fn time(&self, stats: &mut Stats, step: Step, request: RequestBuilder) -> bool {
+        let started = Instant::now();
+        let result = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                // Read the body so the latency covers the whole response
+                match response.bytes().await {
+                    Ok(_) => Ok(started.elapsed()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            Ok(response) => Err(format!("HTTP {}", response.status())),
+            Err(e) => Err(e.to_string()),
+        };
+        let succeeded = result.is_ok();
+        record(stats, step, result);
+        succeeded
+    }
+}
+
+fn record(stats: &mut Stats, step: Step, result: Result<Duration, String>) {
+    let step = &mut stats.0[step as usize];
+    match result {
+        Ok(latency) => step.latencies.push(latency),
+        Err(e) => {
+            step.errors += 1;
+            step.first_error.get_or_insert(e);
+        }
+    }
+}
+
+fn report(stats: &mut Stats, elapsed: Duration) {
+    let total: usize = stats
+        .0
+        .iter()
+        .map(|step| step.latencies.len() + step.errors as usize)
+        .sum();
+    println!();
+    println!(
+        "{} requests in {:.1}s ({:.0} req/s)",
+        total,
+        elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64()
+    );
+    println!();
+    println!(
+        "{:<10} {:>9} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "step", "requests", "errors", "p50", "p90", "p99", "max"
+    );
+    for step in Step::ALL {
+        let stats = &mut stats.0[step as usize];
+        let requests = stats.latencies.len() as u64 + stats.errors;
+        let error_rate = if requests == 0 {
+            0.0
+        } else {
+            stats.errors as f64 * 100.0 / requests as f64
+        };
+        let errors = format!("{:.1}%", error_rate);
+        if stats.latencies.is_empty() {
+            println!(
+                "{:<10} {:>9} {:>8} {:>10} {:>10} {:>10} {:>10}",
+                step.as_str(),
+                requests,
+                errors,
+                "-",
+                "-",
+                "-",
+                "-"
+            );
+        } else {
+            stats.latencies.sort_unstable();
+            println!(
+                "{:<10} {:>9} {:>8} {:>10.2?} {:>10.2?} {:>10.2?} {:>10.2?}",
+                step.as_str(),
+                requests,
+                errors,
+                stats.percentile(0.5),
+                stats.percentile(0.9),
+                stats.percentile(0.99),
+                stats.percentile(1.0)
+            );
+        }
+    }
+    for step in Step::ALL {
+        if let Some(error) = &stats.0[step as usize].first_error {
+            println!("First {} error: {}", step.as_str(), error);
+        }
+    }
+}
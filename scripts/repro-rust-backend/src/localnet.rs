@@ -0,0 +1,125 @@
+//! Local testnet for hermetic end-to-end runs.
+//!
+//! `--with-local-testnet` starts `aptos node run-local-testnet` before the
+//! server and uses its node as `--fullnode-url`, so submit, gas and sequence
+//! number checks work in CI without a shared network. The testnet starts from
+//! scratch every time (in `LOCAL_TESTNET_DIR`, which also holds the CLI's
+//! log), the server waits for it to report ready, and it is stopped when the
+//! server exits.
+//!
+//! The CLI serves the node API on port 8080, the faucet on 8081 and its
+//! readiness check on 8070, so those ports must be free.
+
+use crate::config::ServerArgs;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+use tracing::{info, warn};
+
+/// Node REST API of the local testnet
+const NODE_URL: &str = "http://127.0.0.1:8080/v1";
+/// Faucet of the local testnet
+const FAUCET_URL: &str = "http://127.0.0.1:8081";
+/// Answers `200` once the node and faucet are up
+const READY_URL: &str = "http://127.0.0.1:8070/";
+/// How often readiness is polled while starting
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A running local testnet, stopped when dropped
+pub struct LocalTestnet {
+    child: Child,
+    log_path: PathBuf,
+}
+
+impl LocalTestnet {
+    /// Returns `None` unless `--with-local-testnet` is set; otherwise starts
+    /// the testnet and waits until it is ready
+    pub async fn from_config(config: &ServerArgs) -> Result<Option<Self>, String> {
+        if !config.with_local_testnet {
+            return Ok(None);
+        }
+        let dir = &config.local_testnet_dir;
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        let log_path = dir.join("cli.log");
+        let log = std::fs::File::create(&log_path)
+            .map_err(|e| format!("failed to create {}: {}", log_path.display(), e))?;
+        let stderr = log
+            .try_clone()
+            .map_err(|e| format!("failed to open {}: {}", log_path.display(), e))?;
+
+        info!(cli = %config.aptos_cli.display(), "Starting local testnet");
+        let child = Command::new(&config.aptos_cli)
+            .args([
+                "node",
+                "run-local-testnet",
+                "--force-restart",
+                "--assume-yes",
+            ])
+            .arg("--test-dir")
+            .arg(dir.join("testnet"))
+            .stdin(Stdio::null())
+            .stdout(log)
+            .stderr(stderr)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("failed to run {}: {}", config.aptos_cli.display(), e))?;
+        let mut testnet = Self { child, log_path };
+
+        let timeout = Duration::from_secs(config.local_testnet_timeout_secs);
+        testnet.wait_until_ready(timeout).await?;
+        info!(url = NODE_URL, "Local testnet ready");
+        Ok(Some(testnet))
+    }
+
+    async fn wait_until_ready(&mut self, timeout: Duration) -> Result<(), String> {
+        let http = reqwest::Client::new();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self.child.try_wait().map_err(|e| e.to_string())? {
+                return Err(format!(
+                    "local testnet exited with {} before it was ready; see {}",
+                    status,
+                    self.log_path.display()
+                ));
+            }
+            let ready = http
+                .get(READY_URL)
+                .timeout(POLL_INTERVAL)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            if ready {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "local testnet not ready after {}s; see {}",
+                    timeout.as_secs(),
+                    self.log_path.display()
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Node REST API URL, used as `--fullnode-url`
+    pub fn url(&self) -> &'static str {
+        NODE_URL
+    }
+
+    /// Faucet URL, for funding accounts in tests
+    pub fn faucet_url(&self) -> &'static str {
+        FAUCET_URL
+    }
+
+    /// Stop the testnet and wait for it to exit
+    pub async fn stop(mut self) {
+        if let Err(e) = self.child.kill().await {
+            warn!("Failed to stop local testnet: {}", e);
+        } else {
+            info!("Local testnet stopped");
+        }
+    }
+}
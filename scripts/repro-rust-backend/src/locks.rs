@@ -0,0 +1,117 @@
+//! Per-transaction locks.
+//!
+//! The stores only offer single-key `get`/`put`/`update_signature`, so a
+//! handler that reads a transaction and writes it back based on what it
+//! read (the idempotency check before a store, the session and signer
+//! checks before a signature is written, a partial signature merge)
+//! would race another request for the same id. [`TransactionLocks`] hands
+//! out one async mutex per id for the duration of such a read-modify-write;
+//! requests for different ids never wait on each other. Entries are dropped
+//! with the last guard, so the map only holds ids being written right now.
+//!
+//! The locks are per process: two servers sharing a database still rely on
+//! the store's own last-writer-wins.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::OwnedMutexGuard;
+
+type Entry = Arc<tokio::sync::Mutex<()>>;
+
+#[derive(Default)]
+pub struct TransactionLocks {
+    locks: Mutex<HashMap<String, Entry>>,
+}
+
+/// Held while a transaction is read and written back
+pub struct TransactionLock<'a> {
+    locks: &'a TransactionLocks,
+    transaction_id: String,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl TransactionLocks {
+    /// Wait for exclusive access to `transaction_id`
+    pub async fn lock(&self, transaction_id: &str) -> TransactionLock<'_> {
+        let entry = self
+            .locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(transaction_id.to_string())
+            .or_default()
+            .clone();
+        TransactionLock {
+            locks: self,
+            transaction_id: transaction_id.to_string(),
+            guard: Some(entry.lock_owned().await),
+        }
+    }
+
+    /// Ids currently locked or waited on
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+impl Drop for TransactionLock<'_> {
+    fn drop(&mut self) {
+        // Release first so the map's count below only sees waiters
+        drop(self.guard.take());
+        let mut locks = self
+            .locks
+            .locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Waiters clone the entry under this map lock, so a count of one
+        // means nobody else can be holding or about to take it
+        if locks
+            .get(&self.transaction_id)
+            .is_some_and(|entry| Arc::strong_count(entry) == 1)
+        {
+            locks.remove(&self.transaction_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn same_id_is_serialized() {
+        let locks = Arc::new(TransactionLocks::default());
+        let inside = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (locks, inside) = (locks.clone(), inside.clone());
+                tokio::spawn(async move {
+                    let _lock = locks.lock("tx1").await;
+                    assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    inside.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(locks.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn different_ids_dont_wait() {
+        let locks = TransactionLocks::default();
+        let _first = locks.lock("tx1").await;
+        let second = tokio::time::timeout(Duration::from_secs(1), locks.lock("tx2")).await;
+        assert!(second.is_ok());
+        assert_eq!(locks.len(), 2);
+        drop(second);
+        assert_eq!(locks.len(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+//! Log setup and per-request spans.
+//!
+//! Every request gets an `x-request-id` (the client's, if it sent one, else a
+//! fresh UUID) which is echoed in the response and attached to all log lines
+//! emitted while handling it, so interleaved flows from several signers can be
+//! told apart. Verbosity is controlled with `RUST_LOG`.
+//!
+//! `LOG_FORMAT=json` switches to one JSON object per line, with event fields
+//! (`transaction_id`, `sender`, `sequence_number`, byte lengths, `mismatch`)
+//! at the top level and the request span under `span`, for jq or ELK.
+
+use axum::http::Request;
+use tracing::Span;
+use tracing_subscriber::EnvFilter;
+
+/// Used when `RUST_LOG` is unset: the backend's own debug output, everything else at info
+const DEFAULT_FILTER: &str = "info,repro_rust_backend=debug";
+
+/// Log output format (`--log-format` / `LOG_FORMAT`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+/// Install the global log subscriber
+pub fn init(format: LogFormat) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_env_filter(filter)
+            .init(),
+    }
+}
+
+/// Span wrapping a single request, tagged with its request id
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("-");
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+    )
+}
@@ -0,0 +1,170 @@
+//! Push notifications of signing progress over WebSocket.
+//!
+//! `GET /ws` upgrades to a WebSocket on which a client subscribes to
+//! transaction ids and is sent each event recorded for them: the transaction
+//! being stored, each signature arriving, assembly, submission, a sequence
+//! refresh, or a re-serialization mismatch. The frontend can then wait for
+//! the other signers instead of polling `GET /transaction/:id` in a loop.
+//!
+//! Subscribe with `?transaction_id=` when connecting or by sending
+//! `{"action":"subscribe","transaction_id":"..."}` (`unsubscribe` to stop).
+//! Each event arrives as its history entry plus `transaction_id`. Retrievals
+//! are not pushed, so a client that also polls doesn't hear its own reads.
+
+use crate::storage::{EventKind, TransactionEvent};
+use crate::AppState;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, warn};
+use utoipa::{IntoParams, ToSchema};
+
+/// Events buffered for subscribers that fall behind; older ones are dropped
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Fans recorded events out to every open WebSocket
+pub struct Notifier {
+    sender: broadcast::Sender<Notification>,
+}
+
+/// An event pushed to subscribers of its transaction
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Notification {
+    pub transaction_id: String,
+    #[serde(flatten)]
+    pub event: TransactionEvent,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl Notifier {
+    /// Push `events` of `transaction_id` to its subscribers, skipping reads
+    pub fn publish(&self, transaction_id: &str, events: &[TransactionEvent]) {
+        for event in events {
+            if matches!(event.kind, EventKind::Retrieved | EventKind::Reserialized) {
+                continue;
+            }
+            // Fails only when nobody is connected
+            let _ = self.sender.send(Notification {
+                transaction_id: transaction_id.to_string(),
+                event: event.clone(),
+            });
+        }
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+
+    /// Open WebSockets and pending `GET /transaction/:id/wait` calls
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct WsQuery {
+    /// Transaction to subscribe to right away
+    transaction_id: Option<String>,
+}
+
+/// What a client sends on the socket
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe { transaction_id: String },
+    Unsubscribe { transaction_id: String },
+}
+
+#[derive(Serialize)]
+struct ErrorMessage {
+    error: String,
+}
+
+/// Subscribe to transaction events over WebSocket
+#[utoipa::path(
+    get,
+    path = "/ws",
+    params(WsQuery),
+    responses(
+        (status = 101, description = "Switched to WebSocket; events are sent as JSON text messages", body = Notification),
+    )
+)]
+pub async fn ws(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WsQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let events = state.notifier.subscribe();
+    let subscribed: HashSet<String> = query.transaction_id.into_iter().collect();
+    upgrade.on_upgrade(move |socket| serve(socket, subscribed, events))
+}
+
+async fn serve(
+    mut socket: WebSocket,
+    mut subscribed: HashSet<String>,
+    mut events: broadcast::Receiver<Notification>,
+) {
+    debug!(subscribed = ?subscribed, "WebSocket connected");
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Subscribe { transaction_id }) => {
+                            subscribed.insert(transaction_id);
+                        }
+                        Ok(ClientMessage::Unsubscribe { transaction_id }) => {
+                            subscribed.remove(&transaction_id);
+                        }
+                        Err(e) => {
+                            let error = ErrorMessage {
+                                error: format!("Invalid message: {}", e),
+                            };
+                            if !send(&mut socket, &error).await {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames are ignored
+                Some(Ok(_)) => {}
+            },
+            notification = events.recv() => match notification {
+                Ok(notification) if subscribed.contains(&notification.transaction_id) => {
+                    if !send(&mut socket, &notification).await {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(missed, "WebSocket subscriber fell behind; events dropped");
+                }
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+    debug!("WebSocket disconnected");
+}
+
+/// Send `message` as JSON text, returning `false` once the socket is gone
+async fn send(socket: &mut WebSocket, message: &impl Serialize) -> bool {
+    let text = serde_json::to_string(message).expect("notifications serialize");
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
@@ -0,0 +1,61 @@
+//! OpenAPI description of the HTTP API.
+//!
+//! Served at `GET /openapi.json` so the TypeScript frontend can generate a
+//! typed client instead of hand-writing fetch calls. Paths are relative to
+//! `/v1`; the legacy unversioned paths accept the same requests.
+
+use axum::Json;
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Multi-agent repro backend",
+        description = "Stores serialized multi-agent transactions and secondary signatures"
+    ),
+    servers((url = "/v1")),
+    paths(
+        crate::server::store::store_transaction,
+        crate::server::store::store_transactions_batch,
+        crate::server::store::generate_transactions,
+        crate::server::signatures::store_signature,
+        crate::server::signatures::store_fee_payer_signature,
+        crate::server::signatures::decline_transaction,
+        crate::server::retrieve::get_transaction,
+        crate::server::retrieve::get_transactions_batch,
+        crate::server::retrieve::delete_transaction,
+        crate::server::retrieve::wait_for_signatures,
+        crate::server::retrieve::get_transaction_history,
+        crate::server::inspect::get_signing_message,
+        crate::server::inspect::get_decoded_transaction,
+        crate::server::inspect::explain_transaction,
+        crate::server::inspect::get_transaction_diff,
+        crate::server::inspect::compare_transactions,
+        crate::notify::ws,
+        crate::server::submit::assemble_transaction,
+        crate::server::submit::submit_transaction,
+        crate::server::submit::verify_transaction,
+        crate::server::fullnode::refresh_sequence,
+        crate::server::fullnode::account_sequence,
+        crate::server::fullnode::get_transaction_gas,
+        crate::server::retrieve::list_transactions,
+        crate::server::inspect::canonicalize,
+        crate::server::status::stats,
+        crate::server::status::version,
+        crate::server::status::health,
+        crate::probes::healthz,
+        crate::probes::readyz,
+        crate::admin::export,
+        crate::admin::import,
+        crate::admin::backup,
+        crate::admin::restore,
+        crate::admin::get_mode,
+        crate::admin::set_mode,
+    )
+)]
+struct ApiDoc;
+
+/// The generated OpenAPI document
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,123 @@
+//! Kubernetes-style liveness and readiness probes.
+//!
+//! `GET /healthz` only says the process is up and serving. `GET /readyz`
+//! also checks every dependency — the storage backend and, if `FULLNODE_URL`
+//! is set, the fullnode — and answers `503` if any of them fails, so an
+//! orchestrator stops routing traffic until they recover.
+
+use crate::AppState;
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::AptosConfig;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// How long a single dependency check may take before it counts as failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DependencyCheck {
+    /// `storage` or `fullnode`
+    name: String,
+    status: ProbeStatus,
+    /// How long the check took
+    latency_ms: u64,
+    /// Why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProbeResponse {
+    /// `ok` only if every check passed
+    status: ProbeStatus,
+    checks: Vec<DependencyCheck>,
+}
+
+/// Client for the fullnode at `url` (None if not configured)
+pub fn fullnode_client(url: Option<&str>) -> Result<Option<FullnodeClient>, String> {
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    let config = AptosConfig::custom(url)
+        .map_err(|e| format!("invalid FULLNODE_URL `{}`: {}", url, e))?
+        .with_timeout(CHECK_TIMEOUT)
+        .without_retry();
+    FullnodeClient::new(config)
+        .map(Some)
+        .map_err(|e| format!("failed to create fullnode client: {}", e))
+}
+
+/// Liveness: the process is up and serving requests
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses((status = 200, description = "Process is alive", body = ProbeResponse))
+)]
+pub async fn healthz() -> Json<ProbeResponse> {
+    Json(ProbeResponse {
+        status: ProbeStatus::Ok,
+        checks: Vec::new(),
+    })
+}
+
+/// Readiness: storage (and the fullnode, if configured) can be reached
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Every dependency is reachable", body = ProbeResponse),
+        (status = 503, description = "At least one dependency failed", body = ProbeResponse),
+    )
+)]
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<ProbeResponse>) {
+    let mut checks = vec![check("storage", state.store.ping()).await];
+    if let Some(fullnode) = &state.fullnode {
+        checks.push(check("fullnode", fullnode.get_ledger_info()).await);
+    }
+
+    let ready = checks.iter().all(|check| check.status == ProbeStatus::Ok);
+    let (status_code, status) = if ready {
+        (StatusCode::OK, ProbeStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, ProbeStatus::Unavailable)
+    };
+    (status_code, Json(ProbeResponse { status, checks }))
+}
+
+/// Run one dependency check, bounded by [`CHECK_TIMEOUT`]
+async fn check<T, E: std::fmt::Display>(
+    name: &str,
+    probe: impl Future<Output = Result<T, E>>,
+) -> DependencyCheck {
+    let started = Instant::now();
+    let error = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    if let Some(e) = &error {
+        warn!("Readiness check `{}` failed: {}", name, e);
+    }
+    DependencyCheck {
+        name: name.to_string(),
+        status: if error.is_none() {
+            ProbeStatus::Ok
+        } else {
+            ProbeStatus::Unavailable
+        },
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
@@ -0,0 +1,185 @@
+//! Optional per-client rate limiting.
+//!
+//! When `RATE_LIMIT_PER_SEC` is set, each client may make that many requests
+//! per second on average, with bursts of up to `RATE_LIMIT_BURST` (default
+//! the per-second rate). Requests carrying one of the configured `API_KEYS`
+//! are limited per key, everything else per client IP, so a runaway test
+//! loop hammering `POST /transaction` only throttles itself. Limiting runs
+//! before authentication, so an unknown key counts against the client's IP
+//! rather than a fresh quota of its own. Over-limit requests get
+//! `429 Too Many Requests` with a `Retry-After` header. The health probes are
+//! not limited.
+
+use crate::auth::{ApiKeys, API_KEY_HEADER};
+use crate::config::SecurityArgs;
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often state for clients that have gone quiet is dropped
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Request quota shared by every client key
+pub struct RateLimit {
+    per_sec: NonZeroU32,
+    burst: NonZeroU32,
+    limiter: DefaultKeyedRateLimiter<String>,
+}
+
+impl RateLimit {
+    /// Returns `None` if no rate is configured (no limit)
+    pub fn from_config(config: &SecurityArgs) -> Option<Self> {
+        let per_sec = config.rate_limit_per_sec?;
+        let burst = config.rate_limit_burst.unwrap_or(per_sec);
+        let quota = Quota::per_second(per_sec).allow_burst(burst);
+        Some(Self {
+            per_sec,
+            burst,
+            limiter: RateLimiter::keyed(quota),
+        })
+    }
+
+    pub fn per_sec(&self) -> u32 {
+        self.per_sec.get()
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst.get()
+    }
+
+    /// `Err` with how long to wait if `key` is over its quota
+    fn check(&self, key: &str) -> Result<(), Duration> {
+        self.limiter
+            .check_key(&key.to_string())
+            .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+}
+
+/// Whose quota a request counts against: its API key if it is one of
+/// `api_keys`, else the client's IP
+fn client_key(api_keys: Option<&ApiKeys>, api_key: Option<&str>, ip: IpAddr) -> String {
+    match api_key.filter(|key| api_keys.is_some_and(|keys| keys.contains(key))) {
+        Some(api_key) => format!("key:{}", api_key),
+        None => format!("ip:{}", ip),
+    }
+}
+
+/// Periodically forget clients whose quota has fully replenished
+pub fn spawn_cleanup(rate_limit: Arc<RateLimit>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            rate_limit.limiter.retain_recent();
+            rate_limit.limiter.shrink_to_fit();
+        }
+    });
+}
+
+#[derive(Serialize)]
+struct RateLimitedResponse {
+    success: bool,
+    message: String,
+}
+
+/// Rejects requests over the client's quota when `RATE_LIMIT_PER_SEC` is set
+pub async fn limit(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(rate_limit) = &state.guards.rate_limit else {
+        return next.run(request).await;
+    };
+
+    let api_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let key = client_key(state.guards.api_keys.as_ref(), api_key, peer.ip());
+
+    if let Err(wait) = rate_limit.check(&key) {
+        // Round up so clients never retry before the quota has replenished
+        let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        warn!(
+            client = %peer.ip(),
+            "Rate limited request (retry after {}s)",
+            retry_after
+        );
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(RateLimitedResponse {
+                success: false,
+                message: format!(
+                    "Rate limit of {}/s (burst {}) exceeded; retry after {}s",
+                    rate_limit.per_sec(),
+                    rate_limit.burst(),
+                    retry_after
+                ),
+            }),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limit() -> RateLimit {
+        let one = NonZeroU32::new(1).unwrap();
+        RateLimit {
+            per_sec: one,
+            burst: one,
+            limiter: RateLimiter::keyed(Quota::per_second(one)),
+        }
+    }
+
+    fn api_keys() -> ApiKeys {
+        ApiKeys::new(&["known".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn unknown_keys_share_the_ip_quota() {
+        let rate_limit = rate_limit();
+        let api_keys = api_keys();
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        let check =
+            |api_key: &str| rate_limit.check(&client_key(Some(&api_keys), Some(api_key), ip));
+
+        assert!(check("random-1").is_ok());
+        // A fresh made-up key per request doesn't buy a fresh quota
+        assert!(check("random-2").is_err());
+        assert!(check("random-3").is_err());
+        // A configured key has its own
+        assert!(check("known").is_ok());
+        assert!(check("known").is_err());
+    }
+
+    #[test]
+    fn keys_are_ignored_without_api_keys() {
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        assert_eq!(client_key(None, Some("anything"), ip), "ip:10.0.0.1");
+        assert_eq!(
+            client_key(Some(&api_keys()), Some("known"), ip),
+            "key:known"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Reconciliation of stored signatures with the bytes they sign.
+//!
+//! Signatures are verified when they arrive, but the bytes under them can
+//! change afterwards: a signature attached while `refresh-sequence` rebuilds
+//! the transaction lands next to the new bytes, and `/admin/import` or a
+//! restored backup may bring signatures that were never verified here. With
+//! `SIGNATURE_CHECK_INTERVAL_SECS` set, a background task re-verifies the
+//! Ed25519 and secp256k1 signatures of every session still collecting
+//! signatures against the stored bytes that often.
+//!
+//! A signature that no longer verifies is marked stale with a
+//! `signature_stale` event, pushed to `/ws` subscribers and sent to callbacks
+//! as a `signature_stale` webhook. It stays stored, but until its signer signs
+//! again they are back in `signers_pending` and their signature is left out
+//! of assembly. Each stale signature is reported once.
+
+use crate::authenticator::AccountAuthenticator;
+use crate::session::Operation;
+use crate::storage::{Signer, StoredTransaction, TransactionEvent};
+use crate::transaction::DecodedTransaction;
+use crate::{webhook, AppState};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Signers of `tx` whose signature no longer verifies, and why, leaving out
+/// those already marked stale
+fn stale(tx: &StoredTransaction) -> Vec<(Signer, String)> {
+    let Some(decoded) = tx
+        .bcs()
+        .ok()
+        .and_then(|bcs| DecodedTransaction::decode(&bcs).ok())
+    else {
+        return Vec::new();
+    };
+    let marked = tx.stale_signers();
+    let secondaries = tx
+        .secondary_signatures
+        .keys()
+        .map(|address| Signer::Secondary(address.clone()));
+    let fee_payer = tx.fee_payer_signature.as_ref().map(|_| Signer::FeePayer);
+    secondaries
+        .chain(fee_payer)
+        .filter(|signer| !marked.contains(signer.name()))
+        .filter_map(|signer| {
+            let reason = check(&decoded, &signer, tx.signature(&signer)?)?;
+            Some((signer, reason))
+        })
+        .collect()
+}
+
+/// Why `signer`'s `signature` doesn't verify against `decoded`, or `None` if
+/// it does or its scheme is left to the chain
+fn check(decoded: &DecodedTransaction, signer: &Signer, signature: &[u8]) -> Option<String> {
+    let authenticator = match AccountAuthenticator::decode(signature) {
+        Ok(authenticator) => authenticator,
+        Err(e) => return Some(format!("doesn't decode: {}", e)),
+    };
+    match decoded.verify_self_consistent(&authenticator, *signer == Signer::FeePayer) {
+        Ok(Some(false)) => Some("doesn't verify against the stored bytes".to_string()),
+        Err(e) => Some(format!("doesn't verify: {}", e)),
+        Ok(Some(true) | None) => None,
+    }
+}
+
+/// Mark the stale signatures of every session still collecting signatures,
+/// returning how many were marked
+async fn reconcile(state: &AppState) -> usize {
+    let transactions = match state.store.list().await {
+        Ok(transactions) => transactions,
+        Err(e) => {
+            error!("Failed to list transactions: {}", e);
+            return 0;
+        }
+    };
+
+    let mut marked = 0;
+    for (transaction_id, tx) in transactions {
+        if !state.session_state(&tx).allows(Operation::Sign) {
+            continue;
+        }
+        let stale = stale(&tx);
+        if stale.is_empty() {
+            continue;
+        }
+        // Read back, so a signer who signed again since the listing isn't
+        // marked stale for the signature they replaced
+        let mut current = match state.store.get(&transaction_id).await {
+            Ok(Some(current)) if current.raw_bcs == tx.raw_bcs => current,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("Failed to look up {}: {}", transaction_id, e);
+                continue;
+            }
+        };
+        for (signer, reason) in stale {
+            if current.signature(&signer) != tx.signature(&signer) {
+                continue;
+            }
+            warn!(
+                transaction_id = %transaction_id,
+                signer = signer.name(),
+                "Stale signature: {}",
+                reason
+            );
+            let event = TransactionEvent::stale(&signer, reason.clone());
+            let at_ms = event.at_ms;
+            current.history.push(event.clone());
+            crate::record_history(state, &transaction_id, vec![event]).await;
+            if state.webhooks.wants(&transaction_id) {
+                state.webhooks.send(
+                    webhook::WebhookPayload {
+                        event: webhook::WebhookEvent::SignatureStale,
+                        transaction_id: transaction_id.clone(),
+                        signer: signer.name().to_string(),
+                        missing_signers: crate::missing_signers(&current).unwrap_or_default(),
+                        reason: Some(reason),
+                        at_ms,
+                    },
+                    state.guards.signing.as_ref(),
+                );
+            }
+            marked += 1;
+        }
+    }
+    marked
+}
+
+/// Spawn the background task re-verifying signatures every `interval`
+pub fn spawn(state: Arc<AppState>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let marked = reconcile(&state).await;
+            if marked > 0 {
+                info!("Marked {} signatures stale", marked);
+            }
+        }
+    });
+}
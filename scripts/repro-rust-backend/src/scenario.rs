@@ -0,0 +1,397 @@
+//! Scripted repro scenarios.
+//!
+//! `--scenario repro.yaml` runs the steps of a YAML file against the API
+//! in-process, in order, then exits: store a transaction, wait, attach
+//! signatures, retrieve it in either mode, submit it, and so on. A step can
+//! assert the status it gets (any `2xx` if it doesn't), the session state,
+//! the fullnode's `vm_status`, and for retrievals that the BCS came back
+//! byte-for-byte as stored. The first failing step stops the run with exit
+//! code 1, so ad-hoc curl scripts become versioned, assertable repro cases.
+//!
+//! ```yaml
+//! name: reserialize keeps the bytes
+//! steps:
+//!   - store: { id: tx-a, bcs_hex: "0x…" }
+//!   - wait: 30
+//!   - sign: { id: tx-a, signer: "0xb0b", signature_hex: "0x…" }
+//!   - get: { id: tx-a, mode: reserialize, expect: { bcs_unchanged: true } }
+//!   - submit: { id: tx-a, sender_signature_hex: "0x…" }
+//! ```
+//!
+//! Like a journal replay, scenarios run without the API key, JWT, HMAC and
+//! rate limit checks.
+
+use crate::config::Mode;
+use crate::reserialize::{RESERIALIZE_HEADER, STRATEGY_HEADER};
+use crate::session::SessionState;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    http::{header, Method, StatusCode},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tower::ServiceExt;
+use tracing::info;
+
+/// Largest response body read back
+const RESPONSE_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    steps: Vec<Step>,
+}
+
+/// One step; all but `wait` are a request to the API
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum Step {
+    /// `POST /transaction`
+    Store {
+        id: String,
+        bcs_hex: String,
+        #[serde(default)]
+        kind: Option<String>,
+        #[serde(default)]
+        quorum: Option<u32>,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `POST /signature`
+    Sign {
+        id: String,
+        signer: String,
+        signature_hex: String,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `POST /fee-payer-signature`
+    FeePayerSign {
+        id: String,
+        signature_hex: String,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `POST /transaction/:id/decline`
+    Decline {
+        id: String,
+        signer: String,
+        reason: String,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `GET /transaction/:id`, in the server's mode unless `mode` is given
+    Get {
+        id: String,
+        #[serde(default)]
+        mode: Option<Mode>,
+        /// `typed`, `raw-transaction` or `untyped`
+        #[serde(default)]
+        strategy: Option<String>,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `POST /transaction/:id/submit`
+    Submit {
+        id: String,
+        sender_signature_hex: String,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// `DELETE /transaction/:id`
+    Delete {
+        id: String,
+        #[serde(default)]
+        force: bool,
+        #[serde(default)]
+        expect: Expect,
+    },
+    /// Sleep this many seconds
+    Wait(u64),
+}
+
+/// What a step's response must look like
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expect {
+    /// Exact status; any `2xx` if omitted
+    status: Option<u16>,
+    session_state: Option<SessionState>,
+    /// The fullnode's rejection, e.g. `SEQUENCE_NUMBER_TOO_OLD`
+    vm_status: Option<String>,
+    /// `bcs_hex` of a `get` equals what the scenario stored under that id
+    #[serde(default)]
+    bcs_unchanged: bool,
+}
+
+/// A request to send for a step
+struct StepRequest {
+    method: Method,
+    path: String,
+    headers: Vec<(&'static str, String)>,
+    body: Option<Value>,
+}
+
+impl Step {
+    fn describe(&self) -> String {
+        match self {
+            Step::Store { id, .. } => format!("store {}", id),
+            Step::Sign { id, signer, .. } => format!("sign {} as {}", id, signer),
+            Step::FeePayerSign { id, .. } => format!("sign {} as fee payer", id),
+            Step::Decline { id, signer, .. } => format!("decline {} as {}", id, signer),
+            Step::Get { id, mode, .. } => match mode {
+                Some(mode) => format!("get {} ({})", id, mode.as_str()),
+                None => format!("get {}", id),
+            },
+            Step::Submit { id, .. } => format!("submit {}", id),
+            Step::Delete { id, .. } => format!("delete {}", id),
+            Step::Wait(secs) => format!("wait {}s", secs),
+        }
+    }
+
+    /// The request this step sends and what its response must look like;
+    /// `None` for `wait`
+    fn request(&self) -> Option<(StepRequest, &Expect)> {
+        let post = |path: String, body: Value| StepRequest {
+            method: Method::POST,
+            path,
+            headers: Vec::new(),
+            body: Some(body),
+        };
+        let transaction = |id: &str| format!("/v1/transaction/{}", id);
+        Some(match self {
+            Step::Store {
+                id,
+                bcs_hex,
+                kind,
+                quorum,
+                expect,
+            } => {
+                let body = json!({
+                    "transaction_id": id,
+                    "bcs_hex": bcs_hex,
+                    "kind": kind,
+                    "quorum": quorum,
+                });
+                (post("/v1/transaction".to_string(), body), expect)
+            }
+            Step::Sign {
+                id,
+                signer,
+                signature_hex,
+                expect,
+            } => {
+                let body = json!({
+                    "transaction_id": id,
+                    "signer_address": signer,
+                    "signature_hex": signature_hex,
+                });
+                (post("/v1/signature".to_string(), body), expect)
+            }
+            Step::FeePayerSign {
+                id,
+                signature_hex,
+                expect,
+            } => {
+                let body = json!({ "transaction_id": id, "signature_hex": signature_hex });
+                (post("/v1/fee-payer-signature".to_string(), body), expect)
+            }
+            Step::Decline {
+                id,
+                signer,
+                reason,
+                expect,
+            } => {
+                let body = json!({ "signer_address": signer, "reason": reason });
+                (post(format!("{}/decline", transaction(id)), body), expect)
+            }
+            Step::Get {
+                id,
+                mode,
+                strategy,
+                expect,
+            } => {
+                let mut headers = Vec::new();
+                if let Some(mode) = mode {
+                    let reserialize = if *mode == Mode::Reserialize { "1" } else { "0" };
+                    headers.push((RESERIALIZE_HEADER, reserialize.to_string()));
+                }
+                if let Some(strategy) = strategy {
+                    headers.push((STRATEGY_HEADER, strategy.clone()));
+                }
+                let get = StepRequest {
+                    method: Method::GET,
+                    path: transaction(id),
+                    headers,
+                    body: None,
+                };
+                (get, expect)
+            }
+            Step::Submit {
+                id,
+                sender_signature_hex,
+                expect,
+            } => {
+                let body = json!({ "sender_signature_hex": sender_signature_hex });
+                (post(format!("{}/submit", transaction(id)), body), expect)
+            }
+            Step::Delete { id, force, expect } => {
+                let path = if *force {
+                    format!("{}?force=1", transaction(id))
+                } else {
+                    transaction(id)
+                };
+                let delete = StepRequest {
+                    method: Method::DELETE,
+                    path,
+                    headers: Vec::new(),
+                    body: None,
+                };
+                (delete, expect)
+            }
+            Step::Wait(_) => return None,
+        })
+    }
+}
+
+/// Send `request` through `app` as if from localhost, returning the status
+/// and the JSON body (`null` if it isn't JSON)
+async fn send(app: &Router, request: StepRequest) -> Result<(StatusCode, Value), String> {
+    let mut builder = Request::builder()
+        .method(request.method)
+        .uri(&request.path)
+        .extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0))));
+    for (name, value) in &request.headers {
+        builder = builder.header(*name, value);
+    }
+    let body = match request.body {
+        Some(body) => {
+            builder = builder.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let request = builder.body(body).map_err(|e| e.to_string())?;
+
+    let response = app
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|infallible| match infallible {});
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), RESPONSE_BODY_LIMIT)
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    Ok((
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    ))
+}
+
+/// Why `status` and `body` don't meet `expect`, if they don't. `stored` is
+/// the hex the scenario stored the transaction with.
+fn check(
+    expect: &Expect,
+    status: StatusCode,
+    body: &Value,
+    stored: Option<&str>,
+) -> Result<(), String> {
+    match expect.status {
+        Some(expected) if status.as_u16() != expected => {
+            return Err(format!(
+                "expected status {}, got {}",
+                expected,
+                status.as_u16()
+            ))
+        }
+        None if !status.is_success() => {
+            return Err(format!("expected success, got {}", status.as_u16()))
+        }
+        _ => {}
+    }
+    if let Some(expected) = expect.session_state {
+        let actual = body["session_state"].as_str();
+        if actual != Some(expected.as_str()) {
+            return Err(format!(
+                "expected session_state {}, got {}",
+                expected.as_str(),
+                actual.unwrap_or("none")
+            ));
+        }
+    }
+    if let Some(expected) = &expect.vm_status {
+        let actual = body["vm_status"].as_str();
+        if actual != Some(expected.as_str()) {
+            return Err(format!(
+                "expected vm_status {}, got {}",
+                expected,
+                actual.unwrap_or("none")
+            ));
+        }
+    }
+    if expect.bcs_unchanged {
+        let stored = stored.ok_or("bcs_unchanged needs an earlier store of the same id")?;
+        let returned = body["bcs_hex"]
+            .as_str()
+            .ok_or("no bcs_hex in the response")?;
+        if normalize_hex(returned) != normalize_hex(stored) {
+            return Err(format!("BCS changed: stored {}, got {}", stored, returned));
+        }
+    }
+    Ok(())
+}
+
+fn normalize_hex(hex: &str) -> String {
+    hex.trim_start_matches("0x").to_ascii_lowercase()
+}
+
+/// Run the scenario at `path` through `app`, returning how many steps passed
+/// (all of them) or why it failed
+pub async fn run(app: Router, path: &Path) -> Result<usize, String> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let scenario: Scenario = serde_yaml::from_str(&contents).map_err(|e| e.to_string())?;
+    info!(
+        "Running scenario '{}' ({} steps)",
+        scenario.name,
+        scenario.steps.len()
+    );
+
+    // bcs_hex each transaction was stored with, for `bcs_unchanged`
+    let mut stored: HashMap<String, String> = HashMap::new();
+    for (index, step) in scenario.steps.iter().enumerate() {
+        let label = format!("step {} ({})", index + 1, step.describe());
+        let Some((request, expect)) = step.request() else {
+            info!("{}", label);
+            if let Step::Wait(secs) = step {
+                tokio::time::sleep(Duration::from_secs(*secs)).await;
+            }
+            continue;
+        };
+
+        let (status, body) = send(&app, request)
+            .await
+            .map_err(|e| format!("{}: {}", label, e))?;
+        let stored_hex = match step {
+            Step::Store { id, bcs_hex, .. } if status.is_success() => {
+                stored.insert(id.clone(), bcs_hex.clone());
+                None
+            }
+            Step::Get { id, .. } => stored.get(id).map(String::as_str),
+            _ => None,
+        };
+        check(expect, status, &body, stored_hex)
+            .map_err(|reason| format!("{}: {}; response: {}", label, reason, body))?;
+        info!("{}: {}", label, status.as_u16());
+    }
+    info!("Scenario '{}' passed", scenario.name);
+    Ok(scenario.steps.len())
+}
@@ -0,0 +1,740 @@
+//! Fullnode lookups: sequence numbers, gas estimates and refreshing a stale
+//! sequence number.
+
+use crate::gas::{self, GasEstimate, GasIssue};
+use crate::session::{Operation, SessionState};
+use crate::storage::{hex_bytes, EventKind, StoredTransaction, TransactionEvent};
+use crate::transaction::DecodedTransaction;
+use crate::AppState;
+use aptos_sdk::{aptos_bcs, AccountAddress};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct RefreshSequenceResponse {
+    success: bool,
+    transaction_id: String,
+    /// Sequence number the transaction was stored with
+    previous_sequence_number: Option<u64>,
+    /// The sender's current on-chain sequence number, now in the transaction
+    sequence_number: Option<u64>,
+    /// BCS of the rebuilt transaction, as hex
+    bcs_hex: Option<String>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+/// How a stored transaction's sequence number compares to its sender's
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceStatus {
+    /// Below the on-chain sequence number: submitting it is guaranteed to fail
+    /// with `SEQUENCE_NUMBER_TOO_OLD`
+    TooOld,
+    /// The next sequence number the account can use
+    Current,
+    /// Above it: it waits on the transactions before it
+    Ahead,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StoredSequence {
+    transaction_id: String,
+    sequence_number: u64,
+    status: SequenceStatus,
+    session_state: SessionState,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AccountSequenceResponse {
+    success: bool,
+    /// Long-form address
+    address: Option<String>,
+    /// The account's on-chain sequence number (0 if the account doesn't exist
+    /// yet)
+    sequence_number: Option<u64>,
+    /// Stored transactions from this sender, by sequence number
+    transactions: Vec<StoredSequence>,
+    /// How many of `transactions` are `too_old`
+    too_old: usize,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GasResponse {
+    success: bool,
+    transaction_id: String,
+    gas_unit_price: Option<u64>,
+    max_gas_amount: Option<u64>,
+    /// `max_gas_amount * gas_unit_price`, the most the gas payer can be
+    /// charged, in octas
+    max_fee: Option<u64>,
+    /// Long-form address of the fee payer, or of the sender if there is none
+    gas_payer: Option<String>,
+    /// The gas payer's APT balance, in octas
+    gas_payer_balance: Option<u64>,
+    estimate: Option<GasEstimate>,
+    /// Empty when the gas settings are viable
+    issues: Vec<GasIssue>,
+    /// False if any issue would make the transaction fail on chain
+    viable: Option<bool>,
+    message: String,
+}
+
+/// Rebuild a transaction with its sender's current sequence number.
+/// Fetches the sequence number from the configured fullnode, re-stores the transaction with it and
+/// drops the signatures collected so far, since they signed the old bytes.
+#[utoipa::path(
+    post,
+    path = "/transaction/{transaction_id}/refresh-sequence",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Transaction rebuilt, or already current", body = RefreshSequenceResponse),
+        (status = 400, description = "Transaction is a `SignedTransaction`", body = RefreshSequenceResponse),
+        (status = 404, description = "Unknown transaction", body = RefreshSequenceResponse),
+        (status = 409, description = "Transaction was submitted or finalized", body = RefreshSequenceResponse),
+        (status = 410, description = "Transaction has expired", body = RefreshSequenceResponse),
+        (status = 422, description = "Stored BCS does not decode as a transaction", body = RefreshSequenceResponse),
+        (status = 500, description = "Storage error", body = RefreshSequenceResponse),
+        (status = 502, description = "The fullnode could not be reached or failed", body = RefreshSequenceResponse),
+        (status = 503, description = "No fullnode configured", body = RefreshSequenceResponse),
+    )
+)]
+pub async fn refresh_sequence(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<RefreshSequenceResponse>) {
+    info!(transaction_id = %transaction_id, "Refreshing sequence number");
+
+    let Some(fullnode) = &state.fullnode else {
+        warn!("No fullnode configured");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(RefreshSequenceResponse {
+                success: false,
+                transaction_id,
+                previous_sequence_number: None,
+                sequence_number: None,
+                bcs_hex: None,
+                session_state: None,
+                message: "No fullnode configured (set --fullnode-url)".to_string(),
+            }),
+        );
+    };
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: None,
+                    sequence_number: None,
+                    bcs_hex: None,
+                    session_state: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: None,
+                    sequence_number: None,
+                    bcs_hex: None,
+                    session_state: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: None,
+                    sequence_number: None,
+                    bcs_hex: None,
+                    session_state: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let decoded = match tx
+        .bcs()
+        .map_err(|e| e.to_string())
+        .and_then(|bcs| DecodedTransaction::decode(&bcs).map_err(|e| e.to_string()))
+    {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            warn!("Stored BCS does not decode: {}", e);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: None,
+                    sequence_number: None,
+                    bcs_hex: None,
+                    session_state: None,
+                    message: format!("Stored BCS does not decode as a transaction: {}", e),
+                }),
+            );
+        }
+    };
+    let session_state = state.session_state_decoded(&tx, Some(&decoded));
+    if let Err((status, message)) = session_state.check(Operation::RefreshSequence) {
+        warn!("{}", message);
+        return (
+            status,
+            Json(RefreshSequenceResponse {
+                success: false,
+                transaction_id,
+                previous_sequence_number: None,
+                sequence_number: None,
+                bcs_hex: None,
+                session_state: Some(session_state),
+                message,
+            }),
+        );
+    }
+    let previous = decoded.raw_txn().sequence_number;
+    let sender = decoded.raw_txn().sender;
+
+    let current = match fullnode.get_sequence_number(sender).await {
+        Ok(current) => current,
+        Err(e) => {
+            error!("Failed to fetch sequence number of {}: {}", sender, e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: Some(previous),
+                    sequence_number: None,
+                    bcs_hex: None,
+                    session_state: Some(session_state),
+                    message: format!("Failed to fetch sequence number: {}", e),
+                }),
+            );
+        }
+    };
+    if current == previous {
+        info!(transaction_id = %transaction_id, sequence_number = current, "Sequence number already current");
+        return (
+            StatusCode::OK,
+            Json(RefreshSequenceResponse {
+                success: true,
+                transaction_id,
+                previous_sequence_number: Some(previous),
+                sequence_number: Some(current),
+                bcs_hex: None,
+                session_state: Some(session_state),
+                message: "Sequence number already current".to_string(),
+            }),
+        );
+    }
+
+    let Some(rebuilt) = decoded.with_sequence_number(current) else {
+        warn!("Signed transactions can't be rebuilt");
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RefreshSequenceResponse {
+                success: false,
+                transaction_id,
+                previous_sequence_number: Some(previous),
+                sequence_number: Some(current),
+                bcs_hex: None,
+                session_state: Some(session_state),
+                message: "Signed transactions can't be rebuilt".to_string(),
+            }),
+        );
+    };
+    let raw_bcs = match aptos_bcs::to_bytes(&rebuilt) {
+        Ok(raw_bcs) => raw_bcs,
+        Err(e) => {
+            error!("Failed to encode rebuilt transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: Some(previous),
+                    sequence_number: Some(current),
+                    bcs_hex: None,
+                    session_state: Some(session_state),
+                    message: format!("Failed to encode rebuilt transaction: {}", e),
+                }),
+            );
+        }
+    };
+    let bcs_hex = hex_bytes::encode(&raw_bcs, tx.hex_prefixed);
+    let (stored_bcs, compressed) = match state.compression.map(|c| c.compress(&raw_bcs)) {
+        Some(Ok(Some(compressed))) => (compressed, true),
+        Some(Ok(None)) | None => (raw_bcs, false),
+        Some(Err(e)) => {
+            error!("Failed to compress transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RefreshSequenceResponse {
+                    success: false,
+                    transaction_id,
+                    previous_sequence_number: Some(previous),
+                    sequence_number: Some(current),
+                    bcs_hex: None,
+                    session_state: Some(session_state),
+                    message: format!("Failed to compress transaction: {}", e),
+                }),
+            );
+        }
+    };
+
+    // Signatures over the old bytes would fail on chain, so start over
+    let mut history = tx.history;
+    history.push(TransactionEvent::now(
+        EventKind::SequenceRefreshed,
+        Some(format!("{} -> {}", previous, current)),
+    ));
+    let refreshed = StoredTransaction {
+        raw_bcs: stored_bcs,
+        compressed,
+        hex_prefixed: tx.hex_prefixed,
+        secondary_signatures: BTreeMap::new(),
+        fee_payer_signature: None,
+        stored_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        sender: tx.sender,
+        sequence_number: Some(current),
+        // Re-encoded by the SDK
+        canonical: Some(true),
+        required_signers: Some(rebuilt.required_signers()),
+        quorum: tx.quorum,
+        history,
+    };
+    let refreshed_state = state.session_state_decoded(&refreshed, Some(&rebuilt));
+    if let Err(e) = state.store.put(&transaction_id, refreshed).await {
+        error!("Failed to store transaction: {}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(RefreshSequenceResponse {
+                success: false,
+                transaction_id,
+                previous_sequence_number: Some(previous),
+                sequence_number: Some(current),
+                bcs_hex: None,
+                session_state: Some(session_state),
+                message: format!("Failed to store transaction: {}", e),
+            }),
+        );
+    }
+    if let Some(expiry) = &state.expiry {
+        expiry.clear(&transaction_id);
+    }
+
+    info!(
+        transaction_id = %transaction_id,
+        previous_sequence_number = previous,
+        sequence_number = current,
+        "Sequence number refreshed"
+    );
+    (
+        StatusCode::OK,
+        Json(RefreshSequenceResponse {
+            success: true,
+            transaction_id,
+            previous_sequence_number: Some(previous),
+            sequence_number: Some(current),
+            bcs_hex: Some(bcs_hex),
+            session_state: Some(refreshed_state),
+            message: format!(
+                "Sequence number refreshed from {} to {}; signatures must be collected again",
+                previous, current
+            ),
+        }),
+    )
+}
+
+/// Compare an account's on-chain sequence number with its stored transactions.
+/// Fetches the sequence number from the configured fullnode and flags every stored transaction
+/// from that sender whose own is lower, which no signature can make succeed any more.
+#[utoipa::path(
+    get,
+    path = "/account/{address}/sequence",
+    params(("address" = String, Path, description = "Sender address, short or long form")),
+    responses(
+        (status = 200, description = "On-chain sequence number and the stored transactions it makes stale", body = AccountSequenceResponse),
+        (status = 400, description = "Invalid address", body = AccountSequenceResponse),
+        (status = 500, description = "Storage error", body = AccountSequenceResponse),
+        (status = 502, description = "The fullnode could not be reached or failed", body = AccountSequenceResponse),
+        (status = 503, description = "No fullnode configured", body = AccountSequenceResponse),
+    )
+)]
+pub async fn account_sequence(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(address): axum::extract::Path<String>,
+) -> (StatusCode, Json<AccountSequenceResponse>) {
+    info!(address = %address, "Checking stored sequence numbers");
+    let failure = |status: StatusCode, address: Option<String>, message: String| {
+        (
+            status,
+            Json(AccountSequenceResponse {
+                success: false,
+                address,
+                sequence_number: None,
+                transactions: Vec::new(),
+                too_old: 0,
+                message,
+            }),
+        )
+    };
+
+    let account = match AccountAddress::from_hex(&address) {
+        Ok(account) => account,
+        Err(e) => {
+            warn!("Invalid address: {}", e);
+            return failure(
+                StatusCode::BAD_REQUEST,
+                None,
+                format!("Invalid address: {}", e),
+            );
+        }
+    };
+    let address = account.to_long_string();
+    let Some(fullnode) = &state.fullnode else {
+        warn!("No fullnode configured");
+        return failure(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(address),
+            "No fullnode configured (set --fullnode-url)".to_string(),
+        );
+    };
+
+    let on_chain = match fullnode.get_sequence_number(account).await {
+        Ok(on_chain) => on_chain,
+        // Nothing has been sent from an account that doesn't exist yet
+        Err(e) if e.is_not_found() => 0,
+        Err(e) => {
+            error!("Failed to fetch sequence number of {}: {}", address, e);
+            return failure(
+                StatusCode::BAD_GATEWAY,
+                Some(address),
+                format!("Failed to fetch sequence number: {}", e),
+            );
+        }
+    };
+
+    let stored = match state.store.list_by_sender(&address, 0..=u64::MAX).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Some(address),
+                format!("Failed to read from storage: {}", e),
+            );
+        }
+    };
+    let mut transactions: Vec<StoredSequence> = stored
+        .into_iter()
+        .filter(|(_, tx)| {
+            !state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.is_expired(tx.stored_at))
+        })
+        .filter_map(|(transaction_id, tx)| {
+            let sequence_number = tx.sequence_number?;
+            Some(StoredSequence {
+                status: match sequence_number.cmp(&on_chain) {
+                    std::cmp::Ordering::Less => SequenceStatus::TooOld,
+                    std::cmp::Ordering::Equal => SequenceStatus::Current,
+                    std::cmp::Ordering::Greater => SequenceStatus::Ahead,
+                },
+                session_state: state.session_state(&tx),
+                transaction_id,
+                sequence_number,
+            })
+        })
+        .collect();
+    transactions.sort_by(|a, b| {
+        (a.sequence_number, &a.transaction_id).cmp(&(b.sequence_number, &b.transaction_id))
+    });
+
+    let too_old = transactions
+        .iter()
+        .filter(|tx| tx.status == SequenceStatus::TooOld)
+        .count();
+    if too_old > 0 {
+        warn!(
+            address = %address,
+            sequence_number = on_chain,
+            too_old,
+            "Stored transactions are behind the on-chain sequence number"
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(AccountSequenceResponse {
+            success: true,
+            message: format!(
+                "On-chain sequence number {}; {} of {} stored transactions too old",
+                on_chain,
+                too_old,
+                transactions.len()
+            ),
+            address: Some(address),
+            sequence_number: Some(on_chain),
+            transactions,
+            too_old,
+        }),
+    )
+}
+
+/// Check whether a transaction's gas settings are viable on chain.
+/// Compares `gas_unit_price` against the configured fullnode's gas estimate and the maximum fee
+/// against the gas payer's balance, so underpriced gas can be told apart from serialization bugs.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/gas",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Gas assessment; `viable` is false if the transaction would fail", body = GasResponse),
+        (status = 404, description = "Unknown transaction", body = GasResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = GasResponse),
+        (status = 422, description = "Stored BCS does not decode as a transaction", body = GasResponse),
+        (status = 500, description = "Storage error", body = GasResponse),
+        (status = 502, description = "The fullnode could not be reached or failed", body = GasResponse),
+        (status = 503, description = "No fullnode configured", body = GasResponse),
+    )
+)]
+pub async fn get_transaction_gas(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<GasResponse>) {
+    info!(transaction_id = %transaction_id, "Checking gas");
+
+    let Some(fullnode) = &state.fullnode else {
+        warn!("No fullnode configured");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(GasResponse {
+                success: false,
+                transaction_id,
+                gas_unit_price: None,
+                max_gas_amount: None,
+                max_fee: None,
+                gas_payer: None,
+                gas_payer_balance: None,
+                estimate: None,
+                issues: Vec::new(),
+                viable: None,
+                message: "No fullnode configured (set --fullnode-url)".to_string(),
+            }),
+        );
+    };
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: None,
+                    max_gas_amount: None,
+                    max_fee: None,
+                    gas_payer: None,
+                    gas_payer_balance: None,
+                    estimate: None,
+                    issues: Vec::new(),
+                    viable: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: None,
+                    max_gas_amount: None,
+                    max_fee: None,
+                    gas_payer: None,
+                    gas_payer_balance: None,
+                    estimate: None,
+                    issues: Vec::new(),
+                    viable: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: None,
+                    max_gas_amount: None,
+                    max_fee: None,
+                    gas_payer: None,
+                    gas_payer_balance: None,
+                    estimate: None,
+                    issues: Vec::new(),
+                    viable: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let decoded = match tx
+        .bcs()
+        .map_err(|e| e.to_string())
+        .and_then(|bcs| DecodedTransaction::decode(&bcs).map_err(|e| e.to_string()))
+    {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            warn!("Stored BCS does not decode: {}", e);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: None,
+                    max_gas_amount: None,
+                    max_fee: None,
+                    gas_payer: None,
+                    gas_payer_balance: None,
+                    estimate: None,
+                    issues: Vec::new(),
+                    viable: None,
+                    message: format!("Stored BCS does not decode as a transaction: {}", e),
+                }),
+            );
+        }
+    };
+    let gas_unit_price = decoded.raw_txn().gas_unit_price;
+    let max_gas_amount = decoded.raw_txn().max_gas_amount;
+    let max_fee = gas::max_fee(max_gas_amount, gas_unit_price);
+    let gas_payer = decoded
+        .fee_payer_address()
+        .unwrap_or(decoded.raw_txn().sender);
+
+    let (estimate, balance) = tokio::join!(
+        fullnode.estimate_gas_price(),
+        fullnode.get_account_balance(gas_payer)
+    );
+    let estimate = match estimate {
+        Ok(response) => GasEstimate::from(&response.data),
+        Err(e) => {
+            error!("Failed to fetch gas estimate: {}", e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: Some(gas_unit_price),
+                    max_gas_amount: Some(max_gas_amount),
+                    max_fee,
+                    gas_payer: Some(gas_payer.to_long_string()),
+                    gas_payer_balance: None,
+                    estimate: None,
+                    issues: Vec::new(),
+                    viable: None,
+                    message: format!("Failed to fetch gas estimate: {}", e),
+                }),
+            );
+        }
+    };
+    let balance = match balance {
+        Ok(balance) => balance,
+        Err(e) => {
+            error!("Failed to fetch balance of {}: {}", gas_payer, e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(GasResponse {
+                    success: false,
+                    transaction_id,
+                    gas_unit_price: Some(gas_unit_price),
+                    max_gas_amount: Some(max_gas_amount),
+                    max_fee,
+                    gas_payer: Some(gas_payer.to_long_string()),
+                    gas_payer_balance: None,
+                    estimate: Some(estimate),
+                    issues: Vec::new(),
+                    viable: None,
+                    message: format!("Failed to fetch gas payer balance: {}", e),
+                }),
+            );
+        }
+    };
+
+    let issues = gas::assess(max_gas_amount, gas_unit_price, estimate, balance);
+    let viable = !issues.iter().any(|issue| issue.is_fatal());
+    let message = if issues.is_empty() {
+        "Gas settings are viable".to_string()
+    } else if viable {
+        format!("Gas settings are viable but may be slow: {:?}", issues)
+    } else {
+        format!("Gas settings would fail on chain: {:?}", issues)
+    };
+    info!(
+        transaction_id = %transaction_id,
+        gas_unit_price,
+        recommended = estimate.recommended,
+        viable,
+        "Gas checked"
+    );
+    (
+        StatusCode::OK,
+        Json(GasResponse {
+            success: true,
+            transaction_id,
+            gas_unit_price: Some(gas_unit_price),
+            max_gas_amount: Some(max_gas_amount),
+            max_fee,
+            gas_payer: Some(gas_payer.to_long_string()),
+            gas_payer_balance: Some(balance),
+            estimate: Some(estimate),
+            issues,
+            viable: Some(viable),
+            message,
+        }),
+    )
+}
@@ -0,0 +1,752 @@
+//! Read-only views of a stored transaction: its signing message, decoded
+//! fields, field-by-field explanation and re-serialization diff, plus
+//! `POST /compare` and `GET /type-tag`.
+
+use super::retrieve::retrieval_strategy;
+use crate::bytediff::{diff_bcs, ByteDiff};
+use crate::decode::TransactionView;
+use crate::explain::Explanation;
+use crate::storage::hex_bytes;
+use crate::transaction::{DecodedTransaction, TransactionKind};
+use crate::{reserialize, type_tag, AppState};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, ToSchema)]
+pub struct SigningMessageResponse {
+    success: bool,
+    transaction_id: String,
+    kind: Option<TransactionKind>,
+    /// `sha3_256` domain prefix followed by the BCS of what is signed, as hex
+    signing_message_hex: Option<String>,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DecodedResponse {
+    success: bool,
+    transaction_id: String,
+    decoded: Option<TransactionView>,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExplainResponse {
+    success: bool,
+    transaction_id: String,
+    explanation: Option<Explanation>,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DiffResponse {
+    success: bool,
+    transaction_id: String,
+    /// Decoder the bytes went through
+    strategy: Option<reserialize::Strategy>,
+    /// Whether the re-serialized bytes equal the stored ones
+    identical: Option<bool>,
+    diff: Option<ByteDiff>,
+    message: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CompareRequest {
+    /// First blob as hex, e.g. the TS SDK's serialization
+    left: String,
+    /// Second blob as hex, e.g. the Rust SDK's serialization of the same transaction
+    right: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CompareResponse {
+    success: bool,
+    /// Payload `left` decodes as; `None` if it doesn't decode
+    left_kind: Option<TransactionKind>,
+    /// Payload `right` decodes as; `None` if it doesn't decode
+    right_kind: Option<TransactionKind>,
+    /// Whether both decode to the same transaction; `None` unless both decode
+    equal: Option<bool>,
+    /// Decoded transaction fields that differ (empty unless both decode)
+    changed_fields: Vec<&'static str>,
+    /// Where the bytes diverge; `original` is `left` and `reserialized` is
+    /// `right`, and ranges are attributed to `left`'s fields
+    diff: Option<ByteDiff>,
+    message: String,
+}
+
+/// Compute the message signers sign for a stored transaction.
+/// The same bytes the Rust SDK signs, so wallets can be checked against them.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/signing-message",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Signing message", body = SigningMessageResponse),
+        (status = 404, description = "Unknown transaction", body = SigningMessageResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = SigningMessageResponse),
+        (status = 422, description = "Stored BCS does not decode as a transaction", body = SigningMessageResponse),
+        (status = 500, description = "Storage error", body = SigningMessageResponse),
+    )
+)]
+pub async fn get_signing_message(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<SigningMessageResponse>) {
+    info!(transaction_id = %transaction_id, "Computing signing message");
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: None,
+                    signing_message_hex: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: None,
+                    signing_message_hex: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: None,
+                    signing_message_hex: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let raw_bcs = match tx.bcs() {
+        Ok(raw_bcs) => raw_bcs,
+        Err(e) => {
+            error!("Failed to decompress transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: None,
+                    signing_message_hex: None,
+                    message: format!("Failed to decompress transaction: {}", e),
+                }),
+            );
+        }
+    };
+    let decoded = match DecodedTransaction::decode(&raw_bcs) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            warn!("Stored BCS does not decode: {}", e);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: None,
+                    signing_message_hex: None,
+                    message: format!("Stored BCS does not decode as a transaction: {}", e),
+                }),
+            );
+        }
+    };
+    match decoded.signing_message() {
+        Ok(signing_message) => {
+            debug!(
+                kind = decoded.kind().as_str(),
+                "Signing message is {} bytes",
+                signing_message.len()
+            );
+            (
+                StatusCode::OK,
+                Json(SigningMessageResponse {
+                    success: true,
+                    transaction_id,
+                    kind: Some(decoded.kind()),
+                    signing_message_hex: Some(hex_bytes::encode(&signing_message, true)),
+                    message: "Signing message computed".to_string(),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to compute signing message: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SigningMessageResponse {
+                    success: false,
+                    transaction_id,
+                    kind: Some(decoded.kind()),
+                    signing_message_hex: None,
+                    message: format!("Failed to compute signing message: {}", e),
+                }),
+            )
+        }
+    }
+}
+
+/// Decode a stored transaction into JSON.
+/// Every field the Rust SDK reads from the stored BCS, for inspection and test assertions; entry
+/// function arguments are decoded using the module ABI from the configured fullnode, if any.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/decoded",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Decoded transaction", body = DecodedResponse),
+        (status = 404, description = "Unknown transaction", body = DecodedResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = DecodedResponse),
+        (status = 422, description = "Stored BCS does not decode as a transaction", body = DecodedResponse),
+        (status = 500, description = "Storage error", body = DecodedResponse),
+    )
+)]
+pub async fn get_decoded_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<DecodedResponse>) {
+    info!(transaction_id = %transaction_id, "Decoding transaction");
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(DecodedResponse {
+                    success: false,
+                    transaction_id,
+                    decoded: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(DecodedResponse {
+                    success: false,
+                    transaction_id,
+                    decoded: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(DecodedResponse {
+                    success: false,
+                    transaction_id,
+                    decoded: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let raw_bcs = match tx.bcs() {
+        Ok(raw_bcs) => raw_bcs,
+        Err(e) => {
+            error!("Failed to decompress transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(DecodedResponse {
+                    success: false,
+                    transaction_id,
+                    decoded: None,
+                    message: format!("Failed to decompress transaction: {}", e),
+                }),
+            );
+        }
+    };
+    match DecodedTransaction::decode(&raw_bcs) {
+        Ok(decoded) => {
+            debug!(
+                kind = decoded.kind().as_str(),
+                "Decoded {} bytes",
+                raw_bcs.len()
+            );
+            (
+                StatusCode::OK,
+                Json(DecodedResponse {
+                    success: true,
+                    transaction_id,
+                    decoded: Some(TransactionView::new(&decoded, state.fullnode.as_ref()).await),
+                    message: "Transaction decoded".to_string(),
+                }),
+            )
+        }
+        Err(e) => {
+            warn!("Stored BCS does not decode: {}", e);
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(DecodedResponse {
+                    success: false,
+                    transaction_id,
+                    decoded: None,
+                    message: format!("Stored BCS does not decode as a transaction: {}", e),
+                }),
+            )
+        }
+    }
+}
+
+/// Map the byte ranges of a stored transaction's BCS to its fields.
+/// Each field's offsets, bytes and value, in layout order, so offsets from a diff or mismatch
+/// report can be attributed to a field.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/explain",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Field map", body = ExplainResponse),
+        (status = 404, description = "Unknown transaction", body = ExplainResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = ExplainResponse),
+        (status = 422, description = "Stored BCS does not decode as a transaction", body = ExplainResponse),
+        (status = 500, description = "Storage error", body = ExplainResponse),
+    )
+)]
+pub async fn explain_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<ExplainResponse>) {
+    info!(transaction_id = %transaction_id, "Decoding transaction");
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(ExplainResponse {
+                    success: false,
+                    transaction_id,
+                    explanation: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ExplainResponse {
+                    success: false,
+                    transaction_id,
+                    explanation: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ExplainResponse {
+                    success: false,
+                    transaction_id,
+                    explanation: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let raw_bcs = match tx.bcs() {
+        Ok(raw_bcs) => raw_bcs,
+        Err(e) => {
+            error!("Failed to decompress transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ExplainResponse {
+                    success: false,
+                    transaction_id,
+                    explanation: None,
+                    message: format!("Failed to decompress transaction: {}", e),
+                }),
+            );
+        }
+    };
+    match DecodedTransaction::decode(&raw_bcs) {
+        Ok(decoded) => {
+            let explanation = Explanation::new(decoded.kind(), &raw_bcs);
+            debug!(
+                kind = decoded.kind().as_str(),
+                complete = explanation.complete,
+                "Mapped {} fields over {} bytes",
+                explanation.fields.len(),
+                raw_bcs.len()
+            );
+            (
+                StatusCode::OK,
+                Json(ExplainResponse {
+                    success: true,
+                    transaction_id,
+                    explanation: Some(explanation),
+                    message: "Transaction fields mapped".to_string(),
+                }),
+            )
+        }
+        Err(e) => {
+            warn!("Stored BCS does not decode: {}", e);
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ExplainResponse {
+                    success: false,
+                    transaction_id,
+                    explanation: None,
+                    message: format!("Stored BCS does not decode as a transaction: {}", e),
+                }),
+            )
+        }
+    }
+}
+
+/// Diff a stored transaction against its re-serialization.
+/// Re-serializes on demand, whatever the server's mode, and returns every differing byte range
+/// with hexdump context from both sides.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/diff",
+    params(
+        ("transaction_id" = String, Path, description = "Id the transaction was stored under"),
+        ("x-reserialize-strategy" = Option<String>, Header, description = "`typed`, `raw-transaction` or `untyped` to override the server's reserialize strategy"),
+    ),
+    responses(
+        (status = 200, description = "Diff; `identical` is true if the bytes roundtrip unchanged", body = DiffResponse),
+        (status = 400, description = "Invalid x-reserialize-strategy header", body = DiffResponse),
+        (status = 404, description = "Unknown transaction", body = DiffResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = DiffResponse),
+        (status = 422, description = "Stored BCS does not decode with the strategy", body = DiffResponse),
+        (status = 500, description = "Storage error", body = DiffResponse),
+    )
+)]
+pub async fn get_transaction_diff(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<DiffResponse>) {
+    info!(transaction_id = %transaction_id, "Diffing re-serialized transaction");
+
+    let strategy = match retrieval_strategy(&state, &headers) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            warn!("Invalid retrieval headers: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: None,
+                    identical: None,
+                    diff: None,
+                    message: e,
+                }),
+            );
+        }
+    };
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: Some(strategy),
+                    identical: None,
+                    diff: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: Some(strategy),
+                    identical: None,
+                    diff: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            );
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: Some(strategy),
+                    identical: None,
+                    diff: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+
+    let raw_bcs = match tx.bcs() {
+        Ok(raw_bcs) => raw_bcs,
+        Err(e) => {
+            error!("Failed to decompress transaction: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: Some(strategy),
+                    identical: None,
+                    diff: None,
+                    message: format!("Failed to decompress transaction: {}", e),
+                }),
+            );
+        }
+    };
+    let reserialized = match reserialize::reserialize(strategy, &raw_bcs) {
+        Ok(reserialized) => reserialized,
+        Err(e) => {
+            warn!(
+                strategy = strategy.as_str(),
+                "Failed to re-serialize: {}", e
+            );
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(DiffResponse {
+                    success: false,
+                    transaction_id,
+                    strategy: Some(strategy),
+                    identical: None,
+                    diff: None,
+                    message: format!("Failed to re-serialize with {}: {}", strategy.as_str(), e),
+                }),
+            );
+        }
+    };
+
+    let mut diff = ByteDiff::new(&raw_bcs, &reserialized);
+    if let Ok(decoded) = DecodedTransaction::decode(&raw_bcs) {
+        diff.attribute(&Explanation::new(decoded.kind(), &raw_bcs));
+    }
+    let identical = diff.is_identical();
+    let message = match diff.first_difference {
+        None => format!("Bytes roundtrip unchanged through {}", strategy.as_str()),
+        Some(offset) => format!(
+            "{} differing ranges, first at byte {} ({} -> {} bytes)",
+            diff.range_count, offset, diff.original_len, diff.reserialized_len
+        ),
+    };
+    debug!(strategy = strategy.as_str(), identical, "{}", message);
+    (
+        StatusCode::OK,
+        Json(DiffResponse {
+            success: true,
+            transaction_id,
+            strategy: Some(strategy),
+            identical: Some(identical),
+            diff: Some(diff),
+            message,
+        }),
+    )
+}
+
+/// Compare two serialized transactions.
+/// Meant for the TS SDK's and the Rust SDK's bytes for "the same" transaction: reports whether
+/// they decode to equal transactions, which fields differ, and every byte range where they diverge.
+#[utoipa::path(
+    post,
+    path = "/compare",
+    request_body = CompareRequest,
+    responses(
+        (status = 200, description = "Comparison; `equal` is null unless both blobs decode", body = CompareResponse),
+        (status = 400, description = "A blob is not valid hex", body = CompareResponse),
+        (status = 413, description = "Body is over its size limit", body = CompareResponse),
+    )
+)]
+pub async fn compare_transactions(
+    Json(req): Json<CompareRequest>,
+) -> (StatusCode, Json<CompareResponse>) {
+    let bad_request = |message: String| {
+        warn!("{}", message);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(CompareResponse {
+                success: false,
+                left_kind: None,
+                right_kind: None,
+                equal: None,
+                changed_fields: Vec::new(),
+                diff: None,
+                message,
+            }),
+        )
+    };
+    let left = match hex_bytes::decode(&req.left) {
+        Ok((bytes, _)) => bytes,
+        Err(e) => return bad_request(format!("Invalid hex in left: {}", e)),
+    };
+    let right = match hex_bytes::decode(&req.right) {
+        Ok((bytes, _)) => bytes,
+        Err(e) => return bad_request(format!("Invalid hex in right: {}", e)),
+    };
+    info!(
+        left_len = left.len(),
+        right_len = right.len(),
+        "Comparing transactions"
+    );
+
+    let left_decoded = DecodedTransaction::decode(&left);
+    let right_decoded = DecodedTransaction::decode(&right);
+    let mut diff = ByteDiff::new(&left, &right);
+    if let Ok(decoded) = &left_decoded {
+        diff.attribute(&Explanation::new(decoded.kind(), &left));
+    }
+    let changed_fields = diff_bcs(&left, &right).changed_fields;
+
+    let equal = match (&left_decoded, &right_decoded) {
+        (Ok(a), Ok(b)) => Some(a == b),
+        _ => None,
+    };
+    let message = match (&left_decoded, &right_decoded, diff.first_difference) {
+        (_, _, None) => "Blobs are identical".to_string(),
+        (Err(e), _, _) => format!("Left does not decode as a transaction: {}", e),
+        (_, Err(e), _) => format!("Right does not decode as a transaction: {}", e),
+        (Ok(a), Ok(b), Some(offset)) if a == b => format!(
+            "Blobs decode to the same transaction but differ from byte {}",
+            offset
+        ),
+        (Ok(_), Ok(_), Some(offset)) => format!(
+            "Blobs differ from byte {}: {}",
+            offset,
+            if changed_fields.is_empty() {
+                "no decoded field differs".to_string()
+            } else {
+                changed_fields.join(", ")
+            }
+        ),
+    };
+    debug!(equal = ?equal, ranges = %diff.summary(), "{}", message);
+    (
+        StatusCode::OK,
+        Json(CompareResponse {
+            success: true,
+            left_kind: left_decoded.ok().map(|decoded| decoded.kind()),
+            right_kind: right_decoded.ok().map(|decoded| decoded.kind()),
+            equal,
+            changed_fields,
+            diff: Some(diff),
+            message,
+        }),
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TypeTagQuery {
+    /// Type tag string, e.g. `0x1::coin::CoinStore<0x1::aptos_coin::AptosCoin>`
+    tag: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TypeTagResponse {
+    success: bool,
+    /// The type tag as given
+    tag: String,
+    /// AIP-40 rendering of the parsed type tag
+    canonical: Option<String>,
+    message: String,
+}
+
+/// Parse a type tag string the way the Rust SDK does and return its
+/// canonical form, to check a string the frontend builds before it ends up
+/// in a payload
+#[utoipa::path(
+    get,
+    path = "/type-tag",
+    params(TypeTagQuery),
+    responses(
+        (status = 200, description = "Parsed type tag", body = TypeTagResponse),
+        (status = 400, description = "Not a valid type tag", body = TypeTagResponse),
+    )
+)]
+pub async fn canonicalize(
+    Query(query): Query<TypeTagQuery>,
+) -> (StatusCode, Json<TypeTagResponse>) {
+    match type_tag::parse(&query.tag) {
+        Ok(type_tag) => (
+            StatusCode::OK,
+            Json(TypeTagResponse {
+                success: true,
+                tag: query.tag,
+                canonical: Some(type_tag::canonical(&type_tag)),
+                message: "Type tag parsed".to_string(),
+            }),
+        ),
+        Err(e) => {
+            warn!("Invalid type tag `{}`: {}", query.tag, e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(TypeTagResponse {
+                    success: false,
+                    tag: query.tag,
+                    canonical: None,
+                    message: format!("Invalid type tag: {}", e),
+                }),
+            )
+        }
+    }
+}
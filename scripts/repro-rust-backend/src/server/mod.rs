@@ -0,0 +1,279 @@
+//! The HTTP API: routes and handlers, split by area.
+
+pub mod fullnode;
+pub mod inspect;
+pub mod retrieve;
+mod run;
+pub mod signatures;
+pub mod status;
+pub mod store;
+pub mod submit;
+
+use crate::{
+    admin, auth, chaos, graphql, journal, limits, logging, notify, openapi, probes, ratelimit,
+    signing, AppState,
+};
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use fullnode::{account_sequence, get_transaction_gas, refresh_sequence};
+use inspect::{
+    compare_transactions, explain_transaction, get_decoded_transaction, get_signing_message,
+    get_transaction_diff,
+};
+use retrieve::{
+    delete_transaction, get_transaction, get_transaction_history, get_transactions_batch,
+    list_transactions, wait_for_signatures,
+};
+pub use run::run;
+use signatures::{decline_transaction, store_fee_payer_signature, store_signature};
+use status::{health, stats, version};
+use std::sync::Arc;
+use store::{generate_transactions, store_transaction, store_transactions_batch};
+use submit::{assemble_transaction, submit_transaction, verify_transaction};
+use tower_http::cors::CorsLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+
+/// Largest request body accepted by the batch endpoints
+pub const BATCH_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Every API route, mounted both under `/v1` and at the legacy unversioned paths
+pub fn api_routes(state: &Arc<AppState>) -> Router<Arc<AppState>> {
+    use auth::Scope;
+    let scope =
+        |scope: Scope| middleware::from_fn_with_state((state.clone(), scope), auth::authorize);
+    let signed = || middleware::from_fn_with_state(state.clone(), signing::verify_signature);
+    let sized = |max: usize| middleware::from_fn_with_state(max, limits::reject_oversized);
+    let body_bytes = state.limits.body_bytes;
+
+    Router::new()
+        .route("/stats", get(stats).route_layer(scope(Scope::TxRead)))
+        .route("/version", get(version).route_layer(scope(Scope::TxRead)))
+        .route(
+            "/transaction",
+            post(store_transaction)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/transactions/batch",
+            post(store_transactions_batch)
+                .layer(DefaultBodyLimit::max(BATCH_BODY_LIMIT))
+                .route_layer(signed())
+                .route_layer(sized(BATCH_BODY_LIMIT))
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/debug/generate",
+            post(generate_transactions)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/transactions/get",
+            post(get_transactions_batch)
+                .layer(DefaultBodyLimit::max(BATCH_BODY_LIMIT))
+                .route_layer(sized(BATCH_BODY_LIMIT))
+                .route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/signature",
+            post(store_signature)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::SigWrite)),
+        )
+        .route(
+            "/fee-payer-signature",
+            post(store_fee_payer_signature)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::SigWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}",
+            get(get_transaction).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}",
+            delete(delete_transaction)
+                .route_layer(signed())
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}/wait",
+            get(wait_for_signatures).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/history",
+            get(get_transaction_history).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/signing-message",
+            get(get_signing_message).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/decoded",
+            get(get_decoded_transaction).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/explain",
+            get(explain_transaction).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/diff",
+            get(get_transaction_diff).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/assemble",
+            post(assemble_transaction)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::SigWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}/submit",
+            post(submit_transaction)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::SigWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}/decline",
+            post(decline_transaction)
+                .layer(DefaultBodyLimit::max(body_bytes))
+                .route_layer(signed())
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::SigWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}/verify",
+            post(verify_transaction)
+                .route_layer(signed())
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/transaction/{transaction_id}/gas",
+            get(get_transaction_gas).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transaction/{transaction_id}/refresh-sequence",
+            post(refresh_sequence)
+                .route_layer(signed())
+                .route_layer(scope(Scope::TxWrite)),
+        )
+        .route(
+            "/account/{address}/sequence",
+            get(account_sequence).route_layer(scope(Scope::TxRead)),
+        )
+        .route("/ws", get(notify::ws).route_layer(scope(Scope::TxRead)))
+        .route(
+            "/compare",
+            post(compare_transactions)
+                .layer(DefaultBodyLimit::max(2 * body_bytes))
+                .route_layer(sized(2 * body_bytes))
+                .route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/type-tag",
+            get(inspect::canonicalize).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/transactions",
+            get(list_transactions).route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/graphql",
+            get(graphql::graphiql)
+                .post_service(async_graphql_axum::GraphQL::new(graphql::schema(
+                    state.clone(),
+                )))
+                .route_layer(sized(body_bytes))
+                .route_layer(scope(Scope::TxRead)),
+        )
+        .route(
+            "/admin/export",
+            get(admin::export).route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/admin/import",
+            post(admin::import)
+                .layer(DefaultBodyLimit::max(admin::IMPORT_BODY_LIMIT))
+                .route_layer(signed())
+                .route_layer(sized(admin::IMPORT_BODY_LIMIT))
+                .route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/admin/backup",
+            post(admin::backup)
+                .route_layer(signed())
+                .route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/admin/restore",
+            post(admin::restore)
+                .route_layer(signed())
+                .route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/admin/mode",
+            get(admin::get_mode).route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/admin/mode",
+            post(admin::set_mode)
+                .route_layer(signed())
+                .route_layer(scope(Scope::Admin)),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::limit,
+        ))
+        // Probes stay open so orchestrators don't need credentials
+        .route("/health", get(health))
+        .route("/healthz", get(probes::healthz))
+        .route("/readyz", get(probes::readyz))
+}
+
+/// The API with its request id, tracing and CORS layers, ready to serve,
+/// recording requests to `journal` if given
+pub fn app(
+    state: Arc<AppState>,
+    cors: CorsLayer,
+    journal: Option<Arc<journal::Journal>>,
+) -> Router {
+    // Legacy unversioned paths stay as aliases for the existing TypeScript client
+    let mut router = Router::new()
+        .nest("/v1", api_routes(&state))
+        .merge(api_routes(&state))
+        .route("/openapi.json", get(openapi::spec));
+    if let Some(journal) = journal {
+        router = router.layer(middleware::from_fn_with_state(journal, journal::record));
+    }
+    // Outside the journal, which shouldn't record injected failures, and
+    // inside CORS, so browsers can read them
+    if state.chaos.as_ref().is_some_and(chaos::Chaos::disrupts) {
+        router = router.layer(middleware::from_fn_with_state(
+            state.clone(),
+            chaos::disrupt,
+        ));
+    }
+    router
+        .layer(cors)
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(logging::request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state)
+}
@@ -0,0 +1,1179 @@
+//! Reading transactions back: `GET /transaction/:id` (re-serialized in
+//! reserialize mode), the batch get, listing, deletion, waiting for signatures
+//! and the event history.
+
+use super::store::parse_sequence_number;
+use crate::binary::{self, Negotiated};
+use crate::bytediff::{ByteDiff, MismatchReport};
+use crate::config::Mode;
+use crate::encoding::{self, Encoding};
+use crate::reserialize::{self, RESERIALIZE_HEADER, STRATEGY_HEADER};
+use crate::session::SessionState;
+use crate::storage::{hex_bytes, Commit, EventKind, TransactionEvent};
+use crate::transaction::{AuthenticatorSummary, DecodedTransaction, ExpirationStatus};
+use crate::{etag, record_history, AppState};
+use aptos_sdk::AccountAddress;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, ToSchema)]
+pub struct GetTransactionResponse {
+    pub success: bool,
+    pub bcs_hex: Option<String>,
+    /// The BCS as base64, in place of `bcs_hex` with `x-bcs-encoding: base64`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bcs_base64: Option<String>,
+    /// Secondary signatures collected so far, keyed by long-form signer address
+    pub secondary_signatures: Option<BTreeMap<String, String>>,
+    /// Fee payer's signature, once signed (fee payer transactions only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_payer_signature_hex: Option<String>,
+    pub stored_at: Option<u64>,
+    /// Size of the BCS payload in bytes
+    pub uncompressed_size: Option<usize>,
+    /// Size actually stored, if the payload is stored compressed
+    pub compressed_size: Option<usize>,
+    /// How the re-serialized bytes differ (strict reserialize mode only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mismatch: Option<MismatchReport>,
+    /// Authenticator variant and signers, if a `SignedTransaction` is stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authenticator: Option<AuthenticatorSummary>,
+    /// Where `expiration_timestamp_secs` stands, if the BCS decodes;
+    /// `nearly_expired` within `EXPIRATION_WARNING_SECS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<ExpirationStatus>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_state: Option<SessionState>,
+    /// Required signers other than the sender yet to sign: long-form
+    /// addresses, then `fee_payer`; omitted if the BCS doesn't decode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_pending: Option<Vec<String>>,
+    /// Required signers whose signature is attached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_done: Option<Vec<String>>,
+    /// Secondary signatures that complete the session, if stored with a quorum
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quorum: Option<u32>,
+    /// Version, VM status and gas used, once `POST /transaction/:id/verify`
+    /// found the submitted transaction committed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<Commit>,
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HistoryResponse {
+    success: bool,
+    transaction_id: String,
+    /// Oldest first
+    events: Vec<TransactionEvent>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchGetRequest {
+    transaction_ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchGetEntry {
+    transaction_id: String,
+    #[serde(flatten)]
+    transaction: GetTransactionResponse,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchGetResponse {
+    success: bool,
+    /// Every requested transaction that was found, in request order
+    transactions: Vec<BatchGetEntry>,
+    /// Requested ids that don't exist (or have expired)
+    missing: Vec<String>,
+    message: String,
+}
+
+/// How long `GET /transaction/:id/wait` blocks unless `timeout_ms` is given
+pub const DEFAULT_WAIT_MS: u64 = 30_000;
+
+/// Longest `timeout_ms` honoured; larger values are clamped
+pub const MAX_WAIT_MS: u64 = 300_000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WaitQuery {
+    /// Milliseconds to wait for the signatures (default 30000, max 300000)
+    timeout_ms: Option<u64>,
+}
+
+/// Answer of `GET /transaction/:id/wait` when the signatures didn't arrive
+#[derive(Serialize, ToSchema)]
+pub struct WaitResponse {
+    success: bool,
+    transaction_id: String,
+    /// Signers still to sign: long-form addresses, or `fee_payer`
+    missing_signers: Vec<String>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteTransactionQuery {
+    /// `1` to delete even if a secondary signature is attached
+    force: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DeleteTransactionResponse {
+    success: bool,
+    transaction_id: String,
+    message: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTransactionsQuery {
+    /// Maximum number of transactions to return (default 100, max 1000)
+    limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    cursor: Option<String>,
+    /// Only transactions from this sender address
+    sender: Option<String>,
+    /// Only transactions with at least this sequence number
+    min_seq: Option<u64>,
+    /// Only transactions with at most this sequence number
+    max_seq: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TransactionSummary {
+    transaction_id: String,
+    sender: Option<String>,
+    sequence_number: Option<u64>,
+    /// Whether the BCS was canonical at ingest (`None` if not a transaction)
+    canonical: Option<bool>,
+    has_signature: bool,
+    stored_at: u64,
+    session_state: SessionState,
+    /// Required signers yet to sign, if the BCS decoded when stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signers_pending: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signers_done: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListTransactionsResponse {
+    success: bool,
+    transactions: Vec<TransactionSummary>,
+    /// Pass as `cursor` to fetch the next page (None on the last page)
+    next_cursor: Option<String>,
+    message: String,
+}
+
+/// Mode for a retrieval: `1` or `0` in `x-reserialize` if sent, else the server's
+pub fn retrieval_mode(state: &AppState, headers: &HeaderMap) -> Result<Mode, String> {
+    match headers
+        .get(RESERIALIZE_HEADER)
+        .map(|value| value.as_bytes())
+    {
+        None => Ok(state.mode()),
+        Some(b"1") => Ok(Mode::Reserialize),
+        Some(b"0") => Ok(Mode::PassThrough),
+        Some(_) => Err(format!("{} must be 0 or 1", RESERIALIZE_HEADER)),
+    }
+}
+
+/// Strategy for a retrieval: `x-reserialize-strategy` if sent, else the server's
+pub fn retrieval_strategy(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<reserialize::Strategy, String> {
+    let Some(value) = headers.get(STRATEGY_HEADER) else {
+        return Ok(state.reserialize.strategy);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| clap::ValueEnum::from_str(value, true).ok())
+        .ok_or_else(|| {
+            format!(
+                "{} must be typed, raw-transaction or untyped",
+                STRATEGY_HEADER
+            )
+        })
+}
+
+/// Retrieve a transaction and its signature.
+/// With `Accept: application/octet-stream` a successful retrieval returns just the BCS bytes
+/// `bcs_hex` would hold; errors are JSON either way. Successful retrievals carry an `ETag`, and
+/// `If-None-Match` with it answers `304` until the transaction changes.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}",
+    params(
+        ("transaction_id" = String, Path, description = "Id the transaction was stored under"),
+        ("accept" = Option<String>, Header, description = "`application/octet-stream` for the raw BCS instead of JSON"),
+        ("x-reserialize" = Option<String>, Header, description = "`1` or `0` to reserialize or pass through this retrieval regardless of the server's mode"),
+        ("x-reserialize-strategy" = Option<String>, Header, description = "`typed`, `raw-transaction` or `untyped` to override the server's reserialize strategy"),
+        ("x-bcs-encoding" = Option<String>, Header, description = "`base64` to return the BCS as `bcs_base64` instead of `bcs_hex`"),
+        ("if-none-match" = Option<String>, Header, description = "`ETag` of an earlier retrieval, answered with `304` if nothing changed since"),
+    ),
+    responses(
+        (status = 200, description = "Transaction and signature, with an `ETag`", content(
+            (GetTransactionResponse = "application/json"),
+            (Vec<u8> = "application/octet-stream"),
+        )),
+        (status = 304, description = "Unchanged since the `ETag` in `If-None-Match`"),
+        (status = 400, description = "Invalid x-reserialize, x-reserialize-strategy or x-bcs-encoding header", body = GetTransactionResponse),
+        (status = 404, description = "Unknown transaction", body = GetTransactionResponse),
+        (status = 410, description = "Transaction has expired", body = GetTransactionResponse),
+        (status = 500, description = "Storage error, or re-serialized bytes differ in strict mode", body = GetTransactionResponse),
+    )
+)]
+pub async fn get_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> Negotiated<GetTransactionResponse> {
+    info!(transaction_id = %transaction_id, "Retrieving transaction");
+    let retrieval = retrieval_mode(&state, &headers).and_then(|mode| {
+        Ok((
+            mode,
+            retrieval_strategy(&state, &headers)?,
+            encoding::response_encoding(&headers)?,
+        ))
+    });
+    let (mode, strategy, bcs_encoding) = match retrieval {
+        Ok(retrieval) => retrieval,
+        Err(message) => {
+            warn!("Rejected retrieval: {}", message);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(GetTransactionResponse {
+                    success: false,
+                    bcs_hex: None,
+                    bcs_base64: None,
+                    secondary_signatures: None,
+                    fee_payer_signature_hex: None,
+                    stored_at: None,
+                    uncompressed_size: None,
+                    compressed_size: None,
+                    mismatch: None,
+                    authenticator: None,
+                    expiration: None,
+                    session_state: None,
+                    signers_pending: None,
+                    signers_done: None,
+                    quorum: None,
+                    commit: None,
+                    message,
+                }),
+            )
+                .into();
+        }
+    };
+    debug!("Mode: {}, strategy: {}", mode.as_str(), strategy.as_str());
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(tx) => tx,
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GetTransactionResponse {
+                    success: false,
+                    bcs_hex: None,
+                    bcs_base64: None,
+                    secondary_signatures: None,
+                    fee_payer_signature_hex: None,
+                    stored_at: None,
+                    uncompressed_size: None,
+                    compressed_size: None,
+                    mismatch: None,
+                    authenticator: None,
+                    expiration: None,
+                    session_state: None,
+                    signers_pending: None,
+                    signers_done: None,
+                    quorum: None,
+                    commit: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            )
+                .into();
+        }
+    };
+
+    if let Some(expiry) = &state.expiry {
+        let expired = match &tx {
+            Some(tx) => expiry.is_expired(tx.stored_at),
+            None => expiry.was_swept(&transaction_id),
+        };
+        if expired {
+            warn!("Transaction expired");
+            return (
+                StatusCode::GONE,
+                Json(GetTransactionResponse {
+                    success: false,
+                    bcs_hex: None,
+                    bcs_base64: None,
+                    secondary_signatures: None,
+                    fee_payer_signature_hex: None,
+                    stored_at: None,
+                    uncompressed_size: None,
+                    compressed_size: None,
+                    mismatch: None,
+                    authenticator: None,
+                    expiration: None,
+                    session_state: Some(SessionState::Expired),
+                    signers_pending: None,
+                    signers_done: None,
+                    quorum: None,
+                    commit: None,
+                    message: "Transaction expired".to_string(),
+                }),
+            )
+                .into();
+        }
+    }
+
+    if let Some(tx) = tx {
+        let raw_bcs = match tx.bcs() {
+            Ok(raw_bcs) => raw_bcs,
+            Err(e) => {
+                error!("Failed to decompress transaction: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GetTransactionResponse {
+                        success: false,
+                        bcs_hex: None,
+                        bcs_base64: None,
+                        secondary_signatures: None,
+                        fee_payer_signature_hex: None,
+                        stored_at: None,
+                        uncompressed_size: None,
+                        compressed_size: None,
+                        mismatch: None,
+                        authenticator: None,
+                        expiration: None,
+                        session_state: None,
+                        signers_pending: None,
+                        signers_done: None,
+                        quorum: None,
+                        commit: None,
+                        message: format!("Failed to decompress transaction: {}", e),
+                    }),
+                )
+                    .into();
+            }
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let elapsed = now - tx.stored_at;
+
+        debug!("Found, stored {} seconds ago", elapsed);
+        // Parse sequence number for console logging only
+        if let Some(seq) = parse_sequence_number(&raw_bcs) {
+            debug!("Sequence number in tx: {}", seq);
+        }
+        debug!("Has secondary signature: {}", tx.has_secondary_signature());
+
+        let decoded = DecodedTransaction::decode(&raw_bcs).ok();
+        let session_state = state.session_state_decoded(&tx, decoded.as_ref());
+        let wants_binary = binary::wants_binary(&headers);
+        // Chaos mode may corrupt each retrieval differently, so none is tagged
+        let etag = state.chaos.is_none().then(|| {
+            etag::Representation {
+                bcs: &raw_bcs,
+                secondary_signatures: &tx.secondary_signatures,
+                fee_payer_signature: tx.fee_payer_signature.as_deref(),
+                session_state,
+                quorum: tx.quorum,
+                committed_version: tx.commit().map(|commit| commit.version),
+                variant: [
+                    mode.as_str(),
+                    strategy.as_str(),
+                    if wants_binary {
+                        "binary"
+                    } else {
+                        bcs_encoding.as_str()
+                    },
+                ],
+            }
+            .etag()
+        });
+        // Nothing changed, so nothing is re-serialized or recorded either
+        if let Some(etag) = etag
+            .as_ref()
+            .filter(|etag| etag::not_modified(&headers, etag))
+        {
+            debug!("Not modified");
+            return Negotiated::NotModified(etag.clone());
+        }
+
+        // Determine what BCS to return; `mismatch` is only known in reserialize mode
+        let mut mismatch = None;
+        let mut strict_mismatch = None;
+        let mut events = vec![TransactionEvent::now(EventKind::Retrieved, None)];
+        let bcs_to_return = if mode == Mode::Reserialize {
+            // Try to deserialize with Rust SDK and re-serialize
+            match reserialize::reserialize(strategy, &raw_bcs) {
+                Ok(reserialized) => {
+                    let original_len = raw_bcs.len();
+                    let new_len = reserialized.len();
+                    if original_len != new_len {
+                        warn!(
+                            "BCS length changed after re-serialization: {} -> {} bytes",
+                            original_len, new_len
+                        );
+                    }
+                    events.push(TransactionEvent::now(
+                        EventKind::Reserialized,
+                        Some(format!(
+                            "{}: {} -> {} bytes",
+                            strategy.as_str(),
+                            original_len,
+                            new_len
+                        )),
+                    ));
+                    let changed = *raw_bcs != reserialized;
+                    if changed {
+                        let diff = ByteDiff::new(&raw_bcs, &reserialized);
+                        let offset = diff.first_difference.unwrap_or_default();
+                        events.push(TransactionEvent::now(
+                            EventKind::MismatchDetected,
+                            Some(format!("first difference at byte {}", offset)),
+                        ));
+                        state.reserialize_mismatches.fetch_add(1, Ordering::Relaxed);
+                        warn!(
+                            transaction_id = %transaction_id,
+                            first_difference = offset,
+                            ranges = %diff.summary(),
+                            "BCS content changed after re-serialization; see GET /transaction/{}/diff",
+                            transaction_id
+                        );
+                        if state.reserialize.strict {
+                            strict_mismatch =
+                                Some(MismatchReport::new(strategy, &raw_bcs, &reserialized));
+                        }
+                    } else {
+                        debug!("BCS unchanged after re-serialization");
+                    }
+                    mismatch = Some(changed);
+                    Some(reserialized)
+                }
+                Err(e) => {
+                    state.reserialize_failures.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        transaction_id = %transaction_id,
+                        "Failed to re-serialize, returning original BCS: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(report) = strict_mismatch {
+            record_history(&state, &transaction_id, events).await;
+            error!(
+                transaction_id = %transaction_id,
+                strategy = report.strategy.as_str(),
+                first_difference = report.first_difference,
+                differing_bytes = report.differing_bytes,
+                changed_fields = ?report.changed_fields,
+                "Re-serialized BCS differs from stored bytes (strict mode)"
+            );
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GetTransactionResponse {
+                    success: false,
+                    bcs_hex: None,
+                    bcs_base64: None,
+                    secondary_signatures: None,
+                    fee_payer_signature_hex: None,
+                    stored_at: Some(tx.stored_at),
+                    uncompressed_size: Some(raw_bcs.len()),
+                    compressed_size: None,
+                    session_state: None,
+                    signers_pending: None,
+                    signers_done: None,
+                    quorum: None,
+                    commit: None,
+                    message: format!(
+                        "Re-serialized BCS differs from stored bytes from byte {} ({} -> {} bytes, strict mode)",
+                        report.first_difference, report.original_len, report.reserialized_len
+                    ),
+                    mismatch: Some(report),
+                    authenticator: None,
+                    expiration: None,
+                }),
+            )
+                .into();
+        }
+
+        let bcs_returned = bcs_to_return.as_deref().unwrap_or(&raw_bcs);
+        let corruption = state
+            .chaos
+            .as_ref()
+            .and_then(|chaos| chaos.corrupt(bcs_returned));
+        if let Some(corruption) = &corruption {
+            warn!(
+                transaction_id = %transaction_id,
+                fault = corruption.fault.as_str(),
+                "Chaos mode corrupted the returned BCS: {}",
+                corruption.detail
+            );
+            events.push(TransactionEvent::now(
+                EventKind::Corrupted,
+                Some(format!(
+                    "{}: {}",
+                    corruption.fault.as_str(),
+                    corruption.detail
+                )),
+            ));
+        }
+        let bcs_returned = corruption
+            .as_ref()
+            .map_or(bcs_returned, |corruption| corruption.bcs.as_slice());
+        let (bcs_hex_to_return, bcs_base64_to_return) = match bcs_encoding {
+            Encoding::Hex => (Some(hex_bytes::encode(bcs_returned, tx.hex_prefixed)), None),
+            Encoding::Base64 => (None, Some(encoding::base64(bcs_returned))),
+        };
+
+        let expiration = decoded
+            .as_ref()
+            .map(|decoded| decoded.expiration(now, state.expiration_warning_secs));
+        match expiration {
+            Some(expiration) if expiration.expired => warn!(
+                transaction_id = %transaction_id,
+                expiration_timestamp_secs = expiration.expiration_timestamp_secs,
+                "Transaction has expired on chain"
+            ),
+            Some(expiration) if expiration.nearly_expired => warn!(
+                transaction_id = %transaction_id,
+                expires_in_secs = expiration.expires_in_secs,
+                "Transaction is about to expire on chain"
+            ),
+            _ => {}
+        }
+
+        info!(
+            transaction_id = %transaction_id,
+            sender = tx.sender.as_deref(),
+            sequence_number = tx.sequence_number,
+            bcs_len = raw_bcs.len(),
+            returned_len = bcs_returned.len(),
+            mismatch,
+            "Transaction retrieved"
+        );
+        record_history(&state, &transaction_id, events).await;
+
+        if wants_binary {
+            return Negotiated::Binary(bcs_returned.to_vec()).tagged(etag);
+        }
+        Negotiated::from((
+            StatusCode::OK,
+            Json(GetTransactionResponse {
+                success: true,
+                bcs_hex: bcs_hex_to_return,
+                bcs_base64: bcs_base64_to_return,
+                secondary_signatures: Some(tx.secondary_signatures_hex()),
+                fee_payer_signature_hex: tx.fee_payer_signature_hex(),
+                stored_at: Some(tx.stored_at),
+                uncompressed_size: Some(raw_bcs.len()),
+                compressed_size: tx.compressed.then_some(tx.raw_bcs.len()),
+                mismatch: None,
+                authenticator: decoded.and_then(|decoded| decoded.authenticator()),
+                expiration,
+                session_state: Some(session_state),
+                signers_pending: tx.signers_pending(),
+                signers_done: tx.signers_done(),
+                quorum: tx.quorum,
+                commit: tx.commit().cloned(),
+                message: format!("Transaction retrieved (stored {} seconds ago)", elapsed),
+            }),
+        ))
+        .tagged(etag)
+    } else {
+        warn!("Transaction not found");
+        (
+            StatusCode::NOT_FOUND,
+            Json(GetTransactionResponse {
+                success: false,
+                bcs_hex: None,
+                bcs_base64: None,
+                secondary_signatures: None,
+                fee_payer_signature_hex: None,
+                stored_at: None,
+                uncompressed_size: None,
+                compressed_size: None,
+                mismatch: None,
+                authenticator: None,
+                expiration: None,
+                session_state: None,
+                signers_pending: None,
+                signers_done: None,
+                quorum: None,
+                commit: None,
+                message: "Transaction not found".to_string(),
+            }),
+        )
+            .into()
+    }
+}
+
+/// Retrieve many transactions and their signatures in one request.
+/// Each id is handled exactly like `GET /transaction/:id`, including `x-reserialize`.
+#[utoipa::path(
+    post,
+    path = "/transactions/get",
+    params(
+        ("x-reserialize" = Option<String>, Header, description = "`1` or `0` to reserialize or pass through these retrievals regardless of the server's mode"),
+        ("x-reserialize-strategy" = Option<String>, Header, description = "`typed`, `raw-transaction` or `untyped` to override the server's reserialize strategy"),
+    ),
+    request_body = BatchGetRequest,
+    responses(
+        (status = 200, description = "Found transactions and missing ids", body = BatchGetResponse),
+        (status = 400, description = "Invalid x-reserialize or x-reserialize-strategy header", body = BatchGetResponse),
+        (status = 500, description = "Storage error", body = BatchGetResponse),
+    )
+)]
+pub async fn get_transactions_batch(
+    State(state): State<Arc<AppState>>,
+    mut headers: HeaderMap,
+    Json(req): Json<BatchGetRequest>,
+) -> (StatusCode, Json<BatchGetResponse>) {
+    info!(
+        "Retrieving batch of {} transactions",
+        req.transaction_ids.len()
+    );
+
+    if let Err(message) =
+        retrieval_mode(&state, &headers).and_then(|_| retrieval_strategy(&state, &headers))
+    {
+        warn!("Rejected batch retrieval: {}", message);
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(BatchGetResponse {
+                success: false,
+                transactions: Vec::new(),
+                missing: Vec::new(),
+                message,
+            }),
+        );
+    }
+
+    // Batches are JSON-only and unconditional, whatever the headers ask for
+    headers.remove(axum::http::header::ACCEPT);
+    headers.remove(axum::http::header::IF_NONE_MATCH);
+    let mut transactions = Vec::new();
+    let mut missing = Vec::new();
+    for transaction_id in req.transaction_ids {
+        let (status, Json(transaction)) = match get_transaction(
+            State(state.clone()),
+            axum::extract::Path(transaction_id.clone()),
+            headers.clone(),
+        )
+        .await
+        .untagged()
+        {
+            Negotiated::Json(status, json) => (status, json),
+            Negotiated::Binary(_) | Negotiated::Tagged(..) | Negotiated::NotModified(_) => {
+                unreachable!("Accept and If-None-Match were removed")
+            }
+        };
+        match status {
+            StatusCode::OK => transactions.push(BatchGetEntry {
+                transaction_id,
+                transaction,
+            }),
+            StatusCode::NOT_FOUND | StatusCode::GONE => missing.push(transaction_id),
+            _ => {
+                return (
+                    status,
+                    Json(BatchGetResponse {
+                        success: false,
+                        transactions: Vec::new(),
+                        missing: Vec::new(),
+                        message: format!("{}: {}", transaction_id, transaction.message),
+                    }),
+                );
+            }
+        }
+    }
+
+    info!(
+        "Batch done: {} found, {} missing",
+        transactions.len(),
+        missing.len()
+    );
+    (
+        StatusCode::OK,
+        Json(BatchGetResponse {
+            success: true,
+            message: format!("{} found, {} missing", transactions.len(), missing.len()),
+            transactions,
+            missing,
+        }),
+    )
+}
+
+/// Delete a transaction and its signature.
+/// Refuses (409) if the transaction was already signed, unless `?force=1`.
+#[utoipa::path(
+    delete,
+    path = "/transaction/{transaction_id}",
+    params(
+        ("transaction_id" = String, Path, description = "Id the transaction was stored under"),
+        DeleteTransactionQuery,
+    ),
+    responses(
+        (status = 200, description = "Transaction deleted", body = DeleteTransactionResponse),
+        (status = 404, description = "Unknown transaction", body = DeleteTransactionResponse),
+        (status = 409, description = "Transaction is signed and `force` was not set", body = DeleteTransactionResponse),
+        (status = 500, description = "Storage error", body = DeleteTransactionResponse),
+    )
+)]
+pub async fn delete_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    Query(query): Query<DeleteTransactionQuery>,
+) -> (StatusCode, Json<DeleteTransactionResponse>) {
+    let force = query.force.as_deref() == Some("1");
+    info!(transaction_id = %transaction_id, force, "Deleting transaction");
+
+    if !force {
+        match state.store.get(&transaction_id).await {
+            Ok(Some(tx)) if tx.has_secondary_signature() => {
+                warn!("Transaction already has a secondary signature");
+                return (
+                    StatusCode::CONFLICT,
+                    Json(DeleteTransactionResponse {
+                        success: false,
+                        transaction_id,
+                        message: "Transaction already has a secondary signature \
+                                  (use ?force=1 to delete anyway)"
+                            .to_string(),
+                    }),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to read from storage: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(DeleteTransactionResponse {
+                        success: false,
+                        transaction_id,
+                        message: format!("Failed to read from storage: {}", e),
+                    }),
+                );
+            }
+        }
+    }
+
+    match state.store.remove(&transaction_id).await {
+        Ok(true) => {
+            info!(transaction_id = %transaction_id, "Transaction deleted");
+            state.webhooks.unregister(&transaction_id);
+            (
+                StatusCode::OK,
+                Json(DeleteTransactionResponse {
+                    success: true,
+                    transaction_id,
+                    message: "Transaction deleted".to_string(),
+                }),
+            )
+        }
+        Ok(false) => {
+            warn!("Transaction not found");
+            (
+                StatusCode::NOT_FOUND,
+                Json(DeleteTransactionResponse {
+                    success: false,
+                    transaction_id,
+                    message: "Transaction not found".to_string(),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to delete transaction: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(DeleteTransactionResponse {
+                    success: false,
+                    transaction_id,
+                    message: format!("Failed to delete transaction: {}", e),
+                }),
+            )
+        }
+    }
+}
+
+/// List stored transactions ordered by id; `cursor` is the last id of the previous page
+#[utoipa::path(
+    get,
+    path = "/transactions",
+    params(ListTransactionsQuery),
+    responses(
+        (status = 200, description = "One page of transactions", body = ListTransactionsResponse),
+        (status = 400, description = "Invalid `sender` address", body = ListTransactionsResponse),
+        (status = 500, description = "Storage error", body = ListTransactionsResponse),
+    )
+)]
+pub async fn list_transactions(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListTransactionsQuery>,
+) -> (StatusCode, Json<ListTransactionsResponse>) {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    info!("Listing transactions (limit {})", limit);
+
+    let sender = match query
+        .sender
+        .as_deref()
+        .map(AccountAddress::from_hex)
+        .transpose()
+    {
+        Ok(sender) => sender.map(|sender| sender.to_long_string()),
+        Err(e) => {
+            warn!("Invalid sender address: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ListTransactionsResponse {
+                    success: false,
+                    transactions: Vec::new(),
+                    next_cursor: None,
+                    message: format!("Invalid sender address: {}", e),
+                }),
+            );
+        }
+    };
+    let sequence_numbers = query.min_seq.unwrap_or(0)..=query.max_seq.unwrap_or(u64::MAX);
+    let filter_by_seq = query.min_seq.is_some() || query.max_seq.is_some();
+
+    let transactions = match &sender {
+        Some(sender) => {
+            debug!(
+                "Sender: {}, sequence numbers: {:?}",
+                sender, sequence_numbers
+            );
+            state
+                .store
+                .list_by_sender(sender, sequence_numbers.clone())
+                .await
+        }
+        None => state.store.list().await,
+    };
+    let mut transactions = match transactions {
+        Ok(transactions) => transactions,
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ListTransactionsResponse {
+                    success: false,
+                    transactions: Vec::new(),
+                    next_cursor: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            );
+        }
+    };
+    transactions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    // Entries past their TTL are as good as gone even before the sweeper runs
+    let mut page: Vec<TransactionSummary> = transactions
+        .into_iter()
+        .filter(|(transaction_id, _)| {
+            query
+                .cursor
+                .as_ref()
+                .is_none_or(|cursor| transaction_id > cursor)
+        })
+        .filter(|(_, tx)| {
+            !filter_by_seq
+                || tx
+                    .sequence_number
+                    .is_some_and(|seq| sequence_numbers.contains(&seq))
+        })
+        .filter(|(_, tx)| {
+            !state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.is_expired(tx.stored_at))
+        })
+        .take(limit + 1)
+        .map(|(transaction_id, tx)| TransactionSummary {
+            session_state: state.session_state(&tx),
+            signers_pending: tx.signers_pending(),
+            signers_done: tx.signers_done(),
+            has_signature: tx.has_secondary_signature(),
+            sender: tx.sender,
+            sequence_number: tx.sequence_number,
+            canonical: tx.canonical,
+            stored_at: tx.stored_at,
+            transaction_id,
+        })
+        .collect();
+
+    // One extra entry was fetched to tell whether another page exists
+    let next_cursor = if page.len() > limit {
+        page.truncate(limit);
+        page.last().map(|tx| tx.transaction_id.clone())
+    } else {
+        None
+    };
+
+    debug!("Returning {} transactions", page.len());
+    (
+        StatusCode::OK,
+        Json(ListTransactionsResponse {
+            success: true,
+            message: format!("{} transactions", page.len()),
+            transactions: page,
+            next_cursor,
+        }),
+    )
+}
+
+/// Wait for a transaction's signatures, then retrieve it.
+/// Blocks while the session is `created` or `awaiting_signatures`, i.e. until no secondary (or
+/// fee payer) signature is missing or the quorum is met, then answers exactly like
+/// `GET /transaction/:id`; if `timeout_ms` passes first, answers `408` with who is missing, and
+/// `409` as soon as a signer declines. A transaction that doesn't decode counts as complete once any signature is attached.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/wait",
+    params(
+        ("transaction_id" = String, Path, description = "Id the transaction was stored under"),
+        WaitQuery,
+    ),
+    responses(
+        (status = 200, description = "Every signature is attached; same body as `GET /transaction/:id`", body = GetTransactionResponse),
+        (status = 404, description = "Unknown transaction", body = GetTransactionResponse),
+        (status = 408, description = "Timed out with signatures still missing", body = WaitResponse),
+        (status = 409, description = "A signer declined to sign", body = WaitResponse),
+        (status = 410, description = "Transaction has expired", body = GetTransactionResponse),
+        (status = 500, description = "Storage error", body = WaitResponse),
+    )
+)]
+pub async fn wait_for_signatures(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    Query(query): Query<WaitQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let timeout_ms = query.timeout_ms.unwrap_or(DEFAULT_WAIT_MS).min(MAX_WAIT_MS);
+    info!(transaction_id = %transaction_id, timeout_ms, "Waiting for signatures");
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
+    // Subscribe before the first check so a signature attached in between
+    // isn't missed
+    let mut events = state.notifier.subscribe();
+
+    loop {
+        let (session_state, missing) = match state.store.get(&transaction_id).await {
+            Ok(Some(tx)) => {
+                let decoded = tx
+                    .bcs()
+                    .ok()
+                    .and_then(|bcs| DecodedTransaction::decode(&bcs).ok());
+                let missing = decoded
+                    .as_ref()
+                    .map(|decoded| {
+                        decoded.missing_signers(
+                            &tx.current_secondary_signatures(),
+                            tx.fee_payer_signed(),
+                        )
+                    })
+                    .unwrap_or_default();
+                (
+                    Some(state.session_state_decoded(&tx, decoded.as_ref())),
+                    missing,
+                )
+            }
+            // Answered as not found (or expired) by the retrieval
+            Ok(None) => (None, Vec::new()),
+            Err(e) => {
+                error!("Failed to read from storage: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(WaitResponse {
+                        success: false,
+                        transaction_id,
+                        missing_signers: Vec::new(),
+                        session_state: None,
+                        message: format!("Failed to read from storage: {}", e),
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        if session_state == Some(SessionState::Rejected) {
+            warn!(transaction_id = %transaction_id, "Signing was declined");
+            return (
+                StatusCode::CONFLICT,
+                Json(WaitResponse {
+                    success: false,
+                    transaction_id,
+                    session_state,
+                    message: "Signing was declined; see the history for who and why".to_string(),
+                    missing_signers: missing,
+                }),
+            )
+                .into_response();
+        }
+        let collecting = matches!(
+            session_state,
+            Some(SessionState::Created | SessionState::AwaitingSignatures)
+        );
+        if !collecting {
+            return get_transaction(State(state), axum::extract::Path(transaction_id), headers)
+                .await
+                .into_response();
+        }
+        debug!(missing = ?missing, "Signatures still missing");
+
+        let signed = tokio::time::timeout_at(deadline, async {
+            loop {
+                match events.recv().await {
+                    Ok(notification)
+                        if notification.transaction_id == transaction_id
+                            && matches!(
+                                notification.event.kind,
+                                EventKind::SignatureAdded
+                                    | EventKind::FeePayerSignatureAdded
+                                    | EventKind::Declined
+                                    | EventKind::Expired
+                            ) =>
+                    {
+                        break
+                    }
+                    Ok(_) => {}
+                    // Events were dropped, so check the store again
+                    Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => break,
+                }
+            }
+        })
+        .await;
+        if signed.is_err() {
+            warn!(transaction_id = %transaction_id, missing = ?missing, "Timed out waiting for signatures");
+            return (
+                StatusCode::REQUEST_TIMEOUT,
+                Json(WaitResponse {
+                    success: false,
+                    transaction_id,
+                    session_state,
+                    message: format!("Timed out after {} ms waiting for signatures", timeout_ms),
+                    missing_signers: missing,
+                }),
+            )
+                .into_response();
+        }
+    }
+}
+
+/// Retrieve a transaction's event history.
+/// Answers even after the TTL has passed, as long as the entry hasn't been swept.
+#[utoipa::path(
+    get,
+    path = "/transaction/{transaction_id}/history",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Events, oldest first", body = HistoryResponse),
+        (status = 404, description = "Unknown transaction", body = HistoryResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = HistoryResponse),
+        (status = 500, description = "Storage error", body = HistoryResponse),
+    )
+)]
+pub async fn get_transaction_history(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<HistoryResponse>) {
+    info!(transaction_id = %transaction_id, "Retrieving transaction history");
+
+    match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => {
+            debug!("Returning {} events", tx.history.len());
+            let session_state = state.session_state(&tx);
+            (
+                StatusCode::OK,
+                Json(HistoryResponse {
+                    success: true,
+                    transaction_id,
+                    session_state: Some(session_state),
+                    message: format!("{} events", tx.history.len()),
+                    events: tx.history,
+                }),
+            )
+        }
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            (
+                StatusCode::GONE,
+                Json(HistoryResponse {
+                    success: false,
+                    transaction_id,
+                    events: Vec::new(),
+                    session_state: Some(SessionState::Expired),
+                    message: "Transaction expired".to_string(),
+                }),
+            )
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            (
+                StatusCode::NOT_FOUND,
+                Json(HistoryResponse {
+                    success: false,
+                    transaction_id,
+                    events: Vec::new(),
+                    session_state: None,
+                    message: "Transaction not found".to_string(),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(HistoryResponse {
+                    success: false,
+                    transaction_id,
+                    events: Vec::new(),
+                    session_state: None,
+                    message: format!("Failed to read from storage: {}", e),
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,539 @@
+//! Startup: parses the configuration, builds the store and state, and serves
+//! the API until shutdown (or runs the requested tool instead).
+
+use super::app;
+use crate::config::{Config, Mode, Tool};
+use crate::expiry::{self, Expiry};
+use crate::limits::Limits;
+use crate::storage::{self, Compression, TransactionStore};
+use crate::{
+    auth, backup, chaos, cors, grpc, journal, listener, loadtest, localnet, logging, probes,
+    ratelimit, reconcile, scenario, shutdown, signing, snapshot, tls, verify, wal, webhook,
+    AppState, RequestGuards, Reserialization,
+};
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Run the server (or the requested tool) as configured by the command
+/// line, environment and config file
+pub async fn run() {
+    let config = Config::load();
+    let log_format = config.server.log_format;
+    logging::init(log_format);
+
+    if let Some(tool) = config.tool {
+        let result = match tool {
+            Tool::LoadTest(args) => loadtest::run(args).await,
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let addr = match listener::bind(&config.server) {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("Failed to configure the listener: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let store: Arc<dyn TransactionStore> = match storage::from_config(&config.storage).await {
+        Ok(store) => Arc::from(store),
+        Err(e) => {
+            error!("Failed to initialize storage: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let snapshot_config = snapshot::SnapshotConfig::from_config(&config.persistence);
+    if let Some(snapshot) = &snapshot_config {
+        match snapshot::restore(store.as_ref(), &snapshot.path).await {
+            Ok(count) => info!(
+                "Restored {} transactions from snapshot {}",
+                count,
+                snapshot.path.display()
+            ),
+            Err(e) => {
+                error!(
+                    "Failed to restore snapshot {}: {}",
+                    snapshot.path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let wal_path = config.persistence.wal_path.clone();
+    let wal = match &wal_path {
+        Some(path) => {
+            let replayed = match wal::replay(store.as_ref(), path).await {
+                Ok(replayed) => replayed,
+                Err(e) => {
+                    error!("Failed to replay WAL {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            info!("Replayed {} WAL records from {}", replayed, path.display());
+            let wal_store = match wal::WalStore::open(store.clone(), path).await {
+                Ok(wal_store) => Arc::new(wal_store),
+                Err(e) => {
+                    error!("Failed to open WAL {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            // A durable backend now holds the replayed records
+            if let Err(e) = wal_store.flush().await {
+                warn!("Failed to checkpoint WAL {}: {}", path.display(), e);
+            }
+            Some(wal_store)
+        }
+        None => None,
+    };
+    let store: Arc<dyn TransactionStore> = match &wal {
+        Some(wal) => wal.clone(),
+        None => store,
+    };
+
+    let compression = match Compression::from_config(&config.storage) {
+        Ok(compression) => compression,
+        Err(e) => {
+            error!("Failed to configure compression: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let backup_config = match backup::BackupConfig::from_config(&config.persistence) {
+        Ok(backup_config) => backup_config,
+        Err(e) => {
+            error!("Failed to configure backups: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let limits = Limits::from_config(&config.limits);
+
+    let cors = match cors::layer(&config.cors) {
+        Ok(cors) => cors,
+        Err(e) => {
+            error!("Failed to configure CORS: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let tls = match tls::Tls::from_config(&config.server).await {
+        Ok(tls) => tls,
+        Err(e) => {
+            error!("Failed to configure TLS: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let grpc_addr = match grpc::addr(&config.server) {
+        Ok(grpc_addr) => grpc_addr,
+        Err(e) => {
+            error!("Failed to configure gRPC: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let webhooks = match webhook::Webhooks::from_config(&config.server) {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            error!("Failed to configure webhooks: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Replays and scenarios send their requests in-process, then exit
+    let replay = config.persistence.replay.clone();
+    let scenario = config.server.scenario.clone();
+    let in_process = replay.is_some() || scenario.is_some();
+    let journal = match &config.persistence.journal_path {
+        Some(path) if !in_process => match journal::Journal::open(path).await {
+            Ok(journal) => Some(Arc::new(journal)),
+            Err(e) => {
+                error!("Failed to open journal {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
+
+    // Started last, so a configuration error can't leave it running
+    let local_testnet = match localnet::LocalTestnet::from_config(&config.server).await {
+        Ok(local_testnet) => local_testnet,
+        Err(e) => {
+            error!("Failed to start local testnet: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let fullnode_url = match &local_testnet {
+        Some(local_testnet) => Some(local_testnet.url()),
+        None => config.server.fullnode_url.as_deref(),
+    };
+    let fullnode = match probes::fullnode_client(fullnode_url) {
+        Ok(fullnode) => fullnode,
+        Err(e) => {
+            error!("Failed to configure fullnode: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let api_keys = auth::ApiKeys::from_config(&config.security);
+    let jwt = auth::JwtAuth::from_config(&config.security);
+    let signing = signing::RequestSigning::from_config(&config.security);
+    let rate_limit = ratelimit::RateLimit::from_config(&config.security).map(Arc::new);
+    let expiry = Expiry::from_config(&config.persistence).map(Arc::new);
+    let state = Arc::new(AppState::new(
+        store.clone(),
+        Reserialization {
+            enabled: AtomicBool::new(config.server.mode() == Mode::Reserialize),
+            strict: config.server.reserialize_strict,
+            strategy: config.server.reserialize_strategy,
+        },
+        expiry.clone(),
+        compression,
+        backup_config.dir.clone(),
+        fullnode,
+        (config.server.submission_poll_interval_ms > 0)
+            .then(|| Duration::from_millis(config.server.submission_poll_interval_ms)),
+        config.server.chain_id_check,
+        config.server.reject_non_canonical,
+        config.server.expiration_warning_secs,
+        limits,
+        // Journals and scenarios hold no credentials, so can't pass the guards
+        if in_process {
+            RequestGuards::default()
+        } else {
+            RequestGuards {
+                api_keys,
+                jwt,
+                signing,
+                rate_limit: rate_limit.clone(),
+            }
+        },
+        webhooks,
+        chaos::Chaos::from_config(&config.chaos),
+    ));
+
+    if in_process {
+        // Sessions may be left to expire between steps
+        if let Some(expiry) = expiry {
+            expiry::spawn(state.clone(), expiry);
+        }
+        let app = app(state.clone(), cors, None);
+        let passed = match (replay, scenario) {
+            (Some(path), _) => match journal::replay(app, &path).await {
+                Ok(summary) => summary.diverged == 0,
+                Err(e) => {
+                    error!("Failed to replay {}: {}", path.display(), e);
+                    false
+                }
+            },
+            (None, Some(path)) => match scenario::run(app, &path).await {
+                Ok(_) => true,
+                Err(e) => {
+                    error!("Scenario {} failed: {}", path.display(), e);
+                    false
+                }
+            },
+            (None, None) => unreachable!("in_process needs a replay or scenario"),
+        };
+        let snapshot_path = snapshot_config.as_ref().map(|config| config.path.as_path());
+        shutdown::finish(store.as_ref(), snapshot_path, wal.as_deref()).await;
+        if let Some(local_testnet) = local_testnet {
+            local_testnet.stop().await;
+        }
+        if !passed {
+            std::process::exit(1);
+        }
+        return;
+    }
+    let existing = state.store.list().await.map(|txs| txs.len()).unwrap_or(0);
+    let shutdown = shutdown::Shutdown::listen(config.server.shutdown_drain_secs);
+
+    // The banner is for humans; JSON logs get a single structured startup event
+    if log_format == logging::LogFormat::Text {
+        info!("Multi-Agent Transaction Backend Server");
+        info!("Stores and retrieves serialized transactions to test if the Rust backend causes SEQUENCE_NUMBER issues");
+        info!(
+            "MODE: {}",
+            if state.mode() == Mode::Reserialize {
+                "RESERIALIZE (deserialize with Rust SDK, re-serialize on retrieval)"
+            } else {
+                "PASS-THROUGH (store raw bytes, return unchanged)"
+            }
+        );
+        info!(
+            "STRATEGY: {} (used when reserializing)",
+            state.reserialize.strategy.as_str()
+        );
+        if state.reserialize.strict {
+            info!("STRICT: retrievals whose re-serialized bytes differ fail with 500");
+        }
+        if let Some(chaos) = &state.chaos {
+            info!("CHAOS: {}", chaos.describe());
+        }
+        info!("To enable reserialize mode: cargo run -- --mode reserialize");
+        info!("To persist to SQLite: cargo run --features sqlite -- --storage sqlite");
+        info!("For every option: cargo run -- --help");
+        info!(
+            "STORAGE: {} ({} transactions loaded)",
+            state.store.name(),
+            existing
+        );
+        match &snapshot_config {
+            Some(snapshot) => info!(
+                "SNAPSHOTS: every {}s to {}",
+                snapshot.interval.as_secs(),
+                snapshot.path.display()
+            ),
+            None => info!("SNAPSHOTS: disabled (set --snapshot-path to enable)"),
+        }
+        match &backup_config.schedule {
+            Some(schedule) => info!(
+                "BACKUPS: on schedule `{}` to {}",
+                schedule,
+                backup_config.dir.display()
+            ),
+            None => info!(
+                "BACKUPS: on demand to {} (set --backup-schedule to schedule)",
+                backup_config.dir.display()
+            ),
+        }
+        match &wal_path {
+            Some(path) => info!("WAL: {}", path.display()),
+            None => info!("WAL: disabled (set --wal-path to enable)"),
+        }
+        match &config.persistence.journal_path {
+            Some(path) => info!("JOURNAL: recording requests to {}", path.display()),
+            None => info!("JOURNAL: disabled (set --journal-path to enable)"),
+        }
+        match &expiry {
+            Some(expiry) => info!("EXPIRY: {}", expiry.describe()),
+            None => {
+                info!("EXPIRY: disabled (set --tx-ttl-secs or --session-idle-secs to enable)")
+            }
+        }
+        match config.persistence.signature_check_interval_secs {
+            Some(secs) => info!("SIGNATURE CHECKS: every {}s", secs),
+            None => info!(
+                "SIGNATURE CHECKS: on arrival only (set --signature-check-interval-secs to recheck)"
+            ),
+        }
+        match &compression {
+            Some(compression) => info!("COMPRESSION: zstd (level {})", compression.level()),
+            None => info!("COMPRESSION: disabled (set --compression zstd to enable)"),
+        }
+        match &state.fullnode {
+            Some(fullnode) => info!(
+                "FULLNODE: {} (chain id check: {:?})",
+                fullnode.base_url(),
+                state.chain_id_check
+            ),
+            None => info!("FULLNODE: not configured (set --fullnode-url to check it in /readyz and submit to it)"),
+        }
+        if let (Some(_), Some(interval)) = (&state.fullnode, state.submission_poll_interval) {
+            info!(
+                "SUBMISSIONS: looked up every {:?} until committed or expired",
+                interval
+            );
+        }
+        if let Some(local_testnet) = &local_testnet {
+            info!(
+                "LOCAL TESTNET: node {}, faucet {}",
+                local_testnet.url(),
+                local_testnet.faucet_url()
+            );
+        }
+        match (&state.guards.api_keys, &state.guards.jwt) {
+            (None, None) => {
+                info!("AUTH: disabled (set --api-keys and/or --jwt-secret to enable)")
+            }
+            (api_keys, jwt) => info!(
+                "AUTH: {} API keys, JWT bearer tokens {}",
+                api_keys.as_ref().map_or(0, |keys| keys.count()),
+                if jwt.is_some() {
+                    "accepted"
+                } else {
+                    "disabled"
+                }
+            ),
+        }
+        if state.reject_non_canonical {
+            info!("CANONICAL: non-canonical BCS is rejected at ingest");
+        }
+        match &state.guards.signing {
+            Some(signing) => info!(
+                "HMAC SIGNING: required on writes (tolerance {}s)",
+                signing.tolerance_secs()
+            ),
+            None => info!("HMAC SIGNING: disabled (set --hmac-secret to require x-signature)"),
+        }
+        match state.webhooks.global() {
+            Some(url) => info!("WEBHOOK: {} (plus per-transaction callback_url)", url),
+            None => {
+                info!("WEBHOOK: per-transaction callback_url only (set --webhook-url for all)")
+            }
+        }
+        info!(
+            "LIMITS: body {} bytes, bcs_hex {} chars, signature_hex {} chars",
+            limits.body_bytes, limits.bcs_hex_len, limits.signature_hex_len
+        );
+        match &state.guards.rate_limit {
+            Some(rate_limit) => info!(
+                "RATE LIMIT: {}/s per API key or IP (burst {})",
+                rate_limit.per_sec(),
+                rate_limit.burst()
+            ),
+            None => info!("RATE LIMIT: disabled (set --rate-limit-per-sec to enable)"),
+        }
+        info!(
+            "SHUTDOWN: on SIGINT/SIGTERM, drain requests for up to {}s, then snapshot and flush",
+            shutdown.drain_timeout().as_secs()
+        );
+        match config.cors.cors_origins.as_slice() {
+            [] => info!("CORS: any origin (set --cors-origins to restrict)"),
+            origins => info!("CORS: {}", origins.join(", ")),
+        }
+        match &tls {
+            Some(tls) => info!("TLS: HTTPS with {}", tls.cert_path().display()),
+            None => {
+                info!("TLS: disabled (set --tls-cert-path and --tls-key-path to serve HTTPS)")
+            }
+        }
+        match grpc_addr {
+            Some(grpc_addr) => info!("GRPC: {} (proto/repro.proto)", grpc_addr),
+            None => info!("GRPC: disabled (set --grpc-port and build with --features grpc)"),
+        }
+        info!("Endpoints (also under /v1, e.g. /v1/transaction):");
+        info!("  POST /transaction     - Store a serialized transaction");
+        info!("  POST /transactions/batch - Store many transactions at once");
+        info!("  POST /debug/generate  - Generate and store synthetic transactions from a seed");
+        info!("  POST /signature       - Store secondary signer's signature");
+        info!("  POST /fee-payer-signature - Store fee payer's signature (sponsored transactions)");
+        info!("  GET  /transaction/:id - Retrieve transaction and signature");
+        info!("  DELETE /transaction/:id - Delete a transaction (?force=1 if signed)");
+        info!("  GET  /transaction/:id/wait - Block until the signatures arrive (?timeout_ms=)");
+        info!("  GET  /transaction/:id/history - Events recorded for a transaction");
+        info!("  GET  /transaction/:id/signing-message - Message signers sign for a transaction");
+        info!("  GET  /transaction/:id/decoded - Every decoded field of a transaction, as JSON");
+        info!("  GET  /transaction/:id/explain - Byte range of every BCS field");
+        info!("  GET  /transaction/:id/diff - Byte ranges that change when re-serialized");
+        info!("  POST /compare         - Compare two serialized transactions byte by byte");
+        info!("  GET  /ws              - WebSocket push of transaction events (?transaction_id=)");
+        info!("  POST /transaction/:id/assemble - Build the SignedTransaction once everyone has signed");
+        info!("  POST /transaction/:id/submit - Assemble and submit to the fullnode");
+        info!("  POST /transaction/:id/verify - Record version, VM status and gas used once committed");
+        info!("  POST /transaction/:id/decline - Refuse to sign, rejecting the session");
+        info!(
+            "  GET  /transaction/:id/gas - Check gas price and payer balance against the fullnode"
+        );
+        info!("  POST /transaction/:id/refresh-sequence - Rebuild with the sender's on-chain sequence number");
+        info!("  GET  /account/:address/sequence - Flag stored transactions behind the on-chain sequence number");
+        info!("  POST /transactions/get - Retrieve many transactions at once");
+        info!("  GET  /type-tag        - Parse a type tag (?tag=) and return its canonical form");
+        info!("  GET  /transactions    - List transactions (?limit=&cursor=&sender=&min_seq=&max_seq=)");
+        info!(
+            "  POST /graphql         - GraphQL queries over stored transactions (GET for GraphiQL)"
+        );
+        info!("  GET  /healthz         - Liveness probe");
+        info!("  GET  /readyz          - Readiness probe (storage, fullnode if configured)");
+        info!("  GET  /health          - Health check (plain text)");
+        info!("  GET  /stats           - Server and storage statistics");
+        info!("  GET  /version         - Build info (commit, aptos-sdk version, mode)");
+        info!("  GET  /admin/export    - Download the whole store as JSON");
+        info!("  POST /admin/import    - Load a JSON export");
+        info!("  POST /admin/backup    - Write a timestamped backup");
+        info!("  POST /admin/restore   - Restore a backup (?name=, default latest)");
+        info!("  GET/POST /admin/mode  - Read or switch pass-through/reserialize mode");
+        info!("  GET  /openapi.json    - OpenAPI spec (paths relative to /v1)");
+        info!("Starting server on {}...", addr);
+    } else {
+        info!(
+            mode = state.mode().as_str(),
+            reserialize_strict = state.reserialize.strict,
+            reserialize_strategy = state.reserialize.strategy.as_str(),
+            storage = state.store.name(),
+            transactions = existing,
+            api_keys = state
+                .guards
+                .api_keys
+                .as_ref()
+                .map_or(0, |keys| keys.count()),
+            jwt = state.guards.jwt.is_some(),
+            hmac_signing = state.guards.signing.is_some(),
+            tls = tls.is_some(),
+            rate_limit_per_sec = state
+                .guards
+                .rate_limit
+                .as_ref()
+                .map_or(0, |limit| limit.per_sec()),
+            "Starting server on {}",
+            addr
+        );
+    }
+
+    let snapshot_path = snapshot_config.as_ref().map(|config| config.path.clone());
+    if let Some(snapshot_config) = snapshot_config {
+        snapshot::spawn(store.clone(), snapshot_config, wal.clone());
+    }
+    if let Some(schedule) = backup_config.schedule {
+        backup::spawn(store.clone(), backup_config.dir, schedule);
+    }
+    if let Some(expiry) = expiry {
+        expiry::spawn(state.clone(), expiry);
+    }
+    if let Some(secs) = config.persistence.signature_check_interval_secs {
+        reconcile::spawn(state.clone(), Duration::from_secs(secs));
+    }
+    if let (Some(_), Some(interval)) = (&state.fullnode, state.submission_poll_interval) {
+        tokio::spawn(verify::resume(state.clone(), interval));
+    }
+    if let Some(rate_limit) = rate_limit {
+        ratelimit::spawn_cleanup(rate_limit);
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_addr) = grpc_addr {
+        tokio::spawn(grpc::serve(state.clone(), grpc_addr, shutdown.clone()));
+    }
+
+    let app = app(state, cors, journal);
+
+    match (tls, addr) {
+        (_, listener::Bind::Unix(path)) => {
+            info!("Server listening on unix:{}", path.display());
+            listener::serve_unix(&path, app, shutdown).await.unwrap();
+        }
+        (Some(tls), listener::Bind::Tcp(addr)) => {
+            info!("Server listening on {} (HTTPS)", addr);
+            tls.serve(&addr, app, shutdown).await.unwrap();
+        }
+        (None, listener::Bind::Tcp(addr)) => {
+            let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+            info!("Server listening on {}", addr);
+            let server = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown.clone().requested());
+            tokio::select! {
+                result = server => result.unwrap(),
+                _ = shutdown.drain_deadline() => {}
+            }
+        }
+    }
+
+    shutdown::finish(store.as_ref(), snapshot_path.as_deref(), wal.as_deref()).await;
+    if let Some(local_testnet) = local_testnet {
+        local_testnet.stop().await;
+    }
+    info!("Shutdown complete");
+}
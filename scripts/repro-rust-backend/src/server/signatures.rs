@@ -0,0 +1,794 @@
+//! Attaching signatures: `POST /signature`, `POST /fee-payer-signature` and
+//! `POST /transaction/:id/decline`.
+
+use crate::authenticator::{AccountAuthenticator, Scheme, Threshold};
+use crate::encoding::Encoding;
+use crate::limits::LimitExceeded;
+use crate::locks::TransactionLock;
+use crate::session::{Operation, SessionState};
+use crate::storage::{EventKind, Signer, TransactionEvent, FEE_PAYER};
+use crate::transaction::{DecodedTransaction, TransactionKind};
+use crate::{missing_signers, record_history, webhook, AppState};
+use aptos_sdk::{aptos_bcs, AccountAddress};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct StoreSignatureRequest {
+    pub transaction_id: String,
+    /// Address of the secondary signer the signature belongs to
+    pub signer_address: String,
+    pub signature_hex: String,
+    /// Encoding of `signature_hex`
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// The scheme the signer signed with; `400` if the authenticator is of
+    /// another
+    #[serde(default)]
+    pub scheme: Option<Scheme>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct StoreFeePayerSignatureRequest {
+    pub transaction_id: String,
+    pub signature_hex: String,
+    /// Encoding of `signature_hex`
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// The scheme the signer signed with; `400` if the authenticator is of
+    /// another
+    #[serde(default)]
+    pub scheme: Option<Scheme>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StoreSignatureResponse {
+    pub success: bool,
+    pub transaction_id: String,
+    /// Set with a `413` when `signature_hex` is over `MAX_SIGNATURE_HEX_LEN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_state: Option<SessionState>,
+    /// Required signers other than the sender yet to sign: long-form
+    /// addresses, then `fee_payer`; omitted if the BCS doesn't decode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_pending: Option<Vec<String>>,
+    /// Required signers whose signature is attached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_done: Option<Vec<String>>,
+    /// For a `MultiEd25519` or `MultiKey` signer, the partial signatures
+    /// collected so far against its threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<Threshold>,
+    pub message: String,
+}
+
+impl StoreSignatureResponse {
+    /// A rejected signature, with no details but `message`
+    fn failure(transaction_id: String, message: String) -> Self {
+        Self {
+            success: false,
+            transaction_id,
+            limit_exceeded: None,
+            session_state: None,
+            signers_pending: None,
+            signers_done: None,
+            threshold: None,
+            message,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DeclineRequest {
+    /// Long-form or short address of the declining secondary signer, or
+    /// `fee_payer`
+    signer_address: String,
+    /// Why the signer won't sign, passed on to subscribers
+    reason: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DeclineResponse {
+    success: bool,
+    transaction_id: String,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+/// Store a secondary signer's signature
+/// Validates the signature by deserializing as AccountAuthenticator, checking it is of the declared
+/// scheme and, for Ed25519 and secp256k1, verifying it against the transaction's signing message,
+/// then stores it
+#[utoipa::path(
+    post,
+    path = "/signature",
+    request_body = StoreSignatureRequest,
+    responses(
+        (status = 200, description = "Signature stored", body = StoreSignatureResponse),
+        (status = 400, description = "Not a valid `AccountAuthenticator`, not of the declared `scheme`, or `signer_address` is not a secondary signer of the transaction", body = StoreSignatureResponse),
+        (status = 404, description = "Unknown transaction", body = StoreSignatureResponse),
+        (status = 409, description = "Transaction was already submitted, or a partial multi-signer signature is for other keys or of another scheme than the stored one", body = StoreSignatureResponse),
+        (status = 410, description = "Transaction has expired", body = StoreSignatureResponse),
+        (status = 413, description = "`signature_hex` or the body is over its size limit", body = StoreSignatureResponse),
+        (status = 422, description = "Ed25519 or secp256k1 signature does not verify against the transaction", body = StoreSignatureResponse),
+        (status = 500, description = "Storage error", body = StoreSignatureResponse),
+    )
+)]
+pub async fn store_signature(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<StoreSignatureRequest>,
+) -> (StatusCode, Json<StoreSignatureResponse>) {
+    let address = match AccountAddress::from_hex(&req.signer_address) {
+        Ok(address) => address.to_long_string(),
+        Err(e) => {
+            warn!("Invalid signer address: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(StoreSignatureResponse::failure(
+                    req.transaction_id,
+                    format!("Invalid signer address: {}", e),
+                )),
+            );
+        }
+    };
+    attach_signature(
+        &state,
+        req.transaction_id,
+        req.signature_hex,
+        req.encoding,
+        req.scheme,
+        Signer::Secondary(address),
+    )
+    .await
+}
+
+/// Store the fee payer's signature for a sponsored transaction
+/// Validated like `/signature`; the stored transaction must be a `FeePayerRawTransaction`
+#[utoipa::path(
+    post,
+    path = "/fee-payer-signature",
+    request_body = StoreFeePayerSignatureRequest,
+    responses(
+        (status = 200, description = "Signature stored", body = StoreSignatureResponse),
+        (status = 400, description = "Not a valid `AccountAuthenticator`, not of the declared `scheme`, or not a fee payer transaction", body = StoreSignatureResponse),
+        (status = 404, description = "Unknown transaction", body = StoreSignatureResponse),
+        (status = 409, description = "Transaction was already submitted, or a partial multi-signer signature is for other keys or of another scheme than the stored one", body = StoreSignatureResponse),
+        (status = 410, description = "Transaction has expired", body = StoreSignatureResponse),
+        (status = 413, description = "`signature_hex` or the body is over its size limit", body = StoreSignatureResponse),
+        (status = 422, description = "Ed25519 or secp256k1 signature does not verify against the transaction", body = StoreSignatureResponse),
+        (status = 500, description = "Storage error", body = StoreSignatureResponse),
+    )
+)]
+pub async fn store_fee_payer_signature(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<StoreFeePayerSignatureRequest>,
+) -> (StatusCode, Json<StoreSignatureResponse>) {
+    attach_signature(
+        &state,
+        req.transaction_id,
+        req.signature_hex,
+        req.encoding,
+        req.scheme,
+        Signer::FeePayer,
+    )
+    .await
+}
+
+/// A rejected signature: the status and the response to answer with
+type Rejection = Box<(StatusCode, Json<StoreSignatureResponse>)>;
+
+fn reject(status: StatusCode, response: StoreSignatureResponse) -> Rejection {
+    Box::new((status, Json(response)))
+}
+
+/// Validate and store the signature of `signer`
+pub async fn attach_signature(
+    state: &AppState,
+    transaction_id: String,
+    signature_hex: String,
+    encoding: Encoding,
+    declared: Option<Scheme>,
+    signer: Signer,
+) -> (StatusCode, Json<StoreSignatureResponse>) {
+    info!(transaction_id = %transaction_id, ?signer, "Storing signature");
+    debug!("Signature hex length: {} chars", signature_hex.len());
+
+    let (authenticator, authenticator_bytes) =
+        match decode_authenticator(state, &transaction_id, &signature_hex, encoding, declared) {
+            Ok(decoded) => decoded,
+            Err(rejection) => return *rejection,
+        };
+    // Locked before the transaction is read, for every scheme, so it can't
+    // change between the checks below and the write
+    let lock = state.transaction_locks.lock(&transaction_id).await;
+    let decoded = match check_signer(state, &transaction_id, &signer, &authenticator).await {
+        Ok(decoded) => decoded,
+        Err(rejection) => return *rejection,
+    };
+
+    // Store the ORIGINAL bytes (not re-serialized), unless a partial
+    // multi-signer signature is merged into the stored one
+    let (authenticator, authenticator_bytes) = if matches!(authenticator.threshold(), Ok(Some(_))) {
+        match merge_partial(
+            state,
+            &transaction_id,
+            &signer,
+            authenticator,
+            authenticator_bytes,
+        )
+        .await
+        {
+            Ok(merged) => merged,
+            Err((status, message)) => {
+                warn!("{}", message);
+                return (
+                    status,
+                    Json(StoreSignatureResponse::failure(transaction_id, message)),
+                );
+            }
+        }
+    } else {
+        (authenticator, authenticator_bytes)
+    };
+    write_signature(
+        state,
+        lock,
+        transaction_id,
+        &signer,
+        &authenticator,
+        authenticator_bytes,
+        decoded.as_ref(),
+    )
+    .await
+}
+
+/// Decode `signature_hex` (or base64) and validate it by deserializing it as
+/// an `AccountAuthenticator` of the declared scheme, returning it with its
+/// bytes
+fn decode_authenticator(
+    state: &AppState,
+    transaction_id: &str,
+    signature_hex: &str,
+    encoding: Encoding,
+    declared: Option<Scheme>,
+) -> Result<(AccountAuthenticator, Vec<u8>), Rejection> {
+    let failure =
+        |message: String| StoreSignatureResponse::failure(transaction_id.to_string(), message);
+    if let Some(limit) = LimitExceeded::check(
+        "signature_hex",
+        state.limits.signature_hex_len,
+        signature_hex.len(),
+    ) {
+        let message = limit.message();
+        warn!("Rejected signature: {}", message);
+        return Err(reject(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            StoreSignatureResponse {
+                limit_exceeded: Some(limit),
+                ..failure(message)
+            },
+        ));
+    }
+    debug!(
+        "Signature hex prefix: {}...",
+        &signature_hex[..std::cmp::min(60, signature_hex.len())]
+    );
+
+    let authenticator_bytes = match encoding.decode(signature_hex) {
+        Ok((bytes, _)) => bytes,
+        Err(e) => {
+            warn!("Invalid authenticator {}: {}", encoding.as_str(), e);
+            return Err(reject(
+                StatusCode::BAD_REQUEST,
+                failure(format!(
+                    "Invalid authenticator {} format: {}",
+                    encoding.as_str(),
+                    e
+                )),
+            ));
+        }
+    };
+
+    let authenticator = match AccountAuthenticator::decode(&authenticator_bytes) {
+        Ok(authenticator) => {
+            debug!(
+                scheme = authenticator.scheme().as_str(),
+                iss = authenticator.keyless().map(|key| key.iss_val.as_str()),
+                "Signature validated"
+            );
+            authenticator
+        }
+        Err(e) => {
+            warn!("Invalid authenticator format: {}", e);
+            return Err(reject(
+                StatusCode::BAD_REQUEST,
+                failure(format!("Invalid authenticator format: {}", e)),
+            ));
+        }
+    };
+    if let Err(e) = authenticator.threshold() {
+        warn!("Invalid multi-signer authenticator: {}", e);
+        return Err(reject(
+            StatusCode::BAD_REQUEST,
+            failure(format!("Invalid authenticator: {}", e)),
+        ));
+    }
+    if let Some(declared) = declared.filter(|declared| *declared != authenticator.scheme()) {
+        let message = format!(
+            "Signature is {}, not the declared {}",
+            authenticator.scheme().as_str(),
+            declared.as_str()
+        );
+        warn!("{}", message);
+        return Err(reject(StatusCode::BAD_REQUEST, failure(message)));
+    }
+    Ok((authenticator, authenticator_bytes))
+}
+
+/// Check that the session still takes signatures, that `signer` may sign the
+/// stored transaction and that Ed25519 and secp256k1 signatures verify
+/// against its signing message, returning the decoded transaction. A missing
+/// transaction passes; the write reports it as not found (or expired)
+async fn check_signer(
+    state: &AppState,
+    transaction_id: &str,
+    signer: &Signer,
+    authenticator: &AccountAuthenticator,
+) -> Result<Option<DecodedTransaction>, Rejection> {
+    let tx = match state.store.get(transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            error!("Failed to look up transaction: {}", e);
+            return Err(reject(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StoreSignatureResponse::failure(
+                    transaction_id.to_string(),
+                    format!("Failed to look up transaction: {}", e),
+                ),
+            ));
+        }
+    };
+    let decoded = tx
+        .bcs()
+        .ok()
+        .and_then(|bcs| DecodedTransaction::decode(&bcs).ok());
+    let session_state = state.session_state_decoded(&tx, decoded.as_ref());
+    let rejected = |status: StatusCode, message: String| {
+        warn!("{}", message);
+        reject(
+            status,
+            StoreSignatureResponse {
+                session_state: Some(session_state),
+                ..StoreSignatureResponse::failure(transaction_id.to_string(), message)
+            },
+        )
+    };
+    if let Err((status, message)) = session_state.check(Operation::Sign) {
+        return Err(rejected(status, message));
+    }
+
+    // Only a sponsored transaction has a fee payer to sign for it, and only
+    // the secondary signers it names may sign as one
+    let not_a_signer = match signer {
+        Signer::FeePayer => (decoded.as_ref().map(DecodedTransaction::kind)
+            != Some(TransactionKind::FeePayer))
+        .then(|| "Transaction is not a fee payer transaction".to_string()),
+        // Undecodable transactions can't be checked, so accept any signer
+        Signer::Secondary(address) => decoded
+            .as_ref()
+            .filter(|decoded| {
+                !decoded
+                    .secondary_signer_addresses()
+                    .iter()
+                    .any(|secondary| secondary.to_long_string() == *address)
+            })
+            .map(|_| format!("{} is not a secondary signer of this transaction", address)),
+    };
+    if let Some(message) = not_a_signer {
+        return Err(rejected(StatusCode::BAD_REQUEST, message));
+    }
+
+    let verified = decoded
+        .as_ref()
+        .map(|decoded| decoded.verify_self_consistent(authenticator, *signer == Signer::FeePayer));
+    let failure = match verified {
+        Some(Ok(Some(true))) => {
+            debug!("Signature verified");
+            None
+        }
+        Some(Ok(Some(false))) => {
+            Some("Signature does not verify against the transaction".to_string())
+        }
+        Some(Err(e)) => Some(format!("Signature does not verify: {}", e)),
+        // Not checked here, or the transaction doesn't decode
+        Some(Ok(None)) | None => None,
+    };
+    if let Some(message) = failure {
+        return Err(rejected(StatusCode::UNPROCESSABLE_ENTITY, message));
+    }
+    Ok(decoded)
+}
+
+/// Store the (possibly merged) signature, releasing `lock` once it is
+/// written, then record and announce it
+async fn write_signature(
+    state: &AppState,
+    lock: TransactionLock<'_>,
+    transaction_id: String,
+    signer: &Signer,
+    authenticator: &AccountAuthenticator,
+    authenticator_bytes: Vec<u8>,
+    decoded: Option<&DecodedTransaction>,
+) -> (StatusCode, Json<StoreSignatureResponse>) {
+    let threshold = authenticator.threshold().ok().flatten();
+    let signature_len = authenticator_bytes.len();
+    let updated = state
+        .store
+        .update_signature(&transaction_id, signer, authenticator_bytes)
+        .await;
+    drop(lock);
+    match updated {
+        Ok(true) => {
+            info!(
+                transaction_id = %transaction_id,
+                ?signer,
+                scheme = authenticator.scheme().as_str(),
+                signature_len,
+                "Signature stored"
+            );
+            let event = signed_event(signer, authenticator, threshold, signature_len);
+            let at_ms = event.at_ms;
+            record_history(state, &transaction_id, vec![event]).await;
+            send_signature_webhooks(state, &transaction_id, signer, at_ms).await;
+            // Read back so signatures attached concurrently count too
+            let (session_state, signers_pending, signers_done) =
+                match state.store.get(&transaction_id).await {
+                    Ok(Some(tx)) => (
+                        Some(state.session_state_decoded(&tx, decoded)),
+                        tx.signers_pending(),
+                        tx.signers_done(),
+                    ),
+                    _ => (None, None, None),
+                };
+            (
+                StatusCode::OK,
+                Json(StoreSignatureResponse {
+                    success: true,
+                    transaction_id,
+                    limit_exceeded: None,
+                    session_state,
+                    signers_pending,
+                    signers_done,
+                    threshold,
+                    message: "Signature validated and stored".to_string(),
+                }),
+            )
+        }
+        Ok(false)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            (
+                StatusCode::GONE,
+                Json(StoreSignatureResponse::failure(
+                    transaction_id,
+                    "Transaction expired".to_string(),
+                )),
+            )
+        }
+        Ok(false) => {
+            warn!("Transaction not found");
+            (
+                StatusCode::NOT_FOUND,
+                Json(StoreSignatureResponse::failure(
+                    transaction_id,
+                    "Transaction not found".to_string(),
+                )),
+            )
+        }
+        Err(e) => {
+            error!("Failed to store signature: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(StoreSignatureResponse::failure(
+                    transaction_id,
+                    format!("Failed to store signature: {}", e),
+                )),
+            )
+        }
+    }
+}
+
+/// The `signed` history event for a stored signature
+fn signed_event(
+    signer: &Signer,
+    authenticator: &AccountAuthenticator,
+    threshold: Option<Threshold>,
+    signature_len: usize,
+) -> TransactionEvent {
+    let scheme = match threshold {
+        Some(threshold) => format!(
+            "{}, {} of {} signatures",
+            authenticator.scheme().as_str(),
+            threshold.signed,
+            threshold.required
+        ),
+        None => authenticator.scheme().as_str().to_string(),
+    };
+    match signer {
+        Signer::Secondary(address) => TransactionEvent::signed(
+            signer,
+            format!("{}: {}, {} bytes", address, scheme, signature_len),
+        ),
+        Signer::FeePayer => {
+            TransactionEvent::signed(signer, format!("{}, {} bytes", scheme, signature_len))
+        }
+    }
+}
+
+/// Decline to sign a transaction.
+/// Lets a secondary signer (or the fee payer) refuse explicitly, with a reason, instead of leaving
+/// the other parties to time out. The session becomes `rejected`: a `declined` event reaches
+/// WebSocket subscribers and callback URLs, waiters return, and no more signatures are accepted.
+#[utoipa::path(
+    post,
+    path = "/transaction/{transaction_id}/decline",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    request_body = DeclineRequest,
+    responses(
+        (status = 200, description = "Signing declined", body = DeclineResponse),
+        (status = 400, description = "`signer_address` is not a valid address, or not a signer of the transaction", body = DeclineResponse),
+        (status = 404, description = "Unknown transaction", body = DeclineResponse),
+        (status = 409, description = "Transaction was already submitted or declined", body = DeclineResponse),
+        (status = 410, description = "Transaction has expired", body = DeclineResponse),
+        (status = 413, description = "The body is over its size limit", body = DeclineResponse),
+        (status = 500, description = "Storage error", body = DeclineResponse),
+    )
+)]
+pub async fn decline_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    Json(req): Json<DeclineRequest>,
+) -> (StatusCode, Json<DeclineResponse>) {
+    info!(transaction_id = %transaction_id, signer = %req.signer_address, "Declining transaction");
+    let failure = |status: StatusCode, session_state: Option<SessionState>, message: String| {
+        (
+            status,
+            Json(DeclineResponse {
+                success: false,
+                transaction_id: transaction_id.clone(),
+                session_state,
+                message,
+            }),
+        )
+    };
+
+    let signer = if req.signer_address == FEE_PAYER {
+        FEE_PAYER.to_string()
+    } else {
+        match AccountAddress::from_hex(&req.signer_address) {
+            Ok(address) => address.to_long_string(),
+            Err(e) => {
+                warn!("Invalid signer address: {}", e);
+                return failure(
+                    StatusCode::BAD_REQUEST,
+                    None,
+                    format!("Invalid signer address: {}", e),
+                );
+            }
+        }
+    };
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            return failure(
+                StatusCode::GONE,
+                Some(SessionState::Expired),
+                "Transaction expired".to_string(),
+            );
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            return failure(
+                StatusCode::NOT_FOUND,
+                None,
+                "Transaction not found".to_string(),
+            );
+        }
+        Err(e) => {
+            error!("Failed to look up transaction: {}", e);
+            return failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                None,
+                format!("Failed to look up transaction: {}", e),
+            );
+        }
+    };
+
+    let session_state = state.session_state(&tx);
+    if let Err((status, message)) = session_state.check(Operation::Decline) {
+        warn!("{}", message);
+        return failure(status, Some(session_state), message);
+    }
+    // Undecodable transactions can't be checked, so accept any signer
+    if tx
+        .required_signers
+        .as_ref()
+        .is_some_and(|signers| !signers.contains(&signer))
+    {
+        let message = format!("{} is not a signer of this transaction", signer);
+        warn!("{}", message);
+        return failure(StatusCode::BAD_REQUEST, Some(session_state), message);
+    }
+
+    let event = TransactionEvent::now(
+        EventKind::Declined,
+        Some(format!("{}: {}", signer, req.reason)),
+    );
+    let at_ms = event.at_ms;
+    if let Err(e) = state
+        .store
+        .append_history(&transaction_id, vec![event.clone()])
+        .await
+    {
+        error!("Failed to record decline: {}", e);
+        return failure(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            None,
+            format!("Failed to record decline: {}", e),
+        );
+    }
+    state.notifier.publish(&transaction_id, &[event]);
+    state.webhooks.send(
+        webhook::WebhookPayload {
+            event: webhook::WebhookEvent::SignatureDeclined,
+            transaction_id: transaction_id.clone(),
+            signer: signer.clone(),
+            missing_signers: tx.signers_pending().unwrap_or_default(),
+            reason: Some(req.reason),
+            at_ms,
+        },
+        state.guards.signing.as_ref(),
+    );
+    warn!(transaction_id = %transaction_id, signer = %signer, "Signing declined");
+
+    (
+        StatusCode::OK,
+        Json(DeclineResponse {
+            success: true,
+            transaction_id,
+            session_state: Some(SessionState::Rejected),
+            message: format!("Signing declined by {}", signer),
+        }),
+    )
+}
+
+/// Merge a partial `MultiEd25519` or `MultiKey` signature into the one stored
+/// for `signer`, returning what to store instead. A stored signature of
+/// another scheme, or of the same scheme for other keys, is a conflict, since
+/// replacing it would drop the signatures collected; a stale one is replaced.
+/// Callers hold the transaction's lock.
+pub async fn merge_partial(
+    state: &AppState,
+    transaction_id: &str,
+    signer: &Signer,
+    partial: AccountAuthenticator,
+    partial_bytes: Vec<u8>,
+) -> Result<(AccountAuthenticator, Vec<u8>), (StatusCode, String)> {
+    let stored = match state.store.get(transaction_id).await {
+        Ok(Some(tx)) => tx
+            .signature(signer)
+            .filter(|_| !tx.stale_signers().contains(signer.name()))
+            .map(<[u8]>::to_vec),
+        // Reported as not found (or expired) by the update
+        Ok(None) => None,
+        Err(e) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to look up transaction: {}", e),
+            ))
+        }
+    };
+    let Some(stored) = stored.and_then(|bytes| AccountAuthenticator::decode(&bytes).ok()) else {
+        return Ok((partial, partial_bytes));
+    };
+    if stored.scheme() != partial.scheme() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{} already has a {} signature stored; a {} partial signature can't be merged into it",
+                signer.name(),
+                stored.scheme().as_str(),
+                partial.scheme().as_str()
+            ),
+        ));
+    }
+    let merged = stored.merge(&partial).map_err(|e| {
+        (
+            StatusCode::CONFLICT,
+            format!("Partial signature doesn't merge with the stored one: {}", e),
+        )
+    })?;
+    let bytes = aptos_bcs::to_bytes(&merged).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to encode merged signature: {}", e),
+        )
+    })?;
+    debug!(
+        partial_len = partial_bytes.len(),
+        merged_len = bytes.len(),
+        "Merged partial signature"
+    );
+    Ok((merged, bytes))
+}
+
+/// Tell callback URLs that `signer` signed, and whether any signature is
+/// still missing. Which are missing is read back from the store so that
+/// signatures attached concurrently are accounted for; if the transaction
+/// doesn't decode, nothing is known to be missing and completion isn't sent.
+/// With a quorum, completion is sent once it is met.
+pub async fn send_signature_webhooks(
+    state: &AppState,
+    transaction_id: &str,
+    signer: &Signer,
+    at_ms: u64,
+) {
+    if !state.webhooks.wants(transaction_id) {
+        return;
+    }
+    let (missing_signers, quorum_met) = match state.store.get(transaction_id).await {
+        Ok(Some(tx)) => (missing_signers(&tx), tx.quorum_met()),
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to look up transaction for webhooks: {}", e);
+            return;
+        }
+    };
+    let signer = signer.name().to_string();
+    let signing = state.guards.signing.as_ref();
+    let complete = quorum_met || missing_signers.as_ref().is_some_and(Vec::is_empty);
+    state.webhooks.send(
+        webhook::WebhookPayload {
+            event: webhook::WebhookEvent::SignatureAdded,
+            transaction_id: transaction_id.to_string(),
+            signer: signer.clone(),
+            missing_signers: missing_signers.unwrap_or_default(),
+            reason: None,
+            at_ms,
+        },
+        signing,
+    );
+    if complete {
+        state.webhooks.send(
+            webhook::WebhookPayload {
+                event: webhook::WebhookEvent::SignaturesComplete,
+                transaction_id: transaction_id.to_string(),
+                signer,
+                missing_signers: Vec::new(),
+                reason: None,
+                at_ms,
+            },
+            signing,
+        );
+    }
+}
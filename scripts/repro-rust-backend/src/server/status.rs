@@ -0,0 +1,148 @@
+//! `GET /stats`, `GET /version` and `GET /health`.
+
+use crate::config::Mode;
+use crate::{chaos, AppState};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct StatsResponse {
+    #[schema(value_type = String)]
+    backend: &'static str,
+    transactions: usize,
+    /// Transactions with a secondary signature attached
+    signed: usize,
+    /// Mean BCS size in bytes (before compression); 0 if the store is empty
+    average_bcs_size: f64,
+    evictions: u64,
+    reserialize_mode: bool,
+    /// Retrievals whose re-serialized BCS differed from the stored bytes
+    reserialize_mismatches: u64,
+    /// Retrievals where the stored BCS could not be re-serialized
+    reserialize_failures: u64,
+    /// Retrievals that chaos mode returned corrupted BCS for
+    chaos_injections: u64,
+    /// Requests that chaos mode failed with an injected error status
+    chaos_errors: u64,
+    /// Open `GET /ws` connections, plus pending `GET /transaction/:id/wait` calls
+    websocket_subscribers: usize,
+    uptime_secs: u64,
+}
+
+/// Server and storage statistics
+#[utoipa::path(
+    get,
+    path = "/stats",
+    responses(
+        (status = 200, description = "Server and storage statistics", body = StatsResponse),
+        (status = 500, description = "Storage error", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<StatsResponse>, (StatusCode, String)> {
+    let transactions = state.store.list().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read from storage: {}", e),
+        )
+    })?;
+
+    let signed = transactions
+        .iter()
+        .filter(|(_, tx)| tx.has_secondary_signature())
+        .count();
+    let mut total_bcs_size = 0;
+    for (transaction_id, tx) in &transactions {
+        let bcs = tx.bcs().map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to decompress {}: {}", transaction_id, e),
+            )
+        })?;
+        total_bcs_size += bcs.len();
+    }
+    let average_bcs_size = if transactions.is_empty() {
+        0.0
+    } else {
+        total_bcs_size as f64 / transactions.len() as f64
+    };
+
+    Ok(Json(StatsResponse {
+        backend: state.store.name(),
+        transactions: transactions.len(),
+        signed,
+        average_bcs_size,
+        evictions: state.store.evictions(),
+        reserialize_mode: state.mode() == Mode::Reserialize,
+        reserialize_mismatches: state.reserialize_mismatches.load(Ordering::Relaxed),
+        reserialize_failures: state.reserialize_failures.load(Ordering::Relaxed),
+        chaos_injections: state.chaos.as_ref().map_or(0, chaos::Chaos::injected),
+        chaos_errors: state.chaos.as_ref().map_or(0, chaos::Chaos::errors),
+        websocket_subscribers: state.notifier.subscribers(),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VersionResponse {
+    /// Version of this backend crate
+    version: &'static str,
+    /// Commit the binary was built from
+    git_commit: &'static str,
+    /// Whether the checkout had uncommitted changes (`true`, `false` or `unknown`)
+    git_dirty: &'static str,
+    /// Version of the aptos-sdk crate compiled in
+    aptos_sdk_version: &'static str,
+    rustc_version: &'static str,
+    /// `pass-through` or `reserialize`
+    mode: &'static str,
+    /// Optional Cargo features compiled in
+    features: Vec<&'static str>,
+}
+
+/// Build and configuration info; reserialize mismatches often depend on the
+/// exact SDK revision the backend was built against
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses((status = 200, description = "Build and configuration info", body = VersionResponse))
+)]
+pub async fn version(State(state): State<Arc<AppState>>) -> Json<VersionResponse> {
+    let features = [
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("postgres", cfg!(feature = "postgres")),
+        ("redis", cfg!(feature = "redis")),
+        ("sled", cfg!(feature = "sled")),
+        ("rocksdb", cfg!(feature = "rocksdb")),
+        ("zstd", cfg!(feature = "zstd")),
+        ("tls", cfg!(feature = "tls")),
+        ("grpc", cfg!(feature = "grpc")),
+    ];
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("GIT_COMMIT"),
+        git_dirty: env!("GIT_DIRTY"),
+        aptos_sdk_version: env!("APTOS_SDK_VERSION"),
+        rustc_version: env!("RUSTC_VERSION"),
+        mode: state.mode().as_str(),
+        features: features
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| feature)
+            .collect(),
+    })
+}
+
+/// Health check endpoint
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses((status = 200, description = "Server is up", body = String, content_type = "text/plain"))
+)]
+pub async fn health() -> &'static str {
+    "OK"
+}
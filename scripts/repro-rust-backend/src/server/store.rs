@@ -0,0 +1,910 @@
+//! Storing transactions: `POST /transaction`, the batch store and
+//! `POST /debug/generate`.
+
+use crate::binary::{BinaryTransaction, StoreBody};
+use crate::bytediff::{diff_bcs, BcsDiff};
+use crate::canonical::{self, CanonicalIssue};
+use crate::chain::{ChainIdCheck, ChainIdMismatch};
+use crate::encoding::Encoding;
+use crate::limits::LimitExceeded;
+use crate::session::SessionState;
+use crate::storage::{EventKind, StoredTransaction, TransactionEvent};
+use crate::transaction::{DecodedTransaction, ExpirationStatus, TransactionKind};
+use crate::{generate, webhook, AppState};
+use aptos_sdk::types::ChainId;
+use aptos_sdk::AccountAddress;
+use axum::{extract::State, http::StatusCode, Json};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct StoreTransactionRequest {
+    transaction_id: String,
+    /// The BCS, as hex or, with `encoding: base64`, base64
+    #[serde(alias = "bcs")]
+    bcs_hex: String,
+    /// Encoding of `bcs_hex`
+    #[serde(default)]
+    encoding: Encoding,
+    /// Payload the BCS must decode as; detected from the bytes if omitted
+    #[serde(default)]
+    kind: Option<TransactionKind>,
+    /// `http(s)` URL POSTed to when a signature is attached and when the
+    /// signature set is complete
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Secondary signatures that complete the session, for K-of-N approval;
+    /// every secondary signer's if omitted
+    #[serde(default)]
+    quorum: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StoreTransactionResponse {
+    pub success: bool,
+    pub transaction_id: String,
+    /// Set with a `413` when `bcs_hex` is over `MAX_BCS_HEX_LEN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// Set with a `409` when the id is already stored with different bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<BcsDiff>,
+    /// Payload the stored BCS decoded as (omitted if it didn't decode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<TransactionKind>,
+    /// Where `expiration_timestamp_secs` stands; set when stored, or with a
+    /// `422` if it has already passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<ExpirationStatus>,
+    /// Set when the transaction's `chain_id` differs from the fullnode's:
+    /// with a `422` under `CHAIN_ID_CHECK=reject`, else alongside the store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id_mismatch: Option<ChainIdMismatch>,
+    /// Set when the BCS is not canonical: with a `422` under
+    /// `REJECT_NON_CANONICAL`, else alongside the store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_issues: Option<Vec<CanonicalIssue>>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_state: Option<SessionState>,
+    /// Required signers other than the sender yet to sign: long-form
+    /// addresses, then `fee_payer`; omitted if the BCS doesn't decode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_pending: Option<Vec<String>>,
+    /// Required signers whose signature is attached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signers_done: Option<Vec<String>>,
+    pub message: String,
+}
+
+impl StoreTransactionResponse {
+    /// A rejected store, with no details but `message`
+    fn failure(transaction_id: String, message: String) -> Self {
+        Self {
+            success: false,
+            transaction_id,
+            limit_exceeded: None,
+            conflict: None,
+            kind: None,
+            expiration: None,
+            chain_id_mismatch: None,
+            canonical_issues: None,
+            session_state: None,
+            signers_pending: None,
+            signers_done: None,
+            message,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchStoreResponse {
+    /// Whether every transaction in the batch was stored
+    success: bool,
+    stored: usize,
+    failed: usize,
+    /// One result per submitted transaction, in request order
+    results: Vec<StoreTransactionResponse>,
+    message: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct GenerateRequest {
+    /// The same seed and options always generate the same transactions
+    seed: u64,
+    /// Transactions to generate and store (default 1, max 1000)
+    count: Option<usize>,
+    /// Secondary signers of each transaction (default 2, max 16)
+    secondary_signers: Option<usize>,
+    /// Generate fee payer transactions instead of multi-agent ones
+    #[serde(default)]
+    fee_payer: bool,
+    /// Chain id to build for; the fullnode's if one is configured, else
+    /// testnet's
+    chain_id: Option<u8>,
+    /// Default an hour from now; set it for byte-identical output across runs
+    expiration_timestamp_secs: Option<u64>,
+    /// Transactions are stored as `<id_prefix>-<n>`; default `gen-<seed>`
+    id_prefix: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GeneratedTransaction {
+    transaction_id: String,
+    /// Long-form address
+    sender: String,
+    /// Long-form addresses; random, so nobody can sign for them
+    secondary_signers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee_payer: Option<String>,
+    bcs_hex: String,
+    /// What storing it answered, as `POST /transaction` would
+    result: StoreTransactionResponse,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GenerateResponse {
+    /// Whether every generated transaction was stored
+    success: bool,
+    seed: u64,
+    stored: usize,
+    failed: usize,
+    transactions: Vec<GeneratedTransaction>,
+    message: String,
+}
+
+/// A response that ends a store before the write: a rejection, or the answer
+/// to an identical replay
+type Finished = Box<(StatusCode, Json<StoreTransactionResponse>)>;
+
+fn finish(status: StatusCode, response: StoreTransactionResponse) -> Finished {
+    Box::new((status, Json(response)))
+}
+
+/// A store request with its BCS decoded, from either kind of body
+pub struct StoreRequest {
+    pub transaction_id: String,
+    /// Payload the BCS must decode as, if the request named one
+    pub kind: Option<TransactionKind>,
+    pub raw_bcs: Vec<u8>,
+    /// Whether retrievals render the BCS with a `0x` prefix
+    pub hex_prefixed: bool,
+    pub callback_url: Option<Url>,
+    pub quorum: Option<u32>,
+}
+
+/// Reject BCS whose hex, `bcs_hex_len` characters long, is over the limit
+fn check_bcs_len(
+    state: &AppState,
+    transaction_id: &str,
+    bcs_hex_len: usize,
+) -> Result<(), Finished> {
+    let Some(limit) = LimitExceeded::check("bcs_hex", state.limits.bcs_hex_len, bcs_hex_len) else {
+        return Ok(());
+    };
+    let message = limit.message();
+    warn!("Rejected transaction: {}", message);
+    Err(finish(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        StoreTransactionResponse {
+            limit_exceeded: Some(limit),
+            ..StoreTransactionResponse::failure(transaction_id.to_string(), message)
+        },
+    ))
+}
+
+/// Check and decode the `bcs_hex` of a JSON store request
+pub fn decode_store_request(
+    state: &AppState,
+    req: StoreTransactionRequest,
+) -> Result<StoreRequest, Finished> {
+    info!(transaction_id = %req.transaction_id, "Storing transaction");
+    debug!(
+        "BCS {} length: {} chars",
+        req.encoding.as_str(),
+        req.bcs_hex.len()
+    );
+
+    let callback_url = parse_callback_url(&req.transaction_id, req.callback_url.as_deref())?;
+
+    check_bcs_len(state, &req.transaction_id, req.bcs_hex.len())?;
+    debug!(
+        "BCS {} prefix: {}...",
+        req.encoding.as_str(),
+        &req.bcs_hex[..std::cmp::min(60, req.bcs_hex.len())]
+    );
+
+    // Decode once here; the bytes are stored and hex is only produced on retrieval
+    let (raw_bcs, hex_prefixed) = match req.encoding.decode(&req.bcs_hex) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            warn!("Invalid BCS {}: {}", req.encoding.as_str(), e);
+            return Err(finish(
+                StatusCode::BAD_REQUEST,
+                StoreTransactionResponse::failure(
+                    req.transaction_id,
+                    format!("Invalid BCS {} format: {}", req.encoding.as_str(), e),
+                ),
+            ));
+        }
+    };
+    // Base64 is shorter than the hex the limit is counted in
+    check_bcs_len(state, &req.transaction_id, 2 * raw_bcs.len())?;
+    Ok(StoreRequest {
+        transaction_id: req.transaction_id,
+        kind: req.kind,
+        raw_bcs,
+        // Retrieved as `0x` hex unless stored as unprefixed hex, like the
+        // TS SDK's `bcsToHex()`
+        hex_prefixed: hex_prefixed || req.encoding == Encoding::Base64,
+        callback_url,
+        quorum: req.quorum,
+    })
+}
+
+/// Check a raw BCS body, which carries its id and kind in headers
+fn binary_store_request(
+    state: &AppState,
+    binary: BinaryTransaction,
+) -> Result<StoreRequest, Finished> {
+    info!(transaction_id = %binary.transaction_id, "Storing transaction");
+    debug!("BCS length: {} bytes (binary body)", binary.bcs.len());
+    let callback_url = parse_callback_url(&binary.transaction_id, binary.callback_url.as_deref())?;
+    // Held to the same limit as the hex it replaces
+    check_bcs_len(state, &binary.transaction_id, 2 * binary.bcs.len())?;
+    Ok(StoreRequest {
+        transaction_id: binary.transaction_id,
+        kind: binary.kind,
+        raw_bcs: binary.bcs.to_vec(),
+        // Rendered the way the TS SDK's `bcsToHex()` does on retrieval
+        hex_prefixed: true,
+        callback_url,
+        quorum: binary.quorum,
+    })
+}
+
+/// Check a store's webhook URL, from the JSON body or a binary store's header
+fn parse_callback_url(transaction_id: &str, url: Option<&str>) -> Result<Option<Url>, Finished> {
+    url.map(webhook::parse_url).transpose().map_err(|message| {
+        warn!("Rejected transaction: {}", message);
+        finish(
+            StatusCode::BAD_REQUEST,
+            StoreTransactionResponse::failure(transaction_id.to_string(), message),
+        )
+    })
+}
+
+/// Decode the sender and sequence number once so listings can be filtered by
+/// them; `None` if the BCS isn't a recognized transaction and no kind was
+/// required
+fn decode_stored(req: &StoreRequest) -> Result<Option<DecodedTransaction>, Finished> {
+    let decoded = match req.kind {
+        Some(kind) => match DecodedTransaction::decode_as(kind, &req.raw_bcs) {
+            Ok(decoded) => Some(decoded),
+            Err(e) => {
+                warn!("BCS is not a {}: {}", kind.as_str(), e);
+                return Err(finish(
+                    StatusCode::BAD_REQUEST,
+                    StoreTransactionResponse::failure(
+                        req.transaction_id.clone(),
+                        format!("BCS does not decode as {}: {}", kind.as_str(), e),
+                    ),
+                ));
+            }
+        },
+        None => DecodedTransaction::decode(&req.raw_bcs).ok(),
+    };
+    match decoded.as_ref().map(DecodedTransaction::kind) {
+        Some(kind) => debug!("Decoded {} transaction", kind.as_str()),
+        None => debug!("Not a recognized transaction; sender not indexed"),
+    }
+    Ok(decoded)
+}
+
+/// A quorum counts secondary signers, so there must be enough of them
+fn check_quorum(req: &StoreRequest, decoded: Option<&DecodedTransaction>) -> Result<(), Finished> {
+    let Some(quorum) = req.quorum else {
+        return Ok(());
+    };
+    let secondary_signers = decoded.map_or(0, |decoded| decoded.secondary_signer_addresses().len());
+    if quorum != 0 && quorum as usize <= secondary_signers {
+        return Ok(());
+    }
+    let message = format!(
+        "quorum must be between 1 and the transaction's {} secondary signers",
+        secondary_signers
+    );
+    warn!("Rejected transaction: {}", message);
+    Err(finish(
+        StatusCode::BAD_REQUEST,
+        StoreTransactionResponse::failure(req.transaction_id.clone(), message),
+    ))
+}
+
+/// The ways the BCS is not canonical, rejected under `REJECT_NON_CANONICAL`.
+/// The SDK's decoder refuses non-canonical BCS, so such bytes would be
+/// stored as unrecognized and only fail once something decodes them
+fn check_canonical(
+    state: &AppState,
+    req: &StoreRequest,
+    kind: Option<TransactionKind>,
+) -> Result<Option<Vec<CanonicalIssue>>, Finished> {
+    let canonical_issues = canonical::check(kind, &req.raw_bcs).filter(|issues| !issues.is_empty());
+    let Some(issues) = &canonical_issues else {
+        return Ok(None);
+    };
+    let described: Vec<String> = issues.iter().map(CanonicalIssue::describe).collect();
+    warn!(
+        transaction_id = %req.transaction_id,
+        issues = ?described,
+        "BCS is not canonical"
+    );
+    if !state.reject_non_canonical {
+        return Ok(canonical_issues);
+    }
+    Err(finish(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        StoreTransactionResponse {
+            canonical_issues,
+            ..StoreTransactionResponse::failure(
+                req.transaction_id.clone(),
+                format!(
+                    "BCS is not canonical ({}); the SDK's decoder would reject it",
+                    described.join(", ")
+                ),
+            )
+        },
+    ))
+}
+
+/// Answer a store of an id that is already stored (and not expired): a no-op
+/// for identical bytes, a `409` for different ones. The transaction id
+/// doubles as an idempotency key, and different bytes must not silently
+/// replace a transaction another signer may already have signed
+async fn check_existing(
+    state: &AppState,
+    req: &StoreRequest,
+    decoded: Option<&DecodedTransaction>,
+) -> Result<(), Finished> {
+    let transaction_id = &req.transaction_id;
+    let existing = match state.store.get(transaction_id).await {
+        Ok(existing) => existing.filter(|tx| {
+            !state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.is_expired(tx.stored_at))
+        }),
+        Err(e) => {
+            error!("Failed to look up transaction: {}", e);
+            return Err(finish(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StoreTransactionResponse::failure(
+                    transaction_id.clone(),
+                    format!("Failed to look up transaction: {}", e),
+                ),
+            ));
+        }
+    };
+    let Some(existing) = existing else {
+        return Ok(());
+    };
+    let stored_bcs = match existing.bcs() {
+        Ok(bcs) => bcs,
+        Err(e) => {
+            error!("Failed to decompress stored transaction: {}", e);
+            return Err(finish(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StoreTransactionResponse::failure(
+                    transaction_id.clone(),
+                    format!("Failed to decompress stored transaction: {}", e),
+                ),
+            ));
+        }
+    };
+    if *stored_bcs == req.raw_bcs {
+        info!(transaction_id = %transaction_id, "Identical transaction already stored");
+        return Err(finish(
+            StatusCode::OK,
+            StoreTransactionResponse {
+                success: true,
+                kind: decoded.map(DecodedTransaction::kind),
+                session_state: Some(state.session_state_decoded(&existing, decoded)),
+                signers_pending: existing.signers_pending(),
+                signers_done: existing.signers_done(),
+                ..StoreTransactionResponse::failure(
+                    transaction_id.clone(),
+                    "Transaction already stored".to_string(),
+                )
+            },
+        ));
+    }
+
+    let diff = diff_bcs(&stored_bcs, &req.raw_bcs);
+    warn!(
+        transaction_id = %transaction_id,
+        first_difference = diff.first_difference,
+        changed_fields = ?diff.changed_fields,
+        "Rejected replay with different bytes"
+    );
+    let message = format!(
+        "Transaction id already stored with different bytes (first difference at byte {}); delete it first to replace it",
+        diff.first_difference
+    );
+    Err(finish(
+        StatusCode::CONFLICT,
+        StoreTransactionResponse {
+            session_state: Some(state.session_state(&existing)),
+            conflict: Some(diff),
+            ..StoreTransactionResponse::failure(transaction_id.clone(), message)
+        },
+    ))
+}
+
+/// Where `expiration_timestamp_secs` stands, rejecting a transaction that
+/// has already expired. An expired transaction fails on chain with
+/// TRANSACTION_EXPIRED, which is easily mistaken for a sequence number
+/// problem once signatures are in
+fn check_expiration(
+    state: &AppState,
+    req: &StoreRequest,
+    decoded: Option<&DecodedTransaction>,
+    now: u64,
+) -> Result<Option<ExpirationStatus>, Finished> {
+    let expiration = decoded.map(|decoded| decoded.expiration(now, state.expiration_warning_secs));
+    let Some(expired) = expiration.filter(|expiration| expiration.expired) else {
+        return Ok(expiration);
+    };
+    warn!(
+        transaction_id = %req.transaction_id,
+        expiration_timestamp_secs = expired.expiration_timestamp_secs,
+        "Rejected expired transaction"
+    );
+    let message = format!(
+        "Transaction expired {} seconds ago (expiration_timestamp_secs {}); the chain would reject it with TRANSACTION_EXPIRED",
+        expired.expires_in_secs.unsigned_abs(),
+        expired.expiration_timestamp_secs
+    );
+    Err(finish(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        StoreTransactionResponse {
+            kind: decoded.map(DecodedTransaction::kind),
+            expiration: Some(expired),
+            ..StoreTransactionResponse::failure(req.transaction_id.clone(), message)
+        },
+    ))
+}
+
+/// How the transaction's `chain_id` differs from the fullnode's, rejected
+/// under `CHAIN_ID_CHECK=reject`. A transaction built for another network
+/// fails with BAD_CHAIN_ID, a common false positive when repro runs mix
+/// localnet and testnet
+async fn check_chain_id(
+    state: &AppState,
+    req: &StoreRequest,
+    decoded: Option<&DecodedTransaction>,
+    expiration: Option<ExpirationStatus>,
+) -> Result<Option<ChainIdMismatch>, Finished> {
+    let mismatch = match (&state.fullnode, decoded) {
+        (Some(fullnode), Some(decoded)) if state.chain_id_check != ChainIdCheck::Off => {
+            let transaction_chain_id = decoded.raw_txn().chain_id.id();
+            match state.fullnode_chain_id.get(fullnode).await {
+                Ok(fullnode_chain_id) if fullnode_chain_id != transaction_chain_id => {
+                    Some(ChainIdMismatch {
+                        transaction_chain_id,
+                        fullnode_chain_id,
+                    })
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    // Storing doesn't depend on the fullnode being up
+                    warn!(
+                        "Skipping chain id check, failed to fetch the fullnode's: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let Some(mismatch) = mismatch else {
+        return Ok(None);
+    };
+    warn!(
+        transaction_id = %req.transaction_id,
+        transaction_chain_id = mismatch.transaction_chain_id,
+        fullnode_chain_id = mismatch.fullnode_chain_id,
+        "Transaction is for another network"
+    );
+    if state.chain_id_check != ChainIdCheck::Reject {
+        return Ok(Some(mismatch));
+    }
+    let message = format!(
+        "Transaction chain_id {} does not match the fullnode's {}; the chain would reject it with BAD_CHAIN_ID",
+        mismatch.transaction_chain_id, mismatch.fullnode_chain_id
+    );
+    Err(finish(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        StoreTransactionResponse {
+            kind: decoded.map(DecodedTransaction::kind),
+            expiration,
+            chain_id_mismatch: Some(mismatch),
+            ..StoreTransactionResponse::failure(req.transaction_id.clone(), message)
+        },
+    ))
+}
+
+/// The bytes to store and whether they are compressed
+fn compress(state: &AppState, req: StoreRequest) -> Result<(StoreRequest, bool), Finished> {
+    match state.compression.map(|c| c.compress(&req.raw_bcs)) {
+        Some(Ok(Some(compressed))) => {
+            debug!(
+                "Compressed: {} -> {} bytes",
+                req.raw_bcs.len(),
+                compressed.len()
+            );
+            Ok((
+                StoreRequest {
+                    raw_bcs: compressed,
+                    ..req
+                },
+                true,
+            ))
+        }
+        Some(Ok(None)) | None => Ok((req, false)),
+        Some(Err(e)) => {
+            error!("Failed to compress transaction: {}", e);
+            Err(finish(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StoreTransactionResponse::failure(
+                    req.transaction_id,
+                    format!("Failed to compress transaction: {}", e),
+                ),
+            ))
+        }
+    }
+}
+
+/// Store a serialized transaction from the frontend.
+/// Decodes the hex once and stores the raw BCS bytes as-is (pass-through mode). The body may
+/// instead be the raw BCS under `Content-Type: application/octet-stream`, with the id, kind,
+/// callback URL and quorum in headers.
+#[utoipa::path(
+    post,
+    path = "/transaction",
+    params(
+        ("x-transaction-id" = Option<String>, Header, description = "Id to store under; required with an `application/octet-stream` body"),
+        ("x-transaction-kind" = Option<TransactionKind>, Header, description = "Payload the BCS must decode as, with an `application/octet-stream` body"),
+        ("x-callback-url" = Option<String>, Header, description = "Webhook URL, as `callback_url`, with an `application/octet-stream` body"),
+        ("x-quorum" = Option<u32>, Header, description = "Secondary signatures that complete the session, as `quorum`, with an `application/octet-stream` body"),
+    ),
+    request_body(content(
+        (StoreTransactionRequest = "application/json"),
+        (Vec<u8> = "application/octet-stream"),
+    )),
+    responses(
+        (status = 200, description = "Transaction stored (or an identical replay)", body = StoreTransactionResponse),
+        (status = 400, description = "`bcs_hex` is not valid hex or base64 (per `encoding`), `callback_url` is not an http(s) URL, `quorum` is out of range, or `x-transaction-id` is missing or a binary store's headers are invalid", body = StoreTransactionResponse),
+        (status = 409, description = "Id already stored with different bytes; see `conflict`", body = StoreTransactionResponse),
+        (status = 413, description = "`bcs_hex` or the body is over its size limit", body = StoreTransactionResponse),
+        (status = 422, description = "`expiration_timestamp_secs` has already passed, see `expiration`; `chain_id` is not the fullnode's, see `chain_id_mismatch`; or the BCS is not canonical under `REJECT_NON_CANONICAL`, see `canonical_issues`", body = StoreTransactionResponse),
+        (status = 500, description = "Storage error", body = StoreTransactionResponse),
+    )
+)]
+pub async fn store_transaction(
+    State(state): State<Arc<AppState>>,
+    body: StoreBody<StoreTransactionRequest>,
+) -> (StatusCode, Json<StoreTransactionResponse>) {
+    match store(&state, body).await {
+        Ok(response) => (StatusCode::OK, Json(response)),
+        Err(finished) => *finished,
+    }
+}
+
+/// Check a store request step by step and write it, unless a step answers
+/// first
+async fn store(
+    state: &AppState,
+    body: StoreBody<StoreTransactionRequest>,
+) -> Result<StoreTransactionResponse, Finished> {
+    let req = match body {
+        StoreBody::Json(req) => decode_store_request(state, req)?,
+        StoreBody::Binary(binary) => binary_store_request(state, binary)?,
+    };
+
+    // Parse sequence number for console logging only (works even if the full decode fails)
+    if let Some(seq) = parse_sequence_number(&req.raw_bcs) {
+        debug!("Parsed sequence_number: {}", seq);
+    }
+
+    let decoded = decode_stored(&req)?;
+    let kind = decoded.as_ref().map(DecodedTransaction::kind);
+    check_quorum(&req, decoded.as_ref())?;
+    let canonical_issues = check_canonical(state, &req, kind)?;
+    let canonical = match &canonical_issues {
+        Some(_) => Some(false),
+        None if kind.is_some() => Some(true),
+        None => None,
+    };
+
+    // Locked until the write so two stores of the same id can't both miss
+    // and both write
+    let lock = state.transaction_locks.lock(&req.transaction_id).await;
+    check_existing(state, &req, decoded.as_ref()).await?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let expiration = check_expiration(state, &req, decoded.as_ref(), now)?;
+    let chain_id_mismatch = check_chain_id(state, &req, decoded.as_ref(), expiration).await?;
+
+    let uncompressed_len = req.raw_bcs.len();
+    let (req, compressed) = compress(state, req)?;
+    let StoreRequest {
+        transaction_id,
+        raw_bcs,
+        hex_prefixed,
+        callback_url,
+        quorum,
+        ..
+    } = req;
+
+    let sender = decoded
+        .as_ref()
+        .map(|decoded| decoded.raw_txn().sender.to_long_string());
+    let sequence_number = decoded
+        .as_ref()
+        .map(|decoded| decoded.raw_txn().sequence_number);
+    let required_signers = decoded.as_ref().map(DecodedTransaction::required_signers);
+    let stored_len = raw_bcs.len();
+    let stored_event = TransactionEvent::now(EventKind::Stored, None);
+    let stored = StoredTransaction {
+        sender: sender.clone(),
+        sequence_number,
+        canonical,
+        required_signers,
+        quorum,
+        raw_bcs,
+        compressed,
+        hex_prefixed,
+        secondary_signatures: BTreeMap::new(),
+        fee_payer_signature: None,
+        stored_at: now,
+        history: vec![stored_event.clone()],
+    };
+    let session_state = state.session_state_decoded(&stored, decoded.as_ref());
+    let signers_pending = stored.signers_pending();
+
+    if let Err(e) = state.store.put(&transaction_id, stored).await {
+        error!("Failed to store transaction: {}", e);
+        return Err(finish(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StoreTransactionResponse::failure(
+                transaction_id,
+                format!("Failed to store transaction: {}", e),
+            ),
+        ));
+    }
+    drop(lock);
+
+    if let Some(expiry) = &state.expiry {
+        expiry.clear(&transaction_id);
+    }
+    if let Some(url) = callback_url {
+        state.webhooks.register(&transaction_id, url);
+    }
+    state.notifier.publish(&transaction_id, &[stored_event]);
+
+    info!(
+        transaction_id = %transaction_id,
+        sender = sender.as_deref(),
+        sequence_number,
+        kind = kind.map(TransactionKind::as_str),
+        bcs_len = uncompressed_len,
+        stored_len,
+        "Transaction stored"
+    );
+
+    Ok(StoreTransactionResponse {
+        success: true,
+        transaction_id,
+        limit_exceeded: None,
+        conflict: None,
+        kind,
+        expiration,
+        session_state: Some(session_state),
+        signers_done: signers_pending.as_ref().map(|_| Vec::new()),
+        signers_pending,
+        message: match chain_id_mismatch {
+            Some(mismatch) => format!(
+                "Transaction stored, but its chain_id {} does not match the fullnode's {}",
+                mismatch.transaction_chain_id, mismatch.fullnode_chain_id
+            ),
+            None => "Transaction stored".to_string(),
+        },
+        chain_id_mismatch,
+        canonical_issues,
+    })
+}
+
+/// Store many serialized transactions in one request.
+/// Each item is handled exactly like `POST /transaction`; failures don't stop the batch.
+#[utoipa::path(
+    post,
+    path = "/transactions/batch",
+    request_body = Vec<StoreTransactionRequest>,
+    responses(
+        (status = 200, description = "Per-item results; `success` is false if any item failed", body = BatchStoreResponse),
+    )
+)]
+pub async fn store_transactions_batch(
+    State(state): State<Arc<AppState>>,
+    Json(reqs): Json<Vec<StoreTransactionRequest>>,
+) -> (StatusCode, Json<BatchStoreResponse>) {
+    info!("Storing batch of {} transactions", reqs.len());
+
+    let mut results = Vec::with_capacity(reqs.len());
+    for req in reqs {
+        let (_, Json(result)) = store_transaction(State(state.clone()), StoreBody::Json(req)).await;
+        results.push(result);
+    }
+
+    let stored = results.iter().filter(|result| result.success).count();
+    let failed = results.len() - stored;
+    info!("Batch done: {} stored, {} failed", stored, failed);
+
+    (
+        StatusCode::OK,
+        Json(BatchStoreResponse {
+            success: failed == 0,
+            stored,
+            failed,
+            results,
+            message: format!("{} stored, {} failed", stored, failed),
+        }),
+    )
+}
+
+/// Most transactions one `POST /debug/generate` makes
+pub const MAX_GENERATE_COUNT: usize = 1000;
+
+/// Most secondary signers of a generated transaction
+pub const MAX_GENERATE_SIGNERS: usize = 16;
+
+/// Generate valid transactions from a seed and store them.
+/// Senders, sequence numbers, payloads, gas parameters and signer addresses are random but
+/// reproducible; each transaction is then stored exactly like `POST /transaction`.
+#[utoipa::path(
+    post,
+    path = "/debug/generate",
+    request_body = GenerateRequest,
+    responses(
+        (status = 200, description = "Generated transactions with a store result each; `success` is false if any failed", body = GenerateResponse),
+        (status = 400, description = "`count` or `secondary_signers` is over its maximum", body = GenerateResponse),
+    )
+)]
+pub async fn generate_transactions(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<GenerateRequest>,
+) -> (StatusCode, Json<GenerateResponse>) {
+    let count = req.count.unwrap_or(1);
+    let secondary_signers = req.secondary_signers.unwrap_or(2);
+    if count > MAX_GENERATE_COUNT || secondary_signers > MAX_GENERATE_SIGNERS {
+        let message = format!(
+            "count is at most {} and secondary_signers at most {}",
+            MAX_GENERATE_COUNT, MAX_GENERATE_SIGNERS
+        );
+        warn!("Rejected generation: {}", message);
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenerateResponse {
+                success: false,
+                seed: req.seed,
+                stored: 0,
+                failed: 0,
+                transactions: Vec::new(),
+                message,
+            }),
+        );
+    }
+
+    let chain_id = match (req.chain_id, &state.fullnode) {
+        (Some(chain_id), _) => Some(chain_id),
+        (None, Some(fullnode)) => state.fullnode_chain_id.get(fullnode).await.ok(),
+        (None, None) => None,
+    }
+    .unwrap_or(ChainId::testnet().id());
+    let expiration_timestamp_secs = req.expiration_timestamp_secs.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600
+    });
+    let options = generate::Options {
+        seed: req.seed,
+        count,
+        secondary_signers,
+        fee_payer: req.fee_payer,
+        chain_id,
+        expiration_timestamp_secs,
+    };
+    let prefix = req.id_prefix.unwrap_or_else(|| format!("gen-{}", req.seed));
+    info!(seed = req.seed, count, "Generating transactions");
+
+    let mut transactions = Vec::with_capacity(count);
+    for (index, decoded) in generate::generate(&options).into_iter().enumerate() {
+        let transaction_id = format!("{}-{}", prefix, index);
+        let bcs_hex = hex::encode(generate::to_bcs(&decoded));
+        let store = StoreTransactionRequest {
+            transaction_id: transaction_id.clone(),
+            bcs_hex: bcs_hex.clone(),
+            encoding: Encoding::Hex,
+            kind: Some(decoded.kind()),
+            callback_url: None,
+            quorum: None,
+        };
+        let (_, Json(result)) =
+            store_transaction(State(state.clone()), StoreBody::Json(store)).await;
+        transactions.push(GeneratedTransaction {
+            transaction_id,
+            sender: decoded.raw_txn().sender.to_long_string(),
+            secondary_signers: decoded
+                .secondary_signer_addresses()
+                .iter()
+                .map(AccountAddress::to_long_string)
+                .collect(),
+            fee_payer: decoded
+                .fee_payer_address()
+                .map(|address| address.to_long_string()),
+            bcs_hex,
+            result,
+        });
+    }
+
+    let stored = transactions
+        .iter()
+        .filter(|transaction| transaction.result.success)
+        .count();
+    let failed = transactions.len() - stored;
+    info!("Generation done: {} stored, {} failed", stored, failed);
+
+    (
+        StatusCode::OK,
+        Json(GenerateResponse {
+            success: failed == 0,
+            seed: req.seed,
+            stored,
+            failed,
+            transactions,
+            message: format!("{} generated and stored, {} failed", stored, failed),
+        }),
+    )
+}
+
+/// Try to parse the sequence number from a serialized MultiAgentTransaction
+/// This is for debugging purposes only
+pub fn parse_sequence_number(bytes: &[u8]) -> Option<u64> {
+    // The MultiAgentTransaction BCS format is:
+    // - RawTransaction (which starts with sender address, then sequence_number)
+    // - Secondary signer addresses
+    //
+    // RawTransaction layout:
+    // - sender: 32 bytes (AccountAddress)
+    // - sequence_number: 8 bytes (u64, little-endian)
+    // ... rest of transaction
+    //
+    // We need at least 40 bytes (32 for address + 8 for seq num)
+    if bytes.len() < 40 {
+        return None;
+    }
+
+    // Sequence number is at offset 32, 8 bytes, little-endian
+    let seq_bytes: [u8; 8] = bytes[32..40].try_into().ok()?;
+    Some(u64::from_le_bytes(seq_bytes))
+}
@@ -0,0 +1,641 @@
+//! Assembling a signed transaction from the stored signatures, submitting it
+//! to the fullnode and checking whether it committed.
+
+use crate::authenticator::AccountAuthenticator;
+use crate::limits::LimitExceeded;
+use crate::session::{Operation, SessionState};
+use crate::storage::{hex_bytes, Commit, EventKind, TransactionEvent};
+use crate::transaction::{AssembleError, DecodedTransaction};
+use crate::{record_history, submission, verify, AppState};
+use aptos_sdk::AptosError;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct AssembleRequest {
+    /// The sender's BCS `AccountAuthenticator`, as hex
+    sender_signature_hex: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AssembleResponse {
+    success: bool,
+    transaction_id: String,
+    /// BCS of the assembled `SignedTransaction`, as hex
+    signed_transaction_hex: Option<String>,
+    /// Signers still to sign (`409` only): long-form secondary signer
+    /// addresses, and `fee_payer` for the fee payer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_signers: Option<Vec<String>>,
+    /// Set with a `413` when `sender_signature_hex` is over `MAX_SIGNATURE_HEX_LEN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit_exceeded: Option<LimitExceeded>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SubmitResponse {
+    success: bool,
+    transaction_id: String,
+    /// Hash the fullnode accepted the transaction under
+    hash: Option<String>,
+    /// VM status the fullnode rejected the transaction with, e.g.
+    /// `SEQUENCE_NUMBER_TOO_OLD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vm_status: Option<String>,
+    /// Signers still to sign (`409` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_signers: Option<Vec<String>>,
+    /// Set with a `413` when `sender_signature_hex` is over `MAX_SIGNATURE_HEX_LEN`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit_exceeded: Option<LimitExceeded>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VerifyResponse {
+    success: bool,
+    transaction_id: String,
+    /// Hash the fullnode accepted the transaction under
+    hash: Option<String>,
+    /// Whether the transaction is committed on chain
+    committed: bool,
+    /// Version, VM status and gas used, once committed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<Commit>,
+    /// Where the signing session stands, whenever the transaction exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_state: Option<SessionState>,
+    message: String,
+}
+
+/// Assemble the signed transaction once every signer has signed.
+/// Combines the sender's signature with the stored secondary (and fee payer) signatures into the
+/// multi-agent or fee payer `SignedTransaction` that gets submitted.
+#[utoipa::path(
+    post,
+    path = "/transaction/{transaction_id}/assemble",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    request_body = AssembleRequest,
+    responses(
+        (status = 200, description = "Signed transaction assembled", body = AssembleResponse),
+        (status = 400, description = "Not a valid `AccountAuthenticator`, or not a multi-agent or fee payer transaction", body = AssembleResponse),
+        (status = 404, description = "Unknown transaction", body = AssembleResponse),
+        (status = 409, description = "Signatures are still missing (including when only the quorum has signed)", body = AssembleResponse),
+        (status = 410, description = "Transaction has expired and was removed", body = AssembleResponse),
+        (status = 413, description = "`sender_signature_hex` or the body is over its size limit", body = AssembleResponse),
+        (status = 422, description = "Stored BCS does not decode, a stored signature is keyed by an address that isn't a secondary signer, or the sender's Ed25519 signature does not verify", body = AssembleResponse),
+        (status = 500, description = "Storage error, or a stored signature is corrupt", body = AssembleResponse),
+    )
+)]
+pub async fn assemble_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    Json(req): Json<AssembleRequest>,
+) -> (StatusCode, Json<AssembleResponse>) {
+    info!(transaction_id = %transaction_id, "Assembling signed transaction");
+
+    match assemble(&state, &transaction_id, &req.sender_signature_hex, None).await {
+        Ok(assembled) => {
+            let event = TransactionEvent::now(
+                EventKind::Assembled,
+                Some(format!("{} bytes", assembled.bcs.len())),
+            );
+            record_history(&state, &transaction_id, vec![event]).await;
+            (
+                StatusCode::OK,
+                Json(AssembleResponse {
+                    success: true,
+                    transaction_id,
+                    signed_transaction_hex: Some(hex_bytes::encode(&assembled.bcs, true)),
+                    missing_signers: None,
+                    limit_exceeded: None,
+                    session_state: Some(assembled.session_state),
+                    message: "Signed transaction assembled".to_string(),
+                }),
+            )
+        }
+        Err(failure) => (
+            failure.status,
+            Json(AssembleResponse {
+                success: false,
+                transaction_id,
+                signed_transaction_hex: None,
+                missing_signers: failure.missing_signers,
+                limit_exceeded: failure.limit_exceeded,
+                session_state: failure.session_state,
+                message: failure.message,
+            }),
+        ),
+    }
+}
+
+/// Assemble and submit the signed transaction to the configured fullnode.
+/// Records the returned hash, or the VM status the fullnode rejected it with, in the history.
+#[utoipa::path(
+    post,
+    path = "/transaction/{transaction_id}/submit",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    request_body = AssembleRequest,
+    responses(
+        (status = 200, description = "Accepted by the fullnode", body = SubmitResponse),
+        (status = 400, description = "Not a valid `AccountAuthenticator`, or not a multi-agent or fee payer transaction", body = SubmitResponse),
+        (status = 404, description = "Unknown transaction", body = SubmitResponse),
+        (status = 409, description = "Signatures are still missing (including when only the quorum has signed), or the session was already submitted", body = SubmitResponse),
+        (status = 410, description = "Transaction has expired", body = SubmitResponse),
+        (status = 413, description = "`sender_signature_hex` or the body is over its size limit", body = SubmitResponse),
+        (status = 422, description = "Stored BCS does not decode, a stored signature is keyed by an address that isn't a secondary signer, the sender's Ed25519 signature does not verify, or the fullnode rejected the transaction", body = SubmitResponse),
+        (status = 500, description = "Storage error, or a stored signature is corrupt", body = SubmitResponse),
+        (status = 502, description = "The fullnode could not be reached or failed", body = SubmitResponse),
+        (status = 503, description = "No fullnode configured", body = SubmitResponse),
+    )
+)]
+pub async fn submit_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+    Json(req): Json<AssembleRequest>,
+) -> (StatusCode, Json<SubmitResponse>) {
+    info!(transaction_id = %transaction_id, "Submitting signed transaction");
+
+    let Some(fullnode) = &state.fullnode else {
+        warn!("No fullnode configured");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(SubmitResponse {
+                success: false,
+                transaction_id,
+                hash: None,
+                vm_status: None,
+                missing_signers: None,
+                limit_exceeded: None,
+                session_state: None,
+                message: "No fullnode configured (set --fullnode-url)".to_string(),
+            }),
+        );
+    };
+
+    let assembled = match assemble(
+        &state,
+        &transaction_id,
+        &req.sender_signature_hex,
+        Some(Operation::Submit),
+    )
+    .await
+    {
+        Ok(assembled) => assembled,
+        Err(failure) => {
+            return (
+                failure.status,
+                Json(SubmitResponse {
+                    success: false,
+                    transaction_id,
+                    hash: None,
+                    vm_status: None,
+                    missing_signers: failure.missing_signers,
+                    limit_exceeded: failure.limit_exceeded,
+                    session_state: failure.session_state,
+                    message: failure.message,
+                }),
+            );
+        }
+    };
+
+    match submission::submit(fullnode, assembled.bcs).await {
+        Ok(hash) => {
+            info!(transaction_id = %transaction_id, hash = %hash, "Transaction submitted");
+            let event = TransactionEvent::now(EventKind::Submitted, Some(hash.clone()));
+            record_history(&state, &transaction_id, vec![event]).await;
+            if let Some(interval) = state.submission_poll_interval {
+                verify::watch(
+                    state.clone(),
+                    transaction_id.clone(),
+                    hash.clone(),
+                    interval,
+                );
+            }
+            (
+                StatusCode::OK,
+                Json(SubmitResponse {
+                    success: true,
+                    transaction_id,
+                    hash: Some(hash),
+                    vm_status: None,
+                    missing_signers: None,
+                    limit_exceeded: None,
+                    session_state: Some(SessionState::Submitted),
+                    message: "Transaction submitted".to_string(),
+                }),
+            )
+        }
+        Err(AptosError::Api {
+            status_code,
+            message,
+            ..
+        }) if (400..500).contains(&status_code) => {
+            let vm_status = vm_status(&message);
+            warn!(vm_status = %vm_status, "Fullnode rejected transaction: {}", message);
+            let event = TransactionEvent::now(EventKind::SubmissionFailed, Some(vm_status.clone()));
+            record_history(&state, &transaction_id, vec![event]).await;
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(SubmitResponse {
+                    success: false,
+                    transaction_id,
+                    hash: None,
+                    vm_status: Some(vm_status),
+                    missing_signers: None,
+                    limit_exceeded: None,
+                    session_state: Some(SessionState::Failed),
+                    message: format!("Fullnode rejected transaction: {}", message),
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to submit transaction: {}", e);
+            let event = TransactionEvent::now(EventKind::SubmissionFailed, Some(e.to_string()));
+            record_history(&state, &transaction_id, vec![event]).await;
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(SubmitResponse {
+                    success: false,
+                    transaction_id,
+                    hash: None,
+                    vm_status: None,
+                    missing_signers: None,
+                    limit_exceeded: None,
+                    session_state: Some(SessionState::Failed),
+                    message: format!("Failed to submit transaction: {}", e),
+                }),
+            )
+        }
+    }
+}
+
+/// Check whether a submitted transaction is committed on chain.
+/// Looks the submitted hash up on the configured fullnode and, once committed, records its version,
+/// VM status and gas used in the history; after that the recorded outcome is returned as is.
+#[utoipa::path(
+    post,
+    path = "/transaction/{transaction_id}/verify",
+    params(("transaction_id" = String, Path, description = "Id the transaction was stored under")),
+    responses(
+        (status = 200, description = "Committed (check `commit.success` for how it executed)", body = VerifyResponse),
+        (status = 202, description = "Pending, or not known to the fullnode yet", body = VerifyResponse),
+        (status = 404, description = "Unknown transaction", body = VerifyResponse),
+        (status = 409, description = "Transaction was never accepted by the fullnode", body = VerifyResponse),
+        (status = 410, description = "Transaction has expired", body = VerifyResponse),
+        (status = 500, description = "Storage error", body = VerifyResponse),
+        (status = 502, description = "The fullnode could not be reached or failed", body = VerifyResponse),
+        (status = 503, description = "No fullnode configured", body = VerifyResponse),
+    )
+)]
+pub async fn verify_transaction(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(transaction_id): axum::extract::Path<String>,
+) -> (StatusCode, Json<VerifyResponse>) {
+    info!(transaction_id = %transaction_id, "Verifying submitted transaction");
+    let failure = |status: StatusCode,
+                   transaction_id: String,
+                   hash: Option<String>,
+                   session_state: Option<SessionState>,
+                   message: String| {
+        (
+            status,
+            Json(VerifyResponse {
+                success: false,
+                transaction_id,
+                hash,
+                committed: false,
+                commit: None,
+                session_state,
+                message,
+            }),
+        )
+    };
+
+    let tx = match state.store.get(&transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(&transaction_id)) =>
+        {
+            warn!("Transaction expired");
+            let message = "Transaction expired".to_string();
+            return failure(StatusCode::GONE, transaction_id, None, None, message);
+        }
+        Ok(None) => {
+            warn!("Transaction not found");
+            let message = "Transaction not found".to_string();
+            return failure(StatusCode::NOT_FOUND, transaction_id, None, None, message);
+        }
+        Err(e) => {
+            error!("Failed to read from storage: {}", e);
+            let message = format!("Failed to read from storage: {}", e);
+            return failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                transaction_id,
+                None,
+                None,
+                message,
+            );
+        }
+    };
+    let session_state = state.session_state(&tx);
+    let Some(hash) = tx.submitted_hash().map(str::to_string) else {
+        warn!("Transaction was never submitted");
+        let message = "Transaction was never accepted by the fullnode".to_string();
+        return failure(
+            StatusCode::CONFLICT,
+            transaction_id,
+            None,
+            Some(session_state),
+            message,
+        );
+    };
+
+    // A committed transaction stays committed, so there's nothing to ask
+    if let Some(commit) = tx.commit() {
+        return (
+            StatusCode::OK,
+            Json(VerifyResponse {
+                success: true,
+                transaction_id,
+                hash: Some(hash),
+                committed: true,
+                commit: Some(commit.clone()),
+                session_state: Some(session_state),
+                message: format!("Committed at version {}", commit.version),
+            }),
+        );
+    }
+
+    let Some(fullnode) = &state.fullnode else {
+        warn!("No fullnode configured");
+        let message = "No fullnode configured (set --fullnode-url)".to_string();
+        return failure(
+            StatusCode::SERVICE_UNAVAILABLE,
+            transaction_id,
+            Some(hash),
+            Some(session_state),
+            message,
+        );
+    };
+    let message = match verify::lookup(fullnode, &hash).await {
+        Ok(verify::Lookup::Committed(commit)) => {
+            info!(
+                transaction_id = %transaction_id,
+                version = commit.version,
+                vm_status = %commit.vm_status,
+                gas_used = commit.gas_used,
+                "Transaction committed"
+            );
+            let event = TransactionEvent::committed(commit.clone());
+            record_history(&state, &transaction_id, vec![event]).await;
+            return (
+                StatusCode::OK,
+                Json(VerifyResponse {
+                    success: true,
+                    transaction_id,
+                    hash: Some(hash),
+                    committed: true,
+                    message: format!("Committed at version {}", commit.version),
+                    session_state: Some(if commit.success {
+                        SessionState::Finalized
+                    } else {
+                        SessionState::Failed
+                    }),
+                    commit: Some(commit),
+                }),
+            );
+        }
+        Ok(verify::Lookup::Pending) => "Pending in the fullnode's mempool".to_string(),
+        Ok(verify::Lookup::NotFound) => {
+            "Not known to the fullnode yet (not propagated, dropped or expired)".to_string()
+        }
+        Err(e) => {
+            error!("Failed to look up transaction {}: {}", hash, e);
+            let message = format!("Failed to look up transaction: {}", e);
+            return failure(
+                StatusCode::BAD_GATEWAY,
+                transaction_id,
+                Some(hash),
+                Some(session_state),
+                message,
+            );
+        }
+    };
+    (
+        StatusCode::ACCEPTED,
+        Json(VerifyResponse {
+            success: true,
+            transaction_id,
+            hash: Some(hash),
+            committed: false,
+            commit: None,
+            session_state: Some(session_state),
+            message,
+        }),
+    )
+}
+
+/// The VM status code in a fullnode rejection such as `Invalid transaction:
+/// Type: Validation Code: SEQUENCE_NUMBER_TOO_OLD`, or the whole message if
+/// it doesn't name one
+pub fn vm_status(message: &str) -> String {
+    match message.rsplit_once("Code: ") {
+        Some((_, code)) => code.trim().to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// A signed transaction built by [`assemble`]
+pub struct Assembled {
+    /// BCS of the `SignedTransaction`
+    bcs: Vec<u8>,
+    /// The session's state when it was assembled
+    session_state: SessionState,
+}
+
+/// Why [`assemble`] failed, and the status to answer with
+pub struct AssembleFailure {
+    status: StatusCode,
+    message: String,
+    missing_signers: Option<Vec<String>>,
+    limit_exceeded: Option<LimitExceeded>,
+    /// Set once the stored transaction has been decoded
+    session_state: Option<SessionState>,
+}
+
+impl AssembleFailure {
+    fn new(status: StatusCode, message: String) -> Self {
+        if status.is_server_error() {
+            error!("{}", message);
+        } else {
+            warn!("{}", message);
+        }
+        Self {
+            status,
+            message,
+            missing_signers: None,
+            limit_exceeded: None,
+            session_state: None,
+        }
+    }
+
+    fn in_state(mut self, session_state: SessionState) -> Self {
+        self.session_state = Some(session_state);
+        self
+    }
+}
+
+/// Combine the sender's signature with the stored signatures of
+/// `transaction_id` into its `SignedTransaction`, first checking that the
+/// session allows `operation` if one is given
+pub async fn assemble(
+    state: &AppState,
+    transaction_id: &str,
+    sender_signature_hex: &str,
+    operation: Option<Operation>,
+) -> Result<Assembled, AssembleFailure> {
+    if let Some(limit) = LimitExceeded::check(
+        "sender_signature_hex",
+        state.limits.signature_hex_len,
+        sender_signature_hex.len(),
+    ) {
+        let mut failure = AssembleFailure::new(StatusCode::PAYLOAD_TOO_LARGE, limit.message());
+        failure.limit_exceeded = Some(limit);
+        return Err(failure);
+    }
+
+    let sender = match hex::decode(sender_signature_hex.trim_start_matches("0x")) {
+        Ok(bytes) => AccountAuthenticator::decode(&bytes)
+            .map_err(|e| format!("Invalid authenticator format: {}", e)),
+        Err(e) => Err(format!("Invalid authenticator hex format: {}", e)),
+    }
+    .map_err(|message| AssembleFailure::new(StatusCode::BAD_REQUEST, message))?;
+
+    let tx = match state.store.get(transaction_id).await {
+        Ok(Some(tx)) => tx,
+        Ok(None)
+            if state
+                .expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.was_swept(transaction_id)) =>
+        {
+            return Err(AssembleFailure::new(
+                StatusCode::GONE,
+                "Transaction expired".to_string(),
+            ));
+        }
+        Ok(None) => {
+            return Err(AssembleFailure::new(
+                StatusCode::NOT_FOUND,
+                "Transaction not found".to_string(),
+            ));
+        }
+        Err(e) => {
+            return Err(AssembleFailure::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read from storage: {}", e),
+            ));
+        }
+    };
+
+    let decoded = tx
+        .bcs()
+        .map_err(|e| e.to_string())
+        .and_then(|bcs| DecodedTransaction::decode(&bcs).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            AssembleFailure::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Stored BCS does not decode as a transaction: {}", e),
+            )
+        })?;
+
+    let session_state = state.session_state_decoded(&tx, Some(&decoded));
+    if let Some(operation) = operation {
+        session_state
+            .check(operation)
+            .map_err(|(status, message)| {
+                AssembleFailure::new(status, message).in_state(session_state)
+            })?;
+    }
+
+    // Assemble before verifying, so a transaction that can't be assembled
+    // reports why rather than a signature over the wrong kind of message
+    let signed = decoded
+        .assemble(
+            &sender,
+            &tx.current_secondary_signatures(),
+            tx.current_fee_payer_signature(),
+        )
+        .map(|bcs| Assembled { bcs, session_state });
+    let assembled = match signed {
+        Ok(assembled) => assembled,
+        Err(e) => {
+            let message = e.to_string();
+            let failure = match e {
+                AssembleError::UnsupportedKind(_) => {
+                    AssembleFailure::new(StatusCode::BAD_REQUEST, message)
+                }
+                AssembleError::Missing(signers) => {
+                    // The session's approval is in, so say why that isn't enough
+                    let message = match tx.quorum {
+                        Some(quorum) if session_state == SessionState::QuorumMet => format!(
+                            "Quorum of {} met, but the chain needs every secondary signer: {}",
+                            quorum, message
+                        ),
+                        _ => message,
+                    };
+                    let mut failure = AssembleFailure::new(StatusCode::CONFLICT, message);
+                    failure.missing_signers = Some(signers);
+                    failure
+                }
+                AssembleError::NotSecondarySigners { .. } => {
+                    AssembleFailure::new(StatusCode::UNPROCESSABLE_ENTITY, message)
+                }
+                AssembleError::Corrupt { .. } | AssembleError::Encode(_) => {
+                    AssembleFailure::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+                }
+            };
+            return Err(failure.in_state(session_state));
+        }
+    };
+
+    match decoded.verify_self_consistent(&sender, false) {
+        Ok(Some(false)) => {
+            return Err(AssembleFailure::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Sender signature does not verify against the transaction".to_string(),
+            )
+            .in_state(session_state));
+        }
+        Err(e) => {
+            return Err(AssembleFailure::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Sender signature does not verify: {}", e),
+            )
+            .in_state(session_state));
+        }
+        Ok(_) => {}
+    }
+
+    info!(
+        transaction_id = %transaction_id,
+        kind = decoded.kind().as_str(),
+        signed_len = assembled.bcs.len(),
+        "Signed transaction assembled"
+    );
+    Ok(assembled)
+}
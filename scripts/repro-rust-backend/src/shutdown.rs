@@ -0,0 +1,108 @@
+//! Graceful shutdown on SIGINT/SIGTERM.
+//!
+//! On a signal the server stops accepting connections and waits up to
+//! `SHUTDOWN_DRAIN_SECS` (default 30) for in-flight requests to finish. Then
+//! a final snapshot is written (if `SNAPSHOT_PATH` is set) and the storage
+//! backend is flushed, so nothing acknowledged is lost between the last
+//! periodic snapshot and exit. Either one empties the WAL if it succeeds.
+
+use crate::snapshot;
+use crate::storage::TransactionStore;
+use crate::wal::WalStore;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Shutdown signal shared by the server and its drain deadline
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: watch::Receiver<bool>,
+    drain_timeout: Duration,
+}
+
+impl Shutdown {
+    /// Start listening for SIGINT and SIGTERM
+    pub fn listen(drain_secs: u64) -> Self {
+        let (tx, requested) = watch::channel(false);
+        tokio::spawn(async move {
+            signal().await;
+            let _ = tx.send(true);
+        });
+        Self {
+            requested,
+            drain_timeout: Duration::from_secs(drain_secs),
+        }
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    /// Resolves once a signal has been received
+    pub async fn requested(mut self) {
+        let _ = self.requested.wait_for(|requested| *requested).await;
+    }
+
+    /// Resolves when in-flight requests have had `drain_timeout` to finish
+    pub async fn drain_deadline(self) {
+        let drain_timeout = self.drain_timeout;
+        self.requested().await;
+        tokio::time::sleep(drain_timeout).await;
+        warn!(
+            "In-flight requests did not finish within {}s; shutting down anyway",
+            drain_timeout.as_secs()
+        );
+    }
+}
+
+async fn signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, draining in-flight requests"),
+        _ = terminate => info!("Received SIGTERM, draining in-flight requests"),
+    }
+}
+
+/// Write the final snapshot and flush `store` once the server has stopped
+pub async fn finish(
+    store: &dyn TransactionStore,
+    snapshot_path: Option<&Path>,
+    wal: Option<&WalStore>,
+) {
+    if let Some(path) = snapshot_path {
+        match snapshot::checkpoint(store, path, wal).await {
+            Ok(count) => info!(
+                "Wrote final snapshot of {} transactions to {}",
+                count,
+                path.display()
+            ),
+            Err(e) => error!("Failed to write final snapshot: {}", e),
+        }
+    }
+    match store.flush().await {
+        Ok(()) => info!("Flushed {} storage", store.name()),
+        Err(e) => error!("Failed to flush {} storage: {}", store.name(), e),
+    }
+}
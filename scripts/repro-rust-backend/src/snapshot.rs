@@ -0,0 +1,135 @@
+//! Periodic JSON snapshots of the whole store.
+//!
+//! When `SNAPSHOT_PATH` is set, the full transaction map is written to that
+//! file every `SNAPSHOT_INTERVAL_SECS` seconds (default 30) and reloaded on
+//! startup, so a crash doesn't lose a half-signed multi-agent flow.
+
+use crate::config::PersistenceArgs;
+use crate::storage::{StoreError, StoreResult, StoredTransaction, TransactionStore};
+use crate::wal::WalStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+/// Snapshot format version, bumped on incompatible changes
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// On-disk snapshot of every stored transaction
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Unix timestamp when the snapshot was taken
+    pub created_at: u64,
+    pub transactions: BTreeMap<String, StoredTransaction>,
+}
+
+impl Snapshot {
+    /// Capture the current contents of `store`
+    pub async fn capture(store: &dyn TransactionStore) -> StoreResult<Self> {
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            transactions: store.list().await?.into_iter().collect(),
+        })
+    }
+
+    /// Put every transaction in the snapshot into `store`, returning how many were loaded
+    pub async fn restore_into(self, store: &dyn TransactionStore) -> StoreResult<usize> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(StoreError::Config(format!(
+                "unsupported snapshot version {} (expected {})",
+                self.version, SNAPSHOT_VERSION
+            )));
+        }
+        let count = self.transactions.len();
+        for (transaction_id, tx) in self.transactions {
+            store.put(&transaction_id, tx).await?;
+        }
+        Ok(count)
+    }
+}
+
+/// Snapshot settings
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+impl SnapshotConfig {
+    /// Returns `None` if no snapshot path is set (snapshots disabled)
+    pub fn from_config(config: &PersistenceArgs) -> Option<Self> {
+        Some(Self {
+            path: config.snapshot_path.clone()?,
+            interval: Duration::from_secs(config.snapshot_interval_secs),
+        })
+    }
+}
+
+/// Write a snapshot of `store` to `path`.
+///
+/// The snapshot is written to a temporary file first and then renamed, so a
+/// crash mid-write never leaves a truncated snapshot behind.
+pub async fn write(store: &dyn TransactionStore, path: &Path) -> StoreResult<usize> {
+    let snapshot = Snapshot::capture(store).await?;
+    let count = snapshot.transactions.len();
+    let json = serde_json::to_vec_pretty(&snapshot).map_err(io_error)?;
+
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, json).await.map_err(io_error)?;
+    tokio::fs::rename(&tmp_path, path).await.map_err(io_error)?;
+    Ok(count)
+}
+
+/// Write a snapshot of `store` to `path` and empty `wal`, whose records the
+/// snapshot now covers
+pub async fn checkpoint(
+    store: &dyn TransactionStore,
+    path: &Path,
+    wal: Option<&WalStore>,
+) -> StoreResult<usize> {
+    match wal {
+        Some(wal) => wal.checkpoint(|| write(store, path)).await,
+        None => write(store, path).await,
+    }
+}
+
+/// Load the snapshot at `path` into `store`.
+///
+/// Returns `Ok(0)` if no snapshot exists yet.
+pub async fn restore(store: &dyn TransactionStore, path: &Path) -> StoreResult<usize> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(io_error(e)),
+    };
+    let snapshot: Snapshot = serde_json::from_slice(&bytes).map_err(io_error)?;
+    snapshot.restore_into(store).await
+}
+
+/// Spawn a background task that writes a snapshot every `config.interval`,
+/// truncating `wal` after each one
+pub fn spawn(store: Arc<dyn TransactionStore>, config: SnapshotConfig, wal: Option<Arc<WalStore>>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        // The first tick completes immediately; nothing has changed since restore
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match checkpoint(store.as_ref(), &config.path, wal.as_deref()).await {
+                Ok(count) => info!("Wrote {} transactions to {}", count, config.path.display()),
+                Err(e) => error!("Failed to write snapshot: {}", e),
+            }
+        }
+    });
+}
+
+fn io_error(e: impl std::fmt::Display) -> StoreError {
+    StoreError::Backend(format!("snapshot: {}", e))
+}
@@ -0,0 +1,60 @@
+//! Submitting assembled transactions.
+//!
+//! The fullnode client only submits the SDK's `SignedTransaction`, which
+//! can't hold every authenticator the backend assembles (keyless signers,
+//! for one), so the assembled BCS is posted to the fullnode's
+//! `/transactions` as is. Errors come back as the client's would, so a
+//! rejection still carries the fullnode's status and message.
+
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::{AptosError, AptosResult};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::Value;
+
+const SIGNED_TRANSACTION_CONTENT_TYPE: &str = "application/x.aptos.signed_transaction+bcs";
+
+/// The part of the fullnode's pending transaction the backend keeps
+#[derive(Deserialize)]
+struct PendingTransaction {
+    hash: String,
+}
+
+/// Submit the BCS of a `SignedTransaction` to `fullnode`, returning the hash
+/// it was accepted under
+pub async fn submit(fullnode: &FullnodeClient, bcs: Vec<u8>) -> AptosResult<String> {
+    let mut url = fullnode.base_url().clone();
+    url.path_segments_mut()
+        .map_err(|()| AptosError::Internal("fullnode URL can't have a path".to_string()))?
+        .pop_if_empty()
+        .push("transactions");
+    let response = reqwest::Client::new()
+        .post(url)
+        .header(CONTENT_TYPE, SIGNED_TRANSACTION_CONTENT_TYPE)
+        .header(ACCEPT, "application/json")
+        .body(bcs)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status.is_success() {
+        let pending: PendingTransaction = response.json().await?;
+        return Ok(pending.hash);
+    }
+    // Not a rejection of the transaction, so not reported as one
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after_secs = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        return Err(AptosError::RateLimited { retry_after_secs });
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    Err(AptosError::api_with_details(
+        status.as_u16(),
+        body["message"].as_str().unwrap_or("Unknown error"),
+        body["error_code"].as_str().map(str::to_string),
+        body["vm_error_code"].as_u64(),
+    ))
+}
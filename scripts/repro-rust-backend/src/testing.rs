@@ -0,0 +1,127 @@
+//! In-process server for integration tests.
+//!
+//! [`spawn_test_server`] serves the full API (both `/v1` and the legacy
+//! paths) on an ephemeral localhost port, backed by a fresh [`MemoryStore`],
+//! so tests can run in parallel without sharing state or fighting over a
+//! port. Nothing is read from the command line, environment or a config
+//! file: the server runs in pass-through mode with the default size limits,
+//! no auth, no expiry and no global webhook. It has no fullnode either,
+//! unless started with [`spawn_test_server_with_fullnode`] (a stub answering
+//! `POST /transactions` is enough to submit).
+//!
+//! ```no_run
+//! # async fn run(bcs: Vec<u8>) -> Result<(), repro_core::client::ClientError> {
+//! use repro_core::client::StoreTransaction;
+//!
+//! let server = repro_server::testing::spawn_test_server().await;
+//! let client = server.client();
+//! client.store_transaction(&StoreTransaction::new("tx1", bcs)).await?;
+//! assert_eq!(client.get("tx1").await?.secondary_signatures.len(), 0);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::chain::ChainIdCheck;
+use crate::client::Client;
+use crate::limits::Limits;
+use crate::probes;
+use crate::reserialize::Strategy;
+use crate::storage::MemoryStore;
+use crate::webhook::Webhooks;
+use crate::{AppState, RequestGuards, Reserialization};
+use aptos_sdk::api::FullnodeClient;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tower_http::cors::CorsLayer;
+
+/// A running test server, shut down when dropped
+pub struct TestServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// The address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:54321`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// A [`Client`] for this server
+    pub fn client(&self) -> Client {
+        Client::new(&self.url()).expect("server URL is valid")
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Start the API on `127.0.0.1:0` with an empty in-memory store. Must be
+/// called from within a Tokio runtime, e.g. a `#[tokio::test]`.
+pub async fn spawn_test_server() -> TestServer {
+    spawn(None).await
+}
+
+/// [`spawn_test_server`], submitting to the fullnode API at `fullnode_url`
+/// (e.g. `http://127.0.0.1:8080/v1`)
+pub async fn spawn_test_server_with_fullnode(fullnode_url: &str) -> TestServer {
+    let fullnode = probes::fullnode_client(Some(fullnode_url)).expect("invalid fullnode URL");
+    spawn(fullnode).await
+}
+
+async fn spawn(fullnode: Option<FullnodeClient>) -> TestServer {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind an ephemeral port");
+    let addr = listener
+        .local_addr()
+        .expect("bound listener has an address");
+
+    let state = state(addr.port(), fullnode, Limits::default());
+
+    let app = crate::server::app(state, CorsLayer::new(), None);
+    let task = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .expect("test server failed");
+    });
+    TestServer { addr, task }
+}
+
+/// State for a test server listening on `port`, with `limits`
+pub(crate) fn state(port: u16, fullnode: Option<FullnodeClient>, limits: Limits) -> Arc<AppState> {
+    Arc::new(AppState::new(
+        Arc::new(MemoryStore::default()),
+        Reserialization {
+            enabled: AtomicBool::new(false),
+            strict: false,
+            strategy: Strategy::Typed,
+        },
+        None,
+        None,
+        // Per server, so backups taken by one test can't be restored by another
+        std::env::temp_dir().join(format!("repro-rust-backend-{}", port)),
+        fullnode,
+        None,
+        ChainIdCheck::Off,
+        false,
+        30,
+        limits,
+        RequestGuards::default(),
+        Webhooks::new(None, Duration::from_secs(5)).expect("failed to build webhook client"),
+        None,
+    ))
+}
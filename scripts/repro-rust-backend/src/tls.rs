@@ -0,0 +1,89 @@
+//! Optional HTTPS termination.
+//!
+//! Set `TLS_CERT_PATH` and `TLS_KEY_PATH` to PEM files to serve HTTPS directly
+//! instead of behind a reverse proxy; browser wallets increasingly refuse to
+//! POST signatures to plain-HTTP origins. Requires building with
+//! `--features tls`.
+
+use crate::config::ServerArgs;
+use crate::shutdown::Shutdown;
+use axum::Router;
+use std::path::PathBuf;
+
+/// Certificate and key loaded from `TLS_CERT_PATH` and `TLS_KEY_PATH`
+pub struct Tls {
+    cert_path: PathBuf,
+    #[cfg(feature = "tls")]
+    config: axum_server::tls_rustls::RustlsConfig,
+}
+
+impl Tls {
+    /// Returns `None` if no certificate is configured (serve plain HTTP)
+    pub async fn from_config(config: &ServerArgs) -> Result<Option<Self>, String> {
+        let (cert_path, key_path) = match (&config.tls_cert_path, &config.tls_key_path) {
+            (None, None) => return Ok(None),
+            (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+            // Only reachable from a config file; clap checks the flags
+            _ => return Err("tls_cert_path and tls_key_path must be set together".to_string()),
+        };
+
+        #[cfg(feature = "tls")]
+        {
+            // Several crates enable rustls; pick the provider explicitly
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let config =
+                axum_server::tls_rustls::RustlsConfig::from_pem_file(&cert_path, &key_path)
+                    .await
+                    .map_err(|e| {
+                        format!(
+                            "failed to load {} / {}: {}",
+                            cert_path.display(),
+                            key_path.display(),
+                            e
+                        )
+                    })?;
+            Ok(Some(Self { cert_path, config }))
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = (cert_path, key_path);
+            Err("--tls-cert-path requires building with `--features tls`".to_string())
+        }
+    }
+
+    pub fn cert_path(&self) -> &std::path::Path {
+        &self.cert_path
+    }
+
+    /// Serve `app` over HTTPS on `addr` until `shutdown` is requested and
+    /// in-flight requests have drained
+    pub async fn serve(self, addr: &str, app: Router, shutdown: Shutdown) -> std::io::Result<()> {
+        #[cfg(feature = "tls")]
+        {
+            // Resolve names like `localhost:3001`, as the plain HTTP listener does
+            let addr = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{} resolved to no addresses", addr),
+                )
+            })?;
+            let handle = axum_server::Handle::new();
+            let drain = handle.clone();
+            tokio::spawn(async move {
+                let drain_timeout = shutdown.drain_timeout();
+                shutdown.requested().await;
+                drain.graceful_shutdown(Some(drain_timeout));
+            });
+            axum_server::bind_rustls(addr, self.config)
+                .handle(handle)
+                .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                .await
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            // from_config never returns a certificate without the feature
+            let _ = (addr, app, shutdown);
+            unreachable!("TLS is not compiled in")
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Post-submission verification.
+//!
+//! `POST /transaction/:id/submit` only learns that the fullnode accepted a
+//! transaction. [`lookup`] asks the fullnode for it by the hash it was
+//! accepted under; once it is committed, its version, VM status and gas used
+//! are recorded in the transaction's history as a `committed` event, so one
+//! record tells the whole story from stored to committed.
+//!
+//! [`watch`] does the same in the background after every submission, every
+//! `SUBMISSION_POLL_INTERVAL_MS`, until the transaction is committed or has
+//! expired without being committed, which is recorded as a
+//! `submission_failed` event. Submissions still unresolved when the server
+//! stopped are watched again on startup by [`resume`].
+
+use crate::session::SessionState;
+use crate::storage::{Commit, EventKind, StoredTransaction, TransactionEvent};
+use crate::transaction::DecodedTransaction;
+use crate::AppState;
+use aptos_sdk::api::FullnodeClient;
+use aptos_sdk::types::HashValue;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Seconds past its `expiration_timestamp_secs` before an uncommitted
+/// transaction is given up on, in case the fullnode lags behind the chain
+const EXPIRATION_GRACE_SECS: u64 = 30;
+
+/// Where a submitted transaction stands on chain
+#[derive(Debug)]
+pub enum Lookup {
+    Committed(Commit),
+    /// In the mempool, not executed yet
+    Pending,
+    /// Unknown to the fullnode: dropped from the mempool, expired, or not
+    /// propagated yet
+    NotFound,
+}
+
+/// Look up the transaction the fullnode accepted under `hash`
+pub async fn lookup(fullnode: &FullnodeClient, hash: &str) -> Result<Lookup, String> {
+    let hash = HashValue::from_hex(hash).map_err(|e| format!("invalid hash {}: {}", hash, e))?;
+    match fullnode.get_transaction_by_hash(&hash).await {
+        Ok(response) => parse(&response.data),
+        Err(e) if e.is_not_found() => Ok(Lookup::NotFound),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Read the outcome out of the fullnode's JSON for a transaction, where the
+/// `u64`s are strings
+fn parse(transaction: &Value) -> Result<Lookup, String> {
+    if transaction["type"] == "pending_transaction" {
+        return Ok(Lookup::Pending);
+    }
+    let number = |field: &str| {
+        transaction[field]
+            .as_str()
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| format!("fullnode response has no `{}`", field))
+    };
+    Ok(Lookup::Committed(Commit {
+        version: number("version")?,
+        success: transaction["success"]
+            .as_bool()
+            .ok_or("fullnode response has no `success`")?,
+        vm_status: transaction["vm_status"]
+            .as_str()
+            .ok_or("fullnode response has no `vm_status`")?
+            .to_string(),
+        gas_used: number("gas_used")?,
+    }))
+}
+
+/// Poll the fullnode every `interval` for the transaction stored under
+/// `transaction_id` and accepted as `hash`, until it is committed or has
+/// expired. Stops early once it is deleted, verified by
+/// `POST /transaction/:id/verify`, or submitted again under another hash.
+pub fn watch(state: Arc<AppState>, transaction_id: String, hash: String, interval: Duration) {
+    tokio::spawn(async move {
+        let Some(fullnode) = &state.fullnode else {
+            return;
+        };
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick is immediate, before the transaction can be executed
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let expiration_secs = match state.store.get(&transaction_id).await {
+                Ok(Some(tx))
+                    if tx.commit().is_none() && tx.submitted_hash() == Some(hash.as_str()) =>
+                {
+                    expiration_secs(&tx)
+                }
+                Ok(_) => return,
+                Err(e) => {
+                    warn!("Failed to look up {}: {}", transaction_id, e);
+                    continue;
+                }
+            };
+            match lookup(fullnode, &hash).await {
+                Ok(Lookup::Committed(commit)) => {
+                    info!(
+                        transaction_id = %transaction_id,
+                        version = commit.version,
+                        vm_status = %commit.vm_status,
+                        gas_used = commit.gas_used,
+                        "Transaction committed"
+                    );
+                    let event = TransactionEvent::committed(commit);
+                    crate::record_history(&state, &transaction_id, vec![event]).await;
+                    return;
+                }
+                Ok(Lookup::Pending | Lookup::NotFound) => {}
+                Err(e) => debug!("Failed to look up transaction {}: {}", hash, e),
+            }
+            // Stored transactions that don't decode can't be submitted
+            let Some(expiration_secs) = expiration_secs else {
+                return;
+            };
+            if now_secs() > expiration_secs.saturating_add(EXPIRATION_GRACE_SECS) {
+                warn!(
+                    transaction_id = %transaction_id,
+                    hash = %hash,
+                    "Transaction expired before being committed"
+                );
+                let event = TransactionEvent::now(
+                    EventKind::SubmissionFailed,
+                    Some("expired before being committed".to_string()),
+                );
+                crate::record_history(&state, &transaction_id, vec![event]).await;
+                return;
+            }
+        }
+    });
+}
+
+/// [`watch`] every stored transaction that was submitted and isn't known to
+/// be committed yet
+pub async fn resume(state: Arc<AppState>, interval: Duration) {
+    let transactions = match state.store.list().await {
+        Ok(transactions) => transactions,
+        Err(e) => {
+            error!("Failed to list transactions: {}", e);
+            return;
+        }
+    };
+    let mut watched = 0;
+    for (transaction_id, tx) in transactions {
+        if state.session_state(&tx) != SessionState::Submitted {
+            continue;
+        }
+        if let Some(hash) = tx.submitted_hash() {
+            watch(state.clone(), transaction_id, hash.to_string(), interval);
+            watched += 1;
+        }
+    }
+    if watched > 0 {
+        info!("Watching {} submitted transactions", watched);
+    }
+}
+
+/// `tx`'s `expiration_timestamp_secs`, or `None` if it doesn't decode
+fn expiration_secs(tx: &StoredTransaction) -> Option<u64> {
+    let decoded = DecodedTransaction::decode(&tx.bcs().ok()?).ok()?;
+    Some(decoded.raw_txn().expiration_timestamp_secs)
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -0,0 +1,400 @@
+//! Write-ahead log for crash recovery.
+//!
+//! When `WAL_PATH` is set, [`WalStore`] wraps the configured storage backend
+//! and appends every mutation (transaction store, signature attach, history
+//! events, removal) to the log and fsyncs it before applying it. On startup
+//! the log is replayed on top of whatever the backend and snapshot already
+//! hold, so a crash mid-signing session loses nothing that was acknowledged
+//! to a client.
+//!
+//! Records are JSON lines. Replaying a record twice has the same effect as
+//! replaying it once (history events already present are skipped), so the log
+//! never needs to be reconciled with snapshots.
+//!
+//! The log is emptied at each [checkpoint](WalStore::checkpoint): once a
+//! snapshot is written, and when a durable backend is flushed (at startup
+//! after the replay, and at shutdown). Mutations wait while a checkpoint
+//! runs, so none can land in the log after the snapshot was taken and then
+//! be truncated away with it. With the in-memory store and no snapshots the
+//! log is the only copy and keeps growing until the server restarts.
+
+use crate::storage::{
+    hex_bytes, Signer, StoreError, StoreResult, StoredTransaction, TransactionEvent,
+    TransactionStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// A single logged mutation
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalRecord {
+    Put {
+        transaction_id: String,
+        tx: StoredTransaction,
+    },
+    Signature {
+        transaction_id: String,
+        /// Absent in logs written before fee payer signatures existed
+        #[serde(default)]
+        role: Role,
+        /// Long-form secondary signer address; absent for fee payer
+        /// signatures and in logs written before signatures were keyed by
+        /// signer
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<String>,
+        #[serde(with = "hex_bytes")]
+        signature: Vec<u8>,
+    },
+    History {
+        transaction_id: String,
+        events: Vec<TransactionEvent>,
+    },
+    Remove {
+        transaction_id: String,
+    },
+}
+
+/// Which signer a logged signature belongs to
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Role {
+    #[default]
+    Secondary,
+    FeePayer,
+}
+
+/// Storage decorator that logs every mutation before applying it
+pub struct WalStore {
+    inner: Arc<dyn TransactionStore>,
+    file: Mutex<tokio::fs::File>,
+    /// Read-locked by each mutation from its append until it is applied,
+    /// write-locked by a checkpoint
+    checkpoint: RwLock<()>,
+}
+
+impl WalStore {
+    /// Open (or create) the log at `path` for appending
+    pub async fn open(inner: Arc<dyn TransactionStore>, path: &Path) -> StoreResult<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(wal_error)?;
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+            checkpoint: RwLock::new(()),
+        })
+    }
+
+    /// Run `persist`, which must make every mutation applied so far durable
+    /// (by writing a snapshot, or flushing a durable backend), and empty the
+    /// log if it succeeds. Mutations wait until the checkpoint is done.
+    pub async fn checkpoint<T, F, Fut>(&self, persist: F) -> StoreResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = StoreResult<T>>,
+    {
+        let _mutations = self.checkpoint.write().await;
+        let persisted = persist().await?;
+        let file = self.file.lock().await;
+        // Opened for appending, so the next record goes to the new end
+        file.set_len(0).await.map_err(wal_error)?;
+        file.sync_all().await.map_err(wal_error)?;
+        info!("Truncated the WAL after a checkpoint");
+        Ok(persisted)
+    }
+
+    /// Append `record` and wait until it is on disk
+    async fn append(&self, record: &WalRecord) -> StoreResult<()> {
+        let mut line = serde_json::to_vec(record).map_err(wal_error)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await.map_err(wal_error)?;
+        file.sync_data().await.map_err(wal_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransactionStore for WalStore {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn evictions(&self) -> u64 {
+        self.inner.evictions()
+    }
+
+    async fn ping(&self) -> StoreResult<()> {
+        self.inner.ping().await
+    }
+
+    fn durable(&self) -> bool {
+        self.inner.durable()
+    }
+
+    /// Flushes the backend, then empties the log if the backend is durable
+    async fn flush(&self) -> StoreResult<()> {
+        if !self.inner.durable() {
+            return self.inner.flush().await;
+        }
+        self.checkpoint(|| self.inner.flush()).await
+    }
+
+    async fn put(&self, transaction_id: &str, tx: StoredTransaction) -> StoreResult<()> {
+        let _checkpoint = self.checkpoint.read().await;
+        self.append(&WalRecord::Put {
+            transaction_id: transaction_id.to_string(),
+            tx: tx.clone(),
+        })
+        .await?;
+        self.inner.put(transaction_id, tx).await
+    }
+
+    async fn get(&self, transaction_id: &str) -> StoreResult<Option<StoredTransaction>> {
+        self.inner.get(transaction_id).await
+    }
+
+    async fn update_signature(
+        &self,
+        transaction_id: &str,
+        signer: &Signer,
+        signature: Vec<u8>,
+    ) -> StoreResult<bool> {
+        let _checkpoint = self.checkpoint.read().await;
+        let (role, address) = match signer {
+            Signer::Secondary(address) => (Role::Secondary, Some(address.clone())),
+            Signer::FeePayer => (Role::FeePayer, None),
+        };
+        self.append(&WalRecord::Signature {
+            transaction_id: transaction_id.to_string(),
+            role,
+            address,
+            signature: signature.clone(),
+        })
+        .await?;
+        self.inner
+            .update_signature(transaction_id, signer, signature)
+            .await
+    }
+
+    async fn append_history(
+        &self,
+        transaction_id: &str,
+        events: Vec<TransactionEvent>,
+    ) -> StoreResult<bool> {
+        let _checkpoint = self.checkpoint.read().await;
+        self.append(&WalRecord::History {
+            transaction_id: transaction_id.to_string(),
+            events: events.clone(),
+        })
+        .await?;
+        self.inner.append_history(transaction_id, events).await
+    }
+
+    async fn remove(&self, transaction_id: &str) -> StoreResult<bool> {
+        let _checkpoint = self.checkpoint.read().await;
+        self.append(&WalRecord::Remove {
+            transaction_id: transaction_id.to_string(),
+        })
+        .await?;
+        self.inner.remove(transaction_id).await
+    }
+
+    async fn list(&self) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        self.inner.list().await
+    }
+
+    async fn list_by_sender(
+        &self,
+        sender: &str,
+        sequence_numbers: RangeInclusive<u64>,
+    ) -> StoreResult<Vec<(String, StoredTransaction)>> {
+        self.inner.list_by_sender(sender, sequence_numbers).await
+    }
+}
+
+/// Re-apply every record in the log at `path` to `store`.
+///
+/// Returns the number of records replayed (`0` if the log doesn't exist). A
+/// torn final record from a crash mid-append is truncated away so new records
+/// start on a clean line.
+pub async fn replay(store: &dyn TransactionStore, path: &Path) -> StoreResult<usize> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(wal_error(e)),
+    };
+
+    let mut replayed = 0;
+    let mut valid_len = 0;
+    for line in contents.split_inclusive('\n') {
+        let record: WalRecord = match serde_json::from_str(line.trim_end()) {
+            Ok(record) => record,
+            Err(e) if valid_len + line.len() == contents.len() => {
+                warn!("Truncating torn final record: {}", e);
+                truncate(path, valid_len as u64).await?;
+                break;
+            }
+            Err(e) => return Err(wal_error(format!("record {}: {}", replayed + 1, e))),
+        };
+        match record {
+            WalRecord::Put { transaction_id, tx } => store.put(&transaction_id, tx).await?,
+            WalRecord::Signature {
+                transaction_id,
+                role,
+                address,
+                signature,
+            } => {
+                let signer = match (role, address) {
+                    (Role::FeePayer, _) => Some(Signer::FeePayer),
+                    (Role::Secondary, Some(address)) => Some(Signer::Secondary(address)),
+                    (Role::Secondary, None) => None,
+                };
+                match signer {
+                    Some(signer) => {
+                        store
+                            .update_signature(&transaction_id, &signer, signature)
+                            .await?;
+                    }
+                    None => warn!(
+                        "Skipping secondary signature for {} logged without a signer address",
+                        transaction_id
+                    ),
+                }
+            }
+            WalRecord::History {
+                transaction_id,
+                events,
+            } => {
+                // Unlike the other records, appending isn't idempotent by itself
+                if let Some(tx) = store.get(&transaction_id).await? {
+                    let events: Vec<_> = events
+                        .into_iter()
+                        .filter(|event| !tx.history.contains(event))
+                        .collect();
+                    if !events.is_empty() {
+                        store.append_history(&transaction_id, events).await?;
+                    }
+                }
+            }
+            WalRecord::Remove { transaction_id } => {
+                store.remove(&transaction_id).await?;
+            }
+        }
+        replayed += 1;
+        valid_len += line.len();
+    }
+    Ok(replayed)
+}
+
+async fn truncate(path: &Path, len: u64) -> StoreResult<()> {
+    let file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await
+        .map_err(wal_error)?;
+    file.set_len(len).await.map_err(wal_error)
+}
+
+fn wal_error(e: impl std::fmt::Display) -> StoreError {
+    StoreError::Backend(format!("wal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStore;
+    use std::path::PathBuf;
+
+    fn log_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("repro-wal-{}-{}.wal", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn replays_writes_that_were_never_flushed() {
+        let path = log_path("crash");
+        let wal = WalStore::open(Arc::new(MemoryStore::default()), &path)
+            .await
+            .unwrap();
+        wal.put("tx1", StoredTransaction::default()).await.unwrap();
+        wal.update_signature("tx1", &Signer::Secondary("0x1".to_string()), vec![1])
+            .await
+            .unwrap();
+        wal.update_signature("tx1", &Signer::FeePayer, vec![2])
+            .await
+            .unwrap();
+        wal.put("tx2", StoredTransaction::default()).await.unwrap();
+        wal.remove("tx2").await.unwrap();
+        // A crash: no snapshot, no flush
+        drop(wal);
+
+        let store = MemoryStore::default();
+        assert_eq!(replay(&store, &path).await.unwrap(), 5);
+        let tx = store.get("tx1").await.unwrap().unwrap();
+        assert_eq!(tx.secondary_signatures.get("0x1"), Some(&vec![1]));
+        assert_eq!(tx.fee_payer_signature, Some(vec![2]));
+        assert!(store.get("tx2").await.unwrap().is_none());
+
+        // Reopening appends after the replayed records
+        let wal = WalStore::open(Arc::new(store), &path).await.unwrap();
+        wal.put("tx3", StoredTransaction::default()).await.unwrap();
+        drop(wal);
+        let store = MemoryStore::default();
+        assert_eq!(replay(&store, &path).await.unwrap(), 6);
+        assert_eq!(store.list().await.unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checkpoint_empties_the_log() {
+        let path = log_path("checkpoint");
+        let wal = WalStore::open(Arc::new(MemoryStore::default()), &path)
+            .await
+            .unwrap();
+        wal.put("tx1", StoredTransaction::default()).await.unwrap();
+        wal.checkpoint(|| async { Ok(()) }).await.unwrap();
+        wal.put("tx2", StoredTransaction::default()).await.unwrap();
+        drop(wal);
+
+        let store = MemoryStore::default();
+        assert_eq!(replay(&store, &path).await.unwrap(), 1);
+        assert!(store.get("tx1").await.unwrap().is_none());
+        assert!(store.get("tx2").await.unwrap().is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_checkpoint_keeps_the_log() {
+        let path = log_path("failed-checkpoint");
+        let wal = WalStore::open(Arc::new(MemoryStore::default()), &path)
+            .await
+            .unwrap();
+        wal.put("tx1", StoredTransaction::default()).await.unwrap();
+        let failed = wal
+            .checkpoint(|| async { Err::<(), _>(StoreError::Backend("disk full".to_string())) })
+            .await;
+        assert!(failed.is_err());
+        // Flushing the in-memory store doesn't make anything durable either
+        wal.flush().await.unwrap();
+        drop(wal);
+
+        let store = MemoryStore::default();
+        assert_eq!(replay(&store, &path).await.unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+//! Outbound webhook callbacks.
+//!
+//! Signer services can be driven by callbacks instead of polling: when a
+//! signature is attached the backend POSTs a [`WebhookPayload`] to the
+//! transaction's `callback_url` (given when it was stored) and to the global
+//! `--webhook-url`, and once no signature is missing it POSTs again with
+//! `signatures_complete`. A signer declining to sign is sent as
+//! `signature_declined`, with their reason, and a stored signature found
+//! stale as `signature_stale`. With `HMAC_SECRET` set, callbacks carry an
+//! `x-signature` header in the same format requests are signed with (over
+//! `POST` and the callback URL's path and query), so the receiver can check
+//! they came from this backend.
+//!
+//! Delivery is best effort: each callback is retried a few times in the
+//! background and then dropped with a warning. Per-transaction URLs are held
+//! in memory only and must be registered again after a restart.
+
+use crate::config::ServerArgs;
+use crate::signing::{RequestSigning, SIGNATURE_HEADER};
+use axum::http::Method;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+/// Attempts per callback before it is dropped
+const ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled for each one after it
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Where callbacks go
+pub struct Webhooks {
+    client: reqwest::Client,
+    /// Sent every callback (`--webhook-url`)
+    global: Option<Url>,
+    /// `callback_url` of each stored transaction that gave one
+    callbacks: Mutex<HashMap<String, Url>>,
+}
+
+/// What happened to a transaction
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A secondary or fee payer signature was attached
+    SignatureAdded,
+    /// Every signature the transaction needs besides the sender's is attached
+    SignaturesComplete,
+    /// A signer refused to sign; the session is rejected
+    SignatureDeclined,
+    /// A stored signature no longer verifies; the signer has to sign again
+    SignatureStale,
+}
+
+/// Body of a callback
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub transaction_id: String,
+    /// Long-form address of the signer, or `fee_payer`
+    pub signer: String,
+    /// Signers still to sign (empty with `signatures_complete`)
+    pub missing_signers: Vec<String>,
+    /// Why the signer declined, with `signature_declined`, or why their
+    /// signature is stale, with `signature_stale`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Unix time in milliseconds
+    pub at_ms: u64,
+}
+
+impl Webhooks {
+    pub fn from_config(config: &ServerArgs) -> Result<Self, String> {
+        let global = config
+            .webhook_url
+            .as_deref()
+            .filter(|url| !url.is_empty())
+            .map(parse_url)
+            .transpose()?;
+        Self::new(global, Duration::from_secs(config.webhook_timeout_secs))
+    }
+
+    /// Callbacks that give up after `timeout`, sending every one to `global`
+    /// as well if set
+    pub fn new(global: Option<Url>, timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to build webhook client: {}", e))?;
+        Ok(Self {
+            client,
+            global,
+            callbacks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn global(&self) -> Option<&Url> {
+        self.global.as_ref()
+    }
+
+    /// Registered URLs, even if a holder panicked: a map insert or removal
+    /// can't be left half done
+    fn callbacks(&self) -> MutexGuard<'_, HashMap<String, Url>> {
+        self.callbacks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Send `transaction_id`'s callbacks to `url` from now on
+    pub fn register(&self, transaction_id: &str, url: Url) {
+        self.callbacks().insert(transaction_id.to_string(), url);
+    }
+
+    /// Forget `transaction_id`'s callback URL
+    pub fn unregister(&self, transaction_id: &str) {
+        self.callbacks().remove(transaction_id);
+    }
+
+    /// Whether any callback would be sent for `transaction_id`
+    pub fn wants(&self, transaction_id: &str) -> bool {
+        self.global.is_some() || self.callbacks().contains_key(transaction_id)
+    }
+
+    /// POST `payload` to every URL interested in its transaction, in the
+    /// background
+    pub fn send(&self, payload: WebhookPayload, signing: Option<&RequestSigning>) {
+        let mut urls: Vec<Url> = self.global.iter().cloned().collect();
+        if let Some(url) = self.callbacks().get(&payload.transaction_id) {
+            urls.push(url.clone());
+        }
+        if urls.is_empty() {
+            return;
+        }
+
+        let body = serde_json::to_vec(&payload).expect("webhook payloads serialize");
+        for url in urls {
+            let signature = signing.map(|signing| signing.sign(&Method::POST, &path(&url), &body));
+            tokio::spawn(deliver(
+                self.client.clone(),
+                url,
+                body.clone(),
+                signature,
+                payload.transaction_id.clone(),
+                payload.event,
+            ));
+        }
+    }
+}
+
+/// `url`'s path and query, as a callback's signature covers them
+fn path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// POST `body` to `url`, retrying with backoff until [`ATTEMPTS`] run out
+async fn deliver(
+    client: reqwest::Client,
+    url: Url,
+    body: Vec<u8>,
+    signature: Option<String>,
+    transaction_id: String,
+    event: WebhookEvent,
+) {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=ATTEMPTS {
+        let mut request = client
+            .post(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => {
+                debug!(%transaction_id, ?event, %url, "Webhook delivered");
+                return;
+            }
+            Err(e) if attempt < ATTEMPTS => {
+                debug!(%transaction_id, %url, attempt, "Webhook failed, retrying: {}", e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => warn!(
+                %transaction_id,
+                ?event,
+                %url,
+                "Webhook dropped after {} attempts: {}",
+                ATTEMPTS,
+                e
+            ),
+        }
+    }
+}
+
+/// Parse a callback URL, which must be `http` or `https`
+pub fn parse_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid callback URL `{}`: {}", url, e))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed),
+        scheme => Err(format!(
+            "Invalid callback URL `{}`: scheme must be http or https, not {}",
+            url, scheme
+        )),
+    }
+}
@@ -0,0 +1,178 @@
+//! The multi-agent flow through the HTTP API: store, sign, assemble and
+//! submit, and the status each step answers when the flow can't go on.
+
+mod common;
+
+use common::{Session, StubFullnode};
+use repro_core::client::StoreTransaction;
+use repro_core::session::SessionState;
+use repro_server::testing::{spawn_test_server, spawn_test_server_with_fullnode};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn store_sign_assemble_submit() {
+    let fullnode = StubFullnode::spawn().await;
+    let server = spawn_test_server_with_fullnode(&fullnode.url).await;
+    let client = server.client();
+    let session = Session::new(2);
+
+    let stored = client
+        .store_transaction(&StoreTransaction::new("flow", session.bcs()))
+        .await
+        .unwrap();
+    assert_eq!(stored.session_state, Some(SessionState::Created));
+
+    for index in 0..2 {
+        client
+            .attach_signature("flow", &session.address(index), &session.signature(index))
+            .await
+            .unwrap();
+    }
+    let transaction = client.get("flow").await.unwrap();
+    assert_eq!(transaction.bcs, session.bcs());
+    assert_eq!(transaction.secondary_signatures.len(), 2);
+    assert_eq!(transaction.session_state, Some(SessionState::Complete));
+
+    let (status, body) = common::assemble(&server.url(), "flow", &session).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let signed = body["signed_transaction_hex"].as_str().unwrap();
+    let signed = hex::decode(signed.trim_start_matches("0x")).unwrap();
+
+    let (status, body) = common::submit(&server.url(), "flow", &session).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["hash"], fullnode.hash);
+    assert_eq!(body["session_state"], "submitted");
+    // The fullnode got exactly what assemble answered
+    assert_eq!(*fullnode.submitted.lock().unwrap(), vec![signed]);
+
+    let transaction = client.get("flow").await.unwrap();
+    assert_eq!(transaction.session_state, Some(SessionState::Submitted));
+}
+
+#[tokio::test]
+async fn submit_needs_a_fullnode() {
+    let server = spawn_test_server().await;
+    let session = Session::new(1);
+    let client = server.client();
+    client
+        .store_transaction(&StoreTransaction::new("no-fullnode", session.bcs()))
+        .await
+        .unwrap();
+    client
+        .attach_signature("no-fullnode", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+
+    let (status, body) = common::submit(&server.url(), "no-fullnode", &session).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+    assert_eq!(body["success"], false);
+}
+
+#[tokio::test]
+async fn malformed_input_is_a_bad_request() {
+    let server = spawn_test_server().await;
+    let session = Session::new(1);
+
+    let body = json!({ "transaction_id": "malformed", "bcs_hex": "0xnot-hex" });
+    let (status, body) = common::post(&server.url(), "/transaction", body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["success"], false);
+
+    server
+        .client()
+        .store_transaction(&StoreTransaction::new("malformed", session.bcs()))
+        .await
+        .unwrap();
+    let body = json!({ "sender_signature_hex": "0x00" });
+    let (status, body) = common::post(&server.url(), "/transaction/malformed/assemble", body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+}
+
+#[tokio::test]
+async fn unknown_ids_are_not_found() {
+    let fullnode = StubFullnode::spawn().await;
+    let server = spawn_test_server_with_fullnode(&fullnode.url).await;
+    let client = server.client();
+    let session = Session::new(1);
+
+    let error = client.get("unknown").await.unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+    let error = client
+        .attach_signature("unknown", &session.address(0), &session.signature(0))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::NOT_FOUND));
+    let (status, body) = common::assemble(&server.url(), "unknown", &session).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+    let (status, body) = common::submit(&server.url(), "unknown", &session).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+    assert!(fullnode.submitted.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn conflicts() {
+    let fullnode = StubFullnode::spawn().await;
+    let server = spawn_test_server_with_fullnode(&fullnode.url).await;
+    let client = server.client();
+    let session = Session::new(2);
+    client
+        .store_transaction(&StoreTransaction::new("conflict", session.bcs()))
+        .await
+        .unwrap();
+
+    // Other bytes under a stored id
+    let error = client
+        .store_transaction(&StoreTransaction::new("conflict", Session::new(2).bcs()))
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(StatusCode::CONFLICT));
+
+    // A secondary signer yet to sign
+    client
+        .attach_signature("conflict", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+    let (status, body) = common::assemble(&server.url(), "conflict", &session).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["missing_signers"], json!([session.address(1)]));
+    let (status, body) = common::submit(&server.url(), "conflict", &session).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert!(fullnode.submitted.lock().unwrap().is_empty());
+
+    // Submitting the same session twice
+    client
+        .attach_signature("conflict", &session.address(1), &session.signature(1))
+        .await
+        .unwrap();
+    let (status, body) = common::submit(&server.url(), "conflict", &session).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = common::submit(&server.url(), "conflict", &session).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["session_state"], "submitted");
+    assert_eq!(fullnode.submitted.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn expired_transaction_is_gone() {
+    let fullnode = StubFullnode::spawn().await;
+    let server = spawn_test_server_with_fullnode(&fullnode.url).await;
+    let client = server.client();
+    let session = Session::expiring_in(1, 2);
+    client
+        .store_transaction(&StoreTransaction::new("expiring", session.bcs()))
+        .await
+        .unwrap();
+    client
+        .attach_signature("expiring", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+
+    // Past `expiration_timestamp_secs`, however the seconds were rounded
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let (status, body) = common::submit(&server.url(), "expiring", &session).await;
+    assert_eq!(status, StatusCode::GONE, "{}", body);
+    assert_eq!(body["session_state"], "expired");
+    assert!(fullnode.submitted.lock().unwrap().is_empty());
+}
@@ -0,0 +1,96 @@
+//! Binary stores: raw BCS bodies set a quorum and a webhook in headers, as
+//! JSON stores do in the body.
+
+mod common;
+
+use common::Session;
+use repro_server::testing::spawn_test_server;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// `POST /transaction` with `session`'s BCS as the body and `headers`
+async fn store_binary(
+    url: &str,
+    transaction_id: &str,
+    session: &Session,
+    headers: &[(&str, &str)],
+) -> (StatusCode, Value) {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/transaction", url))
+        .header("content-type", "application/octet-stream")
+        .header("x-transaction-id", transaction_id)
+        .body(session.bcs());
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = request.send().await.unwrap();
+    (response.status(), response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn headers_set_the_quorum_and_webhook() {
+    let server = spawn_test_server().await;
+    let client = server.client();
+    let session = Session::new(3);
+
+    // Receives the webhook deliveries
+    let (delivered, mut deliveries) = mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/cb",
+        axum::routing::post(move |body: axum::Json<Value>| async move {
+            delivered.send(body.0).unwrap();
+            StatusCode::OK
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let callback_url = format!("http://{}/cb", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let headers = [("x-quorum", "2"), ("x-callback-url", callback_url.as_str())];
+    let (status, body) = store_binary(&server.url(), "headers", &session, &headers).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(client.get("headers").await.unwrap().quorum, Some(2));
+
+    for index in 0..2 {
+        client
+            .attach_signature(
+                "headers",
+                &session.address(index),
+                &session.signature(index),
+            )
+            .await
+            .unwrap();
+    }
+    let delivery = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let delivery = deliveries.recv().await.unwrap();
+            if delivery.to_string().contains("signatures_complete") {
+                return delivery;
+            }
+        }
+    })
+    .await
+    .expect("no signatures_complete webhook");
+    assert_eq!(delivery["transaction_id"], "headers");
+}
+
+#[tokio::test]
+async fn invalid_headers_are_rejected() {
+    let server = spawn_test_server().await;
+    let session = Session::new(2);
+
+    for headers in [
+        [("x-quorum", "two")],
+        [("x-quorum", "0")],
+        [("x-quorum", "3")],
+        [("x-callback-url", "ftp://localhost/cb")],
+        [("x-callback-url", "not a url")],
+    ] {
+        let (status, body) = store_binary(&server.url(), "invalid", &session, &headers).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}: {}", headers, body);
+    }
+    let (status, _) = store_binary(&server.url(), "invalid", &session, &[]).await;
+    assert_eq!(status, StatusCode::OK);
+}
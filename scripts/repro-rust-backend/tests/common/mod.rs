@@ -0,0 +1,243 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use aptos_sdk::account::Ed25519Account;
+use aptos_sdk::aptos_bcs;
+use aptos_sdk::transaction::authenticator::AccountAuthenticator;
+use aptos_sdk::transaction::types::MultiAgentRawTransaction;
+use aptos_sdk::transaction::{EntryFunction, RawTransaction, TransactionPayload};
+use aptos_sdk::types::{AccountAddress, ChainId, Identifier, MoveModuleId};
+use repro_core::authenticator::{
+    self, AnyPublicKey, AnySignature, MultiKey, MultiKeyAuthenticator,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A multi-agent transaction whose sender and secondary signers hold real
+/// Ed25519 keys, so the signatures it produces verify
+pub struct Session {
+    pub sender: Ed25519Account,
+    pub secondaries: Vec<Ed25519Account>,
+    pub transaction: MultiAgentRawTransaction,
+}
+
+impl Session {
+    /// A transaction with `secondary_signers` secondary signers, expiring in
+    /// an hour
+    pub fn new(secondary_signers: usize) -> Self {
+        Self::expiring_in(secondary_signers, 3600)
+    }
+
+    /// A transaction with `secondary_signers` secondary signers, expiring
+    /// `secs` seconds from now
+    pub fn expiring_in(secondary_signers: usize, secs: u64) -> Self {
+        let sender = Ed25519Account::generate();
+        let secondaries: Vec<_> = (0..secondary_signers)
+            .map(|_| Ed25519Account::generate())
+            .collect();
+        let module = MoveModuleId::new(
+            AccountAddress::ONE,
+            Identifier::new("aptos_account").expect("valid identifier"),
+        );
+        let payload = TransactionPayload::EntryFunction(EntryFunction::new(
+            module,
+            "transfer",
+            vec![],
+            vec![
+                aptos_bcs::to_bytes(&AccountAddress::ONE).unwrap(),
+                aptos_bcs::to_bytes(&1u64).unwrap(),
+            ],
+        ));
+        let expiration = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + secs;
+        let raw_txn = RawTransaction::new(
+            sender.address(),
+            0,
+            payload,
+            200_000,
+            100,
+            expiration,
+            ChainId::testnet(),
+        );
+        let transaction = MultiAgentRawTransaction::new(
+            raw_txn,
+            secondaries.iter().map(Ed25519Account::address).collect(),
+        );
+        Self {
+            sender,
+            secondaries,
+            transaction,
+        }
+    }
+
+    /// BCS of the unsigned transaction, as stored
+    pub fn bcs(&self) -> Vec<u8> {
+        aptos_bcs::to_bytes(&self.transaction).unwrap()
+    }
+
+    /// Long-form address of secondary signer `index`
+    pub fn address(&self, index: usize) -> String {
+        self.secondaries[index].address().to_long_string()
+    }
+
+    /// Secondary signer `index`'s BCS `AccountAuthenticator`
+    pub fn signature(&self, index: usize) -> Vec<u8> {
+        ed25519_authenticator(&self.secondaries[index], &self.transaction)
+    }
+
+    /// The sender's BCS `AccountAuthenticator`, as hex
+    pub fn sender_signature_hex(&self) -> String {
+        hex::encode(ed25519_authenticator(&self.sender, &self.transaction))
+    }
+
+    /// `key`'s Ed25519 signature over the transaction
+    fn sign(&self, key: &Ed25519Account) -> Vec<u8> {
+        let message = self.transaction.signing_message().unwrap();
+        key.sign_message(&message).to_bytes().to_vec()
+    }
+
+    /// BCS of a partial `MultiEd25519` authenticator of `keys`, needing
+    /// `threshold` signatures, holding key `index`'s signature only
+    pub fn multi_ed25519_partial(
+        &self,
+        keys: &[Ed25519Account],
+        threshold: u8,
+        index: usize,
+    ) -> Vec<u8> {
+        let mut public_key: Vec<u8> = keys
+            .iter()
+            .flat_map(|key| key.public_key().to_bytes())
+            .collect();
+        public_key.push(threshold);
+        let mut signature = self.sign(&keys[index]);
+        signature.extend(bitmap(index, 4));
+        aptos_bcs::to_bytes(&authenticator::AccountAuthenticator::MultiEd25519 {
+            public_key,
+            signature,
+        })
+        .unwrap()
+    }
+
+    /// BCS of a partial `MultiKey` authenticator of Ed25519 `keys`, needing
+    /// `threshold` signatures, holding key `index`'s signature only
+    pub fn multi_key_partial(
+        &self,
+        keys: &[Ed25519Account],
+        threshold: u8,
+        index: usize,
+    ) -> Vec<u8> {
+        let public_keys = keys
+            .iter()
+            .map(|key| AnyPublicKey::Ed25519 {
+                public_key: key.public_key().to_bytes().to_vec(),
+            })
+            .collect();
+        aptos_bcs::to_bytes(&authenticator::AccountAuthenticator::MultiKey {
+            authenticator: MultiKeyAuthenticator {
+                public_keys: MultiKey {
+                    public_keys,
+                    signatures_required: threshold,
+                },
+                signatures: vec![AnySignature::Ed25519 {
+                    signature: self.sign(&keys[index]),
+                }],
+                signatures_bitmap: bitmap(index, 4),
+            },
+        })
+        .unwrap()
+    }
+}
+
+/// `len` bytes with bit `index` set, most significant bit first
+fn bitmap(index: usize, len: usize) -> Vec<u8> {
+    let mut bitmap = vec![0; len];
+    bitmap[index / 8] |= 0x80 >> (index % 8);
+    bitmap
+}
+
+fn ed25519_authenticator(
+    account: &Ed25519Account,
+    transaction: &MultiAgentRawTransaction,
+) -> Vec<u8> {
+    let message = transaction.signing_message().unwrap();
+    aptos_bcs::to_bytes(&AccountAuthenticator::ed25519(
+        account.public_key().to_bytes().to_vec(),
+        account.sign_message(&message).to_bytes().to_vec(),
+    ))
+    .unwrap()
+}
+
+/// `POST {url}{path}` with a JSON body, answering the status and JSON body
+pub async fn post(url: &str, path: &str, body: Value) -> (reqwest::StatusCode, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}{}", url, path))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    (response.status(), response.json().await.unwrap())
+}
+
+/// `POST /transaction/:id/assemble` with `session`'s sender signature
+pub async fn assemble(
+    url: &str,
+    transaction_id: &str,
+    session: &Session,
+) -> (reqwest::StatusCode, Value) {
+    let path = format!("/transaction/{}/assemble", transaction_id);
+    let body = json!({ "sender_signature_hex": session.sender_signature_hex() });
+    post(url, &path, body).await
+}
+
+/// `POST /transaction/:id/submit` with `session`'s sender signature
+pub async fn submit(
+    url: &str,
+    transaction_id: &str,
+    session: &Session,
+) -> (reqwest::StatusCode, Value) {
+    let path = format!("/transaction/{}/submit", transaction_id);
+    let body = json!({ "sender_signature_hex": session.sender_signature_hex() });
+    post(url, &path, body).await
+}
+
+/// A fullnode that accepts every transaction submitted to it
+pub struct StubFullnode {
+    /// Base URL of its API, for `spawn_test_server_with_fullnode`
+    pub url: String,
+    /// Hash it answers every submission with
+    pub hash: &'static str,
+    /// Bodies of the submissions it received
+    pub submitted: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl StubFullnode {
+    /// Serve `POST /v1/transactions` on an ephemeral port until the test's
+    /// runtime shuts down
+    pub async fn spawn() -> Self {
+        let hash = "0x8d5f0c9b3e2a1d4c6b7a8f9e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b5a6f7e8d9c";
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let received = submitted.clone();
+        let app = axum::Router::new().route(
+            "/v1/transactions",
+            axum::routing::post(move |body: axum::body::Bytes| async move {
+                received.lock().unwrap().push(body.to_vec());
+                (
+                    axum::http::StatusCode::ACCEPTED,
+                    axum::Json(json!({ "hash": hash })),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self {
+            url,
+            hash,
+            submitted,
+        }
+    }
+}
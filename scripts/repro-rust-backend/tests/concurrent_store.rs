@@ -0,0 +1,57 @@
+//! Concurrent stores of one transaction id: the first write wins and every
+//! store of other bytes is refused, however the requests interleave.
+
+use repro_core::client::StoreTransaction;
+use repro_core::generate::{self, Options};
+use repro_server::testing::spawn_test_server;
+use reqwest::StatusCode;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn racing_stores_of_different_bytes_keep_one() {
+    let server = spawn_test_server().await;
+    let candidates: Vec<Vec<u8>> = generate::generate(&Options {
+        seed: 39,
+        count: 2,
+        secondary_signers: 1,
+        fee_payer: false,
+        chain_id: 4,
+        expiration_timestamp_secs: u64::MAX,
+    })
+    .iter()
+    .map(generate::to_bcs)
+    .collect();
+
+    let tasks: Vec<_> = (0..16)
+        .map(|i| {
+            let client = server.client();
+            let bcs = candidates[i % 2].clone();
+            tokio::spawn(async move {
+                let result = client
+                    .store_transaction(&StoreTransaction::new("race", bcs.clone()))
+                    .await;
+                (bcs, result)
+            })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+
+    let kept = server.client().get_bcs("race", false, None).await.unwrap();
+    assert!(candidates.contains(&kept));
+    for (bcs, result) in results {
+        if bcs == kept {
+            assert!(
+                result.is_ok(),
+                "store of the kept bytes failed: {:?}",
+                result
+            );
+        } else {
+            let status = result
+                .expect_err("both byte strings were accepted")
+                .status();
+            assert_eq!(status, Some(StatusCode::CONFLICT));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Conditional retrieval: polls that send back a transaction's `ETag` get
+//! `304 Not Modified` until a signature changes it.
+
+mod common;
+
+use common::Session;
+use repro_core::client::StoreTransaction;
+use repro_server::testing::spawn_test_server;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+
+/// `GET /transaction/:id`, sending `etag` in `If-None-Match` if given;
+/// answers the status, the `ETag` and the body
+async fn get(url: &str, transaction_id: &str, etag: Option<&str>) -> (StatusCode, String, String) {
+    let mut request = reqwest::Client::new().get(format!("{}/transaction/{}", url, transaction_id));
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status();
+    let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+    (status, etag, response.text().await.unwrap())
+}
+
+#[tokio::test]
+async fn unchanged_transaction_is_not_modified() {
+    let server = spawn_test_server().await;
+    let session = Session::new(1);
+    server
+        .client()
+        .store_transaction(&StoreTransaction::new("poll", session.bcs()))
+        .await
+        .unwrap();
+
+    let (status, etag, _) = get(&server.url(), "poll", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(etag.starts_with("W/\""), "{}", etag);
+
+    let (status, again, body) = get(&server.url(), "poll", Some(&etag)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(again, etag);
+    assert!(body.is_empty());
+
+    // Among other tags, or strong
+    let listed = format!("\"stale\", {}", etag);
+    let (status, _, _) = get(&server.url(), "poll", Some(&listed)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    let strong = etag.trim_start_matches("W/");
+    let (status, _, _) = get(&server.url(), "poll", Some(strong)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn signature_changes_the_etag() {
+    let server = spawn_test_server().await;
+    let client = server.client();
+    let session = Session::new(1);
+    client
+        .store_transaction(&StoreTransaction::new("signed", session.bcs()))
+        .await
+        .unwrap();
+    let (_, unsigned, _) = get(&server.url(), "signed", None).await;
+
+    client
+        .attach_signature("signed", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+
+    let (status, signed, body) = get(&server.url(), "signed", Some(&unsigned)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(signed, unsigned);
+    assert!(!body.is_empty());
+    let (status, _, _) = get(&server.url(), "signed", Some(&signed)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+}
@@ -0,0 +1,106 @@
+//! Declining: a signer refusing to sign rejects the session, which then
+//! takes no more signatures or declines.
+
+mod common;
+
+use common::Session;
+use repro_core::client::StoreTransaction;
+use repro_core::session::SessionState;
+use repro_server::testing::{spawn_test_server, TestServer};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+async fn setup(transaction_id: &str) -> (TestServer, Session) {
+    let server = spawn_test_server().await;
+    let session = Session::new(2);
+    server
+        .client()
+        .store_transaction(&StoreTransaction::new(transaction_id, session.bcs()))
+        .await
+        .unwrap();
+    (server, session)
+}
+
+async fn decline(
+    server: &TestServer,
+    transaction_id: &str,
+    signer_address: &str,
+) -> (StatusCode, Value) {
+    let path = format!("/transaction/{}/decline", transaction_id);
+    let body = json!({ "signer_address": signer_address, "reason": "wrong amount" });
+    common::post(&server.url(), &path, body).await
+}
+
+#[tokio::test]
+async fn decline_rejects_the_session() {
+    let (server, session) = setup("declined").await;
+    let client = server.client();
+    client
+        .attach_signature("declined", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+
+    let (status, body) = decline(&server, "declined", &session.address(1)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["session_state"], "rejected");
+
+    let transaction = client.get("declined").await.unwrap();
+    assert_eq!(transaction.session_state, Some(SessionState::Rejected));
+    let history = reqwest::get(format!("{}/transaction/declined/history", server.url()))
+        .await
+        .unwrap()
+        .json::<Value>()
+        .await
+        .unwrap();
+    let declined = history["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|event| event["kind"] == "declined")
+        .unwrap();
+    let detail = format!("{}: wrong amount", session.address(1));
+    assert_eq!(declined["detail"], detail);
+
+    // No more signatures or declines
+    let body = json!({
+        "transaction_id": "declined",
+        "signer_address": session.address(1),
+        "signature_hex": hex::encode(session.signature(1)),
+    });
+    let (status, body) = common::post(&server.url(), "/signature", body).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    let (status, body) = decline(&server, "declined", &session.address(0)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+    assert_eq!(body["session_state"], "rejected");
+}
+
+#[tokio::test]
+async fn waiters_return_on_decline() {
+    let (server, session) = setup("waited").await;
+    let url = format!("{}/transaction/waited/wait?timeout_ms=10000", server.url());
+    let waiter = tokio::spawn(async move { reqwest::get(url).await.unwrap().status() });
+    // Let the waiter subscribe first
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let (status, _) = decline(&server, "waited", &session.address(0)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(waiter.await.unwrap(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn only_signers_of_the_transaction_may_decline() {
+    let (server, session) = setup("signers").await;
+
+    let (status, body) = decline(&server, "signers", "0x1234").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let (status, body) = decline(&server, "signers", "not-an-address").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    // Multi-agent, so there is no fee payer
+    let (status, body) = decline(&server, "signers", "fee_payer").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let (status, body) = decline(&server, "unknown", &session.address(0)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+
+    let transaction = server.client().get("signers").await.unwrap();
+    assert_eq!(transaction.session_state, Some(SessionState::Created));
+}
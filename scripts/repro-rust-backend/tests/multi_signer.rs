@@ -0,0 +1,94 @@
+//! Partial `MultiEd25519` and `MultiKey` signatures merged into the one
+//! stored for their signer.
+
+mod common;
+
+use aptos_sdk::account::Ed25519Account;
+use common::Session;
+use repro_core::client::StoreTransaction;
+use repro_server::testing::{spawn_test_server, TestServer};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+/// A stored transaction with one secondary signer, whose account has three
+/// keys and needs two of them
+async fn setup() -> (TestServer, Session, Vec<Ed25519Account>) {
+    let server = spawn_test_server().await;
+    let session = Session::new(1);
+    server
+        .client()
+        .store_transaction(&StoreTransaction::new("multi", session.bcs()))
+        .await
+        .unwrap();
+    let keys = (0..3).map(|_| Ed25519Account::generate()).collect();
+    (server, session, keys)
+}
+
+async fn attach(server: &TestServer, session: &Session, signature: &[u8]) -> (StatusCode, Value) {
+    let body = json!({
+        "transaction_id": "multi",
+        "signer_address": session.address(0),
+        "signature_hex": hex::encode(signature),
+    });
+    common::post(&server.url(), "/signature", body).await
+}
+
+#[tokio::test]
+async fn partials_merge_until_the_threshold() {
+    let (server, session, keys) = setup().await;
+
+    let (status, body) = attach(
+        &server,
+        &session,
+        &session.multi_ed25519_partial(&keys, 2, 0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["threshold"]["signed"], 1);
+    assert_eq!(body["session_state"], "awaiting_signatures");
+
+    let (status, body) = attach(
+        &server,
+        &session,
+        &session.multi_ed25519_partial(&keys, 2, 2),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["threshold"]["signed"], 2);
+    assert_eq!(body["threshold"]["required"], 2);
+    assert_eq!(body["session_state"], "complete");
+
+    let (status, body) = common::assemble(&server.url(), "multi", &session).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn the_same_key_is_counted_once() {
+    let (server, session, keys) = setup().await;
+    let partial = session.multi_ed25519_partial(&keys, 2, 1);
+
+    attach(&server, &session, &partial).await;
+    let (status, body) = attach(&server, &session, &partial).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["threshold"]["signed"], 1);
+    assert_ne!(body["session_state"], "complete");
+}
+
+#[tokio::test]
+async fn another_scheme_is_a_conflict() {
+    let (server, session, keys) = setup().await;
+
+    attach(
+        &server,
+        &session,
+        &session.multi_ed25519_partial(&keys, 2, 0),
+    )
+    .await;
+    let (status, body) = attach(&server, &session, &session.multi_key_partial(&keys, 2, 1)).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+
+    // The partial collected is kept
+    let tx = server.client().get("multi").await.unwrap();
+    let stored = &tx.secondary_signatures[&session.address(0)];
+    assert_eq!(*stored, session.multi_ed25519_partial(&keys, 2, 0));
+}
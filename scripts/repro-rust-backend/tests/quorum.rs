@@ -0,0 +1,46 @@
+//! K-of-N sessions: meeting the quorum finishes collecting, but the chain
+//! still needs every secondary signer before the transaction can go out.
+
+mod common;
+
+use common::Session;
+use repro_core::client::StoreTransaction;
+use repro_core::session::SessionState;
+use repro_server::testing::spawn_test_server;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn quorum_met_is_not_submittable() {
+    let server = spawn_test_server().await;
+    let client = server.client();
+    let session = Session::new(3);
+    let mut store = StoreTransaction::new("k-of-n", session.bcs());
+    store.quorum = Some(2);
+    client.store_transaction(&store).await.unwrap();
+
+    let first = client
+        .attach_signature("k-of-n", &session.address(0), &session.signature(0))
+        .await
+        .unwrap();
+    assert_eq!(first.session_state, Some(SessionState::AwaitingSignatures));
+    let second = client
+        .attach_signature("k-of-n", &session.address(1), &session.signature(1))
+        .await
+        .unwrap();
+    assert_eq!(second.session_state, Some(SessionState::QuorumMet));
+
+    let (status, body) = common::assemble(&server.url(), "k-of-n", &session).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["session_state"], "quorum_met");
+    assert_eq!(body["missing_signers"][0], session.address(2));
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("Quorum of 2 met"), "{}", message);
+
+    let third = client
+        .attach_signature("k-of-n", &session.address(2), &session.signature(2))
+        .await
+        .unwrap();
+    assert_eq!(third.session_state, Some(SessionState::Complete));
+    let (status, body) = common::assemble(&server.url(), "k-of-n", &session).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
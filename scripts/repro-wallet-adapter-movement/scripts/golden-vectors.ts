@@ -0,0 +1,281 @@
+// Regenerates crates/aptos-sdk/tests/testdata/golden_vectors.json with the
+// TypeScript SDK this frontend is pinned to, so the Rust golden vector tests
+// check the SDK's bytes rather than hand-written hex.
+//
+//   npm ci && npm run golden-vectors
+//
+// Needs Node 22.6 or later, which runs the TypeScript directly.
+//
+// The SDK's own MultiAgentRawTransaction and FeePayerRawTransaction classes
+// serialize as the RawTransactionWithData enum, with a leading variant index.
+// The backend stores the Rust SDK's structs, which have none, so the signer
+// addresses after the RawTransaction are written here with the SDK's
+// Serializer instead.
+
+import { readFileSync, writeFileSync } from "node:fs";
+import {
+  AccountAddress,
+  ChainId,
+  EntryFunction,
+  FixedBytes,
+  Hex,
+  RawTransaction,
+  Script,
+  Serializer,
+  TransactionPayloadEntryFunction,
+  TransactionPayloadScript,
+  U64,
+  parseTypeTag,
+} from "@moveindustries/ts-sdk";
+import type {
+  EntryFunctionArgumentTypes,
+  ScriptFunctionArgumentTypes,
+} from "@moveindustries/ts-sdk";
+
+const OUTPUT = new URL(
+  "../../../crates/aptos-sdk/tests/testdata/golden_vectors.json",
+  import.meta.url,
+);
+const SCRIPT_PATH = "scripts/repro-wallet-adapter-movement/scripts/golden-vectors.ts";
+const SDK = "@moveindustries/ts-sdk";
+const SDK_VERSION: string = JSON.parse(
+  readFileSync(new URL(`../node_modules/${SDK}/package.json`, import.meta.url), "utf8"),
+).version;
+
+const ALICE = "0x8f3a1c52e1d4b7a09c6e2f4d3b5a7c9e1f0a2b4c6d8e0f1a3b5c7d9e1f2a3b4c";
+const BOB = "0x2b7e4d9c1a3f5e7b9d0c2e4f6a8b0c1d3e5f7a9b1c3d5e7f9a0b2c4d6e8f0a1b";
+const CAROL = "0x5c0d2e4f6a8b1c3d5e7f9a0b2c4d6e8f1a3b5c7d9e0f2a4b6c8d0e1f3a5b7c9d";
+const SPONSOR = "0x0e9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2a1f0e9d";
+
+// Stand-in for transfer_two_by_two.mv with the same signature
+// (signer, signer, u64, u64, address, address, u64)
+const TRANSFER_TWO_BY_TWO =
+  "0xa11ceb0b0700000a06010004030410051408071c3d085960000000010002010100000203040001050607000003060c060c030305050300010301090003060c060c0305050300";
+
+type Payload =
+  | { script: string; typeArgs: string[]; args: ScriptFunctionArgumentTypes[] }
+  | {
+      function: `${string}::${string}::${string}`;
+      typeArgs: string[];
+      args: EntryFunctionArgumentTypes[];
+    };
+
+interface Input {
+  name: string;
+  description: string;
+  sender: string;
+  sequenceNumber: bigint;
+  payload: Payload;
+  maxGasAmount: bigint;
+  gasUnitPrice: bigint;
+  expirationTimestampSecs: bigint;
+  chainId: number;
+  /** Absent for a plain RawTransaction */
+  secondarySignerAddresses?: string[];
+  /** Present for a FeePayerRawTransaction */
+  feePayerAddress?: string;
+}
+
+const twoByTwo = (): Payload => ({
+  script: TRANSFER_TWO_BY_TWO,
+  typeArgs: [],
+  args: [
+    new U64(1000),
+    new U64(1000),
+    AccountAddress.from(ALICE),
+    AccountAddress.from(BOB),
+    new U64(1000),
+  ],
+});
+
+const INPUTS: Input[] = [
+  {
+    name: "multi_agent_script_transfer",
+    description: "The frontend flow: a script moving 1000 octas each way between two signers",
+    sender: ALICE,
+    sequenceNumber: 7n,
+    payload: twoByTwo(),
+    maxGasAmount: 200000n,
+    gasUnitPrice: 100n,
+    expirationTimestampSecs: 1760000300n,
+    chainId: 250,
+    secondarySignerAddresses: [BOB],
+  },
+  {
+    name: "multi_agent_entry_function_two_secondaries",
+    description: "Generic entry function with two secondary signers",
+    sender: ALICE,
+    sequenceNumber: 0n,
+    payload: {
+      function: "0x1::aptos_account::transfer_coins",
+      typeArgs: ["0x1::aptos_coin::AptosCoin"],
+      args: [AccountAddress.from(BOB), new U64(5000)],
+    },
+    maxGasAmount: 2000n,
+    gasUnitPrice: 100n,
+    expirationTimestampSecs: 1760000300n,
+    chainId: 4,
+    secondarySignerAddresses: [BOB, CAROL],
+  },
+  {
+    name: "multi_agent_no_secondaries",
+    description: "Multi-agent payload with an empty secondary signer list",
+    sender: BOB,
+    sequenceNumber: 42n,
+    payload: {
+      function: "0x1::aptos_account::transfer",
+      typeArgs: [],
+      args: [AccountAddress.from(ALICE), new U64(1)],
+    },
+    maxGasAmount: 1500n,
+    gasUnitPrice: 150n,
+    expirationTimestampSecs: 1760000600n,
+    chainId: 2,
+    secondarySignerAddresses: [],
+  },
+  {
+    name: "fee_payer_sponsored_transfer",
+    description: "Sponsored transfer with a fee payer and no secondary signers",
+    sender: ALICE,
+    sequenceNumber: 3n,
+    payload: {
+      function: "0x1::aptos_account::transfer",
+      typeArgs: [],
+      args: [AccountAddress.from(CAROL), new U64(250000)],
+    },
+    maxGasAmount: 4000n,
+    gasUnitPrice: 100n,
+    expirationTimestampSecs: 1760000300n,
+    chainId: 250,
+    secondarySignerAddresses: [],
+    feePayerAddress: SPONSOR,
+  },
+  {
+    name: "fee_payer_multi_agent_script",
+    description: "Sponsored multi-agent script",
+    sender: ALICE,
+    sequenceNumber: 8n,
+    payload: twoByTwo(),
+    maxGasAmount: 200000n,
+    gasUnitPrice: 100n,
+    expirationTimestampSecs: 1760000300n,
+    chainId: 250,
+    secondarySignerAddresses: [BOB],
+    feePayerAddress: SPONSOR,
+  },
+  {
+    name: "raw_transaction_large_sequence_number",
+    description: "Single signer with values that need every byte of their u64",
+    sender: CAROL,
+    sequenceNumber: 18446744073709551614n,
+    payload: {
+      function: "0x1::aptos_account::transfer",
+      typeArgs: [],
+      args: [AccountAddress.from(BOB), new U64(18446744073709551615n)],
+    },
+    maxGasAmount: 18446744073709551615n,
+    gasUnitPrice: 4294967296n,
+    expirationTimestampSecs: 9223372036854775808n,
+    chainId: 255,
+  },
+  {
+    name: "raw_transaction_long_argument",
+    description: "An argument longer than 127 bytes, so its length takes two ULEB128 bytes",
+    sender: BOB,
+    sequenceNumber: 1n,
+    payload: {
+      function: "0x1::aptos_account::set_metadata",
+      typeArgs: ["vector<u8>"],
+      args: [new FixedBytes(new Uint8Array(200).fill(0x2a))],
+    },
+    maxGasAmount: 1000n,
+    gasUnitPrice: 100n,
+    expirationTimestampSecs: 1760000300n,
+    chainId: 1,
+  },
+];
+
+function rawTransaction(input: Input): RawTransaction {
+  const { payload } = input;
+  const typeArgs = payload.typeArgs.map((typeArg) => parseTypeTag(typeArg));
+  let transactionPayload;
+  if ("script" in payload) {
+    const bytecode = Hex.fromHexInput(payload.script).toUint8Array();
+    transactionPayload = new TransactionPayloadScript(
+      new Script(bytecode, typeArgs, payload.args),
+    );
+  } else {
+    const [address, module, name] = payload.function.split("::");
+    transactionPayload = new TransactionPayloadEntryFunction(
+      EntryFunction.build(`${address}::${module}`, name, typeArgs, payload.args),
+    );
+  }
+  return new RawTransaction(
+    AccountAddress.from(input.sender),
+    input.sequenceNumber,
+    transactionPayload,
+    input.maxGasAmount,
+    input.gasUnitPrice,
+    input.expirationTimestampSecs,
+    new ChainId(input.chainId),
+  );
+}
+
+function bcs(input: Input): string {
+  const serializer = new Serializer();
+  rawTransaction(input).serialize(serializer);
+  if (input.secondarySignerAddresses) {
+    serializer.serializeVector(
+      input.secondarySignerAddresses.map((address) => AccountAddress.from(address)),
+    );
+  }
+  if (input.feePayerAddress) {
+    AccountAddress.from(input.feePayerAddress).serialize(serializer);
+  }
+  return Hex.fromHexInput(serializer.toUint8Array()).toString();
+}
+
+// `expected` comes from what the SDK was given, never from decoding `hex`
+function vector(input: Input) {
+  const { payload } = input;
+  return {
+    name: input.name,
+    description: input.description,
+    kind: input.feePayerAddress
+      ? "fee_payer"
+      : input.secondarySignerAddresses
+        ? "multi_agent"
+        : "raw_transaction",
+    hex: bcs(input),
+    expected: {
+      sender: input.sender,
+      sequence_number: input.sequenceNumber,
+      max_gas_amount: input.maxGasAmount,
+      gas_unit_price: input.gasUnitPrice,
+      expiration_timestamp_secs: input.expirationTimestampSecs,
+      chain_id: input.chainId,
+      payload: "script" in payload ? "script" : "entry_function",
+      function: "function" in payload ? payload.function : undefined,
+      type_args: payload.typeArgs,
+      argument_count: payload.args.length,
+      secondary_signer_addresses: input.secondarySignerAddresses,
+      fee_payer_address: input.feePayerAddress,
+    },
+  };
+}
+
+const corpus = {
+  generated_by: { script: SCRIPT_PATH, sdk: `${SDK}@${SDK_VERSION}` },
+  vectors: INPUTS.map(vector),
+};
+
+// u64 values past 2^53 must stay exact, so bigints are written as bare numbers
+const json = JSON.stringify(
+  corpus,
+  (_, value) => (typeof value === "bigint" ? `bigint:${value}` : value),
+  2,
+).replace(/"bigint:(\d+)"/g, "$1");
+writeFileSync(OUTPUT, `${json}\n`);
+console.log(
+  `Wrote ${corpus.vectors.length} vectors with ${SDK}@${SDK_VERSION} to ${OUTPUT.pathname}`,
+);